@@ -0,0 +1,95 @@
+use clap::{App, Arg};
+use ssl_rust_tools::labeler::auto::{auto_label, AutoLabelerConfig};
+use ssl_rust_tools::labeler::label_file::write_labels_to_path;
+use ssl_rust_tools::labeler::reader::LabelerDataReader;
+use std::path::Path;
+
+fn main() {
+    let matches = App::new("Automatically label a labeler data file.")
+        .version("1.0")
+        .author("Devin Schwab <dschwab@andrew.cmu.edu>")
+        .about("Pre-fill the dribbling and ball possession labels of a labeler data file")
+        .arg(
+            Arg::with_name("LABELER_DATA_FILE")
+                .help("Path to the labeler data file")
+                .required(true)
+                .index(1),
+        )
+        .arg(
+            Arg::with_name("OUTPUT")
+                .help("Path to write the label file to")
+                .required(true)
+                .index(2),
+        )
+        .arg(
+            Arg::with_name("possession-distance")
+                .long("possession-distance")
+                .takes_value(true)
+                .help("Largest robot to ball distance in mm for possession"),
+        )
+        .arg(
+            Arg::with_name("possession-max-angle")
+                .long("possession-max-angle")
+                .takes_value(true)
+                .help("Largest angle in radians between the robot's heading and the ball for possession"),
+        )
+        .arg(
+            Arg::with_name("dribbling-distance")
+                .long("dribbling-distance")
+                .takes_value(true)
+                .help("Largest robot to ball distance in mm for dribbling"),
+        )
+        .arg(
+            Arg::with_name("dribbling-max-angle")
+                .long("dribbling-max-angle")
+                .takes_value(true)
+                .help("Largest angle in radians between the robot's heading and the ball for dribbling"),
+        )
+        .arg(
+            Arg::with_name("min-ball-confidence")
+                .long("min-ball-confidence")
+                .takes_value(true)
+                .help("Balls detected with a lower confidence are ignored"),
+        )
+        .arg(
+            Arg::with_name("min-dribbling-frames")
+                .long("min-dribbling-frames")
+                .takes_value(true)
+                .help("Dribbling shorter than this many frame groups is dropped"),
+        )
+        .get_matches();
+
+    let mut config = AutoLabelerConfig::default();
+    let parse = |name: &str| {
+        matches.value_of(name).map(|value| {
+            value
+                .parse::<f32>()
+                .unwrap_or_else(|_| panic!("Invalid {}", name))
+        })
+    };
+    if let Some(value) = parse("possession-distance") {
+        config.possession_distance = value;
+    }
+    if let Some(value) = parse("possession-max-angle") {
+        config.possession_max_angle = value;
+    }
+    if let Some(value) = parse("dribbling-distance") {
+        config.dribbling_distance = value;
+    }
+    if let Some(value) = parse("dribbling-max-angle") {
+        config.dribbling_max_angle = value;
+    }
+    if let Some(value) = parse("min-ball-confidence") {
+        config.min_ball_confidence = value;
+    }
+    if let Some(value) = matches.value_of("min-dribbling-frames") {
+        config.min_dribbling_frame_groups = value.parse().expect("Invalid min dribbling frames");
+    }
+
+    let reader =
+        LabelerDataReader::new_from_path(Path::new(matches.value_of("LABELER_DATA_FILE").unwrap()))
+            .expect("Failed to open labeler data file");
+    let labels = auto_label(&reader, &config).expect("Failed to read labeler data file");
+    write_labels_to_path(Path::new(matches.value_of("OUTPUT").unwrap()), &labels)
+        .expect("Failed to write label file");
+}
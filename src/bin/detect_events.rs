@@ -0,0 +1,96 @@
+use clap::{App, Arg};
+use ssl_rust_tools::labeler::auto::{auto_label, AutoLabelerConfig};
+use ssl_rust_tools::labeler::detect::{detect_events, EventDetectorConfig};
+use ssl_rust_tools::labeler::label_file::write_labels_to_path;
+use ssl_rust_tools::labeler::metadata::MetadataEditor;
+use ssl_rust_tools::labeler::reader::LabelerDataReader;
+use ssl_rust_tools::labeler::writer::labeler_data_from_log;
+use ssl_rust_tools::labeler::FilterConfig;
+use ssl_rust_tools::persistence::reader::is_log_file;
+use ssl_rust_tools::protos::log_labels;
+use std::io::Read;
+use std::path::Path;
+
+fn detect<T: Read + std::io::Seek>(
+    reader: &LabelerDataReader<T>,
+    frame_labels: bool,
+) -> log_labels::Labels {
+    let events = detect_events(reader, &EventDetectorConfig::default())
+        .expect("Failed to read labeler data file");
+
+    let mut labels = if frame_labels {
+        auto_label(reader, &AutoLabelerConfig::default()).expect("Failed to read labeler data file")
+    } else {
+        log_labels::Labels::new()
+    };
+    labels.set_passing_labels(events.passing_labels.into());
+    labels.set_goal_shot_labels(events.goal_shot_labels.into());
+    labels
+}
+
+fn main() {
+    let matches = App::new("Detect passes and goal shots.")
+        .version("1.0")
+        .author("Devin Schwab <dschwab@andrew.cmu.edu>")
+        .about("Propose passing and goal shot labels for a labeler data file or log")
+        .arg(
+            Arg::with_name("INPUT")
+                .help("Path to the labeler data file or log file")
+                .required(true)
+                .index(1),
+        )
+        .arg(
+            Arg::with_name("OUTPUT")
+                .help("Path to write the label file to")
+                .required(true)
+                .index(2),
+        )
+        .arg(
+            Arg::with_name("frame-labels")
+                .long("frame-labels")
+                .help("Also fill in the dribbling and ball possession labels like auto_label"),
+        )
+        .arg(
+            Arg::with_name("set-num-events")
+                .long("set-num-events")
+                .help("Store the number of detected events in the labeler data file's metadata"),
+        )
+        .get_matches();
+
+    let input_path = Path::new(matches.value_of("INPUT").unwrap());
+    let frame_labels = matches.is_present("frame-labels");
+
+    let is_log = is_log_file(input_path).expect("Failed to read input file");
+    if is_log && matches.is_present("set-num-events") {
+        eprintln!("--set-num-events needs a labeler data file, not a log.");
+        std::process::exit(1);
+    }
+
+    let labels = if is_log {
+        // the frames of the labels match the labeler data file made
+        // from the log
+        let reader = labeler_data_from_log(input_path, FilterConfig::default())
+            .expect("Failed to filter log");
+        detect(&reader, frame_labels)
+    } else {
+        let reader =
+            LabelerDataReader::new_from_path(input_path).expect("Failed to open labeler data file");
+        detect(&reader, frame_labels)
+    };
+    write_labels_to_path(Path::new(matches.value_of("OUTPUT").unwrap()), &labels)
+        .expect("Failed to write label file");
+    println!(
+        "Detected {} passes and {} goal shots.",
+        labels.get_passing_labels().len(),
+        labels.get_goal_shot_labels().len()
+    );
+
+    if matches.is_present("set-num-events") {
+        let mut editor =
+            MetadataEditor::open(input_path).expect("Failed to open labeler data file");
+        let metadata = editor.metadata_mut();
+        metadata.set_num_passing_events(labels.get_passing_labels().len() as u32);
+        metadata.set_num_goal_shot_events(labels.get_goal_shot_labels().len() as u32);
+        editor.save().expect("Failed to save metadata");
+    }
+}
@@ -0,0 +1,40 @@
+use clap::{App, Arg};
+use ssl_rust_tools::persistence::reader::LogReader;
+use ssl_rust_tools::referee::game_event_report;
+use ssl_rust_tools::vision::WorldFrameConfig;
+use std::path::Path;
+
+fn main() {
+    let matches = App::new("Summarize game events.")
+        .version("1.0")
+        .author("Devin Schwab <dschwab@andrew.cmu.edu>")
+        .about("Print the referee game events of a log with a foul breakdown per team")
+        .arg(
+            Arg::with_name("LOG_FILE")
+                .help("Path to the log file")
+                .required(true)
+                .index(1),
+        )
+        .arg(
+            Arg::with_name("format")
+                .long("format")
+                .takes_value(true)
+                .possible_values(&["json", "markdown"])
+                .default_value("markdown")
+                .help("Output format of the report"),
+        )
+        .get_matches();
+
+    let reader = LogReader::new_from_path(Path::new(matches.value_of("LOG_FILE").unwrap()))
+        .expect("Failed to open log file");
+    let report =
+        game_event_report(reader, &WorldFrameConfig::default()).expect("Failed to read log file");
+
+    match matches.value_of("format").unwrap() {
+        "json" => println!(
+            "{}",
+            serde_json::to_string_pretty(&report.to_json()).unwrap()
+        ),
+        _ => print!("{}", report.to_markdown()),
+    }
+}
@@ -0,0 +1,60 @@
+use clap::{App, Arg};
+use ssl_rust_tools::labeler::agreement::label_agreement;
+use ssl_rust_tools::labeler::label_file::read_labels_from_path;
+use ssl_rust_tools::labeler::scoring::ScoringOptions;
+use std::path::Path;
+
+fn main() {
+    let matches = App::new("Compare label files.")
+        .version("1.0")
+        .author("Devin Schwab <dschwab@andrew.cmu.edu>")
+        .about("Measure how well several annotators of the same labeler data file agree")
+        .arg(
+            Arg::with_name("LABEL_FILES")
+                .help("Paths to the label files, one per annotator")
+                .required(true)
+                .multiple(true)
+                .min_values(2)
+                .index(1),
+        )
+        .arg(
+            Arg::with_name("min-iou")
+                .long("min-iou")
+                .takes_value(true)
+                .help("Smallest overlap for two annotators' events to count as the same event"),
+        )
+        .arg(
+            Arg::with_name("json")
+                .long("json")
+                .help("Print the agreement as JSON instead of Markdown"),
+        )
+        .get_matches();
+
+    let mut options = ScoringOptions::default();
+    if let Some(min_iou) = matches.value_of("min-iou") {
+        options.min_iou = min_iou.parse().expect("Invalid min IoU");
+    }
+
+    let label_paths: Vec<&str> = matches.values_of("LABEL_FILES").unwrap().collect();
+    let labels: Vec<_> = label_paths
+        .iter()
+        .map(|path| {
+            read_labels_from_path(Path::new(path))
+                .unwrap_or_else(|e| panic!("Failed to read label file {}: {}", path, e))
+        })
+        .collect();
+
+    for (annotator, path) in label_paths.iter().enumerate() {
+        eprintln!("annotator {}: {}", annotator, path);
+    }
+
+    let report = label_agreement(&labels, &options).expect("Failed to compare label files");
+    if matches.is_present("json") {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&report.to_json()).unwrap()
+        );
+    } else {
+        print!("{}", report.to_markdown());
+    }
+}
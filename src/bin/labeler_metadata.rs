@@ -0,0 +1,130 @@
+use clap::{App, AppSettings, Arg, SubCommand};
+use ssl_rust_tools::labeler::metadata::{
+    metadata_to_json, read_metadata_from_path, MetadataEditor,
+};
+use ssl_rust_tools::protos::log_labeler_data::LabelerMetadata;
+use std::path::Path;
+use std::process;
+
+// fields that can be changed with the set subcommand. Everything else
+// is derived from the frame data and should be rebuilt with
+// repair_labeler_data_file instead.
+const SETTABLE_FIELDS: [&str; 7] = [
+    "num_cameras",
+    "num_passing_events",
+    "num_goal_shot_events",
+    "source_log_file_name",
+    "source_log_sha256",
+    "yellow_team_name",
+    "blue_team_name",
+];
+
+fn parse_u32(field: &str, value: &str) -> u32 {
+    match value.parse::<u32>() {
+        Ok(value) => value,
+        Err(e) => {
+            eprintln!("Invalid value {:?} for {}: {}", value, field, e);
+            process::exit(1);
+        }
+    }
+}
+
+fn set_field(metadata: &mut LabelerMetadata, field: &str, value: &str) {
+    match field {
+        "num_cameras" => metadata.set_num_cameras(parse_u32(field, value)),
+        "num_passing_events" => metadata.set_num_passing_events(parse_u32(field, value)),
+        "num_goal_shot_events" => metadata.set_num_goal_shot_events(parse_u32(field, value)),
+        "source_log_file_name" => metadata.set_source_log_file_name(value.to_owned()),
+        "source_log_sha256" => metadata.set_source_log_sha256(value.to_owned()),
+        "yellow_team_name" => metadata.set_yellow_team_name(value.to_owned()),
+        "blue_team_name" => metadata.set_blue_team_name(value.to_owned()),
+        _ => unreachable!(),
+    };
+}
+
+fn main() {
+    let file_arg = Arg::with_name("LABELER_DATA_FILE")
+        .help("Path to labeler data file")
+        .required(true)
+        .index(1);
+
+    let matches = App::new("Read and edit labeler data file metadata.")
+        .version("1.0")
+        .author("Devin Schwab <dschwab@andrew.cmu.edu>")
+        .about("Get, set or dump the metadata stored at the end of a labeler data file")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .subcommand(
+            SubCommand::with_name("get")
+                .about("Print a single metadata field")
+                .arg(file_arg.clone())
+                .arg(
+                    Arg::with_name("FIELD")
+                        .help("Name of the field, as shown by dump")
+                        .required(true)
+                        .index(2),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("set")
+                .about("Change a single metadata field in place")
+                .arg(file_arg.clone())
+                .arg(
+                    Arg::with_name("FIELD")
+                        .help("Name of the field")
+                        .required(true)
+                        .possible_values(&SETTABLE_FIELDS)
+                        .index(2),
+                )
+                .arg(
+                    Arg::with_name("VALUE")
+                        .help("New value of the field")
+                        .required(true)
+                        .index(3),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("dump")
+                .about("Print all of the metadata as JSON")
+                .arg(file_arg),
+        )
+        .get_matches();
+
+    match matches.subcommand() {
+        ("get", Some(sub_matches)) => {
+            let path = Path::new(sub_matches.value_of("LABELER_DATA_FILE").unwrap());
+            let field = sub_matches.value_of("FIELD").unwrap();
+
+            let metadata = read_metadata_from_path(path).expect("Failed to read metadata");
+            match metadata_to_json(&metadata).get(field) {
+                Some(serde_json::Value::String(value)) => println!("{}", value),
+                Some(value) => println!("{}", value),
+                None => {
+                    eprintln!("Unknown metadata field {:?}", field);
+                    process::exit(1);
+                }
+            };
+        }
+        ("set", Some(sub_matches)) => {
+            let path = Path::new(sub_matches.value_of("LABELER_DATA_FILE").unwrap());
+            let field = sub_matches.value_of("FIELD").unwrap();
+            let value = sub_matches.value_of("VALUE").unwrap();
+
+            let mut editor = MetadataEditor::open(path).expect("Failed to open metadata");
+            set_field(editor.metadata_mut(), field, value);
+            if let Err(e) = editor.save() {
+                eprintln!("Failed to save metadata: {}", e);
+                process::exit(1);
+            }
+        }
+        ("dump", Some(sub_matches)) => {
+            let path = Path::new(sub_matches.value_of("LABELER_DATA_FILE").unwrap());
+
+            let metadata = read_metadata_from_path(path).expect("Failed to read metadata");
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&metadata_to_json(&metadata)).unwrap()
+            );
+        }
+        _ => unreachable!(),
+    };
+}
@@ -0,0 +1,69 @@
+use clap::{App, Arg};
+use ssl_rust_tools::labeler::label_file::{read_labels, write_labels, LabelFormat};
+use std::fs;
+use std::path::Path;
+
+fn format_arg(value: Option<&str>, path: &Path) -> LabelFormat {
+    match value {
+        Some(name) => LabelFormat::from_name(name).unwrap(),
+        None => LabelFormat::from_path(path),
+    }
+}
+
+fn main() {
+    let matches = App::new("Convert a label file.")
+        .version("1.0")
+        .author("Devin Schwab <dschwab@andrew.cmu.edu>")
+        .about("Convert label files between the protobuf, JSON and CSV formats")
+        .arg(
+            Arg::with_name("INPUT")
+                .help("Path to the label file to convert")
+                .required(true)
+                .index(1),
+        )
+        .arg(
+            Arg::with_name("OUTPUT")
+                .help("Path to write the converted label file to")
+                .required(true)
+                .index(2),
+        )
+        .arg(
+            Arg::with_name("from")
+                .long("from")
+                .takes_value(true)
+                .possible_values(&["protobuf", "json", "csv"])
+                .help("Format of the input. Guessed from the file extension by default."),
+        )
+        .arg(
+            Arg::with_name("to")
+                .long("to")
+                .takes_value(true)
+                .possible_values(&["protobuf", "json", "csv"])
+                .help("Format of the output. Guessed from the file extension by default."),
+        )
+        .arg(
+            Arg::with_name("frames-only")
+                .long("frames-only")
+                .help("Drop the passing and goal shot labels. Needed to write CSV."),
+        )
+        .get_matches();
+
+    let input_path = Path::new(matches.value_of("INPUT").unwrap());
+    let output_path = Path::new(matches.value_of("OUTPUT").unwrap());
+    let input_format = format_arg(matches.value_of("from"), input_path);
+    let output_format = format_arg(matches.value_of("to"), output_path);
+
+    let mut input = fs::File::open(input_path).expect("Failed to open input label file");
+    let mut labels = read_labels(&mut input, input_format).expect("Failed to read labels");
+
+    if matches.is_present("frames-only") {
+        labels.clear_passing_labels();
+        labels.clear_goal_shot_labels();
+    }
+
+    // convert in memory first, so a failed conversion does not leave
+    // an empty output file behind
+    let mut converted = vec![];
+    write_labels(&mut converted, &labels, output_format).expect("Failed to convert labels");
+    fs::write(output_path, converted).expect("Failed to write output label file");
+}
@@ -1,11 +1,27 @@
 use clap::{App, Arg};
 use indicatif::{ProgressBar, ProgressStyle};
+use sha2::{Digest, Sha256};
 use ssl_rust_tools::labeler::writer;
 use ssl_rust_tools::persistence::reader;
 use std::fs;
-use std::io::{self, BufRead, BufReader};
+use std::io::{self, BufRead, BufReader, Read};
 use std::path::Path;
 
+// Computes the hash of the log file while it is being read, so that
+// stdin input can be hashed without buffering the whole log.
+struct HashingReader<R: Read> {
+    reader: R,
+    hasher: Sha256,
+}
+
+impl<R: Read> Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let num_read = self.reader.read(buf)?;
+        self.hasher.input(&buf[..num_read]);
+        Ok(num_read)
+    }
+}
+
 fn main() {
     let matches = App::new("Create a log labeler data file.")
         .version("1.0")
@@ -56,7 +72,10 @@ fn main() {
             prog_bar
         }
     };
-    let reader = prog_bar.wrap_read(input);
+    let reader = HashingReader {
+        reader: prog_bar.wrap_read(input),
+        hasher: Sha256::new(),
+    };
 
     let output_path = Path::new(matches.value_of("LABELER_DATA_FILE").unwrap());
 
@@ -65,15 +84,25 @@ fn main() {
         .expect("Unable to get parent directory of output path");
     fs::create_dir_all(output_path_parent).expect("Failed to create output directory");
 
-    let log_reader = reader::LogReader::new(reader).expect("Could not read log file");
+    let mut log_reader = reader::LogReader::new(reader).expect("Could not read log file");
     let mut labeler_data_writer = writer::LabelerDataWriter::new_from_path(output_path)
         .expect("Could not write labeler data file");
 
     prog_bar.set_message("Processing log file");
-    for message in log_reader.filter_map(Result::ok) {
+    for message in log_reader.by_ref().filter_map(Result::ok) {
         labeler_data_writer
             .add_msg(message)
             .expect("Failed to write message to labeler data writer");
     }
+
+    let log_file_name = match matches.value_of("LOG_FILE").unwrap() {
+        "-" => "",
+        log_path => Path::new(log_path)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or(""),
+    };
+    let log_sha256 = log_reader.inner().get_ref().hasher.clone().result();
+    labeler_data_writer.set_source_log(log_file_name, format!("{:x}", log_sha256));
     prog_bar.finish_with_message("Finished pre-processing.");
 }
@@ -0,0 +1,44 @@
+use clap::{App, Arg};
+use ssl_rust_tools::labeler::match_report::{match_report, MatchReportConfig};
+use ssl_rust_tools::labeler::writer::labeler_data_from_log;
+use ssl_rust_tools::labeler::FilterConfig;
+use ssl_rust_tools::referee::Timeline;
+use std::path::Path;
+
+fn main() {
+    let matches = App::new("Match report.")
+        .version("1.0")
+        .author("Devin Schwab <dschwab@andrew.cmu.edu>")
+        .about("Print possession, passing, shot, movement and goal statistics of a log")
+        .arg(
+            Arg::with_name("LOG_FILE")
+                .help("Path to the log file")
+                .required(true)
+                .index(1),
+        )
+        .arg(
+            Arg::with_name("format")
+                .long("format")
+                .takes_value(true)
+                .possible_values(&["json", "markdown"])
+                .default_value("markdown")
+                .help("Output format of the report"),
+        )
+        .get_matches();
+
+    let log_path = Path::new(matches.value_of("LOG_FILE").unwrap());
+    let timeline = Timeline::from_path(log_path).expect("Failed to read log file");
+    // the statistics cover the same frames as the labels
+    let reader =
+        labeler_data_from_log(log_path, FilterConfig::default()).expect("Failed to filter log");
+    let report = match_report(&reader, &timeline, &MatchReportConfig::default())
+        .expect("Failed to read filtered log");
+
+    match matches.value_of("format").unwrap() {
+        "json" => println!(
+            "{}",
+            serde_json::to_string_pretty(&report.to_json()).unwrap()
+        ),
+        _ => print!("{}", report.to_markdown()),
+    }
+}
@@ -0,0 +1,80 @@
+use clap::{App, Arg};
+use ssl_rust_tools::labeler::label_file::{read_labels_from_path, write_labels_to_path};
+use ssl_rust_tools::labeler::merge::{merge_labels, MergeOptions};
+use std::fs;
+use std::path::Path;
+
+fn main() {
+    let matches = App::new("Merge label files.")
+        .version("1.0")
+        .author("Devin Schwab <dschwab@andrew.cmu.edu>")
+        .about("Merge the label files of several annotators into one consensus label file")
+        .arg(
+            Arg::with_name("LABEL_FILES")
+                .help("Paths to the label files, one per annotator")
+                .required(true)
+                .multiple(true)
+                .index(1),
+        )
+        .arg(
+            Arg::with_name("output")
+                .short("o")
+                .long("output")
+                .takes_value(true)
+                .required(true)
+                .help("Path to write the merged label file to"),
+        )
+        .arg(
+            Arg::with_name("review")
+                .long("review")
+                .takes_value(true)
+                .help("Path to write the conflicts to as JSON. Printed if not given."),
+        )
+        .arg(
+            Arg::with_name("quorum")
+                .long("quorum")
+                .takes_value(true)
+                .help(
+                "Number of annotators that must label an event to keep it. Defaults to a majority.",
+            ),
+        )
+        .arg(
+            Arg::with_name("min-iou")
+                .long("min-iou")
+                .takes_value(true)
+                .help("Smallest overlap for two annotators' events to count as the same event"),
+        )
+        .get_matches();
+
+    let mut options = MergeOptions::default();
+    if let Some(min_iou) = matches.value_of("min-iou") {
+        options.min_iou = min_iou.parse().expect("Invalid min IoU");
+    }
+    if let Some(quorum) = matches.value_of("quorum") {
+        options.quorum = Some(quorum.parse().expect("Invalid quorum"));
+    }
+
+    let labels: Vec<_> = matches
+        .values_of("LABEL_FILES")
+        .unwrap()
+        .map(|path| {
+            read_labels_from_path(Path::new(path))
+                .unwrap_or_else(|e| panic!("Failed to read label file {}: {}", path, e))
+        })
+        .collect();
+
+    let merged = merge_labels(&labels, &options).expect("Failed to merge label files");
+    write_labels_to_path(
+        Path::new(matches.value_of("output").unwrap()),
+        &merged.labels,
+    )
+    .expect("Failed to write merged label file");
+
+    let review: Vec<_> = merged.conflicts.iter().map(|c| c.to_json()).collect();
+    let review = serde_json::to_string_pretty(&review).unwrap();
+    match matches.value_of("review") {
+        Some(review_path) => fs::write(review_path, review).expect("Failed to write review file"),
+        None => println!("{}", review),
+    }
+    eprintln!("{} conflicts to review.", merged.conflicts.len());
+}
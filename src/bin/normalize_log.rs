@@ -0,0 +1,70 @@
+use clap::{App, Arg};
+use ssl_rust_tools::persistence::normalize::{Normalize, NormalizeConfig, TeamSelector};
+use ssl_rust_tools::persistence::reader::LogReader;
+use ssl_rust_tools::persistence::writer::LogWriter;
+use ssl_rust_tools::vision::TeamColor;
+use std::path::Path;
+
+fn parse_color(color: &str) -> Option<TeamColor> {
+    match color.to_lowercase().as_str() {
+        "yellow" => Some(TeamColor::Yellow),
+        "blue" => Some(TeamColor::Blue),
+        _ => None,
+    }
+}
+
+fn main() {
+    let matches = App::new("Normalize a log.")
+        .version("1.0")
+        .author("Devin Schwab <dschwab@andrew.cmu.edu>")
+        .about("Rewrite a log so that one team always attacks +x and optionally has the same color")
+        .arg(
+            Arg::with_name("INPUT")
+                .help("Path to the log file to normalize")
+                .required(true)
+                .index(1),
+        )
+        .arg(
+            Arg::with_name("OUTPUT")
+                .help("Path to write the normalized log to")
+                .required(true)
+                .index(2),
+        )
+        .arg(
+            Arg::with_name("team")
+                .long("team")
+                .takes_value(true)
+                .required(true)
+                .help("Team that attacks +x, either yellow, blue or a team name"),
+        )
+        .arg(
+            Arg::with_name("color")
+                .long("color")
+                .takes_value(true)
+                .possible_values(&["yellow", "blue"])
+                .help("Swap the team colors so that the team always has this color"),
+        )
+        .get_matches();
+
+    let team = matches.value_of("team").unwrap();
+    let config = NormalizeConfig {
+        team: match parse_color(team) {
+            Some(color) => TeamSelector::Color(color),
+            None => TeamSelector::Name(team.to_owned()),
+        },
+        team_color: matches.value_of("color").and_then(parse_color),
+    };
+
+    let reader = LogReader::new_from_path(Path::new(matches.value_of("INPUT").unwrap()))
+        .expect("Failed to open log file");
+    let mut writer = LogWriter::new_from_path(Path::new(matches.value_of("OUTPUT").unwrap()))
+        .expect("Failed to create output log file");
+
+    for message in Normalize::new(reader, config) {
+        let message = message.expect("Failed to read log file");
+        writer
+            .write_message(&message)
+            .expect("Failed to write message");
+    }
+    writer.flush().expect("Failed to write output log file");
+}
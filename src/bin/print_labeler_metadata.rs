@@ -19,8 +19,38 @@ fn main() {
     let log_path = Path::new(matches.value_of("LABELER_FILE").unwrap());
     let reader = LabelerDataReader::new_from_path(log_path).unwrap();
 
+    println!("Version: {}", reader.version());
     println!("Num Cameras: {}", reader.num_cameras());
+    println!("Camera IDs: {:?}", reader.camera_ids());
     println!("Num Messages: {}", reader.len());
     println!("Num Passing Events: {}", reader.num_passing_events());
     println!("Num Goal Shot Events: {}", reader.num_goal_shot_events());
+    println!(
+        "Source Log File: {}",
+        reader.source_log_file_name().unwrap_or("unknown")
+    );
+    println!(
+        "Source Log SHA-256: {}",
+        reader.source_log_sha256().unwrap_or("unknown")
+    );
+    println!(
+        "Yellow Team: {}",
+        reader.yellow_team_name().unwrap_or("unknown")
+    );
+    println!(
+        "Blue Team: {}",
+        reader.blue_team_name().unwrap_or("unknown")
+    );
+    match reader.geometry() {
+        Some(geometry) => println!(
+            "Field Size: {} x {}",
+            geometry.get_field().get_field_length(),
+            geometry.get_field().get_field_width()
+        ),
+        None => println!("Field Size: unknown"),
+    };
+    let filter_config = reader.filter_config();
+    println!("Frame Grouping: {:?}", filter_config.grouping);
+    println!("Running Stages: {:?}", filter_config.running_stages);
+    println!("Running Commands: {:?}", filter_config.running_commands);
 }
@@ -0,0 +1,216 @@
+use clap::{App, Arg, ArgGroup};
+use ssl_rust_tools::geometry::{Division, Field};
+use ssl_rust_tools::labeler::label_file::read_labels_from_path;
+use ssl_rust_tools::labeler::reader::LabelerDataReader;
+use ssl_rust_tools::protos::log_labels;
+use ssl_rust_tools::render::{frame_group_timestamp, ClipConfig, ClipRenderer, GifWriter, View};
+use ssl_rust_tools::vision::TeamColor;
+use std::fs;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+fn parse_index(value: &str) -> usize {
+    value.parse().expect("Failed to parse index")
+}
+
+// Frame range and kicker of the pass or goal shot picked on the command
+// line
+fn label_range(
+    labels: &log_labels::Labels,
+    pass: Option<usize>,
+    goal_shot: Option<usize>,
+) -> (u64, u64, (TeamColor, u32)) {
+    if let Some(index) = pass {
+        let label = labels
+            .get_passing_labels()
+            .get(index)
+            .expect("No passing label with this index");
+        (
+            label.get_start_frame(),
+            label.get_end_frame(),
+            (
+                TeamColor::from(label.get_passer_team()),
+                label.get_passer_id(),
+            ),
+        )
+    } else {
+        let label = labels
+            .get_goal_shot_labels()
+            .get(goal_shot.expect("Select a label with --pass or --goal-shot"))
+            .expect("No goal shot label with this index");
+        (
+            label.get_start_frame(),
+            label.get_end_frame(),
+            (
+                TeamColor::from(label.get_shooter_team()),
+                label.get_shooter_id(),
+            ),
+        )
+    }
+}
+
+fn main() {
+    let matches = App::new("Render a clip.")
+        .version("1.0")
+        .author("Devin Schwab <dschwab@andrew.cmu.edu>")
+        .about("Render frames of a labeler data file to an animated GIF or numbered PNGs")
+        .arg(
+            Arg::with_name("LABELER_DATA")
+                .help("Path to the labeler data file")
+                .required(true)
+                .index(1),
+        )
+        .arg(
+            Arg::with_name("OUTPUT")
+                .help("GIF file to write, or a directory for numbered PNGs")
+                .required(true)
+                .index(2),
+        )
+        .arg(
+            Arg::with_name("start")
+                .long("start")
+                .takes_value(true)
+                .help("Index of the first frame group to render"),
+        )
+        .arg(
+            Arg::with_name("end")
+                .long("end")
+                .takes_value(true)
+                .help("Index of the last frame group to render"),
+        )
+        .arg(
+            Arg::with_name("labels")
+                .long("labels")
+                .takes_value(true)
+                .help("Label file to take the frame range from"),
+        )
+        .arg(
+            Arg::with_name("pass")
+                .long("pass")
+                .takes_value(true)
+                .requires("labels")
+                .help("Index of the passing label to render"),
+        )
+        .arg(
+            Arg::with_name("goal-shot")
+                .long("goal-shot")
+                .takes_value(true)
+                .requires("labels")
+                .help("Index of the goal shot label to render"),
+        )
+        .group(
+            ArgGroup::with_name("range")
+                .args(&["start", "pass", "goal-shot"])
+                .required(true),
+        )
+        .arg(
+            Arg::with_name("padding")
+                .long("padding")
+                .takes_value(true)
+                .default_value("30")
+                .help("Frame groups to add before and after a label"),
+        )
+        .arg(
+            Arg::with_name("speed")
+                .long("speed")
+                .takes_value(true)
+                .default_value("1.0")
+                .help("Playback speed of the GIF"),
+        )
+        .arg(
+            Arg::with_name("width")
+                .long("width")
+                .takes_value(true)
+                .default_value("800")
+                .help("Width of the frames in pixels"),
+        )
+        .get_matches();
+
+    let reader =
+        LabelerDataReader::new_from_path(Path::new(matches.value_of("LABELER_DATA").unwrap()))
+            .expect("Failed to open labeler data file");
+    if reader.is_empty() {
+        println!("The labeler data file has no frames");
+        return;
+    }
+    let last_index = reader.len() - 1;
+
+    let mut config = ClipConfig::default();
+    let (start, end) = match matches.value_of("labels") {
+        Some(labels_path) if !matches.is_present("start") => {
+            let labels =
+                read_labels_from_path(Path::new(labels_path)).expect("Failed to read label file");
+            let (start_frame, end_frame, kicker) = label_range(
+                &labels,
+                matches.value_of("pass").map(parse_index),
+                matches.value_of("goal-shot").map(parse_index),
+            );
+            config.highlight = Some(kicker);
+            let padding = parse_index(matches.value_of("padding").unwrap());
+            (
+                (start_frame as usize).saturating_sub(padding),
+                end_frame as usize + padding,
+            )
+        }
+        _ => (
+            parse_index(matches.value_of("start").unwrap()),
+            matches.value_of("end").map_or(last_index, parse_index),
+        ),
+    };
+    let end = end.min(last_index);
+    let start = start.min(end);
+
+    let width: u32 = matches
+        .value_of("width")
+        .unwrap()
+        .parse()
+        .expect("Failed to parse frame width");
+    let speed: f64 = matches
+        .value_of("speed")
+        .unwrap()
+        .parse()
+        .expect("Failed to parse speed");
+    if !speed.is_finite() || speed <= 0.0 {
+        eprintln!("Speed must be greater than 0, got {}", speed);
+        std::process::exit(1);
+    }
+    let field = Field::from_geometry(reader.geometry(), Division::A);
+    let view = View::new(&field, width);
+    let mut renderer = ClipRenderer::new(field, view, config);
+
+    let output_path = Path::new(matches.value_of("OUTPUT").unwrap());
+    let is_gif = output_path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map_or(false, |extension| extension.to_lowercase() == "gif");
+    if is_gif {
+        let file = File::create(output_path).expect("Failed to create output file");
+        let mut writer =
+            GifWriter::new(BufWriter::new(file), &view, speed).expect("Failed to write GIF");
+        for index in start..=end {
+            let frame_group = reader.get(index).expect("Failed to read frame group");
+            let canvas = renderer.render(&frame_group);
+            writer
+                .add_frame(&canvas, frame_group_timestamp(&frame_group).unwrap_or(0))
+                .expect("Failed to write GIF");
+        }
+        writer.finish().expect("Failed to write GIF");
+    } else {
+        fs::create_dir_all(output_path).expect("Failed to create output directory");
+        for index in start..=end {
+            let frame_group = reader.get(index).expect("Failed to read frame group");
+            renderer
+                .render(&frame_group)
+                .save_png(&output_path.join(format!("frame_{:06}.png", index)))
+                .expect("Failed to write PNG");
+        }
+    }
+
+    println!(
+        "Rendered frame groups {} to {} to {}",
+        start,
+        end,
+        output_path.display()
+    );
+}
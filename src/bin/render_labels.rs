@@ -0,0 +1,139 @@
+use clap::{App, Arg};
+use ssl_rust_tools::geometry::{Division, Field};
+use ssl_rust_tools::labeler::label_file::read_labels_from_path;
+use ssl_rust_tools::labeler::reader::LabelerDataReader;
+use ssl_rust_tools::render::scene::draw_field;
+use ssl_rust_tools::render::{save_image, Trajectories, TrajectoryFilter, View};
+use ssl_rust_tools::vision::{TeamColor, WorldFrameConfig};
+use std::fs;
+use std::fs::File;
+use std::path::Path;
+
+// Draws the ball path from the start to the end frame of an event and
+// the robots at its start, with the kicker highlighted
+fn render_event(
+    reader: &LabelerDataReader<File>,
+    field: &Field,
+    view: View,
+    start_frame: u64,
+    end_frame: u64,
+    kicker: (TeamColor, u32),
+    path: &Path,
+) {
+    let end = (end_frame as usize + 1).min(reader.len());
+    let start = (start_frame as usize).min(end);
+    let mut trajectories = Trajectories::new(TrajectoryFilter {
+        team: Some(kicker.0),
+        robot_id: Some(kicker.1),
+        ..Default::default()
+    });
+    for frame_group in reader
+        .get_range(start, end)
+        .expect("Failed to read labeler data file")
+    {
+        trajectories.add_frame_group(&frame_group, &WorldFrameConfig::default());
+    }
+    // only the ball path is of interest, robots are drawn where they
+    // were at the start
+    trajectories.robots.clear();
+
+    save_image(path, view, |canvas| {
+        draw_field(canvas, field);
+        trajectories.draw(canvas, trajectories.first_frame.as_ref());
+    })
+    .expect("Failed to write image");
+}
+
+fn main() {
+    let matches = App::new("Render labels.")
+        .version("1.0")
+        .author("Devin Schwab <dschwab@andrew.cmu.edu>")
+        .about("Draw the ball path of every pass and goal shot of a label file")
+        .arg(
+            Arg::with_name("LABELER_DATA")
+                .help("Path to the labeler data file the labels refer to")
+                .required(true)
+                .index(1),
+        )
+        .arg(
+            Arg::with_name("LABEL_FILE")
+                .help("Path to the label file")
+                .required(true)
+                .index(2),
+        )
+        .arg(
+            Arg::with_name("OUTPUT_DIR")
+                .help("Directory to write the images to")
+                .required(true)
+                .index(3),
+        )
+        .arg(
+            Arg::with_name("format")
+                .long("format")
+                .takes_value(true)
+                .possible_values(&["png", "svg"])
+                .default_value("png")
+                .help("Image format"),
+        )
+        .arg(
+            Arg::with_name("width")
+                .long("width")
+                .takes_value(true)
+                .default_value("800")
+                .help("Width of the images in pixels"),
+        )
+        .get_matches();
+
+    let reader =
+        LabelerDataReader::new_from_path(Path::new(matches.value_of("LABELER_DATA").unwrap()))
+            .expect("Failed to open labeler data file");
+    let labels = read_labels_from_path(Path::new(matches.value_of("LABEL_FILE").unwrap()))
+        .expect("Failed to read label file");
+    let output_dir = Path::new(matches.value_of("OUTPUT_DIR").unwrap());
+    fs::create_dir_all(output_dir).expect("Failed to create output directory");
+
+    let format = matches.value_of("format").unwrap();
+    let width: u32 = matches
+        .value_of("width")
+        .unwrap()
+        .parse()
+        .expect("Failed to parse image width");
+    let field = Field::from_geometry(reader.geometry(), Division::A);
+    let view = View::new(&field, width);
+
+    for (index, label) in labels.get_passing_labels().iter().enumerate() {
+        render_event(
+            &reader,
+            &field,
+            view,
+            label.get_start_frame(),
+            label.get_end_frame(),
+            (
+                TeamColor::from(label.get_passer_team()),
+                label.get_passer_id(),
+            ),
+            &output_dir.join(format!("pass_{}.{}", index, format)),
+        );
+    }
+    for (index, label) in labels.get_goal_shot_labels().iter().enumerate() {
+        render_event(
+            &reader,
+            &field,
+            view,
+            label.get_start_frame(),
+            label.get_end_frame(),
+            (
+                TeamColor::from(label.get_shooter_team()),
+                label.get_shooter_id(),
+            ),
+            &output_dir.join(format!("goal_shot_{}.{}", index, format)),
+        );
+    }
+
+    println!(
+        "Wrote {} pass and {} goal shot images to {}",
+        labels.get_passing_labels().len(),
+        labels.get_goal_shot_labels().len(),
+        output_dir.display()
+    );
+}
@@ -0,0 +1,123 @@
+use clap::{App, Arg};
+use ssl_rust_tools::persistence::reader::LogReader;
+use ssl_rust_tools::render::scene::draw_field;
+use ssl_rust_tools::render::{
+    collect_trajectories, save_image, HeatmapSource, TrajectoryFilter, View,
+};
+use ssl_rust_tools::vision::{TeamColor, WorldFrameConfig};
+use std::path::Path;
+
+// size of the heatmap cells in mm
+const HEATMAP_CELL_SIZE: f32 = 200.0;
+
+fn parse_color(color: &str) -> Option<TeamColor> {
+    match color {
+        "yellow" => Some(TeamColor::Yellow),
+        "blue" => Some(TeamColor::Blue),
+        _ => None,
+    }
+}
+
+fn parse_seconds(value: Option<&str>) -> Option<f64> {
+    value.map(|value| value.parse().expect("Failed to parse time in seconds"))
+}
+
+fn main() {
+    let matches = App::new("Render a log.")
+        .version("1.0")
+        .author("Devin Schwab <dschwab@andrew.cmu.edu>")
+        .about("Draw the ball and robot paths or an occupancy heatmap of a log to a PNG or SVG")
+        .arg(
+            Arg::with_name("LOG_FILE")
+                .help("Path to the log file")
+                .required(true)
+                .index(1),
+        )
+        .arg(
+            Arg::with_name("OUTPUT")
+                .help("Path of the image to write, ending in .png or .svg")
+                .required(true)
+                .index(2),
+        )
+        .arg(
+            Arg::with_name("start")
+                .long("start")
+                .takes_value(true)
+                .help("Seconds since the start of the log to start drawing at"),
+        )
+        .arg(
+            Arg::with_name("end")
+                .long("end")
+                .takes_value(true)
+                .help("Seconds since the start of the log to stop drawing at"),
+        )
+        .arg(
+            Arg::with_name("team")
+                .long("team")
+                .takes_value(true)
+                .possible_values(&["yellow", "blue"])
+                .help("Only draw the paths of robots of this team"),
+        )
+        .arg(
+            Arg::with_name("robot")
+                .long("robot")
+                .takes_value(true)
+                .help("Only draw the paths of robots with this id"),
+        )
+        .arg(
+            Arg::with_name("heatmap")
+                .long("heatmap")
+                .takes_value(true)
+                .possible_values(&["ball", "robots"])
+                .help("Draw where the ball or the selected robots were instead of their paths"),
+        )
+        .arg(
+            Arg::with_name("width")
+                .long("width")
+                .takes_value(true)
+                .default_value("1200")
+                .help("Width of the image in pixels"),
+        )
+        .get_matches();
+
+    let filter = TrajectoryFilter {
+        start: parse_seconds(matches.value_of("start")),
+        end: parse_seconds(matches.value_of("end")),
+        team: matches.value_of("team").and_then(parse_color),
+        robot_id: matches
+            .value_of("robot")
+            .map(|robot| robot.parse().expect("Failed to parse robot id")),
+    };
+    let width: u32 = matches
+        .value_of("width")
+        .unwrap()
+        .parse()
+        .expect("Failed to parse image width");
+
+    let reader = LogReader::new_from_path(Path::new(matches.value_of("LOG_FILE").unwrap()))
+        .expect("Failed to open log file");
+    let trajectories = collect_trajectories(reader, filter, &WorldFrameConfig::default())
+        .expect("Failed to read log file");
+
+    let field = trajectories.field();
+    let heatmap = matches.value_of("heatmap").map(|source| {
+        let source = match source {
+            "ball" => HeatmapSource::Ball,
+            _ => HeatmapSource::Robots,
+        };
+        trajectories.heatmap(&field, HEATMAP_CELL_SIZE, source)
+    });
+
+    save_image(
+        Path::new(matches.value_of("OUTPUT").unwrap()),
+        View::new(&field, width),
+        |canvas| {
+            draw_field(canvas, &field);
+            match heatmap {
+                Some(ref heatmap) => heatmap.draw(canvas, 0.8),
+                None => trajectories.draw(canvas, trajectories.last_frame.as_ref()),
+            }
+        },
+    )
+    .expect("Failed to write image");
+}
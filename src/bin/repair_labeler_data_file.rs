@@ -0,0 +1,42 @@
+use clap::{App, Arg};
+use ssl_rust_tools::labeler::repair::{repair_labeler_data_file, RepairOutcome};
+use std::path::Path;
+
+fn main() {
+    let matches = App::new("Repair a labeler data file.")
+        .version("1.0")
+        .author("Devin Schwab <dschwab@andrew.cmu.edu>")
+        .about("Rebuild the metadata of a labeler data file that was not closed properly.")
+        .arg(
+            Arg::with_name("LABELER_DATA_FILE")
+                .help("Path to the labeler data file to repair. It is modified in place.")
+                .required(true)
+                .index(1),
+        )
+        .arg(
+            Arg::with_name("force")
+                .short("f")
+                .long("force")
+                .help("Rebuild the metadata even if the existing metadata looks valid."),
+        )
+        .get_matches();
+
+    let labeler_data_path = Path::new(matches.value_of("LABELER_DATA_FILE").unwrap());
+    let force = matches.is_present("force");
+
+    match repair_labeler_data_file(labeler_data_path, force)
+        .expect("Failed to repair labeler data file")
+    {
+        RepairOutcome::AlreadyValid => {
+            println!("Metadata is already valid. Use --force to rebuild it anyway.")
+        }
+        RepairOutcome::Repaired {
+            num_frame_groups,
+            discarded_bytes,
+        } => {
+            println!("Recovered {} frame groups.", num_frame_groups);
+            println!("Discarded {} bytes of incomplete data.", discarded_bytes);
+            println!("Use add_num_events_to_labeler to restore the event counts.");
+        }
+    };
+}
@@ -1,8 +1,10 @@
 use clap::{App, Arg};
-use protobuf;
-use ssl_rust_tools::protos::log_labels;
-use std::cmp;
-use std::fs;
+use ssl_rust_tools::labeler::label_file::read_labels_from_path;
+use ssl_rust_tools::labeler::report::detailed_report;
+use ssl_rust_tools::labeler::scoring::{
+    score_labels_with_options, CategoryScore, MatchCounts, ScoringOptions,
+};
+use std::path::Path;
 
 fn main() {
     let matches = App::new("Score a label file.")
@@ -21,182 +23,96 @@ fn main() {
                 .required(true)
                 .index(2),
         )
+        .arg(
+            Arg::with_name("min-iou")
+                .long("min-iou")
+                .takes_value(true)
+                .help("Smallest overlap for a predicted event to match a ground truth event"),
+        )
+        .arg(
+            Arg::with_name("false-positive-penalty")
+                .long("false-positive-penalty")
+                .takes_value(true)
+                .help("Points lost for each predicted event without a ground truth match"),
+        )
+        .arg(
+            Arg::with_name("report")
+                .long("report")
+                .takes_value(true)
+                .possible_values(&["json", "markdown"])
+                .help("Print a detailed report instead of the scores"),
+        )
+        .arg(
+            Arg::with_name("max-events")
+                .long("max-events")
+                .takes_value(true)
+                .default_value("10")
+                .help("Number of worst events listed in the detailed report"),
+        )
         .get_matches();
 
-    let mut ground_truth = fs::File::open(matches.value_of("GROUND_TRUTH").unwrap())
-        .expect("Failed to open ground truth label file");
-    let mut predicted = fs::File::open(matches.value_of("PREDICTED").unwrap())
-        .expect("Failed to open predicted label file");
-
-    let ground_truth_labels: log_labels::Labels = protobuf::parse_from_reader(&mut ground_truth)
-        .expect("Failed to parse ground truth label file");
-    let predicted_labels: log_labels::Labels =
-        protobuf::parse_from_reader(&mut predicted).expect("Failed to parse predicted label file");
-
-    let dribbling_score = score_dribbling(
-        ground_truth_labels.get_dribbling_labels(),
-        predicted_labels.get_dribbling_labels(),
-    );
-    let ball_possession_score = score_ball_possession(
-        ground_truth_labels.get_ball_possession_labels(),
-        predicted_labels.get_ball_possession_labels(),
-    );
-    let passing_score = score_passing(
-        ground_truth_labels.get_passing_labels(),
-        predicted_labels.get_passing_labels(),
-    );
-    let goal_shot_score = score_goal_shot(
-        ground_truth_labels.get_goal_shot_labels(),
-        predicted_labels.get_goal_shot_labels(),
-    );
-
-    println!("Dribbling Score: {}", dribbling_score);
-    println!("Ball Possession Score: {}", ball_possession_score);
-    println!("Passing Score: {}", passing_score);
-    println!("Goal Shot Score: {}", goal_shot_score);
-}
-
-fn score_dribbling(
-    ground_truth_labels: &[log_labels::DribblingLabel],
-    predicted_labels: &[log_labels::DribblingLabel],
-) -> f64 {
-    let mut score: f64 = 0.0;
-    for (ground_truth_label, predicted_label) in
-        ground_truth_labels.iter().zip(predicted_labels.iter())
-    {
-        if ground_truth_label.get_is_dribbling() == predicted_label.get_is_dribbling() {
-            score += 1.0;
-
-            // only score sub-fields if labeled as dribbling in ground truth
-            if ground_truth_label.get_is_dribbling() {
-                if ground_truth_label.get_robot_id() == predicted_label.get_robot_id() {
-                    score += 0.5;
-                }
-
-                if ground_truth_label.get_team() == predicted_label.get_team() {
-                    score += 0.5;
-                }
-            }
-        }
+    let mut options = ScoringOptions::default();
+    if let Some(min_iou) = matches.value_of("min-iou") {
+        options.min_iou = min_iou.parse().expect("Invalid min IoU");
     }
-
-    score
-}
-
-fn score_ball_possession(
-    ground_truth_labels: &[log_labels::BallPossessionLabel],
-    predicted_labels: &[log_labels::BallPossessionLabel],
-) -> f64 {
-    let mut score: f64 = 0.0;
-    for (ground_truth_label, predicted_label) in
-        ground_truth_labels.iter().zip(predicted_labels.iter())
-    {
-        if ground_truth_label.get_state() == predicted_label.get_state() {
-            score += 1.0;
-
-            // only score if yellow/blue in possession
-            if ground_truth_label.get_state() != log_labels::BallPossessionLabel_State::NONE {
-                if ground_truth_label.get_robot_id() == predicted_label.get_robot_id() {
-                    score += 0.5;
-                }
-            }
-        }
+    if let Some(penalty) = matches.value_of("false-positive-penalty") {
+        options.false_positive_penalty = penalty.parse().expect("Invalid false positive penalty");
     }
-    score
-}
 
-fn calc_iou(span_a: (u64, u64), span_b: (u64, u64)) -> f64 {
-    // get the union
-    let union_start = cmp::min(span_a.0, span_b.0);
-    let union_end = cmp::max(span_a.1, span_b.1);
-
-    let union = union_end - union_start;
-    // this shouldn't really happen, but if it does happen in both the
-    // ground truth and predicted, count it as a perfect match
-    if union == 0 {
-        return 1.0;
+    let ground_truth_labels =
+        read_labels_from_path(Path::new(matches.value_of("GROUND_TRUTH").unwrap()))
+            .expect("Failed to read ground truth label file");
+    let predicted_labels = read_labels_from_path(Path::new(matches.value_of("PREDICTED").unwrap()))
+        .expect("Failed to read predicted label file");
+
+    if let Some(format) = matches.value_of("report") {
+        let max_events = matches
+            .value_of("max-events")
+            .unwrap()
+            .parse::<usize>()
+            .expect("Invalid max events");
+        let report = detailed_report(&ground_truth_labels, &predicted_labels, &options);
+        match format {
+            "json" => println!(
+                "{}",
+                serde_json::to_string_pretty(&report.to_json(max_events)).unwrap()
+            ),
+            _ => print!("{}", report.to_markdown(max_events)),
+        };
+        return;
     }
-    let union = union as f64;
-
-    // get the overlap
-    let intersect: f64 = if span_a.0 > span_b.1 || span_b.0 > span_a.1 {
-        0.0
-    } else {
-        let intersect_start = cmp::max(span_a.0, span_b.0);
-        let intersect_end = cmp::min(span_a.1, span_b.1);
 
-        (intersect_end - intersect_start) as f64
-    };
+    let report = score_labels_with_options(&ground_truth_labels, &predicted_labels, &options);
 
-    intersect / union
+    print_score("Dribbling Score", &report.dribbling);
+    print_score("Ball Possession Score", &report.ball_possession);
+    print_score("Passing Score", &report.passing);
+    print_score("Goal Shot Score", &report.goal_shot);
+    print_score("Total Score", &report.total());
+    print_matches("Passing", &report.passing_matches);
+    print_matches("Goal Shot", &report.goal_shot_matches);
 }
 
-fn score_passing(
-    ground_truth_labels: &[log_labels::PassingLabel],
-    predicted_labels: &[log_labels::PassingLabel],
-) -> f64 {
-    let mut score: f64 = 0.0;
-    for (ground_truth_label, predicted_label) in
-        ground_truth_labels.iter().zip(predicted_labels.iter())
-    {
-        let ground_truth_span = (
-            ground_truth_label.get_start_frame(),
-            ground_truth_label.get_end_frame(),
-        );
-        let predicted_span = (
-            predicted_label.get_start_frame(),
-            predicted_label.get_end_frame(),
-        );
-        score += calc_iou(ground_truth_span, predicted_span);
-
-        if ground_truth_label.get_successful() == predicted_label.get_successful() {
-            score += 0.5;
-        }
-
-        if ground_truth_label.get_passer_id() == predicted_label.get_passer_id() {
-            score += 0.5;
-        }
-
-        if ground_truth_label.get_passer_team() == predicted_label.get_passer_team() {
-            score += 0.5;
-        }
-
-        if ground_truth_label.get_receiver_id() == predicted_label.get_receiver_id() {
-            score += 0.5;
-        }
-    }
-    score
+fn print_score(name: &str, score: &CategoryScore) {
+    println!(
+        "{}: {} / {} ({:.1}%)",
+        name,
+        score.score,
+        score.max_score,
+        100.0 * score.ratio()
+    );
 }
 
-fn score_goal_shot(
-    ground_truth_labels: &[log_labels::GoalShotLabel],
-    predicted_labels: &[log_labels::GoalShotLabel],
-) -> f64 {
-    let mut score: f64 = 0.0;
-    for (ground_truth_label, predicted_label) in
-        ground_truth_labels.iter().zip(predicted_labels.iter())
-    {
-        let ground_truth_span = (
-            ground_truth_label.get_start_frame(),
-            ground_truth_label.get_end_frame(),
-        );
-        let predicted_span = (
-            predicted_label.get_start_frame(),
-            predicted_label.get_end_frame(),
-        );
-        score += calc_iou(ground_truth_span, predicted_span);
-
-        if ground_truth_label.get_successful() == predicted_label.get_successful() {
-            score += 0.5;
-        }
-
-        if ground_truth_label.get_shooter_id() == predicted_label.get_shooter_id() {
-            score += 0.5;
-        }
-
-        if ground_truth_label.get_shooter_team() == predicted_label.get_shooter_team() {
-            score += 0.5;
-        }
-    }
-    score
+fn print_matches(name: &str, counts: &MatchCounts) {
+    println!(
+        "{} Matches: {} true positives, {} false positives, {} misses",
+        name, counts.true_positives, counts.false_positives, counts.false_negatives
+    );
+    println!(
+        "{} Precision: {:.3} Recall: {:.3} F1: {:.3}",
+        name,
+        counts.precision(),
+        counts.recall(),
+        counts.f1()
+    );
 }
@@ -0,0 +1,106 @@
+use chrono::Utc;
+use clap::{App, Arg};
+use sha2::{Digest, Sha256};
+use ssl_rust_tools::persistence::message::{Message, MessageType};
+use ssl_rust_tools::persistence::reader::LogReader;
+use ssl_rust_tools::persistence::writer::LogWriter;
+use ssl_rust_tools::vision::{Tracker, TrackerConfig};
+use std::path::Path;
+
+// Tracker sources are told apart by a uuid that stays the same for
+// the whole run
+fn new_uuid(input: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.input(input.as_bytes());
+    hasher.input(Utc::now().to_rfc3339().as_bytes());
+    let hash = format!("{:x}", hasher.result());
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hash[0..8],
+        &hash[8..12],
+        &hash[12..16],
+        &hash[16..20],
+        &hash[20..32]
+    )
+}
+
+fn main() {
+    let matches = App::new("Track a log.")
+        .version("1.0")
+        .author("Devin Schwab <dschwab@andrew.cmu.edu>")
+        .about("Add filtered robot and ball states to a log as tracker messages")
+        .arg(
+            Arg::with_name("LOG_FILE")
+                .help("Path to the log file to track")
+                .required(true)
+                .index(1),
+        )
+        .arg(
+            Arg::with_name("OUTPUT")
+                .help("Path to write the log with tracker messages to")
+                .required(true)
+                .index(2),
+        )
+        .arg(
+            Arg::with_name("rate")
+                .long("rate")
+                .takes_value(true)
+                .help("Tracked frames per second"),
+        )
+        .arg(
+            Arg::with_name("source-name")
+                .long("source-name")
+                .takes_value(true)
+                .default_value("ssl-rust-tools")
+                .help("Name of the tracker source in the tracker messages"),
+        )
+        .arg(
+            Arg::with_name("tracker-only")
+                .long("tracker-only")
+                .help("Leave the vision messages out of the output log"),
+        )
+        .get_matches();
+
+    let mut config = TrackerConfig::default();
+    if let Some(rate) = matches.value_of("rate") {
+        config.output_rate = rate.parse().expect("Invalid rate");
+    }
+    let source_name = matches.value_of("source-name").unwrap();
+    let tracker_only = matches.is_present("tracker-only");
+
+    let input = matches.value_of("LOG_FILE").unwrap();
+    let uuid = new_uuid(input);
+    let reader = LogReader::new_from_path(Path::new(input)).expect("Failed to open log file");
+    let mut writer = LogWriter::new_from_path(Path::new(matches.value_of("OUTPUT").unwrap()))
+        .expect("Failed to create output log file");
+
+    let mut tracker = Tracker::new(&config);
+    for message in reader {
+        let message = message.expect("Failed to read log file");
+
+        // tracked frames are due before the detection that triggered
+        // them, so they are written first
+        if let MessageType::Vision2014(ref vision_msg) = message.msg_type {
+            for tracked_frame in tracker.add_packet(vision_msg) {
+                let tracker_msg = Message {
+                    timestamp: message.timestamp,
+                    msg_type: MessageType::VisionTracker2020(
+                        tracked_frame.to_tracker_packet(&uuid, source_name),
+                    ),
+                };
+                writer
+                    .write_message(&tracker_msg)
+                    .expect("Failed to write tracker message");
+            }
+
+            if tracker_only {
+                continue;
+            }
+        }
+
+        writer
+            .write_message(&message)
+            .expect("Failed to write message");
+    }
+    writer.flush().expect("Failed to write output log file");
+}
@@ -0,0 +1,45 @@
+use clap::{App, Arg};
+use ssl_rust_tools::labeler::label_file::read_labels_from_path;
+use ssl_rust_tools::labeler::reader::LabelerDataReader;
+use ssl_rust_tools::labeler::validate::validate_labels;
+use std::path::Path;
+use std::process;
+
+fn main() {
+    let matches = App::new("Validate a label file.")
+        .version("1.0")
+        .author("Devin Schwab <dschwab@andrew.cmu.edu>")
+        .about("Check a label file against the labeler data file it labels")
+        .arg(
+            Arg::with_name("LABELER_DATA_FILE")
+                .help("Path to the labeler data file")
+                .required(true)
+                .index(1),
+        )
+        .arg(
+            Arg::with_name("LABEL_FILE")
+                .help("Path to the label file")
+                .required(true)
+                .index(2),
+        )
+        .get_matches();
+
+    let reader =
+        LabelerDataReader::new_from_path(Path::new(matches.value_of("LABELER_DATA_FILE").unwrap()))
+            .expect("Failed to open labeler data file");
+
+    let labels = read_labels_from_path(Path::new(matches.value_of("LABEL_FILE").unwrap()))
+        .expect("Failed to read label file");
+
+    let issues = validate_labels(&labels, &reader).expect("Failed to read labeler data file");
+    if issues.is_empty() {
+        println!("Label file is valid.");
+        return;
+    }
+
+    for issue in &issues {
+        println!("{}", issue);
+    }
+    eprintln!("Found {} problems.", issues.len());
+    process::exit(1);
+}
@@ -0,0 +1,444 @@
+use super::point::Point;
+use super::polygon::Polygon;
+use crate::protos::messages_robocup_ssl_geometry::{
+    SSL_GeometryCameraCalibration, SSL_GeometryData,
+};
+
+// The ssl-vision camera model: a pinhole camera with a single radial
+// distortion parameter. Field coordinates are in mm, image coordinates
+// in pixels. Computations are done in f64 like ssl-vision does.
+
+// Image size of older ssl-vision setups, used when the calibration has
+// no image size and no principal point
+const DEFAULT_IMAGE_WIDTH: f64 = 780.0;
+const DEFAULT_IMAGE_HEIGHT: f64 = 580.0;
+
+// Points sampled along each image edge for the coverage polygon, so
+// that distorted edges are followed
+const COVERAGE_POINTS_PER_EDGE: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Pixel {
+    pub x: f32,
+    pub y: f32,
+}
+
+impl Pixel {
+    pub fn new(x: f32, y: f32) -> Pixel {
+        Pixel { x, y }
+    }
+}
+
+type Vector3 = [f64; 3];
+
+fn cross(a: Vector3, b: Vector3) -> Vector3 {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CameraModel {
+    pub camera_id: u32,
+    pub focal_length: f64,
+    pub principal_point_x: f64,
+    pub principal_point_y: f64,
+    pub distortion: f64,
+    // unit quaternion (x, y, z, w) rotating field into camera
+    // coordinates, q0 to q3 in the calibration message
+    pub rotation: [f64; 4],
+    // camera coordinates of the field origin
+    pub translation: Vector3,
+    pub image_width: f64,
+    pub image_height: f64,
+}
+
+impl CameraModel {
+    pub fn from_calibration(calibration: &SSL_GeometryCameraCalibration) -> CameraModel {
+        let mut rotation = [
+            f64::from(calibration.get_q0()),
+            f64::from(calibration.get_q1()),
+            f64::from(calibration.get_q2()),
+            f64::from(calibration.get_q3()),
+        ];
+        let norm = rotation.iter().map(|q| q * q).sum::<f64>().sqrt();
+        if norm > 0.0 {
+            for q in rotation.iter_mut() {
+                *q /= norm;
+            }
+        }
+
+        let principal_point_x = f64::from(calibration.get_principal_point_x());
+        let principal_point_y = f64::from(calibration.get_principal_point_y());
+        // the principal point is usually close to the image center
+        let image_size = |size: u32, principal_point: f64, default: f64| {
+            if size > 0 {
+                f64::from(size)
+            } else if principal_point > 0.0 {
+                2.0 * principal_point
+            } else {
+                default
+            }
+        };
+
+        CameraModel {
+            camera_id: calibration.get_camera_id(),
+            focal_length: f64::from(calibration.get_focal_length()),
+            principal_point_x,
+            principal_point_y,
+            distortion: f64::from(calibration.get_distortion()),
+            rotation,
+            translation: [
+                f64::from(calibration.get_tx()),
+                f64::from(calibration.get_ty()),
+                f64::from(calibration.get_tz()),
+            ],
+            image_width: image_size(
+                calibration.get_pixel_image_width(),
+                principal_point_x,
+                DEFAULT_IMAGE_WIDTH,
+            ),
+            image_height: image_size(
+                calibration.get_pixel_image_height(),
+                principal_point_y,
+                DEFAULT_IMAGE_HEIGHT,
+            ),
+        }
+    }
+
+    // All cameras of the geometry, sorted by camera id
+    pub fn from_geometry(geometry: &SSL_GeometryData) -> Vec<CameraModel> {
+        let mut cameras: Vec<CameraModel> = geometry
+            .get_calib()
+            .iter()
+            .map(CameraModel::from_calibration)
+            .collect();
+        cameras.sort_by_key(|camera| camera.camera_id);
+        cameras
+    }
+
+    fn rotate(&self, v: Vector3, inverse: bool) -> Vector3 {
+        let [x, y, z, w] = self.rotation;
+        let u = if inverse { [-x, -y, -z] } else { [x, y, z] };
+        // v + 2w (u x v) + 2 u x (u x v)
+        let uv = cross(u, v);
+        let uuv = cross(u, uv);
+        [
+            v[0] + 2.0 * (w * uv[0] + uuv[0]),
+            v[1] + 2.0 * (w * uv[1] + uuv[1]),
+            v[2] + 2.0 * (w * uv[2] + uuv[2]),
+        ]
+    }
+
+    // Distorted radius of an undistorted radius, the inverse of
+    // `undistort_radius` solved in closed form like ssl-vision does
+    fn distort_radius(&self, ru: f64) -> f64 {
+        let a = self.distortion;
+        if a <= std::f64::MIN_POSITIVE {
+            return ru;
+        }
+        let b = -9.0 * a * a * ru + a * (a * (12.0 + 81.0 * a * ru * ru)).sqrt();
+        let b = b.signum() * b.abs().cbrt();
+        (2.0f64 / 3.0).cbrt() / b - b / (18.0f64.cbrt() * a)
+    }
+
+    fn undistort_radius(&self, rd: f64) -> f64 {
+        rd * (1.0 + rd * rd * self.distortion)
+    }
+
+    // Camera position in field coordinates
+    pub fn position(&self) -> Vector3 {
+        let [tx, ty, tz] = self.translation;
+        self.rotate([-tx, -ty, -tz], true)
+    }
+
+    // Pixel a point at height `z` mm above the field is seen at. None
+    // if the point is behind the camera. The pixel can be outside of
+    // the image.
+    pub fn field_to_image(&self, point: Point, z: f32) -> Option<Pixel> {
+        let rotated = self.rotate(
+            [f64::from(point.x), f64::from(point.y), f64::from(z)],
+            false,
+        );
+        let camera = [
+            rotated[0] + self.translation[0],
+            rotated[1] + self.translation[1],
+            rotated[2] + self.translation[2],
+        ];
+        if camera[2] <= 0.0 {
+            return None;
+        }
+
+        let (ux, uy) = (camera[0] / camera[2], camera[1] / camera[2]);
+        let ru = ux.hypot(uy);
+        let scale = if ru > 0.0 {
+            self.distort_radius(ru) / ru
+        } else {
+            1.0
+        };
+        Some(Pixel::new(
+            (self.focal_length * ux * scale + self.principal_point_x) as f32,
+            (self.focal_length * uy * scale + self.principal_point_y) as f32,
+        ))
+    }
+
+    // Point at height `z` mm above the field seen at `pixel`. None if
+    // the ray through the pixel does not reach that height in front of
+    // the camera.
+    pub fn image_to_field(&self, pixel: Pixel, z: f32) -> Option<Point> {
+        let dx = (f64::from(pixel.x) - self.principal_point_x) / self.focal_length;
+        let dy = (f64::from(pixel.y) - self.principal_point_y) / self.focal_length;
+        let rd = dx.hypot(dy);
+        let scale = if rd > 0.0 {
+            self.undistort_radius(rd) / rd
+        } else {
+            1.0
+        };
+
+        let ray = self.rotate([dx * scale, dy * scale, 1.0], true);
+        let origin = self.position();
+        if ray[2] == 0.0 {
+            return None;
+        }
+        let t = (f64::from(z) - origin[2]) / ray[2];
+        if t <= 0.0 {
+            return None;
+        }
+        Some(Point::new(
+            (origin[0] + t * ray[0]) as f32,
+            (origin[1] + t * ray[1]) as f32,
+        ))
+    }
+
+    pub fn is_in_image(&self, pixel: Pixel) -> bool {
+        let (x, y) = (f64::from(pixel.x), f64::from(pixel.y));
+        x >= 0.0 && x <= self.image_width && y >= 0.0 && y <= self.image_height
+    }
+
+    // True if the camera sees the point at height `z`
+    pub fn sees(&self, point: Point, z: f32) -> bool {
+        self.field_to_image(point, z)
+            .map_or(false, |pixel| self.is_in_image(pixel))
+    }
+
+    // The part of the plane at height `z` the image covers, found by
+    // projecting points along the image border. Border points whose
+    // rays do not reach the plane, like the horizon of a tilted
+    // camera, are left out.
+    pub fn coverage(&self, z: f32) -> Polygon {
+        let (w, h) = (self.image_width, self.image_height);
+        let corners = [(0.0, 0.0), (w, 0.0), (w, h), (0.0, h)];
+        let mut points = vec![];
+        for (i, &(x1, y1)) in corners.iter().enumerate() {
+            let (x2, y2) = corners[(i + 1) % corners.len()];
+            for step in 0..COVERAGE_POINTS_PER_EDGE {
+                let t = step as f64 / COVERAGE_POINTS_PER_EDGE as f64;
+                let pixel = Pixel::new((x1 + t * (x2 - x1)) as f32, (y1 + t * (y2 - y1)) as f32);
+                if let Some(point) = self.image_to_field(pixel, z) {
+                    points.push(point);
+                }
+            }
+        }
+        Polygon::new(points)
+    }
+}
+
+// Area seen by both cameras at height `z`. Radial distortion bends
+// the coverage edges inwards, so the second coverage is approximated
+// by its convex hull. The overlap can be slightly too large where the
+// edges bend, but no area seen by both cameras is cut off.
+pub fn coverage_overlap(first: &CameraModel, second: &CameraModel, z: f32) -> Polygon {
+    first.coverage(z).clip(&second.coverage(z).convex_hull())
+}
+
+// Overlap of every pair of cameras that see a common area, by camera
+// ids. Approximated like `coverage_overlap`.
+pub fn camera_overlaps(cameras: &[CameraModel], z: f32) -> Vec<(u32, u32, Polygon)> {
+    let coverages: Vec<Polygon> = cameras.iter().map(|camera| camera.coverage(z)).collect();
+    let hulls: Vec<Polygon> = coverages.iter().map(Polygon::convex_hull).collect();
+    let mut overlaps = vec![];
+    for (i, first) in cameras.iter().enumerate() {
+        for (j, second) in cameras.iter().enumerate().skip(i + 1) {
+            let overlap = coverages[i].clip(&hulls[j]);
+            if !overlap.is_empty() {
+                overlaps.push((first.camera_id, second.camera_id, overlap));
+            }
+        }
+    }
+    overlaps
+}
+
+// Distance from the point to the nearest edge of the coverage of any
+// camera that sees it, None if no camera sees it. Small distances mean
+// the point is at a camera boundary.
+pub fn distance_to_camera_boundary(cameras: &[CameraModel], point: Point, z: f32) -> Option<f32> {
+    cameras
+        .iter()
+        .map(|camera| camera.coverage(z))
+        .filter(|coverage| coverage.contains(point))
+        .map(|coverage| coverage.distance_to_boundary(point))
+        .fold(None, |closest: Option<f32>, distance| {
+            Some(closest.map_or(distance, |closest| closest.min(distance)))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    // camera `height` mm above (x, y) looking straight down
+    fn overhead_camera(
+        camera_id: u32,
+        x: f32,
+        y: f32,
+        height: f32,
+        distortion: f32,
+    ) -> CameraModel {
+        let mut calibration = SSL_GeometryCameraCalibration::new();
+        calibration.set_camera_id(camera_id);
+        calibration.set_focal_length(500.0);
+        calibration.set_principal_point_x(390.0);
+        calibration.set_principal_point_y(290.0);
+        calibration.set_distortion(distortion);
+        // 180 degrees around the x axis
+        calibration.set_q0(1.0);
+        calibration.set_q1(0.0);
+        calibration.set_q2(0.0);
+        calibration.set_q3(0.0);
+        calibration.set_tx(-x);
+        calibration.set_ty(y);
+        calibration.set_tz(height);
+        CameraModel::from_calibration(&calibration)
+    }
+
+    proptest! {
+        #[test]
+        fn projection_round_trip(x in -3000.0f32..3000.0,
+                                 y in -2000.0f32..2000.0,
+                                 z in 0.0f32..150.0,
+                                 distortion in 0.0f32..0.2,
+                                 q in prop::array::uniform4(-1.0f32..1.0)) {
+            let mut camera = overhead_camera(0, 0.0, 0.0, 4000.0, distortion);
+            // tilt the camera a little
+            let tilt = [q[0] * 0.1, q[1] * 0.1, q[2] * 0.1, 0.0];
+            let mut rotation = camera.rotation;
+            for (r, t) in rotation.iter_mut().zip(&tilt) {
+                *r += f64::from(*t);
+            }
+            let norm = rotation.iter().map(|q| q * q).sum::<f64>().sqrt();
+            camera.rotation = [rotation[0] / norm, rotation[1] / norm, rotation[2] / norm, rotation[3] / norm];
+
+            let point = Point::new(x, y);
+            let pixel = camera.field_to_image(point, z).unwrap();
+            let back = camera.image_to_field(pixel, z).unwrap();
+            prop_assert!(back.distance(point) < 1.0);
+        }
+
+        #[test]
+        fn distortion_round_trip(r in 0.0f64..2.0, distortion in 0.001f64..0.5) {
+            let mut camera = overhead_camera(0, 0.0, 0.0, 4000.0, 0.0);
+            camera.distortion = distortion;
+            let rd = camera.distort_radius(r);
+            prop_assert!((camera.undistort_radius(rd) - r).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn overhead_camera_projection() {
+        let camera = overhead_camera(0, 1000.0, -500.0, 4000.0, 0.0);
+        let position = camera.position();
+        assert!((position[0] - 1000.0).abs() < 1e-6);
+        assert!((position[1] + 500.0).abs() < 1e-6);
+        assert!((position[2] - 4000.0).abs() < 1e-6);
+
+        assert_eq!(
+            camera.field_to_image(Point::new(1000.0, -500.0), 0.0),
+            Some(Pixel::new(390.0, 290.0))
+        );
+        // 800 mm in x is 100 pixels at 4 m with a focal length of 500
+        assert_eq!(
+            camera.field_to_image(Point::new(1800.0, -500.0), 0.0),
+            Some(Pixel::new(490.0, 290.0))
+        );
+        // the image y axis points towards negative field y
+        assert_eq!(
+            camera.field_to_image(Point::new(1000.0, 300.0), 0.0),
+            Some(Pixel::new(390.0, 190.0))
+        );
+        // above the camera
+        assert_eq!(camera.field_to_image(Point::new(0.0, 0.0), 5000.0), None);
+    }
+
+    #[test]
+    fn overhead_camera_coverage() {
+        let camera = overhead_camera(0, 0.0, 0.0, 4000.0, 0.0);
+        let coverage = camera.coverage(0.0);
+        // 780 x 580 pixels at 8 mm per pixel
+        assert!((coverage.area() - 6240.0 * 4640.0).abs() < 1000.0);
+        assert!(coverage.contains(Point::new(3000.0, 2000.0)));
+        assert!(!coverage.contains(Point::new(3200.0, 0.0)));
+        assert!(camera.sees(Point::new(3000.0, 2000.0), 0.0));
+        assert!(!camera.sees(Point::new(3200.0, 0.0), 0.0));
+
+        // barrel distortion shows more of the field
+        let distorted = overhead_camera(0, 0.0, 0.0, 4000.0, 0.1).coverage(0.0);
+        assert!(distorted.area() > coverage.area());
+    }
+
+    #[test]
+    fn neighbouring_cameras_overlap() {
+        let cameras = vec![
+            overhead_camera(0, -3000.0, 0.0, 4000.0, 0.0),
+            overhead_camera(1, 3000.0, 0.0, 4000.0, 0.0),
+            overhead_camera(2, 20000.0, 0.0, 4000.0, 0.0),
+        ];
+
+        let overlaps = camera_overlaps(&cameras, 0.0);
+        assert_eq!(overlaps.len(), 1);
+        let (first, second, ref overlap) = overlaps[0];
+        assert_eq!((first, second), (0, 1));
+        // each camera sees 3120 mm to each side, so they share 240 mm
+        assert!((overlap.area() - 240.0 * 4640.0).abs() < 1000.0);
+
+        assert!(
+            (distance_to_camera_boundary(&cameras, Point::new(0.0, 0.0), 0.0).unwrap() - 120.0)
+                .abs()
+                < 1.0
+        );
+        assert_eq!(
+            distance_to_camera_boundary(&cameras, Point::new(0.0, 5000.0), 0.0),
+            None
+        );
+    }
+
+    #[test]
+    fn distorted_cameras_overlap() {
+        let cameras = vec![
+            overhead_camera(0, -3000.0, 0.0, 4000.0, 0.2),
+            overhead_camera(1, 3000.0, 0.0, 4000.0, 0.2),
+        ];
+        let overlaps = camera_overlaps(&cameras, 0.0);
+        assert_eq!(overlaps.len(), 1);
+        let overlap = &overlaps[0].2;
+        assert_eq!(coverage_overlap(&cameras[0], &cameras[1], 0.0), *overlap);
+
+        // every point both cameras see is in the overlap, and the
+        // overlap is not much larger than the area they both see.
+        // Clipping with the bent coverage itself cuts off half of it.
+        let mut num_seen = 0;
+        for i in -100..=100 {
+            for j in -100..=100 {
+                let point = Point::new(i as f32 * 20.0, j as f32 * 40.0);
+                if cameras.iter().all(|camera| camera.sees(point, 0.0)) {
+                    num_seen += 1;
+                    assert!(overlap.contains(point));
+                }
+            }
+        }
+        let seen_area = num_seen as f32 * 20.0 * 40.0;
+        assert!(overlap.area() < 1.2 * seen_area);
+    }
+}
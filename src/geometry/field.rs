@@ -0,0 +1,534 @@
+use super::point::Point;
+use crate::protos::messages_robocup_ssl_geometry::{
+    SSL_FieldCicularArc, SSL_FieldLineSegment, SSL_GeometryData, SSL_GeometryFieldSize,
+};
+use std::f32::consts::PI;
+
+// Field model in the ssl-vision coordinate system: mm, the center
+// circle at the origin, the goals on the x axis and the left half at
+// negative x.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Division {
+    A,
+    B,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FieldSide {
+    // negative x
+    Left,
+    // positive x
+    Right,
+}
+
+impl FieldSide {
+    pub fn opposite(self) -> FieldSide {
+        match self {
+            FieldSide::Left => FieldSide::Right,
+            FieldSide::Right => FieldSide::Left,
+        }
+    }
+
+    // sign of x on this side
+    pub fn sign(self) -> f32 {
+        match self {
+            FieldSide::Left => -1.0,
+            FieldSide::Right => 1.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldLine {
+    pub name: String,
+    pub p1: Point,
+    pub p2: Point,
+    pub thickness: f32,
+}
+
+impl FieldLine {
+    fn new(name: &str, p1: Point, p2: Point, thickness: f32) -> FieldLine {
+        FieldLine {
+            name: name.to_owned(),
+            p1,
+            p2,
+            thickness,
+        }
+    }
+
+    fn from_proto(line: &SSL_FieldLineSegment) -> FieldLine {
+        FieldLine {
+            name: line.get_name().to_owned(),
+            p1: Point::new(line.get_p1().get_x(), line.get_p1().get_y()),
+            p2: Point::new(line.get_p2().get_x(), line.get_p2().get_y()),
+            thickness: line.get_thickness(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldArc {
+    pub name: String,
+    pub center: Point,
+    pub radius: f32,
+    // start and end angle, counter-clockwise
+    pub a1: f32,
+    pub a2: f32,
+    pub thickness: f32,
+}
+
+impl FieldArc {
+    fn from_proto(arc: &SSL_FieldCicularArc) -> FieldArc {
+        FieldArc {
+            name: arc.get_name().to_owned(),
+            center: Point::new(arc.get_center().get_x(), arc.get_center().get_y()),
+            radius: arc.get_radius(),
+            a1: arc.get_a1(),
+            a2: arc.get_a2(),
+            thickness: arc.get_thickness(),
+        }
+    }
+}
+
+// Where a ball path crosses a goal line
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GoalLineCrossing {
+    pub side: FieldSide,
+    pub point: Point,
+    // between the goal posts
+    pub is_goal: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Field {
+    pub field_length: f32,
+    pub field_width: f32,
+    pub goal_width: f32,
+    pub goal_depth: f32,
+    pub boundary_width: f32,
+    pub penalty_area_depth: f32,
+    pub penalty_area_width: f32,
+    pub center_circle_radius: f32,
+    pub line_thickness: f32,
+    pub lines: Vec<FieldLine>,
+    pub arcs: Vec<FieldArc>,
+}
+
+impl Field {
+    // Standard field of the division in the 2019 rules
+    pub fn new(division: Division) -> Field {
+        let mut field = match division {
+            Division::A => Field {
+                field_length: 12000.0,
+                field_width: 9000.0,
+                goal_width: 1200.0,
+                goal_depth: 180.0,
+                boundary_width: 300.0,
+                penalty_area_depth: 1200.0,
+                penalty_area_width: 2400.0,
+                center_circle_radius: 500.0,
+                line_thickness: 10.0,
+                lines: vec![],
+                arcs: vec![],
+            },
+            Division::B => Field {
+                field_length: 9000.0,
+                field_width: 6000.0,
+                goal_width: 1000.0,
+                goal_depth: 180.0,
+                boundary_width: 300.0,
+                penalty_area_depth: 1000.0,
+                penalty_area_width: 2000.0,
+                center_circle_radius: 500.0,
+                line_thickness: 10.0,
+                lines: vec![],
+                arcs: vec![],
+            },
+        };
+        field.lines = field.standard_lines();
+        field.arcs = field.standard_arcs();
+        field
+    }
+
+    // Sizes missing from the message are taken from the field lines if
+    // possible and from the standard field of `fallback` otherwise. A
+    // message without a field size gives the standard field.
+    pub fn from_field_size(field_size: &SSL_GeometryFieldSize, fallback: Division) -> Field {
+        let standard = Field::new(fallback);
+        if field_size.get_field_length() <= 0 || field_size.get_field_width() <= 0 {
+            return standard;
+        }
+
+        let lines: Vec<FieldLine> = field_size
+            .get_field_lines()
+            .iter()
+            .map(FieldLine::from_proto)
+            .collect();
+        let arcs: Vec<FieldArc> = field_size
+            .get_field_arcs()
+            .iter()
+            .map(FieldArc::from_proto)
+            .collect();
+
+        let positive = |value: i32| if value > 0 { Some(value as f32) } else { None };
+        let field_length = field_size.get_field_length() as f32;
+        // ssl-vision sent the penalty area only as lines before 2018
+        let penalty_stretch = lines.iter().find(|line| line.name == "LeftPenaltyStretch");
+
+        let mut field = Field {
+            field_length,
+            field_width: field_size.get_field_width() as f32,
+            goal_width: positive(field_size.get_goal_width()).unwrap_or(standard.goal_width),
+            goal_depth: positive(field_size.get_goal_depth()).unwrap_or(standard.goal_depth),
+            boundary_width: positive(field_size.get_boundary_width())
+                .unwrap_or(standard.boundary_width),
+            penalty_area_depth: positive(field_size.get_penalty_area_depth())
+                .or_else(|| penalty_stretch.map(|line| field_length / 2.0 - line.p1.x.abs()))
+                .unwrap_or(standard.penalty_area_depth),
+            penalty_area_width: positive(field_size.get_penalty_area_width())
+                .or_else(|| penalty_stretch.map(|line| (line.p1.y - line.p2.y).abs()))
+                .unwrap_or(standard.penalty_area_width),
+            center_circle_radius: positive(field_size.get_center_circle_radius())
+                .or_else(|| {
+                    arcs.iter()
+                        .find(|arc| arc.name == "CenterCircle")
+                        .map(|arc| arc.radius)
+                })
+                .unwrap_or(standard.center_circle_radius),
+            line_thickness: positive(field_size.get_line_thickness())
+                .unwrap_or(standard.line_thickness),
+            lines,
+            arcs,
+        };
+        if field.lines.is_empty() {
+            field.lines = field.standard_lines();
+        }
+        if field.arcs.is_empty() {
+            field.arcs = field.standard_arcs();
+        }
+        field
+    }
+
+    // For logs and labeler data files that may lack geometry
+    pub fn from_geometry(geometry: Option<&SSL_GeometryData>, fallback: Division) -> Field {
+        match geometry {
+            Some(geometry) if geometry.has_field() => {
+                Field::from_field_size(geometry.get_field(), fallback)
+            }
+            _ => Field::new(fallback),
+        }
+    }
+
+    // Lines named as ssl-vision names them
+    fn standard_lines(&self) -> Vec<FieldLine> {
+        let (l, w) = (self.half_length(), self.half_width());
+        let (d, p) = (self.penalty_area_depth, self.penalty_area_width / 2.0);
+        let t = self.line_thickness;
+        vec![
+            FieldLine::new("TopTouchLine", Point::new(-l, w), Point::new(l, w), t),
+            FieldLine::new("BottomTouchLine", Point::new(-l, -w), Point::new(l, -w), t),
+            FieldLine::new("LeftGoalLine", Point::new(-l, -w), Point::new(-l, w), t),
+            FieldLine::new("RightGoalLine", Point::new(l, -w), Point::new(l, w), t),
+            FieldLine::new("HalfwayLine", Point::new(0.0, -w), Point::new(0.0, w), t),
+            FieldLine::new("CenterLine", Point::new(-l, 0.0), Point::new(l, 0.0), t),
+            FieldLine::new(
+                "LeftPenaltyStretch",
+                Point::new(-l + d, -p),
+                Point::new(-l + d, p),
+                t,
+            ),
+            FieldLine::new(
+                "RightPenaltyStretch",
+                Point::new(l - d, -p),
+                Point::new(l - d, p),
+                t,
+            ),
+            FieldLine::new(
+                "LeftFieldLeftPenaltyStretch",
+                Point::new(-l, -p),
+                Point::new(-l + d, -p),
+                t,
+            ),
+            FieldLine::new(
+                "LeftFieldRightPenaltyStretch",
+                Point::new(-l, p),
+                Point::new(-l + d, p),
+                t,
+            ),
+            FieldLine::new(
+                "RightFieldLeftPenaltyStretch",
+                Point::new(l, -p),
+                Point::new(l - d, -p),
+                t,
+            ),
+            FieldLine::new(
+                "RightFieldRightPenaltyStretch",
+                Point::new(l, p),
+                Point::new(l - d, p),
+                t,
+            ),
+        ]
+    }
+
+    fn standard_arcs(&self) -> Vec<FieldArc> {
+        vec![FieldArc {
+            name: "CenterCircle".to_owned(),
+            center: Point::new(0.0, 0.0),
+            radius: self.center_circle_radius,
+            a1: 0.0,
+            a2: 2.0 * PI,
+            thickness: self.line_thickness,
+        }]
+    }
+
+    pub fn line(&self, name: &str) -> Option<&FieldLine> {
+        self.lines.iter().find(|line| line.name == name)
+    }
+
+    pub fn arc(&self, name: &str) -> Option<&FieldArc> {
+        self.arcs.iter().find(|arc| arc.name == name)
+    }
+
+    pub fn half_length(&self) -> f32 {
+        self.field_length / 2.0
+    }
+
+    pub fn half_width(&self) -> f32 {
+        self.field_width / 2.0
+    }
+
+    // On or inside the touch and goal lines
+    pub fn is_inside_field(&self, point: Point) -> bool {
+        point.x.abs() <= self.half_length() && point.y.abs() <= self.half_width()
+    }
+
+    // On the field or the boundary around it
+    pub fn is_inside_boundary(&self, point: Point) -> bool {
+        point.x.abs() <= self.half_length() + self.boundary_width
+            && point.y.abs() <= self.half_width() + self.boundary_width
+    }
+
+    // Points on the halfway line are in the right half
+    pub fn half(&self, point: Point) -> FieldSide {
+        if point.x < 0.0 {
+            FieldSide::Left
+        } else {
+            FieldSide::Right
+        }
+    }
+
+    pub fn is_in_half(&self, point: Point, side: FieldSide) -> bool {
+        self.half(point) == side
+    }
+
+    pub fn goal_center(&self, side: FieldSide) -> Point {
+        Point::new(side.sign() * self.half_length(), 0.0)
+    }
+
+    // the post at negative y first
+    pub fn goal_posts(&self, side: FieldSide) -> (Point, Point) {
+        let x = side.sign() * self.half_length();
+        let y = self.goal_width / 2.0;
+        (Point::new(x, -y), Point::new(x, y))
+    }
+
+    // The penalty area in front of the goal on `side`, including its
+    // lines
+    pub fn is_in_penalty_area(&self, point: Point, side: FieldSide) -> bool {
+        let x = side.sign() * point.x;
+        x <= self.half_length()
+            && x >= self.half_length() - self.penalty_area_depth
+            && point.y.abs() <= self.penalty_area_width / 2.0
+    }
+
+    // Distance to the closest point of the goal mouth on `side`
+    pub fn distance_to_goal(&self, point: Point, side: FieldSide) -> f32 {
+        let (post1, post2) = self.goal_posts(side);
+        point.distance_to_segment(post1, post2)
+    }
+
+    // Where the path from `start` to `end` first crosses a goal line,
+    // if it does. Paths starting on or behind a goal line do not cross
+    // it.
+    pub fn goal_line_crossing(&self, start: Point, end: Point) -> Option<GoalLineCrossing> {
+        let half_length = self.half_length();
+        if start.x.abs() >= half_length || end.x.abs() < half_length {
+            return None;
+        }
+
+        let side = if end.x > 0.0 {
+            FieldSide::Right
+        } else {
+            FieldSide::Left
+        };
+        let goal_line_x = side.sign() * half_length;
+        let fraction = (goal_line_x - start.x) / (end.x - start.x);
+        let point = Point::new(goal_line_x, start.y + fraction * (end.y - start.y));
+        if point.y.abs() > self.half_width() {
+            // left the field over a touch line first
+            return None;
+        }
+
+        Some(GoalLineCrossing {
+            side,
+            point,
+            is_goal: point.y.abs() <= self.goal_width / 2.0,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protos::messages_robocup_ssl_geometry::Vector2f;
+    use proptest::prelude::*;
+
+    fn proto_line(name: &str, p1: (f32, f32), p2: (f32, f32)) -> SSL_FieldLineSegment {
+        let mut line = SSL_FieldLineSegment::new();
+        line.set_name(name.to_owned());
+        let mut start = Vector2f::new();
+        start.set_x(p1.0);
+        start.set_y(p1.1);
+        line.set_p1(start);
+        let mut end = Vector2f::new();
+        end.set_x(p2.0);
+        end.set_y(p2.1);
+        line.set_p2(end);
+        line.set_thickness(10.0);
+        line
+    }
+
+    fn field_size(length: i32, width: i32) -> SSL_GeometryFieldSize {
+        let mut field_size = SSL_GeometryFieldSize::new();
+        field_size.set_field_length(length);
+        field_size.set_field_width(width);
+        field_size.set_goal_width(1000);
+        field_size.set_goal_depth(180);
+        field_size.set_boundary_width(250);
+        field_size
+    }
+
+    proptest! {
+        #[test]
+        fn penalty_area_is_inside_own_half(x in -6000.0f32..6000.0, y in -4500.0f32..4500.0) {
+            let field = Field::new(Division::A);
+            let point = Point::new(x, y);
+            for &side in &[FieldSide::Left, FieldSide::Right] {
+                if field.is_in_penalty_area(point, side) {
+                    prop_assert!(field.is_in_half(point, side));
+                    prop_assert!(field.is_inside_field(point));
+                    prop_assert!(!field.is_in_penalty_area(point, side.opposite()));
+                }
+            }
+        }
+
+        #[test]
+        fn straight_shots_cross_the_goal_line(y in -1000.0f32..1000.0, right in proptest::bool::ANY) {
+            let field = Field::new(Division::A);
+            let sign = if right { 1.0 } else { -1.0 };
+            let crossing = field
+                .goal_line_crossing(Point::new(sign * 5000.0, y), Point::new(sign * 6500.0, y))
+                .unwrap();
+
+            prop_assert_eq!(crossing.side, if right { FieldSide::Right } else { FieldSide::Left });
+            prop_assert_eq!(crossing.point, Point::new(sign * 6000.0, y));
+            prop_assert_eq!(crossing.is_goal, y.abs() <= 600.0);
+        }
+    }
+
+    #[test]
+    fn standard_fields() {
+        let a = Field::new(Division::A);
+        assert_eq!(a.field_length, 12000.0);
+        assert_eq!(a.penalty_area_width, 2400.0);
+        assert_eq!(
+            a.line("RightPenaltyStretch").unwrap().p1,
+            Point::new(4800.0, -1200.0)
+        );
+        assert_eq!(a.arc("CenterCircle").unwrap().radius, 500.0);
+
+        let b = Field::new(Division::B);
+        assert_eq!(b.field_width, 6000.0);
+        assert_eq!(b.goal_posts(FieldSide::Left).1, Point::new(-4500.0, 500.0));
+    }
+
+    #[test]
+    fn penalty_area_from_field_size() {
+        let mut size = field_size(9000, 6000);
+        size.set_penalty_area_depth(800);
+        size.set_penalty_area_width(1600);
+        let field = Field::from_field_size(&size, Division::A);
+        assert_eq!(field.penalty_area_depth, 800.0);
+        assert_eq!(field.penalty_area_width, 1600.0);
+        assert_eq!(field.boundary_width, 250.0);
+        // no lines in the message
+        assert_eq!(
+            field.line("LeftPenaltyStretch").unwrap().p1,
+            Point::new(-3700.0, -800.0)
+        );
+    }
+
+    #[test]
+    fn penalty_area_from_lines() {
+        let mut size = field_size(9000, 6000);
+        size.mut_field_lines().push(proto_line(
+            "LeftPenaltyStretch",
+            (-3500.0, -1000.0),
+            (-3500.0, 1000.0),
+        ));
+        let field = Field::from_field_size(&size, Division::A);
+        assert_eq!(field.penalty_area_depth, 1000.0);
+        assert_eq!(field.penalty_area_width, 2000.0);
+        assert_eq!(field.lines.len(), 1);
+        // no arcs in the message
+        assert_eq!(field.arc("CenterCircle").unwrap().radius, 500.0);
+    }
+
+    #[test]
+    fn missing_geometry_falls_back() {
+        assert_eq!(
+            Field::from_geometry(None, Division::B),
+            Field::new(Division::B)
+        );
+        assert_eq!(
+            Field::from_field_size(&SSL_GeometryFieldSize::new(), Division::A),
+            Field::new(Division::A)
+        );
+    }
+
+    #[test]
+    fn queries() {
+        let field = Field::new(Division::A);
+
+        assert!(field.is_inside_field(Point::new(6000.0, -4500.0)));
+        assert!(!field.is_inside_field(Point::new(6001.0, 0.0)));
+        assert!(field.is_inside_boundary(Point::new(6200.0, 0.0)));
+        assert!(!field.is_inside_boundary(Point::new(0.0, 4900.0)));
+
+        assert_eq!(field.half(Point::new(-1.0, 0.0)), FieldSide::Left);
+        assert_eq!(field.half(Point::new(0.0, 0.0)), FieldSide::Right);
+
+        assert!(field.is_in_penalty_area(Point::new(-5000.0, 1000.0), FieldSide::Left));
+        assert!(!field.is_in_penalty_area(Point::new(-4700.0, 0.0), FieldSide::Left));
+        assert!(!field.is_in_penalty_area(Point::new(-5000.0, 1300.0), FieldSide::Left));
+
+        assert_eq!(
+            field.distance_to_goal(Point::new(5000.0, 0.0), FieldSide::Right),
+            1000.0
+        );
+        assert_eq!(
+            field.distance_to_goal(Point::new(6000.0, 1600.0), FieldSide::Right),
+            1000.0
+        );
+
+        // path leaving over the touch line
+        assert!(field
+            .goal_line_crossing(Point::new(5000.0, 4000.0), Point::new(7000.0, 6000.0))
+            .is_none());
+        // path starting behind the goal line
+        assert!(field
+            .goal_line_crossing(Point::new(6100.0, 0.0), Point::new(6500.0, 0.0))
+            .is_none());
+    }
+}
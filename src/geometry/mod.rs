@@ -0,0 +1,9 @@
+pub mod camera;
+pub mod field;
+pub mod point;
+pub mod polygon;
+
+pub use camera::{CameraModel, Pixel};
+pub use field::{Division, Field, FieldArc, FieldLine, FieldSide, GoalLineCrossing};
+pub use point::Point;
+pub use polygon::Polygon;
@@ -0,0 +1,63 @@
+use std::ops::{Add, Mul, Sub};
+
+// A point or vector on the field in mm
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Point {
+    pub x: f32,
+    pub y: f32,
+}
+
+impl Point {
+    pub fn new(x: f32, y: f32) -> Point {
+        Point { x, y }
+    }
+
+    pub fn norm(self) -> f32 {
+        self.x.hypot(self.y)
+    }
+
+    pub fn distance(self, other: Point) -> f32 {
+        (self - other).norm()
+    }
+
+    pub fn dot(self, other: Point) -> f32 {
+        self.x * other.x + self.y * other.y
+    }
+
+    // Distance to the segment from `start` to `end`
+    pub fn distance_to_segment(self, start: Point, end: Point) -> f32 {
+        let direction = end - start;
+        let length_squared = direction.dot(direction);
+        if length_squared == 0.0 {
+            return self.distance(start);
+        }
+        let t = ((self - start).dot(direction) / length_squared)
+            .max(0.0)
+            .min(1.0);
+        self.distance(start + direction * t)
+    }
+}
+
+impl Add for Point {
+    type Output = Point;
+
+    fn add(self, other: Point) -> Point {
+        Point::new(self.x + other.x, self.y + other.y)
+    }
+}
+
+impl Sub for Point {
+    type Output = Point;
+
+    fn sub(self, other: Point) -> Point {
+        Point::new(self.x - other.x, self.y - other.y)
+    }
+}
+
+impl Mul<f32> for Point {
+    type Output = Point;
+
+    fn mul(self, factor: f32) -> Point {
+        Point::new(self.x * factor, self.y * factor)
+    }
+}
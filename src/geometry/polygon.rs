@@ -0,0 +1,233 @@
+use super::point::Point;
+use std::cmp::Ordering;
+
+// A simple polygon. The points are in counter-clockwise order; the
+// last point connects back to the first.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Polygon {
+    pub points: Vec<Point>,
+}
+
+// z of the cross product of (b - a) and (c - a), positive if c is
+// left of the line from a to b
+fn cross(a: Point, b: Point, c: Point) -> f32 {
+    (b.x - a.x) * (c.y - a.y) - (b.y - a.y) * (c.x - a.x)
+}
+
+impl Polygon {
+    // Points in clockwise order are reversed
+    pub fn new(mut points: Vec<Point>) -> Polygon {
+        let signed_area = Polygon::signed_area_of(&points);
+        if signed_area < 0.0 {
+            points.reverse();
+        }
+        Polygon { points }
+    }
+
+    fn signed_area_of(points: &[Point]) -> f32 {
+        let mut area = 0.0;
+        for (i, a) in points.iter().enumerate() {
+            let b = points[(i + 1) % points.len()];
+            area += a.x * b.y - b.x * a.y;
+        }
+        area / 2.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.len() < 3
+    }
+
+    pub fn area(&self) -> f32 {
+        if self.is_empty() {
+            0.0
+        } else {
+            Polygon::signed_area_of(&self.points).abs()
+        }
+    }
+
+    fn edges<'a>(&'a self) -> impl Iterator<Item = (Point, Point)> + 'a {
+        let num_points = self.points.len();
+        self.points
+            .iter()
+            .enumerate()
+            .map(move |(i, &a)| (a, self.points[(i + 1) % num_points]))
+    }
+
+    // Points on an edge are inside
+    pub fn contains(&self, point: Point) -> bool {
+        if self.is_empty() {
+            return false;
+        }
+        if self.distance_to_boundary(point) == 0.0 {
+            return true;
+        }
+
+        let mut inside = false;
+        for (a, b) in self.edges() {
+            if (a.y > point.y) != (b.y > point.y) {
+                let x = a.x + (point.y - a.y) / (b.y - a.y) * (b.x - a.x);
+                if point.x < x {
+                    inside = !inside;
+                }
+            }
+        }
+        inside
+    }
+
+    // Distance to the closest edge, for points inside and outside
+    pub fn distance_to_boundary(&self, point: Point) -> f32 {
+        self.edges()
+            .map(|(a, b)| point.distance_to_segment(a, b))
+            .fold(std::f32::INFINITY, f32::min)
+    }
+
+    // Smallest convex polygon containing all points
+    pub fn convex_hull(&self) -> Polygon {
+        let mut points = self.points.clone();
+        points.sort_by(|a, b| {
+            a.x.partial_cmp(&b.x)
+                .unwrap_or(Ordering::Equal)
+                .then(a.y.partial_cmp(&b.y).unwrap_or(Ordering::Equal))
+        });
+        points.dedup();
+        if points.len() < 3 {
+            return Polygon::default();
+        }
+
+        // lower hull left to right, then upper hull right to left
+        let mut hull: Vec<Point> = vec![];
+        for pass in 0..2 {
+            let start = hull.len();
+            for &point in &points {
+                while hull.len() >= start + 2
+                    && cross(hull[hull.len() - 2], hull[hull.len() - 1], point) <= 0.0
+                {
+                    hull.pop();
+                }
+                hull.push(point);
+            }
+            // the last point is the first of the other pass
+            hull.pop();
+            if pass == 0 {
+                points.reverse();
+            }
+        }
+
+        if hull.len() < 3 {
+            Polygon::default()
+        } else {
+            Polygon { points: hull }
+        }
+    }
+
+    // Intersection with a convex polygon, by clipping this polygon with
+    // each edge of `convex` in turn. Clipping with a non-convex polygon
+    // cuts away too much, so clip with its `convex_hull` instead.
+    pub fn clip(&self, convex: &Polygon) -> Polygon {
+        let mut points = self.points.clone();
+        for (a, b) in convex.edges() {
+            if points.is_empty() {
+                break;
+            }
+            let input = points;
+            points = vec![];
+            for (i, &current) in input.iter().enumerate() {
+                let previous = input[(i + input.len() - 1) % input.len()];
+                let current_inside = cross(a, b, current) >= 0.0;
+                let previous_inside = cross(a, b, previous) >= 0.0;
+                if current_inside != previous_inside {
+                    let d1 = cross(a, b, previous);
+                    let d2 = cross(a, b, current);
+                    let t = d1 / (d1 - d2);
+                    points.push(previous + (current - previous) * t);
+                }
+                if current_inside {
+                    points.push(current);
+                }
+            }
+        }
+
+        if points.len() < 3 {
+            Polygon::default()
+        } else {
+            Polygon { points }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn rectangle(x1: f32, y1: f32, x2: f32, y2: f32) -> Polygon {
+        Polygon::new(vec![
+            Point::new(x1, y1),
+            Point::new(x2, y1),
+            Point::new(x2, y2),
+            Point::new(x1, y2),
+        ])
+    }
+
+    proptest! {
+        #[test]
+        fn rectangle_intersection(x1 in -100.0f32..100.0, y1 in -100.0f32..100.0,
+                                  w1 in 1.0f32..100.0, h1 in 1.0f32..100.0,
+                                  x2 in -100.0f32..100.0, y2 in -100.0f32..100.0,
+                                  w2 in 1.0f32..100.0, h2 in 1.0f32..100.0) {
+            let a = rectangle(x1, y1, x1 + w1, y1 + h1);
+            let b = rectangle(x2, y2, x2 + w2, y2 + h2);
+
+            let overlap_x = ((x1 + w1).min(x2 + w2) - x1.max(x2)).max(0.0);
+            let overlap_y = ((y1 + h1).min(y2 + h2) - y1.max(y2)).max(0.0);
+            let area = a.clip(&b).area();
+            prop_assert!((area - overlap_x * overlap_y).abs() < 1e-2 * (1.0 + overlap_x * overlap_y));
+        }
+    }
+
+    #[test]
+    fn clockwise_points_are_reversed() {
+        let polygon = Polygon::new(vec![
+            Point::new(0.0, 0.0),
+            Point::new(0.0, 1.0),
+            Point::new(1.0, 1.0),
+            Point::new(1.0, 0.0),
+        ]);
+        assert_eq!(polygon.points[1], Point::new(1.0, 1.0));
+        assert_eq!(polygon.area(), 1.0);
+    }
+
+    #[test]
+    fn convex_hull_drops_inner_points() {
+        // a square with a notch cut into its top edge
+        let notched = Polygon::new(vec![
+            Point::new(0.0, 0.0),
+            Point::new(10.0, 0.0),
+            Point::new(10.0, 10.0),
+            Point::new(5.0, 5.0),
+            Point::new(0.0, 10.0),
+        ]);
+        let hull = notched.convex_hull();
+        assert_eq!(hull.points.len(), 4);
+        assert!(!hull.points.contains(&Point::new(5.0, 5.0)));
+        assert_eq!(hull.area(), 100.0);
+        assert!(!notched.contains(Point::new(5.0, 8.0)));
+        assert!(hull.contains(Point::new(5.0, 8.0)));
+
+        // clipping with the notched polygon itself keeps only the
+        // triangle below the notch instead of 75
+        let square = rectangle(0.0, 0.0, 10.0, 10.0);
+        assert!((square.clip(&notched).area() - 25.0).abs() < 1e-3);
+        assert!((square.clip(&hull).area() - 100.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn contains_and_distance() {
+        let square = rectangle(0.0, 0.0, 10.0, 10.0);
+        assert!(square.contains(Point::new(5.0, 5.0)));
+        assert!(square.contains(Point::new(10.0, 5.0)));
+        assert!(!square.contains(Point::new(11.0, 5.0)));
+        assert_eq!(square.distance_to_boundary(Point::new(2.0, 5.0)), 2.0);
+        assert_eq!(square.distance_to_boundary(Point::new(13.0, 14.0)), 5.0);
+    }
+}
@@ -1,8 +1,8 @@
 use imgui::*;
-use protobuf::{Message, ProtobufEnum, RepeatedField};
+use protobuf::{ProtobufEnum, RepeatedField};
 use ssl_rust_tools::gui::{support, widgets};
+use ssl_rust_tools::labeler::label_file;
 use ssl_rust_tools::protos;
-use std::fs;
 use std::fs::File;
 use std::io::prelude::*;
 use std::path::PathBuf;
@@ -268,6 +268,8 @@ impl Default for FileMenu {
         ];
         let label_file_filter_lists = vec![
             widgets::FileBrowserFilter::new("label", ".*\\.label").unwrap(),
+            widgets::FileBrowserFilter::new("json", ".*\\.json").unwrap(),
+            widgets::FileBrowserFilter::new("csv", ".*\\.csv").unwrap(),
             widgets::FileBrowserFilter::new("all", ".*").unwrap(),
         ];
 
@@ -638,9 +640,6 @@ fn save_labels(state: &mut State) {
     goal_shot_labels.sort_by(|a, b| a.get_start_frame().cmp(&b.get_start_frame()));
     labels.set_goal_shot_labels(RepeatedField::from(state.goal_shot_labels.clone()));
 
-    // serialize the protobuf
-    let msg_bytes = labels.write_to_bytes().unwrap();
-
     // default file name is same as opened file but with the .label extension
     let label_file_path = match state.file_menu.save_path {
         Some(ref save_path) => save_path,
@@ -658,14 +657,11 @@ fn save_labels(state: &mut State) {
         }
     };
 
-    // open the file for writing, overwriting any data currently there
-    let mut label_file = fs::OpenOptions::new()
-        .write(true)
-        .create(true)
-        .truncate(true)
-        .open(label_file_path)
-        .unwrap();
-    label_file.write_all(&msg_bytes).unwrap();
+    // the format is picked from the file extension
+    // TODO: Show these logs in the gui
+    if let Err(e) = label_file::write_labels_to_path(label_file_path, &labels) {
+        eprintln!("Failed to save labels to {:?}: {}", label_file_path, e);
+    }
 }
 
 fn load_labels(state: &mut State) {
@@ -675,8 +671,14 @@ fn load_labels(state: &mut State) {
         .current_selection()
         .unwrap();
 
-    let mut label_file = fs::File::open(label_file_path.clone()).unwrap();
-    let labels: protos::log_labels::Labels = protobuf::parse_from_reader(&mut label_file).unwrap();
+    // TODO: Show these logs in the gui
+    let labels = match label_file::read_labels_from_path(&label_file_path) {
+        Ok(labels) => labels,
+        Err(e) => {
+            eprintln!("Failed to load labels from {:?}: {}", label_file_path, e);
+            return;
+        }
+    };
 
     let dribbling_labels = labels.get_dribbling_labels().to_vec();
     let ball_possession_labels = labels.get_ball_possession_labels().to_vec();
@@ -0,0 +1,661 @@
+use super::scoring::{
+    calc_iou, goal_shot_pair_score, goal_shot_span, match_events, passing_pair_score, passing_span,
+    MatchCounts, ScoringOptions,
+};
+use crate::protos::log_labels;
+use serde_json::{self, json};
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::hash::Hash;
+
+// Agreement between several annotators labeling the same labeler
+// data file, and the frames where they disagree.
+
+#[derive(Debug, Fail)]
+pub enum AgreementError {
+    #[fail(display = "need at least 2 label files, got {}", num_annotators)]
+    TooFewAnnotators { num_annotators: usize },
+    #[fail(
+        display = "label file {} has {} frame labels, but label file 0 has {}",
+        annotator, num_frames, expected
+    )]
+    FrameCountMismatch {
+        annotator: usize,
+        num_frames: usize,
+        expected: usize,
+    },
+}
+
+type AgreementResult<T> = Result<T, AgreementError>;
+
+// kappa when the expected agreement is already perfect, i.e. everybody
+// used the same single class
+fn kappa(observed_agreement: f64, expected_agreement: f64) -> f64 {
+    if expected_agreement >= 1.0 {
+        1.0
+    } else {
+        (observed_agreement - expected_agreement) / (1.0 - expected_agreement)
+    }
+}
+
+// Cohen's kappa of two annotators labeling the same items. Items past
+// the end of the shorter list are ignored.
+pub fn cohens_kappa<C: Eq + Hash>(labels_a: &[C], labels_b: &[C]) -> f64 {
+    let num_items = labels_a.len().min(labels_b.len());
+    if num_items == 0 {
+        return 1.0;
+    }
+
+    let mut counts_a: HashMap<&C, usize> = HashMap::new();
+    let mut counts_b: HashMap<&C, usize> = HashMap::new();
+    let mut num_agreed = 0;
+    for (a, b) in labels_a.iter().zip(labels_b) {
+        *counts_a.entry(a).or_insert(0) += 1;
+        *counts_b.entry(b).or_insert(0) += 1;
+        if a == b {
+            num_agreed += 1;
+        }
+    }
+
+    let num_items = num_items as f64;
+    let expected_agreement = counts_a
+        .iter()
+        .map(|(class, count_a)| {
+            let count_b = counts_b.get(class).cloned().unwrap_or(0);
+            (*count_a as f64 / num_items) * (count_b as f64 / num_items)
+        })
+        .sum();
+
+    kappa(num_agreed as f64 / num_items, expected_agreement)
+}
+
+// Fleiss' kappa of any number of annotators. `labels[annotator][item]`
+// is the class given by an annotator to an item, every annotator must
+// label the same number of items.
+pub fn fleiss_kappa<C: Eq + Hash>(labels: &[&[C]]) -> f64 {
+    let num_annotators = labels.len();
+    let num_items = labels.first().map_or(0, |l| l.len());
+    if num_annotators < 2 || num_items == 0 {
+        return 1.0;
+    }
+
+    let mut class_totals: HashMap<&C, usize> = HashMap::new();
+    let mut observed_agreement = 0.0;
+    for item in 0..num_items {
+        let mut item_counts: HashMap<&C, usize> = HashMap::new();
+        for annotator_labels in labels {
+            *item_counts.entry(&annotator_labels[item]).or_insert(0) += 1;
+        }
+
+        let agreeing_pairs: usize = item_counts.values().map(|n| n * (n - 1)).sum();
+        observed_agreement +=
+            agreeing_pairs as f64 / (num_annotators * (num_annotators - 1)) as f64;
+
+        for (class, count) in item_counts {
+            *class_totals.entry(class).or_insert(0) += count;
+        }
+    }
+    observed_agreement /= num_items as f64;
+
+    let num_ratings = (num_items * num_annotators) as f64;
+    let expected_agreement = class_totals
+        .values()
+        .map(|count| (*count as f64 / num_ratings).powi(2))
+        .sum();
+
+    kappa(observed_agreement, expected_agreement)
+}
+
+// team and robot if dribbling
+pub(crate) fn dribbling_class(
+    label: &log_labels::DribblingLabel,
+) -> Option<(log_labels::Team, u32)> {
+    if label.get_is_dribbling() {
+        Some((label.get_team(), label.get_robot_id()))
+    } else {
+        None
+    }
+}
+
+// the robot only matters if a team has the ball
+pub(crate) fn ball_possession_class(
+    label: &log_labels::BallPossessionLabel,
+) -> (log_labels::BallPossessionLabel_State, u32) {
+    match label.get_state() {
+        log_labels::BallPossessionLabel_State::NONE => (label.get_state(), 0),
+        state => (state, label.get_robot_id()),
+    }
+}
+
+// Merges the frames into inclusive (start, end) ranges.
+pub(crate) fn frame_ranges<I: IntoIterator<Item = u64>>(frames: I) -> Vec<(u64, u64)> {
+    let mut ranges: Vec<(u64, u64)> = vec![];
+    for frame in frames {
+        match ranges.last_mut() {
+            Some(range) if range.1 + 1 == frame => range.1 = frame,
+            _ => ranges.push((frame, frame)),
+        }
+    }
+    ranges
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrameAgreement {
+    // Cohen's kappa for 2 annotators, Fleiss' kappa for more
+    pub kappa: f64,
+    // fraction of frames where all annotators agree
+    pub observed_agreement: f64,
+    // inclusive frame ranges where some annotators disagree
+    pub disagreements: Vec<(u64, u64)>,
+}
+
+fn frame_agreement<C: Eq + Hash>(classes: &[Vec<C>]) -> FrameAgreement {
+    let class_slices: Vec<&[C]> = classes.iter().map(|c| c.as_slice()).collect();
+    let kappa = if class_slices.len() == 2 {
+        cohens_kappa(class_slices[0], class_slices[1])
+    } else {
+        fleiss_kappa(&class_slices)
+    };
+
+    let num_frames = class_slices[0].len();
+    let disagreeing_frames: Vec<u64> = (0..num_frames)
+        .filter(|frame| {
+            class_slices[1..]
+                .iter()
+                .any(|c| c[*frame] != class_slices[0][*frame])
+        })
+        .map(|frame| frame as u64)
+        .collect();
+
+    FrameAgreement {
+        kappa,
+        observed_agreement: if num_frames == 0 {
+            1.0
+        } else {
+            1.0 - disagreeing_frames.len() as f64 / num_frames as f64
+        },
+        disagreements: frame_ranges(disagreeing_frames),
+    }
+}
+
+// An event that one annotator labeled and the other did not
+// (`other_span` is None), or that both labeled with different
+// attributes such as the robot or success.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventDisagreement {
+    pub annotator: usize,
+    pub other_annotator: usize,
+    pub span: Option<(u64, u64)>,
+    pub other_span: Option<(u64, u64)>,
+}
+
+impl EventDisagreement {
+    // all frames covered by either annotator's event
+    pub fn frame_range(&self) -> (u64, u64) {
+        let spans = self.span.iter().chain(self.other_span.iter());
+        let start = spans.clone().map(|span| span.0.min(span.1)).min().unwrap();
+        let end = spans.map(|span| span.0.max(span.1)).max().unwrap();
+        (start, end)
+    }
+}
+
+// Agreement of two annotators on a duration event category. The
+// matching counts treat `annotators.0` as the ground truth, but F1 and
+// the mean IoU do not depend on the order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PairwiseEventAgreement {
+    pub annotators: (usize, usize),
+    pub counts: MatchCounts,
+    // mean IoU of the matched events, 1 if nothing matched
+    pub mean_iou: f64,
+    // matched events with all attributes equal
+    pub num_same_attributes: usize,
+    pub disagreements: Vec<EventDisagreement>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventAgreement {
+    // one entry for every pair of annotators
+    pub pairs: Vec<PairwiseEventAgreement>,
+}
+
+impl EventAgreement {
+    pub fn mean_f1(&self) -> f64 {
+        if self.pairs.is_empty() {
+            return 1.0;
+        }
+        self.pairs.iter().map(|p| p.counts.f1()).sum::<f64>() / self.pairs.len() as f64
+    }
+}
+
+fn event_agreement<L, S, P, A>(
+    labels: &[&[L]],
+    min_iou: f64,
+    span: S,
+    pair_score: P,
+    same_attributes: A,
+) -> EventAgreement
+where
+    S: Fn(&L) -> (u64, u64),
+    P: Fn(&L, &L) -> f64,
+    A: Fn(&L, &L) -> bool,
+{
+    let mut pairs = vec![];
+    for (annotator, labels_a) in labels.iter().enumerate() {
+        for (other_annotator, labels_b) in labels.iter().enumerate().skip(annotator + 1) {
+            let matching = match_events(labels_a, labels_b, min_iou, &span, &pair_score);
+
+            let mut disagreements = vec![];
+            let mut total_iou = 0.0;
+            let mut num_same_attributes = 0;
+            for (index_a, index_b) in &matching.pairs {
+                let (label_a, label_b) = (&labels_a[*index_a], &labels_b[*index_b]);
+                total_iou += calc_iou(span(label_a), span(label_b));
+                if same_attributes(label_a, label_b) {
+                    num_same_attributes += 1;
+                } else {
+                    disagreements.push(EventDisagreement {
+                        annotator,
+                        other_annotator,
+                        span: Some(span(label_a)),
+                        other_span: Some(span(label_b)),
+                    });
+                }
+            }
+            for index_a in &matching.unmatched_ground_truth {
+                disagreements.push(EventDisagreement {
+                    annotator,
+                    other_annotator,
+                    span: Some(span(&labels_a[*index_a])),
+                    other_span: None,
+                });
+            }
+            for index_b in &matching.unmatched_predicted {
+                disagreements.push(EventDisagreement {
+                    annotator: other_annotator,
+                    other_annotator: annotator,
+                    span: Some(span(&labels_b[*index_b])),
+                    other_span: None,
+                });
+            }
+            disagreements.sort_by_key(|d| d.frame_range());
+
+            pairs.push(PairwiseEventAgreement {
+                annotators: (annotator, other_annotator),
+                counts: matching.counts(),
+                mean_iou: if matching.pairs.is_empty() {
+                    1.0
+                } else {
+                    total_iou / matching.pairs.len() as f64
+                },
+                num_same_attributes,
+                disagreements,
+            });
+        }
+    }
+
+    EventAgreement { pairs }
+}
+
+fn same_passing_attributes(a: &log_labels::PassingLabel, b: &log_labels::PassingLabel) -> bool {
+    a.get_successful() == b.get_successful()
+        && a.get_passer_id() == b.get_passer_id()
+        && a.get_passer_team() == b.get_passer_team()
+        && a.get_receiver_id() == b.get_receiver_id()
+}
+
+fn same_goal_shot_attributes(a: &log_labels::GoalShotLabel, b: &log_labels::GoalShotLabel) -> bool {
+    a.get_successful() == b.get_successful()
+        && a.get_shooter_id() == b.get_shooter_id()
+        && a.get_shooter_team() == b.get_shooter_team()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AgreementReport {
+    pub num_annotators: usize,
+    pub dribbling: FrameAgreement,
+    pub ball_possession: FrameAgreement,
+    pub passing: EventAgreement,
+    pub goal_shot: EventAgreement,
+}
+
+// Compares the labels of every annotator. Events are matched the same
+// way as when scoring, using `options.min_iou`.
+pub fn label_agreement(
+    labels: &[log_labels::Labels],
+    options: &ScoringOptions,
+) -> AgreementResult<AgreementReport> {
+    if labels.len() < 2 {
+        return Err(AgreementError::TooFewAnnotators {
+            num_annotators: labels.len(),
+        });
+    }
+
+    let expected = labels[0].get_dribbling_labels().len();
+    for (annotator, annotator_labels) in labels.iter().enumerate() {
+        for num_frames in &[
+            annotator_labels.get_dribbling_labels().len(),
+            annotator_labels.get_ball_possession_labels().len(),
+        ] {
+            if *num_frames != expected {
+                return Err(AgreementError::FrameCountMismatch {
+                    annotator,
+                    num_frames: *num_frames,
+                    expected,
+                });
+            }
+        }
+    }
+
+    let dribbling_classes: Vec<Vec<_>> = labels
+        .iter()
+        .map(|l| {
+            l.get_dribbling_labels()
+                .iter()
+                .map(dribbling_class)
+                .collect()
+        })
+        .collect();
+    let ball_possession_classes: Vec<Vec<_>> = labels
+        .iter()
+        .map(|l| {
+            l.get_ball_possession_labels()
+                .iter()
+                .map(ball_possession_class)
+                .collect()
+        })
+        .collect();
+
+    let passing_labels: Vec<_> = labels.iter().map(|l| l.get_passing_labels()).collect();
+    let goal_shot_labels: Vec<_> = labels.iter().map(|l| l.get_goal_shot_labels()).collect();
+
+    Ok(AgreementReport {
+        num_annotators: labels.len(),
+        dribbling: frame_agreement(&dribbling_classes),
+        ball_possession: frame_agreement(&ball_possession_classes),
+        passing: event_agreement(
+            &passing_labels,
+            options.min_iou,
+            passing_span,
+            passing_pair_score,
+            same_passing_attributes,
+        ),
+        goal_shot: event_agreement(
+            &goal_shot_labels,
+            options.min_iou,
+            goal_shot_span,
+            goal_shot_pair_score,
+            same_goal_shot_attributes,
+        ),
+    })
+}
+
+fn range_to_string(range: (u64, u64)) -> String {
+    format!("{}-{}", range.0, range.1)
+}
+
+fn span_to_json(span: Option<(u64, u64)>) -> serde_json::Value {
+    match span {
+        Some((start, end)) => json!([start, end]),
+        None => serde_json::Value::Null,
+    }
+}
+
+impl AgreementReport {
+    pub fn to_json(&self) -> serde_json::Value {
+        let frame_json = |agreement: &FrameAgreement| {
+            json!({
+                "kappa": agreement.kappa,
+                "observed_agreement": agreement.observed_agreement,
+                "disagreements": agreement.disagreements,
+            })
+        };
+        let event_json = |agreement: &EventAgreement| {
+            json!({
+                "mean_f1": agreement.mean_f1(),
+                "pairs": agreement
+                    .pairs
+                    .iter()
+                    .map(|pair| json!({
+                        "annotators": [pair.annotators.0, pair.annotators.1],
+                        "matched": pair.counts.true_positives,
+                        "unmatched": [pair.counts.false_negatives, pair.counts.false_positives],
+                        "f1": pair.counts.f1(),
+                        "mean_iou": pair.mean_iou,
+                        "same_attributes": pair.num_same_attributes,
+                        "disagreements": pair
+                            .disagreements
+                            .iter()
+                            .map(|d| json!({
+                                "annotator": d.annotator,
+                                "frames": span_to_json(d.span),
+                                "other_annotator": d.other_annotator,
+                                "other_frames": span_to_json(d.other_span),
+                            }))
+                            .collect::<Vec<_>>(),
+                    }))
+                    .collect::<Vec<_>>(),
+            })
+        };
+
+        json!({
+            "num_annotators": self.num_annotators,
+            "dribbling": frame_json(&self.dribbling),
+            "ball_possession": frame_json(&self.ball_possession),
+            "passing": event_json(&self.passing),
+            "goal_shot": event_json(&self.goal_shot),
+        })
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut output = String::new();
+        let kappa_name = if self.num_annotators == 2 {
+            "Cohen's kappa"
+        } else {
+            "Fleiss' kappa"
+        };
+
+        writeln!(
+            output,
+            "# Agreement of {} Annotators\n",
+            self.num_annotators
+        )
+        .unwrap();
+        writeln!(output, "| category | {} | frames agreed |", kappa_name).unwrap();
+        writeln!(output, "|---|---|---|").unwrap();
+        for (name, agreement) in &[
+            ("dribbling", &self.dribbling),
+            ("ball possession", &self.ball_possession),
+        ] {
+            writeln!(
+                output,
+                "| {} | {:.3} | {:.1}% |",
+                name,
+                agreement.kappa,
+                agreement.observed_agreement * 100.0
+            )
+            .unwrap();
+        }
+
+        writeln!(
+            output,
+            "\n| event | annotators | matched | unmatched | F1 | mean IoU | same attributes |"
+        )
+        .unwrap();
+        writeln!(output, "|---|---|---|---|---|---|---|").unwrap();
+        for (name, agreement) in &[("passing", &self.passing), ("goal shot", &self.goal_shot)] {
+            for pair in &agreement.pairs {
+                writeln!(
+                    output,
+                    "| {} | {} and {} | {} | {} and {} | {:.3} | {:.3} | {} |",
+                    name,
+                    pair.annotators.0,
+                    pair.annotators.1,
+                    pair.counts.true_positives,
+                    pair.counts.false_negatives,
+                    pair.counts.false_positives,
+                    pair.counts.f1(),
+                    pair.mean_iou,
+                    pair.num_same_attributes
+                )
+                .unwrap();
+            }
+        }
+
+        writeln!(output, "\n## Disagreements\n").unwrap();
+        for (name, agreement) in &[
+            ("dribbling", &self.dribbling),
+            ("ball possession", &self.ball_possession),
+        ] {
+            let ranges: Vec<String> = agreement
+                .disagreements
+                .iter()
+                .map(|range| range_to_string(*range))
+                .collect();
+            writeln!(output, "- {} frames: {}", name, ranges.join(", ")).unwrap();
+        }
+        for (name, agreement) in &[("passing", &self.passing), ("goal shot", &self.goal_shot)] {
+            for pair in &agreement.pairs {
+                for disagreement in &pair.disagreements {
+                    let description = match disagreement.other_span {
+                        Some(_) => format!(
+                            "annotators {} and {} disagree on the details",
+                            disagreement.annotator, disagreement.other_annotator
+                        ),
+                        None => format!(
+                            "only annotator {} labeled it, not {}",
+                            disagreement.annotator, disagreement.other_annotator
+                        ),
+                    };
+                    writeln!(
+                        output,
+                        "- {} frames {}: {}",
+                        name,
+                        range_to_string(disagreement.frame_range()),
+                        description
+                    )
+                    .unwrap();
+                }
+            }
+        }
+
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::labels as test_utils_labels;
+    use proptest::prelude::*;
+
+    // 50 items, 20 both yes, 5 only a, 10 only b, 15 both no
+    fn yes_no_example() -> (Vec<bool>, Vec<bool>) {
+        let mut labels_a = vec![];
+        let mut labels_b = vec![];
+        for (count, a, b) in &[
+            (20, true, true),
+            (5, true, false),
+            (10, false, true),
+            (15, false, false),
+        ] {
+            for _ in 0..*count {
+                labels_a.push(*a);
+                labels_b.push(*b);
+            }
+        }
+        (labels_a, labels_b)
+    }
+
+    #[test]
+    fn cohens_kappa_example() {
+        let (labels_a, labels_b) = yes_no_example();
+
+        assert!((cohens_kappa(&labels_a, &labels_b) - 0.4).abs() < 1e-9);
+    }
+
+    #[test]
+    fn fleiss_kappa_of_two_annotators() {
+        let (labels_a, labels_b) = yes_no_example();
+
+        // with 2 annotators Fleiss' kappa is Scott's pi
+        let expected_agreement = 0.55f64.powi(2) + 0.45f64.powi(2);
+        let scotts_pi = (0.7 - expected_agreement) / (1.0 - expected_agreement);
+        assert!(
+            (fleiss_kappa(&[labels_a.as_slice(), labels_b.as_slice()]) - scotts_pi).abs() < 1e-9
+        );
+    }
+
+    #[test]
+    fn frame_ranges_are_merged() {
+        assert_eq!(
+            frame_ranges(vec![1, 2, 3, 7, 9, 10]),
+            vec![(1, 3), (7, 7), (9, 10)]
+        );
+    }
+
+    #[test]
+    fn too_few_annotators() {
+        match label_agreement(&[log_labels::Labels::new()], &ScoringOptions::default()) {
+            Err(AgreementError::TooFewAnnotators { num_annotators: 1 }) => {}
+            result => panic!("Unexpected result: {:?}", result),
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn identical_labels_agree(labels in test_utils_labels::random_labels_strategy(20, 5),
+                                  num_annotators in 2..5usize) {
+            let labels = vec![labels; num_annotators];
+
+            let report = label_agreement(&labels, &ScoringOptions::default()).unwrap();
+
+            prop_assert_eq!(report.dribbling.kappa, 1.0);
+            prop_assert_eq!(report.ball_possession.kappa, 1.0);
+            prop_assert!(report.dribbling.disagreements.is_empty());
+            prop_assert!(report.ball_possession.disagreements.is_empty());
+            prop_assert_eq!(report.passing.mean_f1(), 1.0);
+            prop_assert_eq!(report.goal_shot.mean_f1(), 1.0);
+            prop_assert_eq!(report.passing.pairs.len(), num_annotators * (num_annotators - 1) / 2);
+        }
+
+        #[test]
+        fn disagreements_cover_changed_frames(
+            labels in test_utils_labels::random_labels_strategy(20, 5),
+            changed_frames in proptest::collection::btree_set(0..20usize, 0..20)
+        ) {
+            let mut other_labels = labels.clone();
+            for frame in &changed_frames {
+                let label = &mut other_labels.mut_dribbling_labels()[*frame];
+                let is_dribbling = label.get_is_dribbling();
+                label.set_is_dribbling(!is_dribbling);
+            }
+
+            let report = label_agreement(&[labels, other_labels], &ScoringOptions::default()).unwrap();
+
+            let disagreeing_frames: Vec<usize> = report
+                .dribbling
+                .disagreements
+                .iter()
+                .flat_map(|(start, end)| *start as usize..=*end as usize)
+                .collect();
+            prop_assert_eq!(disagreeing_frames, changed_frames.into_iter().collect::<Vec<_>>());
+            prop_assert!(report.dribbling.kappa <= 1.0);
+        }
+
+        #[test]
+        fn missing_events_are_disagreements(labels in test_utils_labels::random_labels_strategy(20, 5)) {
+            let mut other_labels = labels.clone();
+            other_labels.clear_goal_shot_labels();
+
+            let report = label_agreement(&[labels.clone(), other_labels], &ScoringOptions::default()).unwrap();
+
+            let pair = &report.goal_shot.pairs[0];
+            prop_assert_eq!(pair.disagreements.len(), labels.get_goal_shot_labels().len());
+            for disagreement in &pair.disagreements {
+                prop_assert_eq!(disagreement.annotator, 0);
+                prop_assert_eq!(disagreement.other_span, None);
+            }
+        }
+    }
+}
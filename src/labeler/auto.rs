@@ -0,0 +1,294 @@
+use super::reader::{LabelerDataReader, LabelerDataReaderError};
+use crate::protos::log_labeler_data::LabelerFrameGroup;
+use crate::protos::log_labels;
+use crate::vision::{BallState, RobotState, WorldFrame, WorldFrameConfig};
+use std::cmp::Ordering;
+use std::f32::consts::PI;
+use std::io::{Read, Seek};
+
+// Heuristic dribbling and ball possession labels, used as a starting
+// point for labeling by hand.
+
+// Distances are in mm and angles in radians. The angle is between
+// the robot's orientation and the direction from the robot to the
+// ball, so 0 is a ball right in front of the robot.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AutoLabelerConfig {
+    // largest distance from the robot center to the ball center for
+    // the robot to possess the ball
+    pub possession_distance: f32,
+    pub possession_max_angle: f32,
+    // same as above for dribbling, usually stricter
+    pub dribbling_distance: f32,
+    pub dribbling_max_angle: f32,
+    // balls with a lower confidence are ignored
+    pub min_ball_confidence: f32,
+    // dribbling shorter than this many frame groups is dropped
+    pub min_dribbling_frame_groups: usize,
+}
+
+impl Default for AutoLabelerConfig {
+    fn default() -> Self {
+        AutoLabelerConfig {
+            possession_distance: 150.0,
+            possession_max_angle: PI / 2.0,
+            dribbling_distance: 115.0,
+            dribbling_max_angle: 0.5,
+            min_ball_confidence: 0.3,
+            min_dribbling_frame_groups: 3,
+        }
+    }
+}
+
+// Distance from the robot to the ball and the angle of the ball
+// relative to the robot's orientation, in [-PI, PI]. The angle is None
+// if the robot's orientation is unknown.
+fn ball_relative_to_robot(ball: &BallState, robot: &RobotState) -> (f32, Option<f32>) {
+    let dx = ball.x - robot.x;
+    let dy = ball.y - robot.y;
+    let distance = dx.hypot(dy);
+
+    let angle = if let Some(orientation) = robot.orientation {
+        let mut angle = dy.atan2(dx) - orientation;
+        while angle > PI {
+            angle -= 2.0 * PI;
+        }
+        while angle < -PI {
+            angle += 2.0 * PI;
+        }
+        Some(angle)
+    } else {
+        None
+    };
+
+    (distance, angle)
+}
+
+// Labels a single frame group. The closest robot within the
+// possession thresholds possesses the ball, and dribbles it if it is
+// also within the dribbling thresholds.
+pub fn label_frame_group(
+    frame_group: &LabelerFrameGroup,
+    config: &AutoLabelerConfig,
+) -> (log_labels::DribblingLabel, log_labels::BallPossessionLabel) {
+    let mut dribbling_label = log_labels::DribblingLabel::new();
+    let mut ball_possession_label = log_labels::BallPossessionLabel::new();
+
+    let world_frame =
+        WorldFrame::from_frame_group(frame_group, &WorldFrameConfig::default()).unwrap_or_default();
+    let ball = match world_frame.ball() {
+        Some(ball) if ball.confidence >= config.min_ball_confidence => ball,
+        _ => return (dribbling_label, ball_possession_label),
+    };
+
+    let closest = world_frame
+        .robots
+        .iter()
+        .map(|robot| {
+            let (distance, angle) = ball_relative_to_robot(ball, robot);
+            let team: log_labels::Team = robot.team.into();
+            (team, robot.robot_id, distance, angle)
+        })
+        .filter(|(_, _, distance, angle)| {
+            *distance <= config.possession_distance
+                && angle.map_or(true, |angle| angle.abs() <= config.possession_max_angle)
+        })
+        .min_by(|a, b| a.2.partial_cmp(&b.2).unwrap_or(Ordering::Equal));
+
+    if let Some((team, robot_id, distance, angle)) = closest {
+        ball_possession_label.set_state(match team {
+            log_labels::Team::YELLOW => log_labels::BallPossessionLabel_State::YELLOW_POSSES,
+            log_labels::Team::BLUE => log_labels::BallPossessionLabel_State::BLUE_POSSES,
+        });
+        ball_possession_label.set_robot_id(robot_id);
+
+        // dribbling needs the orientation to tell the front of the robot
+        let is_dribbling = distance <= config.dribbling_distance
+            && angle.map_or(false, |angle| angle.abs() <= config.dribbling_max_angle);
+        if is_dribbling {
+            dribbling_label.set_is_dribbling(true);
+            dribbling_label.set_robot_id(robot_id);
+            dribbling_label.set_team(team);
+        }
+    }
+
+    (dribbling_label, ball_possession_label)
+}
+
+// Clears dribbling by the same robot that lasts fewer than
+// `min_frame_groups` consecutive frame groups.
+fn remove_short_dribbling(labels: &mut [log_labels::DribblingLabel], min_frame_groups: usize) {
+    let same_dribbler = |a: &log_labels::DribblingLabel, b: &log_labels::DribblingLabel| {
+        a.get_is_dribbling()
+            && b.get_is_dribbling()
+            && a.get_team() == b.get_team()
+            && a.get_robot_id() == b.get_robot_id()
+    };
+
+    let mut start = 0;
+    while start < labels.len() {
+        let mut end = start + 1;
+        while end < labels.len() && same_dribbler(&labels[start], &labels[end]) {
+            end += 1;
+        }
+        if labels[start].get_is_dribbling() && end - start < min_frame_groups {
+            for label in &mut labels[start..end] {
+                *label = log_labels::DribblingLabel::new();
+            }
+        }
+        start = end;
+    }
+}
+
+// Labels every frame group of a labeler data file. Only the dribbling
+// and ball possession labels are filled in.
+pub fn auto_label<T: Read + Seek>(
+    reader: &LabelerDataReader<T>,
+    config: &AutoLabelerConfig,
+) -> Result<log_labels::Labels, LabelerDataReaderError> {
+    let mut dribbling_labels = Vec::with_capacity(reader.len());
+    let mut ball_possession_labels = Vec::with_capacity(reader.len());
+    for frame_group in reader {
+        let (dribbling_label, ball_possession_label) = label_frame_group(&frame_group?, config);
+        dribbling_labels.push(dribbling_label);
+        ball_possession_labels.push(ball_possession_label);
+    }
+
+    remove_short_dribbling(&mut dribbling_labels, config.min_dribbling_frame_groups);
+
+    let mut labels = log_labels::Labels::new();
+    labels.set_dribbling_labels(dribbling_labels.into());
+    labels.set_ball_possession_labels(ball_possession_labels.into());
+    Ok(labels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::vision::{ball, detection, frame_group, robot};
+    use proptest::prelude::*;
+
+    fn dribbling(robot_id: u32) -> log_labels::DribblingLabel {
+        let mut label = log_labels::DribblingLabel::new();
+        label.set_is_dribbling(true);
+        label.set_robot_id(robot_id);
+        label
+    }
+
+    proptest! {
+        #[test]
+        fn ball_in_front_is_dribbled(orientation in -PI..PI,
+                                     angle in -0.4f32..0.4,
+                                     distance in 90.0f32..110.0) {
+            let direction = orientation + angle;
+            let group = frame_group(vec![detection(
+                0,
+                0.0,
+                vec![ball(1000.0 + distance * direction.cos(), distance * direction.sin())],
+                vec![],
+                vec![robot(4, 1000.0, 0.0, orientation)],
+            )]);
+
+            let (dribbling_label, ball_possession_label) =
+                label_frame_group(&group, &AutoLabelerConfig::default());
+
+            prop_assert!(dribbling_label.get_is_dribbling());
+            prop_assert_eq!(dribbling_label.get_team(), log_labels::Team::BLUE);
+            prop_assert_eq!(dribbling_label.get_robot_id(), 4);
+            prop_assert_eq!(ball_possession_label.get_state(), log_labels::BallPossessionLabel_State::BLUE_POSSES);
+            prop_assert_eq!(ball_possession_label.get_robot_id(), 4);
+        }
+
+        #[test]
+        fn ball_behind_is_not_possessed(orientation in -PI..PI,
+                                        angle in 1.7f32..4.5,
+                                        distance in 90.0f32..140.0) {
+            let direction = orientation + angle;
+            let group = frame_group(vec![detection(
+                0,
+                0.0,
+                vec![ball(distance * direction.cos(), distance * direction.sin())],
+                vec![robot(2, 0.0, 0.0, orientation)],
+                vec![],
+            )]);
+
+            let (dribbling_label, ball_possession_label) =
+                label_frame_group(&group, &AutoLabelerConfig::default());
+
+            prop_assert!(!dribbling_label.get_is_dribbling());
+            prop_assert_eq!(ball_possession_label.get_state(), log_labels::BallPossessionLabel_State::NONE);
+        }
+    }
+
+    #[test]
+    fn closest_robot_possesses() {
+        let group = frame_group(vec![detection(
+            0,
+            0.0,
+            vec![ball(0.0, 0.0)],
+            vec![robot(1, -140.0, 0.0, 0.0)],
+            vec![robot(7, 0.0, 120.0, -PI / 2.0)],
+        )]);
+
+        let (dribbling_label, ball_possession_label) =
+            label_frame_group(&group, &AutoLabelerConfig::default());
+
+        assert!(!dribbling_label.get_is_dribbling());
+        assert_eq!(
+            ball_possession_label.get_state(),
+            log_labels::BallPossessionLabel_State::BLUE_POSSES
+        );
+        assert_eq!(ball_possession_label.get_robot_id(), 7);
+    }
+
+    #[test]
+    fn no_ball_no_possession() {
+        let mut unsure_ball = ball(0.0, 0.0);
+        unsure_ball.set_confidence(0.1);
+        let group = frame_group(vec![detection(
+            0,
+            0.0,
+            vec![unsure_ball],
+            vec![robot(1, -100.0, 0.0, 0.0)],
+            vec![],
+        )]);
+
+        let (dribbling_label, ball_possession_label) =
+            label_frame_group(&group, &AutoLabelerConfig::default());
+
+        assert!(!dribbling_label.get_is_dribbling());
+        assert_eq!(
+            ball_possession_label.get_state(),
+            log_labels::BallPossessionLabel_State::NONE
+        );
+    }
+
+    #[test]
+    fn short_dribbling_is_removed() {
+        let none = log_labels::DribblingLabel::new();
+        let mut labels = vec![
+            dribbling(1),
+            dribbling(1),
+            none.clone(),
+            dribbling(2),
+            dribbling(2),
+            dribbling(2),
+            dribbling(3),
+        ];
+
+        remove_short_dribbling(&mut labels, 3);
+
+        assert_eq!(
+            labels,
+            vec![
+                none.clone(),
+                none.clone(),
+                none.clone(),
+                dribbling(2),
+                dribbling(2),
+                dribbling(2),
+                none,
+            ]
+        );
+    }
+}
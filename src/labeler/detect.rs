@@ -0,0 +1,587 @@
+use super::reader::{LabelerDataReader, LabelerDataReaderError};
+use crate::geometry::{Division, Field, Point};
+use crate::protos::log_labeler_data::LabelerFrameGroup;
+use crate::protos::log_labels;
+use crate::vision::{WorldFrame, WorldFrameConfig};
+use std::cmp::Ordering;
+use std::io::{Read, Seek};
+
+// Heuristic passing and goal shot detection. Kicks are found from
+// jumps in the ball speed, then the ball is followed until a robot
+// touches it, it crosses a field line, it stops or the event gets too
+// long.
+
+// Distances are in mm and speeds in mm/s.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EventDetectorConfig {
+    // a kick makes the ball at least this fast
+    pub min_kick_speed: f32,
+    // and at least this much faster than before
+    pub min_speed_increase: f32,
+    // largest distance from the kicker to the ball right before a kick
+    pub kicker_distance: f32,
+    // largest distance from a robot to the ball to touch it
+    pub receiver_distance: f32,
+    // the ball is stopped below this speed
+    pub stop_speed: f32,
+    // balls with a lower confidence are ignored
+    pub min_ball_confidence: f32,
+    // kicks towards the goal line that pass at most this far outside
+    // of a goal post are shots
+    pub shot_margin: f32,
+    // events are cut off after this many frame groups
+    pub max_event_frame_groups: usize,
+    // the ball velocity is only computed from detections at most this
+    // many frame groups apart
+    pub max_ball_gap: usize,
+}
+
+impl Default for EventDetectorConfig {
+    fn default() -> Self {
+        EventDetectorConfig {
+            min_kick_speed: 1500.0,
+            min_speed_increase: 1000.0,
+            kicker_distance: 200.0,
+            receiver_distance: 150.0,
+            stop_speed: 150.0,
+            min_ball_confidence: 0.3,
+            shot_margin: 250.0,
+            max_event_frame_groups: 360,
+            max_ball_gap: 5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Ball {
+    x: f32,
+    y: f32,
+    // None if there is no recent detection to compare to
+    velocity: Option<(f32, f32)>,
+}
+
+impl Ball {
+    fn speed(&self) -> f32 {
+        self.velocity.map_or(0.0, |(vx, vy)| vx.hypot(vy))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Robot {
+    team: log_labels::Team,
+    robot_id: u32,
+    x: f32,
+    y: f32,
+}
+
+// The part of a frame group the detector needs
+#[derive(Debug, Clone, Default)]
+struct Sample {
+    ball: Option<Ball>,
+    robots: Vec<Robot>,
+    // from the last referee message that had it
+    blue_team_on_positive_half: Option<bool>,
+}
+
+impl Sample {
+    // closest robot to the point within `max_distance`, skipping robots
+    // for which `skip` is true
+    fn closest_robot<F: Fn(&Robot) -> bool>(
+        &self,
+        x: f32,
+        y: f32,
+        max_distance: f32,
+        skip: F,
+    ) -> Option<Robot> {
+        self.robots
+            .iter()
+            .filter(|robot| !skip(robot))
+            .map(|robot| (robot, (robot.x - x).hypot(robot.y - y)))
+            .filter(|(_, distance)| *distance <= max_distance)
+            .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal))
+            .map(|(robot, _)| *robot)
+    }
+}
+
+// Turns frame groups into samples, computing the ball velocity from
+// the previous ball detection.
+struct Sampler {
+    config: EventDetectorConfig,
+    samples: Vec<Sample>,
+    // index, capture time and position of the last ball detection
+    last_ball: Option<(usize, f64, f32, f32)>,
+    blue_team_on_positive_half: Option<bool>,
+}
+
+impl Sampler {
+    fn new(config: &EventDetectorConfig) -> Sampler {
+        Sampler {
+            config: *config,
+            samples: vec![],
+            last_ball: None,
+            blue_team_on_positive_half: None,
+        }
+    }
+
+    fn add(&mut self, frame_group: &LabelerFrameGroup) {
+        let index = self.samples.len();
+        for frame in frame_group.get_frames() {
+            let referee = frame.get_referee_frame();
+            if frame.has_referee_frame() && referee.has_blueTeamOnPositiveHalf() {
+                self.blue_team_on_positive_half = Some(referee.get_blueTeamOnPositiveHalf());
+            }
+        }
+
+        let world_frame = WorldFrame::from_frame_group(frame_group, &WorldFrameConfig::default())
+            .unwrap_or_default();
+        let mut sample = Sample {
+            ball: None,
+            robots: world_frame
+                .robots
+                .iter()
+                .map(|robot| Robot {
+                    team: robot.team.into(),
+                    robot_id: robot.robot_id,
+                    x: robot.x,
+                    y: robot.y,
+                })
+                .collect(),
+            blue_team_on_positive_half: self.blue_team_on_positive_half,
+        };
+
+        if let Some(ball) = world_frame.ball() {
+            if ball.confidence >= self.config.min_ball_confidence {
+                let (x, y, time) = (ball.x, ball.y, world_frame.t_capture);
+                let velocity = match self.last_ball {
+                    Some((last_index, last_time, last_x, last_y))
+                        if index - last_index <= self.config.max_ball_gap && time > last_time =>
+                    {
+                        let dt = (time - last_time) as f32;
+                        Some(((x - last_x) / dt, (y - last_y) / dt))
+                    }
+                    _ => None,
+                };
+                sample.ball = Some(Ball { x, y, velocity });
+                self.last_ball = Some((index, time, x, y));
+            }
+        }
+
+        self.samples.push(sample);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum EventEnd {
+    // the ball crossed the goal line at this y
+    GoalLine(f32),
+    Touch(Robot),
+    // stopped, left the field over a touch line, or took too long
+    Lost,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct DetectedEvents {
+    pub passing_labels: Vec<log_labels::PassingLabel>,
+    pub goal_shot_labels: Vec<log_labels::GoalShotLabel>,
+    // for each goal shot label, true if the ball was kicked towards
+    // the goal mouth between the posts
+    pub goal_shots_on_target: Vec<bool>,
+}
+
+struct Detector<'a> {
+    samples: &'a [Sample],
+    field: &'a Field,
+    config: &'a EventDetectorConfig,
+}
+
+impl<'a> Detector<'a> {
+    // A kick at `index` returns the index of the ball detection right
+    // before it and the kicker.
+    fn kick_at(&self, index: usize) -> Option<(usize, Robot)> {
+        let ball = self.samples[index].ball?;
+        if ball.velocity.is_none() || ball.speed() < self.config.min_kick_speed {
+            return None;
+        }
+
+        let before = (index.saturating_sub(self.config.max_ball_gap)..index)
+            .rev()
+            .find(|i| self.samples[*i].ball.is_some())?;
+        let ball_before = self.samples[before].ball.unwrap();
+        if ball.speed() - ball_before.speed() < self.config.min_speed_increase {
+            return None;
+        }
+
+        // a single bad detection also looks like a kick, so the ball
+        // has to keep moving
+        let next_ball = self.samples[index + 1..]
+            .iter()
+            .take(self.config.max_ball_gap)
+            .find_map(|sample| sample.ball)?;
+        if next_ball.speed() < self.config.min_kick_speed / 2.0 {
+            return None;
+        }
+
+        let kicker = self.samples[before].closest_robot(
+            ball_before.x,
+            ball_before.y,
+            self.config.kicker_distance,
+            |_| false,
+        )?;
+        Some((before, kicker))
+    }
+
+    // Follows the ball after a kick. Returns the index where the event
+    // ends and how.
+    fn follow_ball(&self, kick_index: usize, kicker: &Robot) -> (usize, EventEnd) {
+        let half_length = self.field.half_length();
+        let half_width = self.field.half_width();
+        let is_kicker =
+            |robot: &Robot| robot.team == kicker.team && robot.robot_id == kicker.robot_id;
+
+        let last_index =
+            (kick_index + self.config.max_event_frame_groups).min(self.samples.len() - 1);
+        let mut previous = self.samples[kick_index].ball.unwrap();
+        let mut kicker_left = false;
+        for index in kick_index + 1..=last_index {
+            let sample = &self.samples[index];
+            let ball = match sample.ball {
+                Some(ball) => ball,
+                None => continue,
+            };
+
+            if ball.x.abs() >= half_length {
+                let crossing = self.field.goal_line_crossing(
+                    Point::new(previous.x, previous.y),
+                    Point::new(ball.x, ball.y),
+                );
+                return match crossing {
+                    Some(crossing) => (index, EventEnd::GoalLine(crossing.point.y)),
+                    None => (index, EventEnd::Lost),
+                };
+            }
+            if ball.y.abs() >= half_width {
+                return (index, EventEnd::Lost);
+            }
+
+            // the kicker is too close to the ball to tell if it touches
+            // it again until the ball has left it once
+            if let Some(kicker_robot) = sample.robots.iter().find(|robot| is_kicker(robot)) {
+                let distance = (kicker_robot.x - ball.x).hypot(kicker_robot.y - ball.y);
+                if distance > self.config.receiver_distance {
+                    kicker_left = true;
+                }
+            }
+            let toucher =
+                sample.closest_robot(ball.x, ball.y, self.config.receiver_distance, |robot| {
+                    !kicker_left && is_kicker(robot)
+                });
+            if let Some(toucher) = toucher {
+                return (index, EventEnd::Touch(toucher));
+            }
+
+            if ball.velocity.is_some() && ball.speed() < self.config.stop_speed {
+                return (index, EventEnd::Lost);
+            }
+            previous = ball;
+        }
+
+        (last_index, EventEnd::Lost)
+    }
+
+    // true if the ball moves towards the goal mouth of the kicker's
+    // opponent, widened by `margin` on both sides. Without a referee
+    // message telling the sides apart either goal counts.
+    fn heads_for_goal(&self, kick_index: usize, kicker: &Robot, margin: f32) -> bool {
+        let sample = &self.samples[kick_index];
+        let ball = sample.ball.unwrap();
+        let (vx, vy) = ball.velocity.unwrap();
+        if vx == 0.0 {
+            return false;
+        }
+        if let Some(blue_team_on_positive_half) = sample.blue_team_on_positive_half {
+            let defends_positive_half =
+                (kicker.team == log_labels::Team::BLUE) == blue_team_on_positive_half;
+            if (vx > 0.0) == defends_positive_half {
+                return false;
+            }
+        }
+
+        let half_length = self.field.half_length();
+        let goal_line_x = if vx > 0.0 { half_length } else { -half_length };
+        let y = ball.y + vy * (goal_line_x - ball.x) / vx;
+        y.abs() <= self.field.goal_width / 2.0 + margin
+    }
+
+    fn detect(&self) -> DetectedEvents {
+        let mut events = DetectedEvents::default();
+        let half_goal_width = self.field.goal_width / 2.0;
+
+        let mut index = 1;
+        while index + 1 < self.samples.len() {
+            let (start_frame, kicker) = match self.kick_at(index) {
+                Some(kick) => kick,
+                None => {
+                    index += 1;
+                    continue;
+                }
+            };
+            let (end_index, end) = self.follow_ball(index, &kicker);
+
+            if self.heads_for_goal(index, &kicker, self.config.shot_margin) {
+                let mut label = log_labels::GoalShotLabel::new();
+                label.set_start_frame(start_frame as u64);
+                label.set_end_frame(end_index as u64);
+                label.set_shooter_id(kicker.robot_id);
+                label.set_shooter_team(kicker.team);
+                label.set_successful(match end {
+                    EventEnd::GoalLine(y) => y.abs() <= half_goal_width,
+                    _ => false,
+                });
+                events.goal_shot_labels.push(label);
+                events
+                    .goal_shots_on_target
+                    .push(self.heads_for_goal(index, &kicker, 0.0));
+            } else {
+                let receiver = match end {
+                    // getting the ball back is not a pass
+                    EventEnd::Touch(robot)
+                        if robot.team == kicker.team && robot.robot_id == kicker.robot_id =>
+                    {
+                        None
+                    }
+                    EventEnd::Touch(robot) if robot.team == kicker.team => Some((robot, true)),
+                    _ => {
+                        // guess the intended receiver from where the
+                        // ball ended up
+                        let end_ball = self.samples[..=end_index]
+                            .iter()
+                            .rev()
+                            .find_map(|sample| sample.ball)
+                            .unwrap();
+                        let receiver = self.samples[end_index].closest_robot(
+                            end_ball.x,
+                            end_ball.y,
+                            std::f32::INFINITY,
+                            |robot| robot.team != kicker.team || robot.robot_id == kicker.robot_id,
+                        );
+                        Some((receiver.unwrap_or(kicker), false))
+                    }
+                };
+
+                if let Some((receiver, successful)) = receiver {
+                    let mut label = log_labels::PassingLabel::new();
+                    label.set_start_frame(start_frame as u64);
+                    label.set_end_frame(end_index as u64);
+                    label.set_passer_id(kicker.robot_id);
+                    label.set_passer_team(kicker.team);
+                    label.set_receiver_id(receiver.robot_id);
+                    label.set_successful(successful);
+                    events.passing_labels.push(label);
+                }
+            }
+
+            index = end_index.max(index + 1);
+        }
+
+        events
+    }
+}
+
+// Detects events in the given frame groups. The frames of the labels
+// are indices into `frame_groups`.
+pub fn detect_events_in_frame_groups<I>(
+    frame_groups: I,
+    field: &Field,
+    config: &EventDetectorConfig,
+) -> DetectedEvents
+where
+    I: IntoIterator<Item = LabelerFrameGroup>,
+{
+    let mut sampler = Sampler::new(config);
+    for frame_group in frame_groups {
+        sampler.add(&frame_group);
+    }
+
+    Detector {
+        samples: &sampler.samples,
+        field,
+        config,
+    }
+    .detect()
+}
+
+// Detects events in a labeler data file, using the field size from its
+// geometry if there is one.
+pub fn detect_events<T: Read + Seek>(
+    reader: &LabelerDataReader<T>,
+    config: &EventDetectorConfig,
+) -> Result<DetectedEvents, LabelerDataReaderError> {
+    let field = Field::from_geometry(reader.geometry(), Division::A);
+
+    let mut sampler = Sampler::new(config);
+    for frame_group in reader {
+        sampler.add(&frame_group?);
+    }
+
+    Ok(Detector {
+        samples: &sampler.samples,
+        field: &field,
+        config,
+    }
+    .detect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protos::log_labeler_data::LabelerFrame;
+    use crate::protos::messages_robocup_ssl_referee::SSL_Referee;
+    use crate::test_utils::vision::{ball, detection, frame_group, robot};
+    use log_labels::Team::{BLUE, YELLOW};
+
+    // a frame group at 60 Hz
+    fn frame_group_at(
+        index: usize,
+        ball_position: (f32, f32),
+        robots: &[(log_labels::Team, u32, f32, f32)],
+    ) -> LabelerFrameGroup {
+        let mut robots_yellow = vec![];
+        let mut robots_blue = vec![];
+        for (team, robot_id, x, y) in robots {
+            match team {
+                YELLOW => robots_yellow.push(robot(*robot_id, *x, *y, 0.0)),
+                BLUE => robots_blue.push(robot(*robot_id, *x, *y, 0.0)),
+            }
+        }
+
+        frame_group(vec![detection(
+            0,
+            index as f64 / 60.0,
+            vec![ball(ball_position.0, ball_position.1)],
+            robots_yellow,
+            robots_blue,
+        )])
+    }
+
+    // The ball lies still for 5 frame groups, then moves `step` mm per
+    // frame group for `num_steps` frame groups and stops.
+    fn kicked_ball(
+        start: (f32, f32),
+        step: (f32, f32),
+        num_steps: usize,
+        robots: &[(log_labels::Team, u32, f32, f32)],
+    ) -> Vec<LabelerFrameGroup> {
+        (0..num_steps + 20)
+            .map(|index| {
+                let moved = (index.max(4) - 4).min(num_steps) as f32;
+                let ball = (start.0 + moved * step.0, start.1 + moved * step.1);
+                frame_group_at(index, ball, robots)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn successful_pass() {
+        let robots = [(YELLOW, 1, 0.0, 0.0), (YELLOW, 2, 100.0, 2000.0)];
+        // 3 m/s towards robot 2, reaching it after 37 frame groups
+        let frame_groups = kicked_ball((100.0, 0.0), (0.0, 50.0), 40, &robots);
+
+        let events = detect_events_in_frame_groups(
+            frame_groups,
+            &Field::new(Division::A),
+            &EventDetectorConfig::default(),
+        );
+
+        let mut expected = log_labels::PassingLabel::new();
+        expected.set_start_frame(4);
+        expected.set_end_frame(41);
+        expected.set_successful(true);
+        expected.set_passer_id(1);
+        expected.set_passer_team(YELLOW);
+        expected.set_receiver_id(2);
+        assert_eq!(events.passing_labels, vec![expected]);
+        assert!(events.goal_shot_labels.is_empty());
+    }
+
+    #[test]
+    fn intercepted_pass() {
+        let robots = [
+            (YELLOW, 1, 0.0, 0.0),
+            (YELLOW, 2, 100.0, 2000.0),
+            (BLUE, 5, 100.0, 1000.0),
+        ];
+        let frame_groups = kicked_ball((100.0, 0.0), (0.0, 50.0), 17, &robots);
+
+        let events = detect_events_in_frame_groups(
+            frame_groups,
+            &Field::new(Division::A),
+            &EventDetectorConfig::default(),
+        );
+
+        assert_eq!(events.passing_labels.len(), 1);
+        let label = &events.passing_labels[0];
+        assert!(!label.get_successful());
+        assert_eq!(label.get_end_frame(), 21);
+        assert_eq!(label.get_receiver_id(), 2);
+    }
+
+    #[test]
+    fn goal() {
+        let robots = [(BLUE, 3, 4000.0, 0.0)];
+        // 6 m/s straight into the goal
+        let frame_groups = kicked_ball((4100.0, 0.0), (100.0, 0.0), 25, &robots);
+
+        let events = detect_events_in_frame_groups(
+            frame_groups,
+            &Field::new(Division::A),
+            &EventDetectorConfig::default(),
+        );
+
+        let mut expected = log_labels::GoalShotLabel::new();
+        expected.set_start_frame(4);
+        expected.set_end_frame(23);
+        expected.set_successful(true);
+        expected.set_shooter_id(3);
+        expected.set_shooter_team(BLUE);
+        assert_eq!(events.goal_shot_labels, vec![expected]);
+        assert_eq!(events.goal_shots_on_target, vec![true]);
+        assert!(events.passing_labels.is_empty());
+    }
+
+    #[test]
+    fn kicks_towards_own_goal_are_not_shots() {
+        let robots = [(BLUE, 3, 4000.0, 0.0)];
+        for &blue_team_on_positive_half in &[true, false] {
+            let mut frame_groups = kicked_ball((4100.0, 0.0), (100.0, 0.0), 25, &robots);
+            let mut referee = SSL_Referee::new();
+            referee.set_blueTeamOnPositiveHalf(blue_team_on_positive_half);
+            let mut frame = LabelerFrame::new();
+            frame.set_referee_frame(referee);
+            frame_groups[0].mut_frames().push(frame);
+
+            let events = detect_events_in_frame_groups(
+                frame_groups,
+                &Field::new(Division::A),
+                &EventDetectorConfig::default(),
+            );
+
+            // blue kicks towards +x
+            let num_goal_shots = if blue_team_on_positive_half { 0 } else { 1 };
+            assert_eq!(events.goal_shot_labels.len(), num_goal_shots);
+        }
+    }
+
+    #[test]
+    fn still_ball_has_no_events() {
+        let robots = [(YELLOW, 1, 0.0, 0.0)];
+        let frame_groups = kicked_ball((100.0, 0.0), (0.0, 0.0), 10, &robots);
+
+        let events = detect_events_in_frame_groups(
+            frame_groups,
+            &Field::new(Division::A),
+            &EventDetectorConfig::default(),
+        );
+
+        assert_eq!(events, DetectedEvents::default());
+    }
+}
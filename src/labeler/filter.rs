@@ -0,0 +1,135 @@
+use crate::protos::log_labeler_data;
+use crate::protos::messages_robocup_ssl_referee::{SSL_Referee_Command, SSL_Referee_Stage};
+use crate::referee;
+use crate::referee::RefereeState;
+use protobuf::ProtobufEnum;
+
+// Decides which referee states are kept in a labeler data file and
+// how the kept messages are grouped into frames.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilterConfig {
+    pub grouping: log_labeler_data::FrameGrouping,
+    pub running_stages: Vec<SSL_Referee_Stage>,
+    pub running_commands: Vec<SSL_Referee_Command>,
+}
+
+impl Default for FilterConfig {
+    fn default() -> Self {
+        FilterConfig {
+            grouping: log_labeler_data::FrameGrouping::CAMERA_REPEAT,
+            running_stages: referee::RUNNING_STAGES.to_vec(),
+            running_commands: referee::RUNNING_COMMANDS.to_vec(),
+        }
+    }
+}
+
+impl FilterConfig {
+    // Keeps every referee state, for looking at a whole log instead of
+    // only the game play
+    pub fn keep_all() -> Self {
+        FilterConfig {
+            running_stages: SSL_Referee_Stage::values().to_vec(),
+            running_commands: SSL_Referee_Command::values().to_vec(),
+            ..Default::default()
+        }
+    }
+
+    pub fn is_running_stage(&self, stage: Option<SSL_Referee_Stage>) -> bool {
+        match stage {
+            Some(stage) => self.running_stages.contains(&stage),
+            None => false,
+        }
+    }
+
+    pub fn is_running_command(&self, command: Option<SSL_Referee_Command>) -> bool {
+        match command {
+            Some(command) => self.running_commands.contains(&command),
+            None => false,
+        }
+    }
+
+    pub fn is_running(&self, state: Option<&RefereeState>) -> bool {
+        self.is_running_stage(state.map(|state| state.stage))
+            && self.is_running_command(state.map(|state| state.command))
+    }
+
+    pub fn to_proto(&self) -> log_labeler_data::LabelerFilterConfig {
+        let mut config = log_labeler_data::LabelerFilterConfig::new();
+        config.set_grouping(self.grouping);
+        config.set_running_stages(self.running_stages.iter().map(|s| s.value()).collect());
+        config.set_running_commands(self.running_commands.iter().map(|c| c.value()).collect());
+
+        config
+    }
+
+    // Unknown enum values are dropped, as they could only come from a
+    // newer version of the referee protocol.
+    pub fn from_proto(config: &log_labeler_data::LabelerFilterConfig) -> FilterConfig {
+        FilterConfig {
+            grouping: config.get_grouping(),
+            running_stages: config
+                .get_running_stages()
+                .iter()
+                .filter_map(|s| SSL_Referee_Stage::from_i32(*s))
+                .collect(),
+            running_commands: config
+                .get_running_commands()
+                .iter()
+                .filter_map(|c| SSL_Referee_Command::from_i32(*c))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::labeler as test_utils_labeler;
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn default_running_stages(stage in test_utils_labeler::running_stage_strategy()) {
+            prop_assert!(FilterConfig::default().is_running_stage(Some(stage)));
+        }
+
+        #[test]
+        fn default_not_running_stages(stage in test_utils_labeler::not_running_stage_strategy()) {
+            prop_assert!(!FilterConfig::default().is_running_stage(Some(stage)));
+        }
+
+        #[test]
+        fn default_running_commands(command in test_utils_labeler::running_command_strategy()) {
+            prop_assert!(FilterConfig::default().is_running_command(Some(command)));
+        }
+
+        #[test]
+        fn default_not_running_commands(command in test_utils_labeler::not_running_command_strategy()) {
+            prop_assert!(!FilterConfig::default().is_running_command(Some(command)));
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn keep_all_keeps_not_running(stage in test_utils_labeler::not_running_stage_strategy(),
+                                      command in test_utils_labeler::not_running_command_strategy()) {
+            let config = FilterConfig::keep_all();
+            prop_assert!(config.is_running_stage(Some(stage)));
+            prop_assert!(config.is_running_command(Some(command)));
+        }
+    }
+
+    #[test]
+    fn proto_round_trip() {
+        let config = FilterConfig::default();
+        assert_eq!(FilterConfig::from_proto(&config.to_proto()), config);
+    }
+
+    #[test]
+    fn no_referee_state_is_not_running() {
+        let config = FilterConfig::default();
+        assert!(!config.is_running_stage(None));
+        assert!(!config.is_running_command(None));
+        assert!(!config.is_running(None));
+    }
+}
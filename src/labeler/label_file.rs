@@ -0,0 +1,689 @@
+// Reading and writing label files in the binary protobuf format and in
+// human readable formats.
+//
+// JSON holds the complete Labels message:
+//
+//   {
+//     "dribbling_labels": [{"is_dribbling": true, "robot_id": 3, "team": "BLUE"}, ...],
+//     "ball_possession_labels": [{"state": "YELLOW_POSSES", "robot_id": 1}, ...],
+//     "passing_labels": [{"start_frame": 10, "end_frame": 25, "successful": true,
+//                         "passer_id": 1, "passer_team": "YELLOW", "receiver_id": 4}, ...],
+//     "goal_shot_labels": [{"start_frame": 40, "end_frame": 52, "successful": false,
+//                           "shooter_id": 2, "shooter_team": "BLUE"}, ...]
+//   }
+//
+// Enums are written by name, but numbers are accepted when reading.
+// Like in proto3, missing fields are set to their default value.
+//
+// CSV only holds the per frame dribbling and ball possession labels,
+// with one row per frame:
+//
+//   frame,is_dribbling,dribbling_robot_id,dribbling_team,ball_possession_state,ball_possession_robot_id
+use crate::protos::log_labels;
+use protobuf::{self, Message, ProtobufEnum};
+use serde_json::{self, json, Map, Value};
+use std::fs::File;
+use std::io;
+use std::io::{Read, Write};
+use std::path::Path;
+
+pub const CSV_HEADER: &str = "frame,is_dribbling,dribbling_robot_id,dribbling_team,ball_possession_state,ball_possession_robot_id";
+
+#[derive(Debug, Fail)]
+pub enum LabelFileError {
+    #[fail(display = "{}", _0)]
+    Io(#[fail(cause)] io::Error),
+    #[fail(display = "{}", _0)]
+    Protobuf(#[fail(cause)] protobuf::ProtobufError),
+    #[fail(display = "{}", _0)]
+    Json(#[fail(cause)] serde_json::Error),
+    #[fail(display = "invalid JSON labels at {}: {}", path, reason)]
+    InvalidJson { path: String, reason: String },
+    #[fail(display = "invalid CSV labels on line {}: {}", line, reason)]
+    InvalidCsv { line: usize, reason: String },
+    #[fail(
+        display = "{} dribbling labels but {} ball possession labels",
+        num_dribbling, num_ball_possession
+    )]
+    FrameLabelCountMismatch {
+        num_dribbling: usize,
+        num_ball_possession: usize,
+    },
+    #[fail(
+        display = "CSV label files cannot hold the {} event labels",
+        num_events
+    )]
+    EventsNotSupported { num_events: usize },
+}
+
+impl From<io::Error> for LabelFileError {
+    fn from(error: io::Error) -> Self {
+        LabelFileError::Io(error)
+    }
+}
+
+impl From<protobuf::ProtobufError> for LabelFileError {
+    fn from(error: protobuf::ProtobufError) -> Self {
+        match error {
+            protobuf::ProtobufError::IoError(e) => LabelFileError::Io(e),
+            e => LabelFileError::Protobuf(e),
+        }
+    }
+}
+
+impl From<serde_json::Error> for LabelFileError {
+    fn from(error: serde_json::Error) -> Self {
+        LabelFileError::Json(error)
+    }
+}
+
+type LabelFileResult<T> = Result<T, LabelFileError>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LabelFormat {
+    Protobuf,
+    Json,
+    Csv,
+}
+
+impl LabelFormat {
+    // .json and .csv files are JSON and CSV, everything else is
+    // protobuf
+    pub fn from_path(path: &Path) -> LabelFormat {
+        match path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| extension.to_lowercase())
+        {
+            Some(ref extension) if extension == "json" => LabelFormat::Json,
+            Some(ref extension) if extension == "csv" => LabelFormat::Csv,
+            _ => LabelFormat::Protobuf,
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<LabelFormat> {
+        match name {
+            "protobuf" | "label" => Some(LabelFormat::Protobuf),
+            "json" => Some(LabelFormat::Json),
+            "csv" => Some(LabelFormat::Csv),
+            _ => None,
+        }
+    }
+}
+
+fn enum_name<E: ProtobufEnum>(value: E) -> &'static str {
+    value.descriptor().name()
+}
+
+pub fn labels_to_json(labels: &log_labels::Labels) -> Value {
+    json!({
+        "dribbling_labels": labels
+            .get_dribbling_labels()
+            .iter()
+            .map(|label| json!({
+                "is_dribbling": label.get_is_dribbling(),
+                "robot_id": label.get_robot_id(),
+                "team": enum_name(label.get_team()),
+            }))
+            .collect::<Vec<_>>(),
+        "ball_possession_labels": labels
+            .get_ball_possession_labels()
+            .iter()
+            .map(|label| json!({
+                "state": enum_name(label.get_state()),
+                "robot_id": label.get_robot_id(),
+            }))
+            .collect::<Vec<_>>(),
+        "passing_labels": labels
+            .get_passing_labels()
+            .iter()
+            .map(|label| json!({
+                "start_frame": label.get_start_frame(),
+                "end_frame": label.get_end_frame(),
+                "successful": label.get_successful(),
+                "passer_id": label.get_passer_id(),
+                "passer_team": enum_name(label.get_passer_team()),
+                "receiver_id": label.get_receiver_id(),
+            }))
+            .collect::<Vec<_>>(),
+        "goal_shot_labels": labels
+            .get_goal_shot_labels()
+            .iter()
+            .map(|label| json!({
+                "start_frame": label.get_start_frame(),
+                "end_frame": label.get_end_frame(),
+                "successful": label.get_successful(),
+                "shooter_id": label.get_shooter_id(),
+                "shooter_team": enum_name(label.get_shooter_team()),
+            }))
+            .collect::<Vec<_>>(),
+    })
+}
+
+fn invalid_json<T, S: Into<String>>(path: &str, reason: S) -> LabelFileResult<T> {
+    Err(LabelFileError::InvalidJson {
+        path: path.to_owned(),
+        reason: reason.into(),
+    })
+}
+
+// Fields of a JSON object. Remembers the path of the object for the
+// error messages and rejects keys that are not part of the schema.
+struct JsonObject<'a> {
+    path: String,
+    fields: &'a Map<String, Value>,
+}
+
+impl<'a> JsonObject<'a> {
+    fn new(value: &'a Value, path: String, keys: &[&str]) -> LabelFileResult<JsonObject<'a>> {
+        let fields = match value.as_object() {
+            Some(fields) => fields,
+            None => return invalid_json(&path, "expected an object"),
+        };
+        if let Some(key) = fields.keys().find(|key| !keys.contains(&key.as_str())) {
+            return invalid_json(&path, format!("unknown field \"{}\"", key));
+        }
+
+        Ok(JsonObject { path, fields })
+    }
+
+    fn field_path(&self, key: &str) -> String {
+        if self.path.is_empty() {
+            key.to_owned()
+        } else {
+            format!("{}.{}", self.path, key)
+        }
+    }
+
+    fn get_u64(&self, key: &str) -> LabelFileResult<u64> {
+        match self.fields.get(key) {
+            None => Ok(0),
+            Some(value) => match value.as_u64() {
+                Some(value) => Ok(value),
+                None => invalid_json(&self.field_path(key), "expected an unsigned integer"),
+            },
+        }
+    }
+
+    fn get_u32(&self, key: &str) -> LabelFileResult<u32> {
+        let value = self.get_u64(key)?;
+        if value > u64::from(std::u32::MAX) {
+            return invalid_json(&self.field_path(key), "value does not fit in 32 bits");
+        }
+        Ok(value as u32)
+    }
+
+    fn get_bool(&self, key: &str) -> LabelFileResult<bool> {
+        match self.fields.get(key) {
+            None => Ok(false),
+            Some(value) => match value.as_bool() {
+                Some(value) => Ok(value),
+                None => invalid_json(&self.field_path(key), "expected a boolean"),
+            },
+        }
+    }
+
+    fn get_enum<E: ProtobufEnum + Default>(&self, key: &str) -> LabelFileResult<E> {
+        let value = match self.fields.get(key) {
+            None => return Ok(E::default()),
+            Some(value) => value,
+        };
+        let parsed = match value {
+            Value::String(name) => E::values()
+                .iter()
+                .find(|v| enum_name(**v) == name.as_str())
+                .cloned(),
+            Value::Number(number) => number
+                .as_i64()
+                .filter(|number| *number >= 0 && *number <= i64::from(std::i32::MAX))
+                .and_then(|number| E::from_i32(number as i32)),
+            _ => None,
+        };
+        match parsed {
+            Some(parsed) => Ok(parsed),
+            None => {
+                let names: Vec<_> = E::values().iter().map(|v| enum_name(*v)).collect();
+                invalid_json(
+                    &self.field_path(key),
+                    format!("expected one of {}", names.join(", ")),
+                )
+            }
+        }
+    }
+
+    fn get_array(&self, key: &str) -> LabelFileResult<&'a [Value]> {
+        match self.fields.get(key) {
+            None => Ok(&[]),
+            Some(Value::Array(values)) => Ok(values.as_slice()),
+            Some(_) => invalid_json(&self.field_path(key), "expected an array"),
+        }
+    }
+
+    // parses every element of an array field with `parse`
+    fn parse_array<T, F>(&self, key: &str, keys: &[&str], parse: F) -> LabelFileResult<Vec<T>>
+    where
+        F: Fn(&JsonObject) -> LabelFileResult<T>,
+    {
+        self.get_array(key)?
+            .iter()
+            .enumerate()
+            .map(|(index, value)| {
+                let path = format!("{}[{}]", self.field_path(key), index);
+                parse(&JsonObject::new(value, path, keys)?)
+            })
+            .collect()
+    }
+}
+
+pub fn labels_from_json(value: &Value) -> LabelFileResult<log_labels::Labels> {
+    let root = JsonObject::new(
+        value,
+        String::new(),
+        &[
+            "dribbling_labels",
+            "ball_possession_labels",
+            "passing_labels",
+            "goal_shot_labels",
+        ],
+    )?;
+
+    let dribbling_labels = root.parse_array(
+        "dribbling_labels",
+        &["is_dribbling", "robot_id", "team"],
+        |object| {
+            let mut label = log_labels::DribblingLabel::new();
+            label.set_is_dribbling(object.get_bool("is_dribbling")?);
+            label.set_robot_id(object.get_u32("robot_id")?);
+            label.set_team(object.get_enum("team")?);
+            Ok(label)
+        },
+    )?;
+
+    let ball_possession_labels =
+        root.parse_array("ball_possession_labels", &["state", "robot_id"], |object| {
+            let mut label = log_labels::BallPossessionLabel::new();
+            label.set_state(object.get_enum("state")?);
+            label.set_robot_id(object.get_u32("robot_id")?);
+            Ok(label)
+        })?;
+
+    let passing_labels = root.parse_array(
+        "passing_labels",
+        &[
+            "start_frame",
+            "end_frame",
+            "successful",
+            "passer_id",
+            "passer_team",
+            "receiver_id",
+        ],
+        |object| {
+            let mut label = log_labels::PassingLabel::new();
+            label.set_start_frame(object.get_u64("start_frame")?);
+            label.set_end_frame(object.get_u64("end_frame")?);
+            label.set_successful(object.get_bool("successful")?);
+            label.set_passer_id(object.get_u32("passer_id")?);
+            label.set_passer_team(object.get_enum("passer_team")?);
+            label.set_receiver_id(object.get_u32("receiver_id")?);
+            Ok(label)
+        },
+    )?;
+
+    let goal_shot_labels = root.parse_array(
+        "goal_shot_labels",
+        &[
+            "start_frame",
+            "end_frame",
+            "successful",
+            "shooter_id",
+            "shooter_team",
+        ],
+        |object| {
+            let mut label = log_labels::GoalShotLabel::new();
+            label.set_start_frame(object.get_u64("start_frame")?);
+            label.set_end_frame(object.get_u64("end_frame")?);
+            label.set_successful(object.get_bool("successful")?);
+            label.set_shooter_id(object.get_u32("shooter_id")?);
+            label.set_shooter_team(object.get_enum("shooter_team")?);
+            Ok(label)
+        },
+    )?;
+
+    let mut labels = log_labels::Labels::new();
+    labels.set_dribbling_labels(dribbling_labels.into());
+    labels.set_ball_possession_labels(ball_possession_labels.into());
+    labels.set_passing_labels(passing_labels.into());
+    labels.set_goal_shot_labels(goal_shot_labels.into());
+    Ok(labels)
+}
+
+// Only the dribbling and ball possession labels are written, the
+// passing and goal shot labels are ignored.
+pub fn labels_to_csv(labels: &log_labels::Labels) -> LabelFileResult<String> {
+    let dribbling_labels = labels.get_dribbling_labels();
+    let ball_possession_labels = labels.get_ball_possession_labels();
+    if dribbling_labels.len() != ball_possession_labels.len() {
+        return Err(LabelFileError::FrameLabelCountMismatch {
+            num_dribbling: dribbling_labels.len(),
+            num_ball_possession: ball_possession_labels.len(),
+        });
+    }
+
+    let mut csv = String::from(CSV_HEADER);
+    csv.push('\n');
+    for (frame, (dribbling, ball_possession)) in dribbling_labels
+        .iter()
+        .zip(ball_possession_labels)
+        .enumerate()
+    {
+        csv.push_str(&format!(
+            "{},{},{},{},{},{}\n",
+            frame,
+            dribbling.get_is_dribbling(),
+            dribbling.get_robot_id(),
+            enum_name(dribbling.get_team()),
+            enum_name(ball_possession.get_state()),
+            ball_possession.get_robot_id(),
+        ));
+    }
+    Ok(csv)
+}
+
+fn parse_csv_field<T: std::str::FromStr>(
+    line: usize,
+    name: &str,
+    field: &str,
+) -> LabelFileResult<T> {
+    field
+        .trim()
+        .parse()
+        .map_err(|_| LabelFileError::InvalidCsv {
+            line,
+            reason: format!("invalid {} \"{}\"", name, field),
+        })
+}
+
+fn parse_csv_enum<E: ProtobufEnum>(line: usize, name: &str, field: &str) -> LabelFileResult<E> {
+    let field = field.trim();
+    E::values()
+        .iter()
+        .find(|v| enum_name(**v) == field)
+        .cloned()
+        .ok_or_else(|| LabelFileError::InvalidCsv {
+            line,
+            reason: format!("invalid {} \"{}\"", name, field),
+        })
+}
+
+// Returns labels with only the dribbling and ball possession labels
+// set. Rows must be in frame order.
+pub fn labels_from_csv(csv: &str) -> LabelFileResult<log_labels::Labels> {
+    let mut lines = csv
+        .lines()
+        .enumerate()
+        .filter(|(_, l)| !l.trim().is_empty());
+    match lines.next() {
+        Some((_, header)) if header.trim() == CSV_HEADER => {}
+        _ => {
+            return Err(LabelFileError::InvalidCsv {
+                line: 1,
+                reason: format!("expected header \"{}\"", CSV_HEADER),
+            })
+        }
+    }
+
+    let mut dribbling_labels = vec![];
+    let mut ball_possession_labels = vec![];
+    for (index, row) in lines {
+        let line = index + 1;
+        let fields: Vec<&str> = row.split(',').collect();
+        if fields.len() != 6 {
+            return Err(LabelFileError::InvalidCsv {
+                line,
+                reason: format!("expected 6 fields, found {}", fields.len()),
+            });
+        }
+
+        let frame: usize = parse_csv_field(line, "frame", fields[0])?;
+        if frame != dribbling_labels.len() {
+            return Err(LabelFileError::InvalidCsv {
+                line,
+                reason: format!("expected frame {}, found {}", dribbling_labels.len(), frame),
+            });
+        }
+
+        let mut dribbling = log_labels::DribblingLabel::new();
+        dribbling.set_is_dribbling(parse_csv_field(line, "is_dribbling", fields[1])?);
+        dribbling.set_robot_id(parse_csv_field(line, "dribbling_robot_id", fields[2])?);
+        dribbling.set_team(parse_csv_enum(line, "dribbling_team", fields[3])?);
+        dribbling_labels.push(dribbling);
+
+        let mut ball_possession = log_labels::BallPossessionLabel::new();
+        ball_possession.set_state(parse_csv_enum(line, "ball_possession_state", fields[4])?);
+        ball_possession.set_robot_id(parse_csv_field(
+            line,
+            "ball_possession_robot_id",
+            fields[5],
+        )?);
+        ball_possession_labels.push(ball_possession);
+    }
+
+    let mut labels = log_labels::Labels::new();
+    labels.set_dribbling_labels(dribbling_labels.into());
+    labels.set_ball_possession_labels(ball_possession_labels.into());
+    Ok(labels)
+}
+
+pub fn read_labels<R: Read>(
+    reader: &mut R,
+    format: LabelFormat,
+) -> LabelFileResult<log_labels::Labels> {
+    match format {
+        LabelFormat::Protobuf => Ok(protobuf::parse_from_reader(reader)?),
+        LabelFormat::Json => labels_from_json(&serde_json::from_reader(reader)?),
+        LabelFormat::Csv => {
+            let mut csv = String::new();
+            reader.read_to_string(&mut csv)?;
+            labels_from_csv(&csv)
+        }
+    }
+}
+
+// Writing CSV fails if there are passing or goal shot labels, since
+// they would be lost.
+pub fn write_labels<W: Write>(
+    writer: &mut W,
+    labels: &log_labels::Labels,
+    format: LabelFormat,
+) -> LabelFileResult<()> {
+    match format {
+        LabelFormat::Protobuf => labels.write_to_writer(writer)?,
+        LabelFormat::Json => {
+            serde_json::to_writer_pretty(&mut *writer, &labels_to_json(labels))?;
+            writer.write_all(b"\n")?;
+        }
+        LabelFormat::Csv => {
+            let num_events =
+                labels.get_passing_labels().len() + labels.get_goal_shot_labels().len();
+            if num_events > 0 {
+                return Err(LabelFileError::EventsNotSupported { num_events });
+            }
+            writer.write_all(labels_to_csv(labels)?.as_bytes())?;
+        }
+    }
+    Ok(())
+}
+
+// Reads a label file in the format given by its extension.
+pub fn read_labels_from_path(path: &Path) -> LabelFileResult<log_labels::Labels> {
+    let mut file = File::open(path)?;
+    read_labels(&mut file, LabelFormat::from_path(path))
+}
+
+// Writes a label file in the format given by its extension,
+// overwriting any existing file. The labels are serialized before the
+// file is created, so a failed write leaves an existing file intact.
+pub fn write_labels_to_path(path: &Path, labels: &log_labels::Labels) -> LabelFileResult<()> {
+    let mut buffer = vec![];
+    write_labels(&mut buffer, labels, LabelFormat::from_path(path))?;
+
+    let mut file = File::create(path)?;
+    file.write_all(&buffer)?;
+    file.sync_all()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate tempfile;
+
+    use super::*;
+    use crate::test_utils::labels as test_utils_labels;
+    use proptest::prelude::*;
+
+    impl From<LabelFileError> for TestCaseError {
+        fn from(error: LabelFileError) -> TestCaseError {
+            TestCaseError::fail(format!("{}", error))
+        }
+    }
+
+    fn frame_labels(labels: &log_labels::Labels) -> log_labels::Labels {
+        let mut frame_labels = labels.clone();
+        frame_labels.clear_passing_labels();
+        frame_labels.clear_goal_shot_labels();
+        frame_labels
+    }
+
+    proptest! {
+        #[test]
+        fn json_round_trip(labels in test_utils_labels::random_labels_strategy(20, 10)) {
+            let mut json = vec![];
+            write_labels(&mut json, &labels, LabelFormat::Json)?;
+            let read_labels = read_labels(&mut json.as_slice(), LabelFormat::Json)?;
+
+            prop_assert_eq!(read_labels, labels);
+        }
+
+        #[test]
+        fn csv_round_trip(labels in test_utils_labels::random_labels_strategy(20, 10)) {
+            let labels = frame_labels(&labels);
+
+            let mut csv = vec![];
+            write_labels(&mut csv, &labels, LabelFormat::Csv)?;
+            let read_labels = read_labels(&mut csv.as_slice(), LabelFormat::Csv)?;
+
+            prop_assert_eq!(read_labels, labels);
+        }
+
+        #[test]
+        fn csv_rejects_events(labels in test_utils_labels::random_labels_strategy(20, 10)) {
+            let num_events = labels.get_passing_labels().len() + labels.get_goal_shot_labels().len();
+            prop_assume!(num_events > 0);
+
+            let mut csv = vec![];
+            match write_labels(&mut csv, &labels, LabelFormat::Csv) {
+                Err(LabelFileError::EventsNotSupported { num_events: n }) => prop_assert_eq!(n, num_events),
+                result => return Err(TestCaseError::fail(format!("Unexpected result: {:?}", result))),
+            }
+        }
+    }
+
+    #[test]
+    fn json_defaults_and_numbers() {
+        let value = json!({
+            "dribbling_labels": [{"team": 1}],
+            "passing_labels": [{"start_frame": 3, "passer_team": "BLUE"}],
+        });
+
+        let labels = labels_from_json(&value).unwrap();
+
+        assert_eq!(
+            labels.get_dribbling_labels()[0].get_team(),
+            log_labels::Team::BLUE
+        );
+        assert!(!labels.get_dribbling_labels()[0].get_is_dribbling());
+        assert!(labels.get_ball_possession_labels().is_empty());
+        assert_eq!(labels.get_passing_labels()[0].get_start_frame(), 3);
+        assert_eq!(labels.get_passing_labels()[0].get_end_frame(), 0);
+    }
+
+    #[test]
+    fn json_errors_name_the_field() {
+        let invalid_values = vec![
+            (
+                json!({"passing_labels": [{}, {"passer_team": "GREEN"}]}),
+                "passing_labels[1].passer_team",
+            ),
+            (
+                json!({"goal_shot_labels": [{"start_frame": -1}]}),
+                "goal_shot_labels[0].start_frame",
+            ),
+            (
+                json!({"dribbling_labels": [{"robot": 1}]}),
+                "dribbling_labels[0]",
+            ),
+            (json!({"dribbling_labels": {}}), "dribbling_labels"),
+        ];
+
+        for (value, expected_path) in invalid_values {
+            match labels_from_json(&value) {
+                Err(LabelFileError::InvalidJson { path, .. }) => assert_eq!(path, expected_path),
+                result => panic!("Unexpected result: {:?}", result),
+            }
+        }
+    }
+
+    #[test]
+    fn csv_errors_name_the_line() {
+        let csv = format!(
+            "{}\n0,true,1,YELLOW,NONE,0\n2,true,1,YELLOW,NONE,0\n",
+            CSV_HEADER
+        );
+
+        match labels_from_csv(&csv) {
+            Err(LabelFileError::InvalidCsv { line, .. }) => assert_eq!(line, 3),
+            result => panic!("Unexpected result: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn format_from_path() {
+        assert_eq!(
+            LabelFormat::from_path(Path::new("a.label")),
+            LabelFormat::Protobuf
+        );
+        assert_eq!(
+            LabelFormat::from_path(Path::new("a.JSON")),
+            LabelFormat::Json
+        );
+        assert_eq!(LabelFormat::from_path(Path::new("a.csv")), LabelFormat::Csv);
+        assert_eq!(
+            LabelFormat::from_path(Path::new("a")),
+            LabelFormat::Protobuf
+        );
+    }
+
+    #[test]
+    fn failed_csv_save_keeps_old_file() {
+        let path = tempfile::Builder::new()
+            .suffix(".csv")
+            .tempfile()
+            .unwrap()
+            .into_temp_path();
+        let old_labels = log_labels::Labels::new();
+        write_labels_to_path(&path, &old_labels).unwrap();
+        let old_csv = std::fs::read(&path).unwrap();
+
+        let mut labels = log_labels::Labels::new();
+        labels
+            .mut_passing_labels()
+            .push(log_labels::PassingLabel::new());
+        match write_labels_to_path(&path, &labels) {
+            Err(LabelFileError::EventsNotSupported { num_events: 1 }) => {}
+            result => panic!("expected EventsNotSupported, got {:?}", result),
+        }
+
+        assert_eq!(std::fs::read(&path).unwrap(), old_csv);
+        assert_eq!(read_labels_from_path(&path).unwrap(), old_labels);
+    }
+}
@@ -0,0 +1,619 @@
+use super::auto::{label_frame_group, AutoLabelerConfig};
+use super::detect::{detect_events, EventDetectorConfig};
+use super::reader::{LabelerDataReader, LabelerDataReaderError};
+use crate::geometry::{Division, Field};
+use crate::protos::log_labels;
+use crate::referee::{Goal, Timeline};
+use crate::vision::{TeamColor, WorldFrame, WorldFrameConfig};
+use serde_json::{self, json};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::io::{Read, Seek};
+
+// Match statistics per team, built from the possession, passing and
+// goal shot heuristics of the labeler. Only the frame groups of a
+// labeler data file are used, so with the default filter the
+// statistics cover the running game and not stoppages.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MatchReportConfig {
+    pub auto_labeler: AutoLabelerConfig,
+    pub event_detector: EventDetectorConfig,
+    // robot movement across longer gaps between frame groups, for
+    // example over a stoppage, is not counted
+    pub max_frame_gap: f64,
+    // faster robot movements in mm/s are vision glitches and are not
+    // counted
+    pub max_robot_speed: f32,
+}
+
+impl Default for MatchReportConfig {
+    fn default() -> Self {
+        MatchReportConfig {
+            auto_labeler: AutoLabelerConfig::default(),
+            event_detector: EventDetectorConfig::default(),
+            max_frame_gap: 0.1,
+            max_robot_speed: 5000.0,
+        }
+    }
+}
+
+// Thirds are seen from the team, so the defensive third is the one
+// with the team's own goal.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Third {
+    Defensive,
+    Middle,
+    Attacking,
+}
+
+fn third(field: &Field, x: f32, defends_positive_half: bool) -> Third {
+    let own_x = if defends_positive_half { x } else { -x };
+    let third_length = field.field_length / 3.0;
+    if own_x > third_length / 2.0 {
+        Third::Defensive
+    } else if own_x < -third_length / 2.0 {
+        Third::Attacking
+    } else {
+        Third::Middle
+    }
+}
+
+// Times in s. Only counted while the referee tells which half each
+// team plays on.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ThirdTimes {
+    pub defensive: f64,
+    pub middle: f64,
+    pub attacking: f64,
+}
+
+impl ThirdTimes {
+    fn add(&mut self, third: Third, time: f64) {
+        match third {
+            Third::Defensive => self.defensive += time,
+            Third::Middle => self.middle += time,
+            Third::Attacking => self.attacking += time,
+        }
+    }
+
+    fn add_times(&mut self, other: &ThirdTimes) {
+        self.defensive += other.defensive;
+        self.middle += other.middle;
+        self.attacking += other.attacking;
+    }
+
+    pub fn total(&self) -> f64 {
+        self.defensive + self.middle + self.attacking
+    }
+
+    // Shares of the total time, all 0 if there is none
+    pub fn fractions(&self) -> ThirdTimes {
+        let total = self.total();
+        if total == 0.0 {
+            return ThirdTimes::default();
+        }
+        ThirdTimes {
+            defensive: self.defensive / total,
+            middle: self.middle / total,
+            attacking: self.attacking / total,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct RobotStats {
+    pub robot_id: u32,
+    // in mm
+    pub distance: f32,
+    // time in s the robot was seen in consecutive frame groups
+    pub tracked_time: f64,
+    pub thirds: ThirdTimes,
+}
+
+impl RobotStats {
+    // in mm/s
+    pub fn average_speed(&self) -> f32 {
+        if self.tracked_time > 0.0 {
+            self.distance / self.tracked_time as f32
+        } else {
+            0.0
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TeamStats {
+    pub name: String,
+    // frame groups in which a robot of the team possessed the ball
+    pub possession_frame_groups: usize,
+    pub passes: usize,
+    pub successful_passes: usize,
+    pub shots: usize,
+    pub shots_on_target: usize,
+    pub robots: BTreeMap<u32, RobotStats>,
+}
+
+impl TeamStats {
+    // None if the team did not pass
+    pub fn pass_success_rate(&self) -> Option<f64> {
+        if self.passes == 0 {
+            None
+        } else {
+            Some(self.successful_passes as f64 / self.passes as f64)
+        }
+    }
+
+    pub fn distance(&self) -> f32 {
+        self.robots.values().map(|robot| robot.distance).sum()
+    }
+
+    // Average over all robots, weighted by how long each was tracked
+    pub fn average_speed(&self) -> f32 {
+        let tracked_time: f64 = self.robots.values().map(|robot| robot.tracked_time).sum();
+        if tracked_time > 0.0 {
+            self.distance() / tracked_time as f32
+        } else {
+            0.0
+        }
+    }
+
+    // Summed over all robots
+    pub fn thirds(&self) -> ThirdTimes {
+        let mut thirds = ThirdTimes::default();
+        for robot in self.robots.values() {
+            thirds.add_times(&robot.thirds);
+        }
+        thirds
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct MatchReport {
+    pub yellow: TeamStats,
+    pub blue: TeamStats,
+    pub num_frame_groups: usize,
+    // time in s covered by the frame groups
+    pub play_time: f64,
+    pub goals: Vec<Goal>,
+    // log timestamp of the first referee message, goal times are
+    // relative to it
+    pub start_timestamp: i64,
+}
+
+impl MatchReport {
+    pub fn team(&self, team: TeamColor) -> &TeamStats {
+        match team {
+            TeamColor::Yellow => &self.yellow,
+            TeamColor::Blue => &self.blue,
+        }
+    }
+
+    fn team_mut(&mut self, team: TeamColor) -> &mut TeamStats {
+        match team {
+            TeamColor::Yellow => &mut self.yellow,
+            TeamColor::Blue => &mut self.blue,
+        }
+    }
+
+    // Share of the frame groups in which either team possessed the
+    // ball. 0 if nobody ever did.
+    pub fn possession(&self, team: TeamColor) -> f64 {
+        let total = self.yellow.possession_frame_groups + self.blue.possession_frame_groups;
+        if total == 0 {
+            0.0
+        } else {
+            self.team(team).possession_frame_groups as f64 / total as f64
+        }
+    }
+
+    pub fn goal_count(&self, team: TeamColor) -> usize {
+        self.goals.iter().filter(|goal| goal.team == team).count()
+    }
+
+    // Time since the first referee message in s
+    pub fn goal_time(&self, goal: &Goal) -> f64 {
+        (goal.timestamp - self.start_timestamp) as f64 / 1e9
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        let thirds_json = |thirds: &ThirdTimes| {
+            json!({
+                "defensive": thirds.defensive,
+                "middle": thirds.middle,
+                "attacking": thirds.attacking,
+            })
+        };
+        let team_json = |team: TeamColor| {
+            let stats = self.team(team);
+            json!({
+                "name": stats.name,
+                "possession": self.possession(team),
+                "passes": stats.passes,
+                "successful_passes": stats.successful_passes,
+                "pass_success_rate": stats.pass_success_rate(),
+                "shots": stats.shots,
+                "shots_on_target": stats.shots_on_target,
+                "goals": self.goal_count(team),
+                "distance": stats.distance() / 1000.0,
+                "average_speed": stats.average_speed() / 1000.0,
+                "thirds": thirds_json(&stats.thirds()),
+                "robots": stats
+                    .robots
+                    .values()
+                    .map(|robot| json!({
+                        "robot_id": robot.robot_id,
+                        "distance": robot.distance / 1000.0,
+                        "average_speed": robot.average_speed() / 1000.0,
+                        "tracked_time": robot.tracked_time,
+                        "thirds": thirds_json(&robot.thirds),
+                    }))
+                    .collect::<Vec<_>>(),
+            })
+        };
+
+        json!({
+            "num_frame_groups": self.num_frame_groups,
+            "play_time": self.play_time,
+            "yellow": team_json(TeamColor::Yellow),
+            "blue": team_json(TeamColor::Blue),
+            "goals": self
+                .goals
+                .iter()
+                .map(|goal| json!({
+                    "time": self.goal_time(goal),
+                    "timestamp": goal.timestamp,
+                    "team": match goal.team {
+                        TeamColor::Yellow => "yellow",
+                        TeamColor::Blue => "blue",
+                    },
+                    "yellow_score": goal.yellow_score,
+                    "blue_score": goal.blue_score,
+                }))
+                .collect::<Vec<_>>(),
+        })
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut output = String::new();
+        let percent = |fraction: f64| format!("{:.1}%", fraction * 100.0);
+
+        writeln!(output, "# Match Report\n").unwrap();
+        writeln!(
+            output,
+            "{} frame groups, {:.0} s of play\n",
+            self.num_frame_groups, self.play_time
+        )
+        .unwrap();
+
+        writeln!(
+            output,
+            "| | yellow ({}) | blue ({}) |",
+            self.yellow.name, self.blue.name
+        )
+        .unwrap();
+        writeln!(output, "|---|---|---|").unwrap();
+        let mut row = |name: &str, value: &dyn Fn(TeamColor) -> String| {
+            writeln!(
+                output,
+                "| {} | {} | {} |",
+                name,
+                value(TeamColor::Yellow),
+                value(TeamColor::Blue)
+            )
+            .unwrap();
+        };
+        row("goals", &|team| self.goal_count(team).to_string());
+        row("ball possession", &|team| percent(self.possession(team)));
+        row("passes", &|team| self.team(team).passes.to_string());
+        row("successful passes", &|team| {
+            let stats = self.team(team);
+            match stats.pass_success_rate() {
+                Some(rate) => format!("{} ({})", stats.successful_passes, percent(rate)),
+                None => "0".to_owned(),
+            }
+        });
+        row("shots", &|team| self.team(team).shots.to_string());
+        row("shots on target", &|team| {
+            self.team(team).shots_on_target.to_string()
+        });
+        row("distance travelled", &|team| {
+            format!("{:.1} m", self.team(team).distance() / 1000.0)
+        });
+        row("average robot speed", &|team| {
+            format!("{:.2} m/s", self.team(team).average_speed() / 1000.0)
+        });
+        row("time in defensive third", &|team| {
+            percent(self.team(team).thirds().fractions().defensive)
+        });
+        row("time in middle third", &|team| {
+            percent(self.team(team).thirds().fractions().middle)
+        });
+        row("time in attacking third", &|team| {
+            percent(self.team(team).thirds().fractions().attacking)
+        });
+
+        writeln!(output, "\n## Goals\n").unwrap();
+        writeln!(output, "| time | team | score |").unwrap();
+        writeln!(output, "|---|---|---|").unwrap();
+        for goal in &self.goals {
+            let seconds = self.goal_time(goal).max(0.0).round() as u64;
+            writeln!(
+                output,
+                "| {}:{:02} | {} | {}:{} |",
+                seconds / 60,
+                seconds % 60,
+                self.team(goal.team).name,
+                goal.yellow_score,
+                goal.blue_score
+            )
+            .unwrap();
+        }
+
+        writeln!(output, "\n## Robots\n").unwrap();
+        writeln!(
+            output,
+            "| team | robot | distance | average speed | defensive third | middle third | attacking third |"
+        )
+        .unwrap();
+        writeln!(output, "|---|---|---|---|---|---|---|").unwrap();
+        for team in &[TeamColor::Yellow, TeamColor::Blue] {
+            for robot in self.team(*team).robots.values() {
+                let thirds = robot.thirds.fractions();
+                writeln!(
+                    output,
+                    "| {:?} | {} | {:.1} m | {:.2} m/s | {} | {} | {} |",
+                    team,
+                    robot.robot_id,
+                    robot.distance / 1000.0,
+                    robot.average_speed() / 1000.0,
+                    percent(thirds.defensive),
+                    percent(thirds.middle),
+                    percent(thirds.attacking)
+                )
+                .unwrap();
+            }
+        }
+
+        output
+    }
+}
+
+// Builds the report from a labeler data file and the referee timeline
+// of the log it was made from. The timeline provides the goals and the
+// half each team plays on.
+pub fn match_report<T: Read + Seek>(
+    reader: &LabelerDataReader<T>,
+    timeline: &Timeline,
+    config: &MatchReportConfig,
+) -> Result<MatchReport, LabelerDataReaderError> {
+    let field = Field::from_geometry(reader.geometry(), Division::A);
+    let mut report = MatchReport::default();
+
+    let last_state = timeline.intervals().last().map(|interval| &interval.state);
+    report.yellow.name = reader
+        .yellow_team_name()
+        .or_else(|| last_state.map(|state| state.yellow.name.as_str()))
+        .unwrap_or("")
+        .to_owned();
+    report.blue.name = reader
+        .blue_team_name()
+        .or_else(|| last_state.map(|state| state.blue.name.as_str()))
+        .unwrap_or("")
+        .to_owned();
+    report.goals = timeline.goals();
+    report.start_timestamp = timeline
+        .intervals()
+        .first()
+        .map_or(0, |interval| interval.start);
+
+    let mut last_t_capture: Option<f64> = None;
+    let mut last_positions = HashMap::<(TeamColor, u32), (f64, f32, f32)>::new();
+    for frame_group in reader {
+        let frame_group = frame_group?;
+        report.num_frame_groups += 1;
+
+        let (_, possession) = label_frame_group(&frame_group, &config.auto_labeler);
+        match possession.get_state() {
+            log_labels::BallPossessionLabel_State::YELLOW_POSSES => {
+                report.yellow.possession_frame_groups += 1
+            }
+            log_labels::BallPossessionLabel_State::BLUE_POSSES => {
+                report.blue.possession_frame_groups += 1
+            }
+            log_labels::BallPossessionLabel_State::NONE => {}
+        }
+
+        let world_frame =
+            match WorldFrame::from_frame_group(&frame_group, &WorldFrameConfig::default()) {
+                Some(world_frame) => world_frame,
+                None => continue,
+            };
+        let t_capture = world_frame.t_capture;
+        if let Some(last_t_capture) = last_t_capture {
+            let dt = t_capture - last_t_capture;
+            if dt > 0.0 && dt <= config.max_frame_gap {
+                report.play_time += dt;
+            }
+        }
+        last_t_capture = Some(t_capture);
+
+        let blue_on_positive_half = frame_group
+            .get_frames()
+            .first()
+            .and_then(|frame| timeline.state_at(frame.get_timestamp() as i64))
+            .and_then(|state| state.blue_team_on_positive_half);
+
+        for robot in &world_frame.robots {
+            let key = (robot.team, robot.robot_id);
+            let last_position = last_positions.insert(key, (t_capture, robot.x, robot.y));
+            let (last_t, last_x, last_y) = match last_position {
+                Some(last_position) => last_position,
+                None => continue,
+            };
+            let dt = t_capture - last_t;
+            if dt <= 0.0 || dt > config.max_frame_gap {
+                continue;
+            }
+            let distance = (robot.x - last_x).hypot(robot.y - last_y);
+            if distance / dt as f32 > config.max_robot_speed {
+                continue;
+            }
+
+            let stats = report
+                .team_mut(robot.team)
+                .robots
+                .entry(robot.robot_id)
+                .or_insert_with(|| RobotStats {
+                    robot_id: robot.robot_id,
+                    ..RobotStats::default()
+                });
+            stats.distance += distance;
+            stats.tracked_time += dt;
+            if let Some(blue_on_positive_half) = blue_on_positive_half {
+                let defends_positive_half = match robot.team {
+                    TeamColor::Yellow => !blue_on_positive_half,
+                    TeamColor::Blue => blue_on_positive_half,
+                };
+                stats
+                    .thirds
+                    .add(third(&field, robot.x, defends_positive_half), dt);
+            }
+        }
+    }
+
+    let events = detect_events(reader, &config.event_detector)?;
+    for label in &events.passing_labels {
+        let stats = report.team_mut(TeamColor::from(label.get_passer_team()));
+        stats.passes += 1;
+        if label.get_successful() {
+            stats.successful_passes += 1;
+        }
+    }
+    for (label, on_target) in events
+        .goal_shot_labels
+        .iter()
+        .zip(&events.goal_shots_on_target)
+    {
+        let stats = report.team_mut(TeamColor::from(label.get_shooter_team()));
+        stats.shots += 1;
+        if *on_target {
+            stats.shots_on_target += 1;
+        }
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::labeler::writer::LabelerDataWriter;
+    use crate::persistence::message::{Message, MessageType};
+    use crate::protos::messages_robocup_ssl_referee::{
+        SSL_Referee, SSL_Referee_Command, SSL_Referee_Stage, SSL_Referee_TeamInfo,
+    };
+    use crate::protos::messages_robocup_ssl_wrapper::SSL_WrapperPacket;
+    use crate::test_utils::vision::{ball, detection, robot};
+    use std::io::Cursor;
+
+    // with every required field set, so it can be written
+    fn team_info(name: &str, score: u32) -> SSL_Referee_TeamInfo {
+        let mut team_info = SSL_Referee_TeamInfo::new();
+        team_info.set_name(name.to_owned());
+        team_info.set_score(score);
+        team_info.set_red_cards(0);
+        team_info.set_yellow_cards(0);
+        team_info.set_timeouts(4);
+        team_info.set_timeout_time(300_000_000);
+        team_info.set_goalie(0);
+        team_info
+    }
+
+    fn referee_msg(timestamp: i64, blue_score: u32) -> Message {
+        let mut referee = SSL_Referee::new();
+        referee.set_packet_timestamp(timestamp as u64 / 1000);
+        referee.set_stage(SSL_Referee_Stage::NORMAL_FIRST_HALF);
+        referee.set_command(SSL_Referee_Command::FORCE_START);
+        referee.set_command_counter(1);
+        referee.set_command_timestamp(0);
+        referee.set_yellow(team_info("ER-Force", 0));
+        referee.set_blue(team_info("TIGERs", blue_score));
+        referee.set_blueTeamOnPositiveHalf(true);
+        Message {
+            timestamp,
+            msg_type: MessageType::Refbox2013(referee),
+        }
+    }
+
+    // The yellow robot drives at 1 m/s from the middle third into its
+    // attacking third at +x, the blue robot stands next to the ball.
+    fn vision_msg(index: usize) -> Message {
+        let t_capture = index as f64 / 60.0;
+        let mut packet = SSL_WrapperPacket::new();
+        packet.set_detection(detection(
+            0,
+            t_capture,
+            vec![ball(-3000.0, 0.0)],
+            vec![robot(1, 1000.0 + 1000.0 * t_capture as f32, 0.0, 0.0)],
+            vec![robot(2, -3100.0, 0.0, 0.0)],
+        ));
+        Message {
+            timestamp: 1_000_000_000 + (t_capture * 1e9) as i64,
+            msg_type: MessageType::Vision2014(packet),
+        }
+    }
+
+    fn example_report() -> MatchReport {
+        let mut messages = vec![referee_msg(0, 0)];
+        messages.extend((0..=120).map(vision_msg));
+        messages.push(referee_msg(4_000_000_000, 1));
+
+        let timeline = Timeline::from_messages(messages.iter().cloned().map(Ok::<_, ()>)).unwrap();
+        let mut buffer = vec![];
+        {
+            let mut writer = LabelerDataWriter::new(Cursor::new(&mut buffer)).unwrap();
+            for message in messages {
+                writer.add_msg(message).unwrap();
+            }
+        }
+        let reader = LabelerDataReader::new(Cursor::new(buffer)).unwrap();
+
+        match_report(&reader, &timeline, &MatchReportConfig::default()).unwrap()
+    }
+
+    #[test]
+    fn robot_movement() {
+        let report = example_report();
+        assert_eq!(report.num_frame_groups, 121);
+        assert!((report.play_time - 2.0).abs() < 1e-6);
+
+        let yellow = &report.yellow.robots[&1];
+        assert!((yellow.distance - 2000.0).abs() < 1.0);
+        assert!((yellow.average_speed() - 1000.0).abs() < 1.0);
+        // the attacking third starts at x = 2000
+        assert!((yellow.thirds.middle - 1.0).abs() < 0.05);
+        assert!((yellow.thirds.attacking - 1.0).abs() < 0.05);
+        assert_eq!(report.blue.robots[&2].distance, 0.0);
+        assert!((report.blue.thirds().attacking - 2.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn possession_and_goals() {
+        let report = example_report();
+        assert_eq!(report.possession(TeamColor::Blue), 1.0);
+        assert_eq!(report.possession(TeamColor::Yellow), 0.0);
+        assert_eq!(report.yellow.name, "ER-Force");
+        assert_eq!(report.goal_count(TeamColor::Blue), 1);
+        assert_eq!(report.goal_time(&report.goals[0]), 4.0);
+        assert_eq!(report.blue.passes + report.blue.shots, 0);
+    }
+
+    #[test]
+    fn thirds_from_own_goal() {
+        let field = Field::new(Division::A);
+        assert_eq!(third(&field, 3000.0, true), Third::Defensive);
+        assert_eq!(third(&field, 3000.0, false), Third::Attacking);
+        assert_eq!(third(&field, -1000.0, true), Third::Middle);
+    }
+}
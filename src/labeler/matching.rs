@@ -0,0 +1,176 @@
+// Maximum weight bipartite matching, used to pair ground truth and
+// predicted events when scoring.
+
+// Returns (row, column) pairs of the matching with the largest total
+// weight. `weights[row][column]` is the weight of pairing row with
+// column, and must not be negative. Every row of `weights` must have
+// the same length. Pairs with a weight of 0 may be part of the
+// result, callers that need to forbid pairs should filter them out.
+//
+// This is the Hungarian algorithm, O(n^2 m) for n rows and m
+// columns with n <= m.
+pub fn max_weight_matching(weights: &[Vec<f64>]) -> Vec<(usize, usize)> {
+    let num_rows = weights.len();
+    let num_columns = weights.first().map_or(0, |row| row.len());
+    if num_rows == 0 || num_columns == 0 {
+        return vec![];
+    }
+
+    // the algorithm needs at least as many columns as rows
+    if num_rows > num_columns {
+        let transposed: Vec<Vec<f64>> = (0..num_columns)
+            .map(|column| weights.iter().map(|row| row[column]).collect())
+            .collect();
+        return max_weight_matching(&transposed)
+            .into_iter()
+            .map(|(row, column)| (column, row))
+            .collect();
+    }
+
+    // minimize the negated weights. Everything is 1 indexed, index 0
+    // is a virtual row/column used to start each augmenting path.
+    let cost = |row: usize, column: usize| -weights[row - 1][column - 1];
+    let mut row_potential = vec![0.0; num_rows + 1];
+    let mut column_potential = vec![0.0; num_columns + 1];
+    // row assigned to each column, 0 if none
+    let mut assigned_row = vec![0usize; num_columns + 1];
+    let mut previous_column = vec![0usize; num_columns + 1];
+
+    for row in 1..=num_rows {
+        assigned_row[0] = row;
+        let mut column = 0;
+        let mut min_slack = vec![std::f64::INFINITY; num_columns + 1];
+        let mut visited = vec![false; num_columns + 1];
+
+        // grow the alternating tree until it reaches a free column
+        loop {
+            visited[column] = true;
+            let current_row = assigned_row[column];
+            let mut delta = std::f64::INFINITY;
+            let mut next_column = 0;
+
+            for j in 1..=num_columns {
+                if visited[j] {
+                    continue;
+                }
+                let slack = cost(current_row, j) - row_potential[current_row] - column_potential[j];
+                if slack < min_slack[j] {
+                    min_slack[j] = slack;
+                    previous_column[j] = column;
+                }
+                if min_slack[j] < delta {
+                    delta = min_slack[j];
+                    next_column = j;
+                }
+            }
+
+            for (j, visited) in visited.iter().enumerate() {
+                if *visited {
+                    row_potential[assigned_row[j]] += delta;
+                    column_potential[j] -= delta;
+                } else {
+                    min_slack[j] -= delta;
+                }
+            }
+
+            column = next_column;
+            if assigned_row[column] == 0 {
+                break;
+            }
+        }
+
+        // flip the augmenting path
+        while column != 0 {
+            let previous = previous_column[column];
+            assigned_row[column] = assigned_row[previous];
+            column = previous;
+        }
+    }
+
+    let mut matching: Vec<(usize, usize)> = (1..=num_columns)
+        .filter(|column| assigned_row[*column] != 0)
+        .map(|column| (assigned_row[column] - 1, column - 1))
+        .collect();
+    matching.sort();
+    matching
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use std::collections::HashSet;
+
+    fn matching_weight(weights: &[Vec<f64>], matching: &[(usize, usize)]) -> f64 {
+        matching
+            .iter()
+            .map(|(row, column)| weights[*row][*column])
+            .sum()
+    }
+
+    // tries every assignment of rows to distinct columns (or to
+    // nothing)
+    fn brute_force_weight(weights: &[Vec<f64>], row: usize, used: &mut [bool]) -> f64 {
+        if row == weights.len() {
+            return 0.0;
+        }
+
+        let mut best = brute_force_weight(weights, row + 1, used);
+        for (column, column_weight) in weights[row].iter().enumerate() {
+            if !used[column] {
+                used[column] = true;
+                let weight = column_weight + brute_force_weight(weights, row + 1, used);
+                used[column] = false;
+                if weight > best {
+                    best = weight;
+                }
+            }
+        }
+        best
+    }
+
+    fn weights_strategy() -> impl Strategy<Value = Vec<Vec<f64>>> {
+        (1..6usize, 1..6usize).prop_flat_map(|(num_rows, num_columns)| {
+            proptest::collection::vec(
+                proptest::collection::vec(0.0..10.0f64, num_columns),
+                num_rows,
+            )
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn matching_is_valid(weights in weights_strategy()) {
+            let matching = max_weight_matching(&weights);
+
+            let rows: HashSet<_> = matching.iter().map(|(row, _)| *row).collect();
+            let columns: HashSet<_> = matching.iter().map(|(_, column)| *column).collect();
+            prop_assert_eq!(rows.len(), matching.len());
+            prop_assert_eq!(columns.len(), matching.len());
+            prop_assert_eq!(matching.len(), std::cmp::min(weights.len(), weights[0].len()));
+        }
+
+        #[test]
+        fn matching_is_optimal(weights in weights_strategy()) {
+            let matching = max_weight_matching(&weights);
+
+            let mut used = vec![false; weights[0].len()];
+            let best = brute_force_weight(&weights, 0, &mut used);
+            prop_assert!((matching_weight(&weights, &matching) - best).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn greedy_is_not_optimal() {
+        // greedy would take (0, 0) and then only get (1, 1)
+        let weights = vec![vec![10.0, 9.0], vec![8.0, 0.0]];
+
+        assert_eq!(max_weight_matching(&weights), vec![(0, 1), (1, 0)]);
+    }
+
+    #[test]
+    fn empty_weights() {
+        assert!(max_weight_matching(&[]).is_empty());
+        assert!(max_weight_matching(&[vec![], vec![]]).is_empty());
+    }
+}
@@ -0,0 +1,585 @@
+use super::agreement::{ball_possession_class, dribbling_class, frame_ranges};
+use super::scoring::{
+    goal_shot_pair_score, goal_shot_span, match_events, passing_pair_score, passing_span,
+    ScoringOptions,
+};
+use super::validate::LabelCategory;
+use crate::protos::log_labels;
+use serde_json::{self, json};
+
+// Merging the labels of several annotators into one consensus label
+// file, with a list of the places that need a human to review them.
+
+#[derive(Debug, Fail)]
+pub enum MergeError {
+    #[fail(display = "no label files to merge")]
+    NoLabels,
+    #[fail(
+        display = "label file {} has {} frame labels, but label file 0 has {}",
+        annotator, num_frames, expected
+    )]
+    FrameCountMismatch {
+        annotator: usize,
+        num_frames: usize,
+        expected: usize,
+    },
+}
+
+type MergeResult<T> = Result<T, MergeError>;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MergeOptions {
+    // smallest overlap for two annotators' events to be the same event
+    pub min_iou: f64,
+    // number of annotators that must label an event to keep it. None
+    // is a strict majority.
+    pub quorum: Option<usize>,
+}
+
+impl Default for MergeOptions {
+    fn default() -> Self {
+        MergeOptions {
+            min_iou: ScoringOptions::default().min_iou,
+            quorum: None,
+        }
+    }
+}
+
+impl MergeOptions {
+    fn quorum(&self, num_annotators: usize) -> usize {
+        self.quorum.unwrap_or(num_annotators / 2 + 1)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MergeConflict {
+    // frames without a strict majority for one label. The merged
+    // labels use the most common label.
+    Frames {
+        category: LabelCategory,
+        start_frame: u64,
+        end_frame: u64,
+    },
+    // an event labeled by fewer annotators than the quorum. It is not
+    // in the merged labels.
+    UnconfirmedEvent {
+        category: LabelCategory,
+        span: (u64, u64),
+        annotators: Vec<usize>,
+    },
+    // an event in the merged labels whose annotators do not have a
+    // strict majority on the robots or the outcome
+    EventAttributes {
+        category: LabelCategory,
+        span: (u64, u64),
+        annotators: Vec<usize>,
+    },
+}
+
+impl MergeConflict {
+    // inclusive frame range to review
+    pub fn frames(&self) -> (u64, u64) {
+        match self {
+            MergeConflict::Frames {
+                start_frame,
+                end_frame,
+                ..
+            } => (*start_frame, *end_frame),
+            MergeConflict::UnconfirmedEvent { span, .. }
+            | MergeConflict::EventAttributes { span, .. } => *span,
+        }
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        match self {
+            MergeConflict::Frames {
+                category,
+                start_frame,
+                end_frame,
+            } => json!({
+                "conflict": "frames",
+                "category": category.to_string(),
+                "frames": [start_frame, end_frame],
+            }),
+            MergeConflict::UnconfirmedEvent {
+                category,
+                span,
+                annotators,
+            } => json!({
+                "conflict": "unconfirmed_event",
+                "category": category.to_string(),
+                "frames": [span.0, span.1],
+                "annotators": annotators,
+            }),
+            MergeConflict::EventAttributes {
+                category,
+                span,
+                annotators,
+            } => json!({
+                "conflict": "event_attributes",
+                "category": category.to_string(),
+                "frames": [span.0, span.1],
+                "annotators": annotators,
+            }),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergedLabels {
+    pub labels: log_labels::Labels,
+    // sorted by category and frame
+    pub conflicts: Vec<MergeConflict>,
+}
+
+// Most common value, ties go to the value seen first. Also returns if
+// the value has a strict majority.
+fn majority<T: PartialEq + Clone>(values: &[T]) -> (T, bool) {
+    let count = |value: &T| values.iter().filter(|v| *v == value).count();
+    let mut best = &values[0];
+    let mut best_count = count(best);
+    for value in values {
+        let value_count = count(value);
+        if value_count > best_count {
+            best = value;
+            best_count = value_count;
+        }
+    }
+    (best.clone(), best_count * 2 > values.len())
+}
+
+// Majority vote for every frame. Returns the index of the annotator
+// whose label won for each frame and the frame ranges without a
+// strict majority.
+fn merge_frames<C: PartialEq + Clone>(
+    classes: &[Vec<C>],
+    category: LabelCategory,
+) -> (Vec<usize>, Vec<MergeConflict>) {
+    let num_frames = classes[0].len();
+    let mut winners = Vec::with_capacity(num_frames);
+    let mut conflicting_frames = vec![];
+    for frame in 0..num_frames {
+        let votes: Vec<C> = classes.iter().map(|c| c[frame].clone()).collect();
+        let (winner, is_majority) = majority(&votes);
+        winners.push(votes.iter().position(|v| *v == winner).unwrap());
+        if !is_majority {
+            conflicting_frames.push(frame as u64);
+        }
+    }
+
+    let conflicts = frame_ranges(conflicting_frames)
+        .into_iter()
+        .map(|(start_frame, end_frame)| MergeConflict::Frames {
+            category,
+            start_frame,
+            end_frame,
+        })
+        .collect();
+    (winners, conflicts)
+}
+
+fn median(values: &mut [u64]) -> u64 {
+    values.sort();
+    values[(values.len() - 1) / 2]
+}
+
+// Events of all annotators that were matched to each other, as
+// (annotator, index) pairs. There is at most one event per annotator.
+type EventCluster = Vec<(usize, usize)>;
+
+// Each annotator's events are matched to the clusters found so far,
+// using the first event of each cluster. Events without a match start
+// a new cluster.
+fn cluster_events<L, S, P>(
+    labels: &[&[L]],
+    min_iou: f64,
+    span: S,
+    pair_score: P,
+) -> Vec<EventCluster>
+where
+    L: Clone,
+    S: Fn(&L) -> (u64, u64),
+    P: Fn(&L, &L) -> f64,
+{
+    let mut clusters: Vec<EventCluster> = vec![];
+    for (annotator, annotator_labels) in labels.iter().enumerate() {
+        let representatives: Vec<L> = clusters
+            .iter()
+            .map(|cluster| {
+                let (first_annotator, first_index) = cluster[0];
+                labels[first_annotator][first_index].clone()
+            })
+            .collect();
+        let matching = match_events(
+            &representatives,
+            annotator_labels,
+            min_iou,
+            &span,
+            &pair_score,
+        );
+
+        for (cluster_index, index) in matching.pairs {
+            clusters[cluster_index].push((annotator, index));
+        }
+        for index in matching.unmatched_predicted {
+            clusters.push(vec![(annotator, index)]);
+        }
+    }
+    clusters
+}
+
+// Merges the events of one category. `merge` builds the merged label
+// with the given span from the cluster's labels, and returns if every
+// attribute had a strict majority.
+fn merge_events<L, S, P, M>(
+    labels: &[&[L]],
+    options: &MergeOptions,
+    category: LabelCategory,
+    span: S,
+    pair_score: P,
+    merge: M,
+) -> (Vec<L>, Vec<MergeConflict>)
+where
+    L: Clone,
+    S: Fn(&L) -> (u64, u64),
+    P: Fn(&L, &L) -> f64,
+    M: Fn(&[&L], (u64, u64)) -> (L, bool),
+{
+    let quorum = options.quorum(labels.len());
+    let mut merged_labels = vec![];
+    let mut conflicts = vec![];
+
+    for cluster in cluster_events(labels, options.min_iou, &span, &pair_score) {
+        let members: Vec<&L> = cluster
+            .iter()
+            .map(|(annotator, index)| &labels[*annotator][*index])
+            .collect();
+        let mut starts: Vec<u64> = members
+            .iter()
+            .map(|label| {
+                let (start, end) = span(label);
+                start.min(end)
+            })
+            .collect();
+        let mut ends: Vec<u64> = members
+            .iter()
+            .map(|label| {
+                let (start, end) = span(label);
+                start.max(end)
+            })
+            .collect();
+        // the median start is never after the median end, since every
+        // start is before its end
+        let merged_span = (median(&mut starts), median(&mut ends));
+        let mut annotators: Vec<usize> = cluster.iter().map(|(annotator, _)| *annotator).collect();
+        annotators.sort();
+
+        if cluster.len() < quorum {
+            conflicts.push(MergeConflict::UnconfirmedEvent {
+                category,
+                span: merged_span,
+                annotators,
+            });
+            continue;
+        }
+
+        let (merged_label, attributes_agree) = merge(&members, merged_span);
+        if !attributes_agree {
+            conflicts.push(MergeConflict::EventAttributes {
+                category,
+                span: merged_span,
+                annotators,
+            });
+        }
+        merged_labels.push(merged_label);
+    }
+
+    merged_labels.sort_by_key(|label| span(label).0);
+    conflicts.sort_by_key(|conflict| conflict.frames());
+    (merged_labels, conflicts)
+}
+
+fn merge_passing(
+    members: &[&log_labels::PassingLabel],
+    span: (u64, u64),
+) -> (log_labels::PassingLabel, bool) {
+    let (successful, successful_agree) = majority(
+        &members
+            .iter()
+            .map(|l| l.get_successful())
+            .collect::<Vec<_>>(),
+    );
+    let (passer_team, passer_team_agree) = majority(
+        &members
+            .iter()
+            .map(|l| l.get_passer_team())
+            .collect::<Vec<_>>(),
+    );
+    let (passer_id, passer_id_agree) = majority(
+        &members
+            .iter()
+            .map(|l| l.get_passer_id())
+            .collect::<Vec<_>>(),
+    );
+    let (receiver_id, receiver_id_agree) = majority(
+        &members
+            .iter()
+            .map(|l| l.get_receiver_id())
+            .collect::<Vec<_>>(),
+    );
+
+    let mut label = log_labels::PassingLabel::new();
+    label.set_start_frame(span.0);
+    label.set_end_frame(span.1);
+    label.set_successful(successful);
+    label.set_passer_team(passer_team);
+    label.set_passer_id(passer_id);
+    label.set_receiver_id(receiver_id);
+    (
+        label,
+        successful_agree && passer_team_agree && passer_id_agree && receiver_id_agree,
+    )
+}
+
+fn merge_goal_shot(
+    members: &[&log_labels::GoalShotLabel],
+    span: (u64, u64),
+) -> (log_labels::GoalShotLabel, bool) {
+    let (successful, successful_agree) = majority(
+        &members
+            .iter()
+            .map(|l| l.get_successful())
+            .collect::<Vec<_>>(),
+    );
+    let (shooter_team, shooter_team_agree) = majority(
+        &members
+            .iter()
+            .map(|l| l.get_shooter_team())
+            .collect::<Vec<_>>(),
+    );
+    let (shooter_id, shooter_id_agree) = majority(
+        &members
+            .iter()
+            .map(|l| l.get_shooter_id())
+            .collect::<Vec<_>>(),
+    );
+
+    let mut label = log_labels::GoalShotLabel::new();
+    label.set_start_frame(span.0);
+    label.set_end_frame(span.1);
+    label.set_successful(successful);
+    label.set_shooter_team(shooter_team);
+    label.set_shooter_id(shooter_id);
+    (
+        label,
+        successful_agree && shooter_team_agree && shooter_id_agree,
+    )
+}
+
+// Merges the labels of every annotator into one set of labels. The
+// per frame labels are a majority vote, the events are clustered by
+// overlap and kept if at least a quorum of annotators labeled them.
+pub fn merge_labels(
+    labels: &[log_labels::Labels],
+    options: &MergeOptions,
+) -> MergeResult<MergedLabels> {
+    if labels.is_empty() {
+        return Err(MergeError::NoLabels);
+    }
+
+    let expected = labels[0].get_dribbling_labels().len();
+    for (annotator, annotator_labels) in labels.iter().enumerate() {
+        for num_frames in &[
+            annotator_labels.get_dribbling_labels().len(),
+            annotator_labels.get_ball_possession_labels().len(),
+        ] {
+            if *num_frames != expected {
+                return Err(MergeError::FrameCountMismatch {
+                    annotator,
+                    num_frames: *num_frames,
+                    expected,
+                });
+            }
+        }
+    }
+
+    let mut conflicts = vec![];
+
+    let dribbling_classes: Vec<Vec<_>> = labels
+        .iter()
+        .map(|l| {
+            l.get_dribbling_labels()
+                .iter()
+                .map(dribbling_class)
+                .collect()
+        })
+        .collect();
+    let (winners, dribbling_conflicts) = merge_frames(&dribbling_classes, LabelCategory::Dribbling);
+    let dribbling_labels: Vec<_> = winners
+        .iter()
+        .enumerate()
+        .map(|(frame, annotator)| labels[*annotator].get_dribbling_labels()[frame].clone())
+        .collect();
+    conflicts.extend(dribbling_conflicts);
+
+    let ball_possession_classes: Vec<Vec<_>> = labels
+        .iter()
+        .map(|l| {
+            l.get_ball_possession_labels()
+                .iter()
+                .map(ball_possession_class)
+                .collect()
+        })
+        .collect();
+    let (winners, ball_possession_conflicts) =
+        merge_frames(&ball_possession_classes, LabelCategory::BallPossession);
+    let ball_possession_labels: Vec<_> = winners
+        .iter()
+        .enumerate()
+        .map(|(frame, annotator)| labels[*annotator].get_ball_possession_labels()[frame].clone())
+        .collect();
+    conflicts.extend(ball_possession_conflicts);
+
+    let passing_labels: Vec<_> = labels.iter().map(|l| l.get_passing_labels()).collect();
+    let (passing_labels, passing_conflicts) = merge_events(
+        &passing_labels,
+        options,
+        LabelCategory::Passing,
+        passing_span,
+        passing_pair_score,
+        merge_passing,
+    );
+    conflicts.extend(passing_conflicts);
+
+    let goal_shot_labels: Vec<_> = labels.iter().map(|l| l.get_goal_shot_labels()).collect();
+    let (goal_shot_labels, goal_shot_conflicts) = merge_events(
+        &goal_shot_labels,
+        options,
+        LabelCategory::GoalShot,
+        goal_shot_span,
+        goal_shot_pair_score,
+        merge_goal_shot,
+    );
+    conflicts.extend(goal_shot_conflicts);
+
+    let mut merged_labels = log_labels::Labels::new();
+    merged_labels.set_dribbling_labels(dribbling_labels.into());
+    merged_labels.set_ball_possession_labels(ball_possession_labels.into());
+    merged_labels.set_passing_labels(passing_labels.into());
+    merged_labels.set_goal_shot_labels(goal_shot_labels.into());
+
+    Ok(MergedLabels {
+        labels: merged_labels,
+        conflicts,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::labeler::validate::{validate_labels_with_context, LabelIssue, ValidationContext};
+    use crate::test_utils::labels as test_utils_labels;
+    use proptest::prelude::*;
+
+    fn sorted_events(labels: &log_labels::Labels) -> log_labels::Labels {
+        let mut labels = labels.clone();
+        labels
+            .mut_passing_labels()
+            .sort_by_key(|label| label.get_start_frame());
+        labels
+            .mut_goal_shot_labels()
+            .sort_by_key(|label| label.get_start_frame());
+        labels
+    }
+
+    fn goal_shot_label(
+        start_frame: u64,
+        end_frame: u64,
+        shooter_id: u32,
+    ) -> log_labels::GoalShotLabel {
+        let mut label = log_labels::GoalShotLabel::new();
+        label.set_start_frame(start_frame);
+        label.set_end_frame(end_frame);
+        label.set_shooter_id(shooter_id);
+        label
+    }
+
+    proptest! {
+        #[test]
+        fn identical_labels_merge_to_themselves(
+            labels in test_utils_labels::random_labels_strategy(20, 5),
+            num_annotators in 1..5usize
+        ) {
+            let merged = merge_labels(&vec![labels.clone(); num_annotators], &MergeOptions::default()).unwrap();
+
+            prop_assert_eq!(merged.labels, sorted_events(&labels));
+            prop_assert!(merged.conflicts.is_empty());
+        }
+
+        #[test]
+        fn merged_labels_are_valid(
+            labels in proptest::collection::vec(test_utils_labels::random_labels_strategy(20, 5), 1..5)
+        ) {
+            let merged = merge_labels(&labels, &MergeOptions::default()).unwrap();
+
+            let context = ValidationContext {
+                num_frames: 20,
+                yellow_robot_ids: (0..12).collect(),
+                blue_robot_ids: (0..12).collect(),
+                ..Default::default()
+            };
+            for issue in validate_labels_with_context(&merged.labels, &context) {
+                match issue {
+                    // the annotators may have labeled overlapping passes
+                    LabelIssue::OverlappingPasses { .. } => {}
+                    issue => return Err(TestCaseError::fail(format!("Unexpected issue: {}", issue))),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn majority_vote() {
+        assert_eq!(majority(&[1, 2, 2]), (2, true));
+        assert_eq!(majority(&[3, 1, 2, 1]), (1, false));
+        assert_eq!(majority(&[3, 4]), (3, false));
+    }
+
+    #[test]
+    fn events_need_quorum() {
+        let mut labels = vec![log_labels::Labels::new(); 3];
+        // confirmed by two annotators, but with different shooters
+        labels[0]
+            .mut_goal_shot_labels()
+            .push(goal_shot_label(10, 20, 1));
+        labels[1]
+            .mut_goal_shot_labels()
+            .push(goal_shot_label(12, 22, 2));
+        // only one annotator
+        labels[2]
+            .mut_goal_shot_labels()
+            .push(goal_shot_label(50, 60, 3));
+
+        let merged = merge_labels(&labels, &MergeOptions::default()).unwrap();
+
+        assert_eq!(
+            merged.labels.get_goal_shot_labels(),
+            &[goal_shot_label(10, 20, 1)]
+        );
+        assert_eq!(
+            merged.conflicts,
+            vec![
+                MergeConflict::EventAttributes {
+                    category: LabelCategory::GoalShot,
+                    span: (10, 20),
+                    annotators: vec![0, 1],
+                },
+                MergeConflict::UnconfirmedEvent {
+                    category: LabelCategory::GoalShot,
+                    span: (50, 60),
+                    annotators: vec![2],
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,430 @@
+use super::reader::{read_header, read_metadata, LabelerDataReaderError};
+use super::writer::{write_metadata, LabelerDataWriterError};
+use super::FilterConfig;
+use crate::protos::log_labeler_data;
+use crate::protos::messages_robocup_ssl_geometry::SSL_GeometryData;
+use byteorder::{BigEndian, ReadBytesExt};
+use protobuf;
+use serde_json::{self, json};
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+#[derive(Debug, Fail)]
+pub enum LabelerMetadataError {
+    #[fail(display = "{}", _0)]
+    Io(#[fail(cause)] io::Error),
+    #[fail(display = "{}", _0)]
+    Reader(#[fail(cause)] LabelerDataReaderError),
+    #[fail(display = "{}", _0)]
+    Writer(#[fail(cause)] LabelerDataWriterError),
+    #[fail(display = "metadata trailer is missing or corrupt")]
+    InvalidTrailer,
+    #[fail(display = "invalid metadata: {}", reason)]
+    InvalidMetadata { reason: String },
+}
+
+impl From<io::Error> for LabelerMetadataError {
+    fn from(error: io::Error) -> Self {
+        LabelerMetadataError::Io(error)
+    }
+}
+
+impl From<LabelerDataReaderError> for LabelerMetadataError {
+    fn from(error: LabelerDataReaderError) -> Self {
+        match error {
+            LabelerDataReaderError::Io(e) => LabelerMetadataError::Io(e),
+            e => LabelerMetadataError::Reader(e),
+        }
+    }
+}
+
+impl From<LabelerDataWriterError> for LabelerMetadataError {
+    fn from(error: LabelerDataWriterError) -> Self {
+        match error {
+            LabelerDataWriterError::Io(e) => LabelerMetadataError::Io(e),
+            e => LabelerMetadataError::Writer(e),
+        }
+    }
+}
+
+type LabelerMetadataResult<T> = Result<T, LabelerMetadataError>;
+
+fn invalid<T, S: Into<String>>(reason: S) -> LabelerMetadataResult<T> {
+    Err(LabelerMetadataError::InvalidMetadata {
+        reason: reason.into(),
+    })
+}
+
+// Checks that the trailer exists and that its offsets describe a
+// contiguous run of records ending exactly at the trailer. A
+// truncated file can still produce a parsable (but wrong) trailer, so
+// just parsing it is not enough.
+pub fn has_valid_trailer<T: Read + Seek>(reader: T) -> LabelerMetadataResult<bool> {
+    let mut reader = BufReader::new(reader);
+    reader.seek(SeekFrom::Start(0))?;
+    read_header(&mut reader)?;
+    let data_start = reader.seek(SeekFrom::Current(0))?;
+
+    let file_len = reader.seek(SeekFrom::End(0))?;
+    let size_len = std::mem::size_of::<u32>() as u64;
+    if file_len < data_start + size_len {
+        return Ok(false);
+    }
+
+    reader.seek(SeekFrom::End(-(size_len as i64)))?;
+    let metadata_size = u64::from(reader.read_u32::<BigEndian>()?);
+    if metadata_size + size_len > file_len - data_start {
+        return Ok(false);
+    }
+    let metadata_start = file_len - size_len - metadata_size;
+
+    reader.seek(SeekFrom::Start(metadata_start))?;
+    let mut metadata_bytes = vec![0u8; metadata_size as usize];
+    reader.read_exact(&mut metadata_bytes)?;
+    let metadata =
+        match protobuf::parse_from_bytes::<log_labeler_data::LabelerMetadata>(&metadata_bytes) {
+            Ok(metadata) => metadata,
+            Err(_) => return Ok(false),
+        };
+
+    let mut expected_offset = data_start;
+    for offset in metadata.get_message_offsets() {
+        if *offset != expected_offset || offset + size_len > metadata_start {
+            return Ok(false);
+        }
+        reader.seek(SeekFrom::Start(*offset))?;
+        expected_offset = offset + size_len + u64::from(reader.read_u32::<BigEndian>()?);
+    }
+
+    Ok(expected_offset == metadata_start)
+}
+
+// Checks the metadata is consistent with itself and with the frame
+// group data, which lies between data_start and data_end.
+pub fn validate_metadata(
+    metadata: &log_labeler_data::LabelerMetadata,
+    data_start: u64,
+    data_end: u64,
+) -> LabelerMetadataResult<()> {
+    let offsets = metadata.get_message_offsets();
+    if let Some(first_offset) = offsets.first() {
+        if *first_offset != data_start {
+            return invalid(format!(
+                "first message offset {} is not the start of the data {}",
+                first_offset, data_start
+            ));
+        }
+    }
+    if offsets.windows(2).any(|w| w[0] >= w[1]) {
+        return invalid("message offsets are not strictly increasing");
+    }
+    if let Some(last_offset) = offsets.last() {
+        if *last_offset >= data_end {
+            return invalid(format!(
+                "message offset {} is past the end of the data {}",
+                last_offset, data_end
+            ));
+        }
+    }
+
+    let timestamps = metadata.get_frame_group_timestamps();
+    if !timestamps.is_empty() && timestamps.len() != offsets.len() {
+        return invalid(format!(
+            "{} frame group timestamps for {} frame groups",
+            timestamps.len(),
+            offsets.len()
+        ));
+    }
+
+    let camera_ids = metadata.get_camera_ids();
+    if camera_ids.windows(2).any(|w| w[0] >= w[1]) {
+        return invalid("camera ids are not sorted and unique");
+    }
+    if let Some(max_camera_id) = camera_ids.last() {
+        if metadata.get_num_cameras() < *max_camera_id {
+            return invalid(format!(
+                "num_cameras {} is less than camera id {}",
+                metadata.get_num_cameras(),
+                max_camera_id
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+// Read only access to the metadata of a labeler data file. Fails if
+// the trailer is missing, see labeler::repair for fixing that.
+pub fn read_metadata_from_path(
+    path: &Path,
+) -> LabelerMetadataResult<log_labeler_data::LabelerMetadata> {
+    let mut file = File::open(path)?;
+    if !has_valid_trailer(&mut file)? {
+        return Err(LabelerMetadataError::InvalidTrailer);
+    }
+
+    let (metadata, _) = read_metadata(&mut file)?;
+    Ok(metadata)
+}
+
+// Edits the metadata trailer of a labeler data file in place. Changes
+// are only written by save, after they have been validated. The file
+// is truncated after the new trailer, so shrinking the metadata is
+// safe. The trailer is overwritten without a copy, so a crash during
+// save leaves the file without valid metadata; the frame groups are
+// untouched and labeler::repair rebuilds it, minus the edits.
+pub struct MetadataEditor {
+    file: File,
+    version: u32,
+    data_start: u64,
+    data_end: u64,
+    original_offsets: Vec<u64>,
+    metadata: log_labeler_data::LabelerMetadata,
+}
+
+impl MetadataEditor {
+    pub fn open(path: &Path) -> LabelerMetadataResult<MetadataEditor> {
+        let mut file = OpenOptions::new().read(true).write(true).open(path)?;
+
+        file.seek(SeekFrom::Start(0))?;
+        let version = read_header(&mut file)?;
+        let data_start = file.seek(SeekFrom::Current(0))?;
+
+        if !has_valid_trailer(&mut file)? {
+            return Err(LabelerMetadataError::InvalidTrailer);
+        }
+        let (metadata, data_end) = read_metadata(&mut file)?;
+
+        Ok(MetadataEditor {
+            file,
+            version,
+            data_start,
+            data_end,
+            original_offsets: metadata.get_message_offsets().to_vec(),
+            metadata,
+        })
+    }
+
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    pub fn metadata(&self) -> &log_labeler_data::LabelerMetadata {
+        &self.metadata
+    }
+
+    pub fn metadata_mut(&mut self) -> &mut log_labeler_data::LabelerMetadata {
+        &mut self.metadata
+    }
+
+    pub fn save(&mut self) -> LabelerMetadataResult<()> {
+        // the offsets describe the frame data, which the editor does
+        // not touch
+        if self.metadata.get_message_offsets() != self.original_offsets.as_slice() {
+            return invalid("message offsets cannot be edited");
+        }
+        validate_metadata(&self.metadata, self.data_start, self.data_end)?;
+
+        self.file.seek(SeekFrom::Start(self.data_end))?;
+        write_metadata(&mut self.file, &self.metadata)?;
+        let new_len = self.file.seek(SeekFrom::Current(0))?;
+        self.file.set_len(new_len)?;
+        self.file.sync_all()?;
+
+        Ok(())
+    }
+}
+
+fn geometry_to_json(geometry: &SSL_GeometryData) -> serde_json::Value {
+    let field = geometry.get_field();
+    json!({
+        "field": {
+            "field_length": field.get_field_length(),
+            "field_width": field.get_field_width(),
+            "goal_width": field.get_goal_width(),
+            "goal_depth": field.get_goal_depth(),
+            "boundary_width": field.get_boundary_width(),
+            "field_lines": field.get_field_lines().iter().map(|line| json!({
+                "name": line.get_name(),
+                "p1": [line.get_p1().get_x(), line.get_p1().get_y()],
+                "p2": [line.get_p2().get_x(), line.get_p2().get_y()],
+                "thickness": line.get_thickness(),
+            })).collect::<Vec<_>>(),
+            "field_arcs": field.get_field_arcs().iter().map(|arc| json!({
+                "name": arc.get_name(),
+                "center": [arc.get_center().get_x(), arc.get_center().get_y()],
+                "radius": arc.get_radius(),
+                "a1": arc.get_a1(),
+                "a2": arc.get_a2(),
+                "thickness": arc.get_thickness(),
+            })).collect::<Vec<_>>(),
+        },
+        "calib": geometry.get_calib().iter().map(|calib| json!({
+            "camera_id": calib.get_camera_id(),
+            "focal_length": calib.get_focal_length(),
+            "principal_point_x": calib.get_principal_point_x(),
+            "principal_point_y": calib.get_principal_point_y(),
+            "distortion": calib.get_distortion(),
+            "q": [calib.get_q0(), calib.get_q1(), calib.get_q2(), calib.get_q3()],
+            "t": [calib.get_tx(), calib.get_ty(), calib.get_tz()],
+        })).collect::<Vec<_>>(),
+    })
+}
+
+pub fn metadata_to_json(metadata: &log_labeler_data::LabelerMetadata) -> serde_json::Value {
+    let filter_config = FilterConfig::from_proto(metadata.get_filter_config());
+    json!({
+        "num_cameras": metadata.get_num_cameras(),
+        "camera_ids": metadata.get_camera_ids(),
+        "num_frame_groups": metadata.get_message_offsets().len(),
+        "message_offsets": metadata.get_message_offsets(),
+        "frame_group_timestamps": metadata
+            .get_frame_group_timestamps()
+            .iter()
+            .map(|t| [t.get_first_timestamp(), t.get_last_timestamp()])
+            .collect::<Vec<_>>(),
+        "num_passing_events": metadata.get_num_passing_events(),
+        "num_goal_shot_events": metadata.get_num_goal_shot_events(),
+        "source_log_file_name": metadata.get_source_log_file_name(),
+        "source_log_sha256": metadata.get_source_log_sha256(),
+        "yellow_team_name": metadata.get_yellow_team_name(),
+        "blue_team_name": metadata.get_blue_team_name(),
+        "geometry": if metadata.has_geometry() {
+            geometry_to_json(metadata.get_geometry())
+        } else {
+            serde_json::Value::Null
+        },
+        "filter_config": if metadata.has_filter_config() {
+            json!({
+                "grouping": format!("{:?}", filter_config.grouping),
+                "running_stages": filter_config
+                    .running_stages
+                    .iter()
+                    .map(|s| format!("{:?}", s))
+                    .collect::<Vec<_>>(),
+                "running_commands": filter_config
+                    .running_commands
+                    .iter()
+                    .map(|c| format!("{:?}", c))
+                    .collect::<Vec<_>>(),
+            })
+        } else {
+            serde_json::Value::Null
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate tempfile;
+
+    use super::*;
+    use crate::labeler::reader::LabelerDataReader;
+    use crate::labeler::writer::LabelerDataWriter;
+    use crate::test_utils::labeler as test_utils_labeler;
+    use proptest::prelude::*;
+
+    impl From<LabelerMetadataError> for TestCaseError {
+        fn from(error: LabelerMetadataError) -> Self {
+            TestCaseError::fail(format!("{}", error))
+        }
+    }
+
+    fn write_data_file(path: &Path, team_name: &str) {
+        let mut writer = LabelerDataWriter::new_from_path(path).unwrap();
+        writer.set_source_log(team_name, team_name);
+    }
+
+    #[test]
+    fn edit_shrinks_metadata() {
+        let tmpfile = tempfile::NamedTempFile::new().unwrap();
+        write_data_file(tmpfile.path(), "a very long source log file name");
+
+        let mut editor = MetadataEditor::open(tmpfile.path()).unwrap();
+        editor
+            .metadata_mut()
+            .set_source_log_file_name("short".to_owned());
+        editor.metadata_mut().set_source_log_sha256(String::new());
+        editor.save().unwrap();
+
+        // old trailer would still be partially in the file if it was
+        // not truncated
+        assert!(has_valid_trailer(File::open(tmpfile.path()).unwrap()).unwrap());
+        let reader = LabelerDataReader::new_from_path(tmpfile.path()).unwrap();
+        assert_eq!(reader.source_log_file_name(), Some("short"));
+        assert_eq!(reader.source_log_sha256(), None);
+    }
+
+    #[test]
+    fn edit_rejects_offset_changes() {
+        let tmpfile = tempfile::NamedTempFile::new().unwrap();
+        write_data_file(tmpfile.path(), "log");
+
+        let mut editor = MetadataEditor::open(tmpfile.path()).unwrap();
+        editor.metadata_mut().mut_message_offsets().push(20);
+        match editor.save() {
+            Err(LabelerMetadataError::InvalidMetadata { .. }) => {}
+            result => panic!("Unexpected result {:?}", result),
+        };
+    }
+
+    #[test]
+    fn open_without_trailer_fails() {
+        let tmpfile = tempfile::NamedTempFile::new().unwrap();
+        write_data_file(tmpfile.path(), "log");
+        let file_len = tmpfile.as_file().metadata().unwrap().len();
+        tmpfile.as_file().set_len(file_len - 1).unwrap();
+
+        match MetadataEditor::open(tmpfile.path()) {
+            Err(LabelerMetadataError::InvalidTrailer) => {}
+            Err(e) => panic!("Unexpected error {}", e),
+            Ok(_) => panic!("Opened file without a valid trailer"),
+        };
+    }
+
+    #[test]
+    fn validate_camera_ids() {
+        let mut metadata = log_labeler_data::LabelerMetadata::new();
+        metadata.set_camera_ids(vec![0, 3]);
+        metadata.set_num_cameras(3);
+        assert!(validate_metadata(&metadata, 20, 20).is_ok());
+
+        metadata.set_num_cameras(2);
+        assert!(validate_metadata(&metadata, 20, 20).is_err());
+
+        metadata.set_num_cameras(3);
+        metadata.set_camera_ids(vec![3, 0]);
+        assert!(validate_metadata(&metadata, 20, 20).is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn edit_event_counts(num_passing_events in proptest::num::u32::ANY,
+                             num_goal_shot_events in proptest::num::u32::ANY,
+                             running_ref_msg in test_utils_labeler::running_ref_msg(),
+                             camera_msgs in test_utils_labeler::no_camera_repeats_strategy(1, 10)) {
+            let tmpfile = tempfile::NamedTempFile::new()?;
+            {
+                let mut writer = LabelerDataWriter::new_from_path(tmpfile.path()).unwrap();
+                writer.add_msg(running_ref_msg)?;
+                for camera_msg in camera_msgs {
+                    writer.add_msg(camera_msg)?;
+                }
+            }
+            let original = read_metadata_from_path(tmpfile.path())?;
+
+            let mut editor = MetadataEditor::open(tmpfile.path())?;
+            editor.metadata_mut().set_num_passing_events(num_passing_events);
+            editor.metadata_mut().set_num_goal_shot_events(num_goal_shot_events);
+            editor.save()?;
+
+            let edited = read_metadata_from_path(tmpfile.path())?;
+            prop_assert_eq!(edited.get_num_passing_events(), num_passing_events);
+            prop_assert_eq!(edited.get_num_goal_shot_events(), num_goal_shot_events);
+            prop_assert_eq!(edited.get_message_offsets(), original.get_message_offsets());
+            prop_assert_eq!(edited.get_camera_ids(), original.get_camera_ids());
+        }
+    }
+}
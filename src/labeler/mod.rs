@@ -1,8 +1,26 @@
 const LABELER_DATA_HEADER: [u8; 16] = [
     b'S', b'S', b'L', b'_', b'L', b'A', b'b', b'E', b'L', b'E', b'R', b'_', b'D', b'A', b'T', b'A',
 ];
-const LABELER_DATA_VERSION: u32 = 1u32;
+const LABELER_DATA_VERSION: u32 = 2u32;
+// oldest file version the reader can still open
+const LABELER_DATA_MIN_VERSION: u32 = 1u32;
 
+pub mod agreement;
+pub mod auto;
+pub mod detect;
+pub mod filter;
+pub mod label_file;
+pub mod match_report;
+pub mod matching;
+pub mod merge;
+pub mod metadata;
 pub mod player;
 pub mod reader;
+pub mod repair;
+pub mod report;
+pub mod scoring;
+pub mod timestamps;
+pub mod validate;
 pub mod writer;
+
+pub use filter::FilterConfig;
@@ -1,12 +1,13 @@
 use super::*;
 use crate::protos::log_labeler_data;
+use crate::protos::messages_robocup_ssl_geometry::SSL_GeometryData;
 use byteorder::{BigEndian, ReadBytesExt};
 use protobuf;
-use std::cell::RefCell;
 use std::fs::File;
 use std::io;
 use std::io::{BufReader, Read, Seek, SeekFrom};
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 
 // This is necessary for the write_to_bytes method used below, but
 // rust is generating a warning.
@@ -23,6 +24,21 @@ pub enum LabelerDataReaderError {
     InvalidHeader { header: Vec<u8> },
     #[fail(display = "unsupported log version: {}", version)]
     UnsupportedVersion { version: u32 },
+    #[fail(display = "frame group {} is out of range, file has {}", index, len)]
+    IndexOutOfRange { index: usize, len: usize },
+    #[fail(
+        display = "invalid frame group range {}..{}, file has {}",
+        start, end, len
+    )]
+    InvalidRange {
+        start: usize,
+        end: usize,
+        len: usize,
+    },
+    #[fail(display = "reader was not opened from a path")]
+    NoPath,
+    #[fail(display = "file does not store frame group timestamps")]
+    MissingTimestamps,
 }
 
 impl From<io::Error> for LabelerDataReaderError {
@@ -39,8 +55,53 @@ impl From<protobuf::ProtobufError> for LabelerDataReaderError {
 
 type LabelerDataReaderResult<T> = Result<T, LabelerDataReaderError>;
 
+// Checks the file type string and returns the file version. Leaves
+// the reader at the start of the first frame group.
+pub(crate) fn read_header<T: Read>(reader: &mut T) -> LabelerDataReaderResult<u32> {
+    let mut header = vec![0u8; LABELER_DATA_HEADER.len()];
+    reader.read_exact(&mut header)?;
+    if header != LABELER_DATA_HEADER {
+        return Err(LabelerDataReaderError::InvalidHeader { header });
+    }
+
+    let version = reader.read_u32::<BigEndian>()?;
+    if version < LABELER_DATA_MIN_VERSION || version > LABELER_DATA_VERSION {
+        return Err(LabelerDataReaderError::UnsupportedVersion { version });
+    }
+
+    Ok(version)
+}
+
+// Reads the metadata trailer at the end of the file. Also returns the
+// offset the trailer starts at, which is the end of the frame group
+// data.
+pub(crate) fn read_metadata<T: Read + Seek>(
+    reader: &mut T,
+) -> LabelerDataReaderResult<(log_labeler_data::LabelerMetadata, u64)> {
+    let metadata_size_offset = -(std::mem::size_of::<u32>() as i64);
+    reader.seek(SeekFrom::End(metadata_size_offset))?; // read a u32 from end
+    let metadata_msg_size = reader.read_u32::<BigEndian>()? as usize;
+    let metadata_start = reader.seek(SeekFrom::End(
+        metadata_size_offset - (metadata_msg_size as i64),
+    ))?;
+
+    let mut metadata_msg_bytes = vec![0u8; metadata_msg_size];
+    reader.read_exact(&mut metadata_msg_bytes)?;
+    let metadata =
+        protobuf::parse_from_bytes::<log_labeler_data::LabelerMetadata>(&metadata_msg_bytes)?;
+
+    Ok((metadata, metadata_start))
+}
+
+// Reads frame groups from a labeler data file. Every read seeks to
+// the frame group offset, so the reader can be shared between
+// threads. Only the raw bytes are read while holding the lock, the
+// protobuf decoding happens in parallel. Use try_clone to get a
+// fully independent handle to the same file.
 pub struct LabelerDataReader<T: Read + Seek> {
-    reader: RefCell<BufReader<T>>,
+    reader: Mutex<BufReader<T>>,
+    path: Option<PathBuf>,
+    version: u32,
     metadata: log_labeler_data::LabelerMetadata,
 }
 
@@ -48,46 +109,28 @@ impl<T: Read + Seek> LabelerDataReader<T> {
     pub fn new(reader: T) -> LabelerDataReaderResult<LabelerDataReader<T>> {
         let mut reader = BufReader::new(reader);
 
-        // read the file header
-        let mut header = vec![0u8; LABELER_DATA_HEADER.len()];
-        reader.read_exact(&mut header)?;
-        if header != LABELER_DATA_HEADER {
-            return Err(LabelerDataReaderError::InvalidHeader { header });
-        }
-
-        // check the version
-        let version = reader.read_u32::<BigEndian>()?;
-        if version != LABELER_DATA_VERSION {
-            return Err(LabelerDataReaderError::UnsupportedVersion { version });
-        }
-
-        // should be the start of the first message (or metadata if no
-        // messages in the data file)
-        let curr_offset = reader.seek(SeekFrom::Current(0))?;
-
-        // read the metadata
-        let metadata_size_offset = -(std::mem::size_of::<u32>() as i64);
-        reader.seek(SeekFrom::End(metadata_size_offset))?; // read a u64 from end
-        let metadata_msg_size = reader.read_u32::<BigEndian>()? as usize;
-        reader.seek(SeekFrom::End(
-            metadata_size_offset - (metadata_msg_size as i64),
-        ))?;
-
-        let mut metadata_msg_bytes = vec![0u8; metadata_msg_size];
-        reader.read_exact(&mut metadata_msg_bytes)?;
-        let metadata =
-            protobuf::parse_from_bytes::<log_labeler_data::LabelerMetadata>(&metadata_msg_bytes)?;
-
-        // reset to current offset
-        let new_offset = reader.seek(SeekFrom::Start(curr_offset))?;
-        assert_eq!(new_offset, curr_offset); // double check successfully reset
+        let version = read_header(&mut reader)?;
+        let (metadata, _) = read_metadata(&mut reader)?;
 
         Ok(LabelerDataReader {
-            reader: RefCell::new(reader),
+            reader: Mutex::new(reader),
+            path: None,
+            version,
             metadata,
         })
     }
 
+    // File format version. Version 1 files only contain the offsets,
+    // camera count and event counts, so most of the other metadata
+    // accessors return nothing for them.
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    pub fn metadata(&self) -> &log_labeler_data::LabelerMetadata {
+        &self.metadata
+    }
+
     pub fn len(&self) -> usize {
         self.metadata.get_message_offsets().len()
     }
@@ -108,76 +151,219 @@ impl<T: Read + Seek> LabelerDataReader<T> {
         self.metadata.get_num_goal_shot_events()
     }
 
-    fn _read_message(&self) -> LabelerDataReaderResult<log_labeler_data::LabelerFrameGroup> {
-        let mut reader = self.reader.borrow_mut();
+    // Receiver timestamps (ns) of the first and last frame in the
+    // frame group at `index`.
+    pub fn frame_group_timestamps(&self, index: usize) -> Option<(u64, u64)> {
+        self.metadata
+            .get_frame_group_timestamps()
+            .get(index)
+            .map(|t| (t.get_first_timestamp(), t.get_last_timestamp()))
+    }
+
+    fn check_timestamps(&self) -> LabelerDataReaderResult<()> {
+        if self.metadata.get_frame_group_timestamps().len() != self.len() {
+            return Err(LabelerDataReaderError::MissingTimestamps);
+        }
+
+        Ok(())
+    }
 
+    // Like frame_group_timestamps, but fails if the file does not
+    // store timestamps. Version 1 files can be upgraded with
+    // repair_labeler_data_file --force.
+    pub fn timestamp_range(&self, index: usize) -> LabelerDataReaderResult<(u64, u64)> {
+        self.check_timestamps()?;
+
+        match self.frame_group_timestamps(index) {
+            Some(timestamps) => Ok(timestamps),
+            None => Err(LabelerDataReaderError::IndexOutOfRange {
+                index,
+                len: self.len(),
+            }),
+        }
+    }
+
+    // Index of the last frame group starting at or before
+    // `timestamp`. Frame groups are stored in log order, so this is
+    // the frame group being shown at that time. None if the timestamp
+    // is before the first frame group.
+    pub fn frame_group_at_timestamp(
+        &self,
+        timestamp: u64,
+    ) -> LabelerDataReaderResult<Option<usize>> {
+        self.check_timestamps()?;
+
+        // binary search for the number of frame groups starting at or
+        // before the timestamp
+        let timestamps = self.metadata.get_frame_group_timestamps();
+        let mut low = 0;
+        let mut high = timestamps.len();
+        while low < high {
+            let mid = low + (high - low) / 2;
+            if timestamps[mid].get_first_timestamp() <= timestamp {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+
+        Ok(low.checked_sub(1))
+    }
+
+    pub fn source_log_file_name(&self) -> Option<&str> {
+        non_empty(self.metadata.get_source_log_file_name())
+    }
+
+    // Hex encoded SHA-256 of the source log file.
+    pub fn source_log_sha256(&self) -> Option<&str> {
+        non_empty(self.metadata.get_source_log_sha256())
+    }
+
+    pub fn yellow_team_name(&self) -> Option<&str> {
+        non_empty(self.metadata.get_yellow_team_name())
+    }
+
+    pub fn blue_team_name(&self) -> Option<&str> {
+        non_empty(self.metadata.get_blue_team_name())
+    }
+
+    // The latest geometry packet seen in the source log.
+    pub fn geometry(&self) -> Option<&SSL_GeometryData> {
+        if self.metadata.has_geometry() {
+            Some(self.metadata.get_geometry())
+        } else {
+            None
+        }
+    }
+
+    // Sorted ids of all cameras that appear in the frame groups.
+    pub fn camera_ids(&self) -> &[u32] {
+        self.metadata.get_camera_ids()
+    }
+
+    // The filter used to create the file. Version 1 files were
+    // always created with the default filter.
+    pub fn filter_config(&self) -> FilterConfig {
+        if self.metadata.has_filter_config() {
+            FilterConfig::from_proto(self.metadata.get_filter_config())
+        } else {
+            FilterConfig::default()
+        }
+    }
+
+    fn read_message_bytes(&self, offset: u64) -> LabelerDataReaderResult<Vec<u8>> {
+        // a panic while holding the lock cannot leave the reader in a
+        // bad state because every read starts with a seek
+        let mut reader = match self.reader.lock() {
+            Ok(reader) => reader,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+
+        reader.seek(SeekFrom::Start(offset))?;
         let msg_size = reader.read_u32::<BigEndian>()?;
         let mut msg_bytes = vec![0u8; msg_size as usize];
         reader.read_exact(&mut msg_bytes)?;
 
+        Ok(msg_bytes)
+    }
+
+    fn read_message(
+        &self,
+        offset: u64,
+    ) -> LabelerDataReaderResult<log_labeler_data::LabelerFrameGroup> {
+        let msg_bytes = self.read_message_bytes(offset)?;
+
         Ok(protobuf::parse_from_bytes::<
             log_labeler_data::LabelerFrameGroup,
         >(&msg_bytes)?)
     }
 
-    pub fn get(&self, index: usize) -> Option<log_labeler_data::LabelerFrameGroup> {
-        if index >= self.metadata.get_message_offsets().len() {
-            return None;
+    pub fn get(
+        &self,
+        index: usize,
+    ) -> LabelerDataReaderResult<log_labeler_data::LabelerFrameGroup> {
+        match self.metadata.get_message_offsets().get(index) {
+            Some(offset) => self.read_message(*offset),
+            None => Err(LabelerDataReaderError::IndexOutOfRange {
+                index,
+                len: self.len(),
+            }),
         }
-
-        let offset = self.metadata.get_message_offsets()[index];
-        let new_offset = self
-            .reader
-            .borrow_mut()
-            .seek(SeekFrom::Start(offset))
-            .ok()?;
-        assert_eq!(offset, new_offset);
-
-        Some(self._read_message().ok()?)
     }
 
-    fn _read_messages(
+    fn read_messages(
         &self,
         message_offsets: &[u64],
     ) -> LabelerDataReaderResult<Vec<log_labeler_data::LabelerFrameGroup>> {
-        let mut frame_groups = Vec::<log_labeler_data::LabelerFrameGroup>::new();
-
-        for offset in message_offsets {
-            let new_offset = self.reader.borrow_mut().seek(SeekFrom::Start(*offset))?;
-            assert_eq!(*offset, new_offset);
-
-            let frame_group = self._read_message()?;
-            frame_groups.push(frame_group);
-        }
-
-        Ok(frame_groups)
+        message_offsets
+            .iter()
+            .map(|offset| self.read_message(*offset))
+            .collect()
     }
 
     pub fn get_range(
         &self,
         start: usize,
         end: usize,
-    ) -> Option<Vec<log_labeler_data::LabelerFrameGroup>> {
-        let offsets: Vec<u64> = self
-            .metadata
-            .get_message_offsets()
-            .get(start..end)?
-            .to_vec();
+    ) -> LabelerDataReaderResult<Vec<log_labeler_data::LabelerFrameGroup>> {
+        match self.metadata.get_message_offsets().get(start..end) {
+            Some(offsets) => self.read_messages(offsets),
+            None => Err(LabelerDataReaderError::InvalidRange {
+                start,
+                end,
+                len: self.len(),
+            }),
+        }
+    }
 
-        Some(self._read_messages(&offsets).ok()?)
+    pub fn get_range_from(
+        &self,
+        start: usize,
+    ) -> LabelerDataReaderResult<Vec<log_labeler_data::LabelerFrameGroup>> {
+        self.get_range(start, self.len())
     }
 
-    pub fn get_range_from(&self, start: usize) -> Option<Vec<log_labeler_data::LabelerFrameGroup>> {
-        let offsets: Vec<u64> = self.metadata.get_message_offsets().get(start..)?.to_vec();
+    pub fn iter(&self) -> LabelerDataReaderIterator<T> {
+        LabelerDataReaderIterator {
+            reader: self,
+            index: 0,
+        }
+    }
+}
 
-        Some(self._read_messages(&offsets).ok()?)
+fn non_empty(value: &str) -> Option<&str> {
+    if value.is_empty() {
+        None
+    } else {
+        Some(value)
     }
 }
 
 impl LabelerDataReader<File> {
     pub fn new_from_path(log_path: &Path) -> LabelerDataReaderResult<LabelerDataReader<File>> {
         let f = File::open(log_path)?;
-        LabelerDataReader::new(f)
+        let mut reader = LabelerDataReader::new(f)?;
+        reader.path = Some(log_path.to_path_buf());
+
+        Ok(reader)
+    }
+
+    // Reopens the file, giving a reader with its own file offset. The
+    // metadata is copied instead of being read again. Only works for
+    // readers created with new_from_path.
+    pub fn try_clone(&self) -> LabelerDataReaderResult<LabelerDataReader<File>> {
+        let path = match self.path {
+            Some(ref path) => path.clone(),
+            None => return Err(LabelerDataReaderError::NoPath),
+        };
+        let f = File::open(&path)?;
+
+        Ok(LabelerDataReader {
+            reader: Mutex::new(BufReader::new(f)),
+            path: Some(path),
+            version: self.version,
+            metadata: self.metadata.clone(),
+        })
     }
 }
 
@@ -190,16 +376,27 @@ impl<T> Iterator for LabelerDataReaderIntoIterator<T>
 where
     T: Read + Seek,
 {
-    type Item = log_labeler_data::LabelerFrameGroup;
+    type Item = LabelerDataReaderResult<log_labeler_data::LabelerFrameGroup>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let item = self.reader.get(self.index)?;
+        if self.index >= self.reader.len() {
+            return None;
+        }
+
+        let item = self.reader.get(self.index);
         self.index += 1;
 
         Some(item)
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.reader.len().saturating_sub(self.index);
+        (remaining, Some(remaining))
+    }
 }
 
+impl<T: Read + Seek> ExactSizeIterator for LabelerDataReaderIntoIterator<T> {}
+
 impl<T> IntoIterator for LabelerDataReader<T>
 where
     T: Read + Seek,
@@ -224,15 +421,27 @@ impl<'a, T> Iterator for LabelerDataReaderIterator<'a, T>
 where
     T: Read + Seek,
 {
-    type Item = log_labeler_data::LabelerFrameGroup;
+    type Item = LabelerDataReaderResult<log_labeler_data::LabelerFrameGroup>;
+
     fn next(&mut self) -> Option<Self::Item> {
-        let item = self.reader.get(self.index)?;
+        if self.index >= self.reader.len() {
+            return None;
+        }
+
+        let item = self.reader.get(self.index);
         self.index += 1;
 
         Some(item)
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.reader.len().saturating_sub(self.index);
+        (remaining, Some(remaining))
+    }
 }
 
+impl<'a, T: Read + Seek> ExactSizeIterator for LabelerDataReaderIterator<'a, T> {}
+
 impl<'a, T> IntoIterator for &'a LabelerDataReader<T>
 where
     T: Read + Seek,
@@ -241,10 +450,7 @@ where
     type IntoIter = LabelerDataReaderIterator<'a, T>;
 
     fn into_iter(self) -> Self::IntoIter {
-        LabelerDataReaderIterator {
-            reader: &self,
-            index: 0,
-        }
+        self.iter()
     }
 }
 
@@ -253,6 +459,7 @@ mod tests {
     use super::*;
     use crate::protos::log_labeler_data;
     use crate::test_utils::labeler as test_utils_labeler;
+    use crate::test_utils::vision::geometry;
     use byteorder::{BigEndian, WriteBytesExt};
     use proptest::prelude::*;
     use std::io::{Cursor, Seek, SeekFrom, Write};
@@ -342,7 +549,7 @@ mod tests {
 
         #[test]
         fn new_bad_version(bad_version in proptest::num::u32::ANY) {
-            prop_assume!(bad_version != LABELER_DATA_VERSION);
+            prop_assume!(bad_version < LABELER_DATA_MIN_VERSION || bad_version > LABELER_DATA_VERSION);
 
             let mut buffer = Vec::<u8>::new();
             let mut writer = Cursor::new(&mut buffer);
@@ -416,8 +623,8 @@ mod tests {
 
             for index in message_indexes {
                 match reader.get(index) {
-                    Some(ref frame_group) => prop_assert_eq!(frame_group, &random_frame_group_msgs[index]),
-                    None => return Err(TestCaseError::fail(format!("Failed to get message at index {}", index))),
+                    Ok(ref frame_group) => prop_assert_eq!(frame_group, &random_frame_group_msgs[index]),
+                    Err(e) => return Err(TestCaseError::fail(format!("Failed to get message at index {}: {}", index, e))),
                 }
             }
         }
@@ -439,13 +646,13 @@ mod tests {
 
             for range_index in range_indexes {
                 match reader.get_range(range_index.0, range_index.1) {
-                    Some(frame_groups) => {
+                    Ok(frame_groups) => {
                         prop_assert_eq!(frame_groups.len(), range_index.1-range_index.0);
                         for (frame_group, index) in frame_groups.iter().zip(range_index.0..range_index.1) {
                             prop_assert_eq!(frame_group, &random_frame_group_msgs[index]);
                         }
                     },
-                    None => return Err(TestCaseError::fail(format!("Failed to get message with range {:?}", range_index))),
+                    Err(e) => return Err(TestCaseError::fail(format!("Failed to get message with range {:?}: {}", range_index, e))),
                 }
             }
         }
@@ -467,13 +674,13 @@ mod tests {
 
             for range_index in range_indexes {
                 match reader.get_range_from(range_index.0) {
-                    Some(frame_groups) => {
+                    Ok(frame_groups) => {
                         prop_assert_eq!(frame_groups.len(), reader.len()-range_index.0);
                         for (frame_group, index) in frame_groups.iter().zip(range_index.0..reader.len()) {
                             prop_assert_eq!(frame_group, &random_frame_group_msgs[index]);
                         }
                     },
-                    None => return Err(TestCaseError::fail(format!("Failed to get message with range {:?}", range_index))),
+                    Err(e) => return Err(TestCaseError::fail(format!("Failed to get message with range {:?}: {}", range_index, e))),
                 }
             }
         }
@@ -493,9 +700,8 @@ mod tests {
             prop_assert_eq!(reader.num_cameras(), metadata.get_num_cameras());
             prop_assert_eq!(reader.len(), metadata.get_message_offsets().len());
 
-            for (read_frame_msg, frame_msg) in reader.into_iter().zip(random_frame_group_msgs) {
-                prop_assert_eq!(read_frame_msg, frame_msg);
-            }
+            let read_frame_msgs = reader.into_iter().collect::<LabelerDataReaderResult<Vec<_>>>()?;
+            prop_assert_eq!(read_frame_msgs, random_frame_group_msgs);
         }
 
         #[test]
@@ -514,13 +720,43 @@ mod tests {
             prop_assert_eq!(reader.len(), metadata.get_message_offsets().len());
             prop_assert!(!reader.is_empty());
 
+            prop_assert_eq!(reader.iter().len(), random_frame_group_msgs.len());
             for (read_frame_msg, frame_msg) in (&reader).into_iter().zip(&random_frame_group_msgs) {
-                prop_assert_eq!(read_frame_msg, frame_msg.clone());
+                prop_assert_eq!(&read_frame_msg?, frame_msg);
             }
 
             // should be able to compile without move errors
-            for (read_frame_msg, frame_msg) in (&reader).into_iter().zip(&random_frame_group_msgs) {
-                prop_assert_eq!(read_frame_msg, frame_msg.clone());
+            for (read_frame_msg, frame_msg) in reader.iter().zip(&random_frame_group_msgs) {
+                prop_assert_eq!(&read_frame_msg?, frame_msg);
+            }
+        }
+
+        #[test]
+        fn get_random_frames_from_threads(
+            random_frame_group_msgs in proptest::collection::vec(
+                test_utils_labeler::random_no_ref_frame_group_msg_strategy(1, 10), 1..10),
+            num_threads in 2..5usize) {
+            let mut tmpfile = tempfile::NamedTempFile::new()?;
+            write_header(&mut tmpfile, &LABELER_DATA_HEADER, LABELER_DATA_VERSION)?;
+            write_msgs(&mut tmpfile, &random_frame_group_msgs)?;
+
+            let reader = LabelerDataReader::new_from_path(tmpfile.path())?;
+            let cloned_reader = reader.try_clone()?;
+
+            // each thread reads every frame group, so the reads from
+            // different threads interleave
+            let results = crossbeam::scope(|scope| {
+                let handles: Vec<_> = (0..num_threads)
+                    .map(|thread_index| {
+                        let reader = if thread_index % 2 == 0 { &reader } else { &cloned_reader };
+                        scope.spawn(move |_| reader.iter().collect::<LabelerDataReaderResult<Vec<_>>>())
+                    })
+                    .collect();
+                handles.into_iter().map(|handle| handle.join().unwrap()).collect::<Vec<_>>()
+            }).unwrap();
+
+            for result in results {
+                prop_assert_eq!(&result?, &random_frame_group_msgs);
             }
         }
 
@@ -542,6 +778,104 @@ mod tests {
         assert_eq!(reader.num_cameras(), 0);
     }
 
+    #[test]
+    fn new_v1_blank() {
+        let mut buffer = Vec::<u8>::new();
+        let mut writer = Cursor::new(&mut buffer);
+        write_header(&mut writer, &LABELER_DATA_HEADER, 1).unwrap();
+        let mut metadata = log_labeler_data::LabelerMetadata::new();
+        metadata.set_num_passing_events(3);
+        metadata.set_num_goal_shot_events(2);
+        write_metadata(&mut writer, &metadata).unwrap();
+        drop(writer);
+
+        let reader = LabelerDataReader::new(Cursor::new(buffer.as_mut_slice())).unwrap();
+
+        assert_eq!(reader.version(), 1);
+        assert_eq!(reader.num_passing_events(), 3);
+        assert_eq!(reader.num_goal_shot_events(), 2);
+        assert_eq!(reader.frame_group_timestamps(0), None);
+        assert_eq!(reader.source_log_file_name(), None);
+        assert_eq!(reader.source_log_sha256(), None);
+        assert_eq!(reader.yellow_team_name(), None);
+        assert_eq!(reader.blue_team_name(), None);
+        assert!(reader.geometry().is_none());
+        assert!(reader.camera_ids().is_empty());
+        assert_eq!(reader.filter_config(), FilterConfig::default());
+    }
+
+    #[test]
+    fn new_v2_metadata() {
+        let mut buffer = Vec::<u8>::new();
+        let mut writer = Cursor::new(&mut buffer);
+        write_header(&mut writer, &LABELER_DATA_HEADER, LABELER_DATA_VERSION).unwrap();
+        let mut metadata = log_labeler_data::LabelerMetadata::new();
+        let mut timestamps = log_labeler_data::FrameGroupTimestamps::new();
+        timestamps.set_first_timestamp(10);
+        timestamps.set_last_timestamp(20);
+        metadata.mut_frame_group_timestamps().push(timestamps);
+        metadata.set_source_log_file_name("game.log".to_owned());
+        metadata.set_source_log_sha256("deadbeef".to_owned());
+        metadata.set_yellow_team_name("ER-Force".to_owned());
+        metadata.set_blue_team_name("TIGERs Mannheim".to_owned());
+        metadata.set_geometry(geometry(9000, 6000));
+        metadata.set_camera_ids(vec![0, 1, 3]);
+        let mut config = FilterConfig::default();
+        config.running_commands.truncate(2);
+        metadata.set_filter_config(config.to_proto());
+        write_metadata(&mut writer, &metadata).unwrap();
+        drop(writer);
+
+        let reader = LabelerDataReader::new(Cursor::new(buffer.as_mut_slice())).unwrap();
+
+        assert_eq!(reader.version(), LABELER_DATA_VERSION);
+        assert_eq!(reader.frame_group_timestamps(0), Some((10, 20)));
+        assert_eq!(reader.frame_group_timestamps(1), None);
+        assert_eq!(reader.source_log_file_name(), Some("game.log"));
+        assert_eq!(reader.source_log_sha256(), Some("deadbeef"));
+        assert_eq!(reader.yellow_team_name(), Some("ER-Force"));
+        assert_eq!(reader.blue_team_name(), Some("TIGERs Mannheim"));
+        assert!(reader.geometry().is_some());
+        assert_eq!(reader.camera_ids(), &[0, 1, 3]);
+        assert_eq!(reader.filter_config(), config);
+    }
+
+    #[test]
+    fn get_out_of_range() {
+        let mut buffer = Vec::<u8>::new();
+        let mut writer = Cursor::new(&mut buffer);
+        write_header(&mut writer, &LABELER_DATA_HEADER, LABELER_DATA_VERSION).unwrap();
+        write_metadata(&mut writer, &log_labeler_data::LabelerMetadata::new()).unwrap();
+        drop(writer);
+
+        let reader = LabelerDataReader::new(Cursor::new(buffer.as_mut_slice())).unwrap();
+
+        match reader.get(0) {
+            Err(LabelerDataReaderError::IndexOutOfRange { index: 0, len: 0 }) => {}
+            result => panic!("Unexpected result {:?}", result),
+        };
+        match reader.get_range(0, 1) {
+            Err(LabelerDataReaderError::InvalidRange { .. }) => {}
+            result => panic!("Unexpected result {:?}", result),
+        };
+        assert!(reader.get_range_from(0).unwrap().is_empty());
+        assert_eq!(reader.iter().count(), 0);
+    }
+
+    #[test]
+    fn try_clone_without_path() {
+        let mut tmpfile = tempfile::NamedTempFile::new().unwrap();
+        write_header(&mut tmpfile, &LABELER_DATA_HEADER, LABELER_DATA_VERSION).unwrap();
+        write_metadata(&mut tmpfile, &log_labeler_data::LabelerMetadata::new()).unwrap();
+
+        let reader = LabelerDataReader::new(tmpfile.reopen().unwrap()).unwrap();
+        match reader.try_clone() {
+            Err(LabelerDataReaderError::NoPath) => {}
+            Err(e) => panic!("Unexpected error {}", e),
+            Ok(_) => panic!("Cloned reader without a path"),
+        };
+    }
+
     #[test]
     fn new_from_path_blank() {
         let mut tmpfile = tempfile::NamedTempFile::new().unwrap();
@@ -0,0 +1,379 @@
+use super::metadata::{has_valid_trailer, LabelerMetadataError};
+use super::reader::{read_header, read_metadata, LabelerDataReaderError};
+use super::writer::{write_metadata, LabelerDataWriterError};
+use crate::protos::log_labeler_data;
+use byteorder::{BigEndian, ReadBytesExt};
+use protobuf;
+use std::collections::BTreeSet;
+use std::fs::OpenOptions;
+use std::io;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+#[derive(Debug, Fail)]
+pub enum LabelerDataRepairError {
+    #[fail(display = "{}", _0)]
+    Io(#[fail(cause)] io::Error),
+    #[fail(display = "{}", _0)]
+    Reader(#[fail(cause)] LabelerDataReaderError),
+    #[fail(display = "{}", _0)]
+    Writer(#[fail(cause)] LabelerDataWriterError),
+    #[fail(display = "{}", _0)]
+    Metadata(#[fail(cause)] LabelerMetadataError),
+}
+
+impl From<io::Error> for LabelerDataRepairError {
+    fn from(error: io::Error) -> Self {
+        LabelerDataRepairError::Io(error)
+    }
+}
+
+impl From<LabelerDataReaderError> for LabelerDataRepairError {
+    fn from(error: LabelerDataReaderError) -> Self {
+        match error {
+            LabelerDataReaderError::Io(e) => LabelerDataRepairError::Io(e),
+            e => LabelerDataRepairError::Reader(e),
+        }
+    }
+}
+
+impl From<LabelerDataWriterError> for LabelerDataRepairError {
+    fn from(error: LabelerDataWriterError) -> Self {
+        match error {
+            LabelerDataWriterError::Io(e) => LabelerDataRepairError::Io(e),
+            e => LabelerDataRepairError::Writer(e),
+        }
+    }
+}
+
+impl From<LabelerMetadataError> for LabelerDataRepairError {
+    fn from(error: LabelerMetadataError) -> Self {
+        match error {
+            LabelerMetadataError::Io(e) => LabelerDataRepairError::Io(e),
+            e => LabelerDataRepairError::Metadata(e),
+        }
+    }
+}
+
+type LabelerDataRepairResult<T> = Result<T, LabelerDataRepairError>;
+
+#[derive(Debug)]
+pub struct RecoveredMetadata {
+    pub metadata: log_labeler_data::LabelerMetadata,
+    // offset just past the last complete frame group. Everything
+    // after this is either a partial record or an old trailer.
+    pub data_end: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RepairOutcome {
+    AlreadyValid,
+    Repaired {
+        num_frame_groups: usize,
+        discarded_bytes: u64,
+    },
+}
+
+// Read a single length prefixed frame group. Returns None if the
+// record is incomplete or cannot be parsed.
+fn read_frame_group<T: Read>(
+    reader: &mut T,
+) -> io::Result<Option<(u64, log_labeler_data::LabelerFrameGroup)>> {
+    let msg_size = match reader.read_u32::<BigEndian>() {
+        Ok(msg_size) => msg_size,
+        Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    };
+
+    let mut msg_bytes = Vec::new();
+    reader
+        .by_ref()
+        .take(u64::from(msg_size))
+        .read_to_end(&mut msg_bytes)?;
+    if msg_bytes.len() != msg_size as usize {
+        return Ok(None);
+    }
+
+    match protobuf::parse_from_bytes::<log_labeler_data::LabelerFrameGroup>(&msg_bytes) {
+        Ok(frame_group) => Ok(Some((4 + u64::from(msg_size), frame_group))),
+        Err(_) => Ok(None),
+    }
+}
+
+// Scan the frame groups from the start of the file and rebuild the
+// metadata from them. Scanning stops at the first incomplete
+// record. Fields that cannot be derived from the frames (the filter
+// config, source log and event counts) are left empty.
+pub fn recover_metadata<T: Read + Seek>(reader: T) -> LabelerDataRepairResult<RecoveredMetadata> {
+    let mut reader = BufReader::new(reader);
+    reader.seek(SeekFrom::Start(0))?;
+    read_header(&mut reader)?;
+
+    let mut metadata = log_labeler_data::LabelerMetadata::new();
+    let mut camera_ids = BTreeSet::new();
+    let mut offset = reader.seek(SeekFrom::Current(0))?;
+
+    while let Some((record_size, frame_group)) = read_frame_group(&mut reader)? {
+        metadata.mut_message_offsets().push(offset);
+        offset += record_size;
+
+        for frame in frame_group.get_frames() {
+            if frame.has_vision_frame() {
+                let vision_frame = frame.get_vision_frame();
+                if vision_frame.has_detection() {
+                    camera_ids.insert(vision_frame.get_detection().get_camera_id());
+                }
+                if vision_frame.has_geometry() {
+                    metadata.set_geometry(vision_frame.get_geometry().clone());
+                }
+            } else if frame.has_referee_frame() {
+                let referee_frame = frame.get_referee_frame();
+                if !referee_frame.get_yellow().get_name().is_empty() {
+                    metadata.set_yellow_team_name(referee_frame.get_yellow().get_name().to_owned());
+                }
+                if !referee_frame.get_blue().get_name().is_empty() {
+                    metadata.set_blue_team_name(referee_frame.get_blue().get_name().to_owned());
+                }
+            }
+        }
+
+        if let (Some(first), Some(last)) = (
+            frame_group.get_frames().first(),
+            frame_group.get_frames().last(),
+        ) {
+            let mut timestamps = log_labeler_data::FrameGroupTimestamps::new();
+            timestamps.set_first_timestamp(first.get_timestamp());
+            timestamps.set_last_timestamp(last.get_timestamp());
+            metadata.mut_frame_group_timestamps().push(timestamps);
+        } else {
+            metadata
+                .mut_frame_group_timestamps()
+                .push(log_labeler_data::FrameGroupTimestamps::new());
+        }
+    }
+
+    metadata.set_num_cameras(camera_ids.iter().cloned().max().unwrap_or(0));
+    metadata.set_camera_ids(camera_ids.into_iter().collect());
+
+    Ok(RecoveredMetadata {
+        metadata,
+        data_end: offset,
+    })
+}
+
+// Copies the fields `recover_metadata` cannot derive from the frames
+fn keep_underived_fields(
+    old_metadata: &log_labeler_data::LabelerMetadata,
+    metadata: &mut log_labeler_data::LabelerMetadata,
+) {
+    metadata.set_num_passing_events(old_metadata.get_num_passing_events());
+    metadata.set_num_goal_shot_events(old_metadata.get_num_goal_shot_events());
+    metadata.set_source_log_file_name(old_metadata.get_source_log_file_name().to_owned());
+    metadata.set_source_log_sha256(old_metadata.get_source_log_sha256().to_owned());
+    if old_metadata.has_filter_config() {
+        metadata.set_filter_config(old_metadata.get_filter_config().clone());
+    }
+}
+
+// Rebuild the metadata trailer of a labeler data file in place. Any
+// partial record at the end of the file is discarded. Files with a
+// valid trailer are left alone unless `force` is set, in which case
+// the fields of the old trailer that cannot be rebuilt are kept.
+pub fn repair_labeler_data_file(
+    path: &Path,
+    force: bool,
+) -> LabelerDataRepairResult<RepairOutcome> {
+    let mut file = OpenOptions::new().read(true).write(true).open(path)?;
+
+    let old_trailer = if has_valid_trailer(&mut file)? {
+        if !force {
+            return Ok(RepairOutcome::AlreadyValid);
+        }
+        Some(read_metadata(&mut file)?)
+    } else {
+        None
+    };
+
+    let mut recovered = recover_metadata(&mut file)?;
+    // a valid old trailer is replaced, not discarded
+    let data_len = match old_trailer {
+        Some((ref old_metadata, trailer_start)) => {
+            keep_underived_fields(old_metadata, &mut recovered.metadata);
+            trailer_start
+        }
+        None => file.seek(SeekFrom::End(0))?,
+    };
+
+    file.set_len(recovered.data_end)?;
+    file.seek(SeekFrom::Start(recovered.data_end))?;
+    write_metadata(&mut file, &recovered.metadata)?;
+    file.sync_all()?;
+
+    Ok(RepairOutcome::Repaired {
+        num_frame_groups: recovered.metadata.get_message_offsets().len(),
+        discarded_bytes: data_len.saturating_sub(recovered.data_end),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate tempfile;
+
+    use super::*;
+    use crate::labeler::reader::LabelerDataReader;
+    use crate::labeler::FilterConfig;
+    use crate::labeler::LABELER_DATA_HEADER;
+    use crate::labeler::LABELER_DATA_VERSION;
+    use crate::test_utils::labeler as test_utils_labeler;
+    use byteorder::WriteBytesExt;
+    use proptest::prelude::*;
+    use protobuf::Message;
+    use std::io::{Cursor, Write};
+
+    impl From<LabelerDataRepairError> for TestCaseError {
+        fn from(error: LabelerDataRepairError) -> Self {
+            TestCaseError::fail(format!("{}", error))
+        }
+    }
+
+    // writes the header and frame groups without a trailer, as if the
+    // writer was killed. Returns the offset of each frame group.
+    fn write_frames<T: Write + Seek>(
+        writer: &mut T,
+        frame_group_msgs: &[log_labeler_data::LabelerFrameGroup],
+    ) -> LabelerDataRepairResult<Vec<u64>> {
+        writer.write_all(&LABELER_DATA_HEADER)?;
+        writer.write_u32::<BigEndian>(LABELER_DATA_VERSION)?;
+
+        let mut offsets = vec![];
+        for frame_group_msg in frame_group_msgs {
+            offsets.push(writer.seek(SeekFrom::Current(0))?);
+            let msg_bytes = frame_group_msg
+                .write_to_bytes()
+                .map_err(LabelerDataWriterError::Protobuf)?;
+            writer.write_u32::<BigEndian>(msg_bytes.len() as u32)?;
+            writer.write_all(&msg_bytes)?;
+        }
+
+        Ok(offsets)
+    }
+
+    proptest! {
+        #[test]
+        fn recover_complete_frames(
+            random_frame_group_msgs in proptest::collection::vec(
+                test_utils_labeler::random_no_ref_frame_group_msg_strategy(1, 10), 1..10)) {
+            let mut buffer = Vec::<u8>::new();
+            let offsets = write_frames(&mut Cursor::new(&mut buffer), &random_frame_group_msgs)?;
+
+            let recovered = recover_metadata(Cursor::new(&buffer))?;
+
+            prop_assert_eq!(recovered.metadata.get_message_offsets(), offsets.as_slice());
+            prop_assert_eq!(recovered.data_end, buffer.len() as u64);
+            prop_assert_eq!(
+                recovered.metadata.get_frame_group_timestamps().len(),
+                random_frame_group_msgs.len()
+            );
+        }
+
+        #[test]
+        fn recover_truncated_frame(
+            random_frame_group_msgs in proptest::collection::vec(
+                test_utils_labeler::random_no_ref_frame_group_msg_strategy(1, 10), 2..10),
+            truncate_by in 1..4usize) {
+            let mut buffer = Vec::<u8>::new();
+            let offsets = write_frames(&mut Cursor::new(&mut buffer), &random_frame_group_msgs)?;
+            let new_len = buffer.len() - truncate_by;
+            buffer.truncate(new_len);
+
+            let recovered = recover_metadata(Cursor::new(&buffer))?;
+
+            let num_complete = random_frame_group_msgs.len() - 1;
+            prop_assert_eq!(recovered.metadata.get_message_offsets(), &offsets[..num_complete]);
+            prop_assert_eq!(recovered.data_end, offsets[num_complete]);
+        }
+
+        #[test]
+        fn repair_truncated_file(
+            random_frame_group_msgs in proptest::collection::vec(
+                test_utils_labeler::random_no_ref_frame_group_msg_strategy(1, 10), 2..10),
+            truncate_by in 1..4u64) {
+            let mut tmpfile = tempfile::NamedTempFile::new()?;
+            write_frames(&mut tmpfile, &random_frame_group_msgs)?;
+            let file_len = tmpfile.seek(SeekFrom::End(0))?;
+            tmpfile.as_file().set_len(file_len - truncate_by)?;
+
+            prop_assert!(!has_valid_trailer(tmpfile.as_file_mut())?);
+
+            let outcome = repair_labeler_data_file(tmpfile.path(), false)?;
+            let num_complete = random_frame_group_msgs.len() - 1;
+            match outcome {
+                RepairOutcome::Repaired { num_frame_groups, .. } => prop_assert_eq!(num_frame_groups, num_complete),
+                RepairOutcome::AlreadyValid => return Err(TestCaseError::fail("Truncated file reported as valid")),
+            };
+
+            let reader = LabelerDataReader::new_from_path(tmpfile.path())?;
+            prop_assert_eq!(reader.len(), num_complete);
+            for (index, frame_group_msg) in random_frame_group_msgs.iter().take(num_complete).enumerate() {
+                prop_assert_eq!(&reader.get(index).unwrap(), frame_group_msg);
+            }
+
+            // a second repair should not touch the file
+            prop_assert_eq!(repair_labeler_data_file(tmpfile.path(), false)?, RepairOutcome::AlreadyValid);
+        }
+    }
+
+    #[test]
+    fn forced_repair_keeps_underived_fields() {
+        let mut tmpfile = tempfile::NamedTempFile::new().unwrap();
+        let frame_group_msgs = vec![log_labeler_data::LabelerFrameGroup::new(); 2];
+        let offsets = write_frames(&mut tmpfile, &frame_group_msgs).unwrap();
+        let mut metadata = log_labeler_data::LabelerMetadata::new();
+        metadata.set_message_offsets(offsets);
+        metadata.set_num_passing_events(3);
+        metadata.set_num_goal_shot_events(1);
+        metadata.set_source_log_file_name("game.log".to_owned());
+        metadata.set_source_log_sha256("deadbeef".to_owned());
+        let mut config = FilterConfig::default();
+        config.running_commands.truncate(2);
+        metadata.set_filter_config(config.to_proto());
+        write_metadata(&mut tmpfile, &metadata).unwrap();
+        assert!(has_valid_trailer(tmpfile.as_file_mut()).unwrap());
+
+        let outcome = repair_labeler_data_file(tmpfile.path(), true).unwrap();
+        assert_eq!(
+            outcome,
+            RepairOutcome::Repaired {
+                num_frame_groups: 2,
+                discarded_bytes: 0
+            }
+        );
+
+        let reader = LabelerDataReader::new_from_path(tmpfile.path()).unwrap();
+        assert_eq!(reader.len(), 2);
+        assert_eq!(reader.frame_group_timestamps(1), Some((0, 0)));
+        assert_eq!(reader.num_passing_events(), 3);
+        assert_eq!(reader.num_goal_shot_events(), 1);
+        assert_eq!(reader.source_log_file_name(), Some("game.log"));
+        assert_eq!(reader.source_log_sha256(), Some("deadbeef"));
+        assert_eq!(reader.filter_config(), config);
+    }
+
+    #[test]
+    fn repair_header_only() {
+        let mut tmpfile = tempfile::NamedTempFile::new().unwrap();
+        write_frames(&mut tmpfile, &[]).unwrap();
+
+        let outcome = repair_labeler_data_file(tmpfile.path(), false).unwrap();
+        assert_eq!(
+            outcome,
+            RepairOutcome::Repaired {
+                num_frame_groups: 0,
+                discarded_bytes: 0
+            }
+        );
+
+        let reader = LabelerDataReader::new_from_path(tmpfile.path()).unwrap();
+        assert!(reader.is_empty());
+    }
+}
@@ -0,0 +1,589 @@
+use super::scoring::{
+    calc_iou, goal_shot_pair_score, goal_shot_span, match_events, passing_pair_score, passing_span,
+    score_labels_with_options, CategoryScore, MatchCounts, ScoreReport, ScoringOptions,
+    MAX_GOAL_SHOT_PAIR_SCORE, MAX_PASSING_PAIR_SCORE,
+};
+use crate::protos::log_labels;
+use serde_json::{self, json};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+// Detailed breakdown of where a set of predicted labels differs from
+// the ground truth, to help debug event detectors.
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Accuracy {
+    pub correct: usize,
+    pub total: usize,
+}
+
+impl Accuracy {
+    fn add(&mut self, correct: bool) {
+        self.total += 1;
+        if correct {
+            self.correct += 1;
+        }
+    }
+
+    // 1 if there was nothing to get right
+    pub fn ratio(&self) -> f64 {
+        if self.total == 0 {
+            1.0
+        } else {
+            self.correct as f64 / self.total as f64
+        }
+    }
+}
+
+fn accuracy_to_json(accuracy: &Accuracy) -> serde_json::Value {
+    json!({
+        "correct": accuracy.correct,
+        "total": accuracy.total,
+        "accuracy": accuracy.ratio(),
+    })
+}
+
+// Rows are the ground truth class, columns the predicted class.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfusionMatrix {
+    pub classes: Vec<&'static str>,
+    pub counts: Vec<Vec<usize>>,
+}
+
+impl ConfusionMatrix {
+    fn new(classes: &[&'static str]) -> ConfusionMatrix {
+        ConfusionMatrix {
+            classes: classes.to_vec(),
+            counts: vec![vec![0; classes.len()]; classes.len()],
+        }
+    }
+
+    fn add(&mut self, ground_truth_class: usize, predicted_class: usize) {
+        self.counts[ground_truth_class][predicted_class] += 1;
+    }
+
+    pub fn accuracy(&self) -> Accuracy {
+        Accuracy {
+            correct: (0..self.classes.len()).map(|i| self.counts[i][i]).sum(),
+            total: self
+                .counts
+                .iter()
+                .map(|row| row.iter().sum::<usize>())
+                .sum(),
+        }
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        json!({
+            "classes": self.classes,
+            "counts": self.counts,
+        })
+    }
+
+    fn to_markdown(&self, output: &mut String) {
+        writeln!(
+            output,
+            "| ground truth \\ predicted | {} |",
+            self.classes.join(" | ")
+        )
+        .unwrap();
+        writeln!(output, "|---|{}", "---|".repeat(self.classes.len())).unwrap();
+        for (class, row) in self.classes.iter().zip(&self.counts) {
+            let row: Vec<String> = row.iter().map(|count| count.to_string()).collect();
+            writeln!(output, "| {} | {} |", class, row.join(" | ")).unwrap();
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct TeamAccuracy {
+    // frames where the ground truth has the team/robot dribbling
+    pub dribbling: Accuracy,
+    // frames where the ground truth has the team/robot in possession
+    pub ball_possession: Accuracy,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventKind {
+    Passing,
+    GoalShot,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventOutcome {
+    Matched,
+    Missed,
+    FalsePositive,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventDetail {
+    pub kind: EventKind,
+    pub outcome: EventOutcome,
+    pub ground_truth_span: Option<(u64, u64)>,
+    pub predicted_span: Option<(u64, u64)>,
+    pub iou: f64,
+    // points lost on this event compared to a perfect prediction
+    pub lost_score: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DetailedReport {
+    pub scores: ScoreReport,
+    pub dribbling_confusion: ConfusionMatrix,
+    pub ball_possession_confusion: ConfusionMatrix,
+    pub team_accuracy: BTreeMap<&'static str, TeamAccuracy>,
+    pub robot_accuracy: BTreeMap<(&'static str, u32), TeamAccuracy>,
+    // sorted from the most to the least points lost
+    pub events: Vec<EventDetail>,
+}
+
+fn team_name(team: log_labels::Team) -> &'static str {
+    match team {
+        log_labels::Team::YELLOW => "yellow",
+        log_labels::Team::BLUE => "blue",
+    }
+}
+
+fn possession_class(state: log_labels::BallPossessionLabel_State) -> usize {
+    match state {
+        log_labels::BallPossessionLabel_State::NONE => 0,
+        log_labels::BallPossessionLabel_State::YELLOW_POSSES => 1,
+        log_labels::BallPossessionLabel_State::BLUE_POSSES => 2,
+    }
+}
+
+fn event_details<L, S, P>(
+    kind: EventKind,
+    ground_truth_labels: &[L],
+    predicted_labels: &[L],
+    span: S,
+    pair_score: P,
+    max_pair_score: f64,
+    options: &ScoringOptions,
+) -> Vec<EventDetail>
+where
+    S: Fn(&L) -> (u64, u64),
+    P: Fn(&L, &L) -> f64,
+{
+    let matching = match_events(
+        ground_truth_labels,
+        predicted_labels,
+        options.min_iou,
+        &span,
+        &pair_score,
+    );
+    let mut details = vec![];
+
+    for (ground_truth_index, predicted_index) in &matching.pairs {
+        let ground_truth_label = &ground_truth_labels[*ground_truth_index];
+        let predicted_label = &predicted_labels[*predicted_index];
+        details.push(EventDetail {
+            kind,
+            outcome: EventOutcome::Matched,
+            ground_truth_span: Some(span(ground_truth_label)),
+            predicted_span: Some(span(predicted_label)),
+            iou: calc_iou(span(ground_truth_label), span(predicted_label)),
+            lost_score: max_pair_score - pair_score(ground_truth_label, predicted_label),
+        });
+    }
+
+    for ground_truth_index in &matching.unmatched_ground_truth {
+        details.push(EventDetail {
+            kind,
+            outcome: EventOutcome::Missed,
+            ground_truth_span: Some(span(&ground_truth_labels[*ground_truth_index])),
+            predicted_span: None,
+            iou: 0.0,
+            lost_score: max_pair_score,
+        });
+    }
+
+    for predicted_index in &matching.unmatched_predicted {
+        details.push(EventDetail {
+            kind,
+            outcome: EventOutcome::FalsePositive,
+            ground_truth_span: None,
+            predicted_span: Some(span(&predicted_labels[*predicted_index])),
+            iou: 0.0,
+            lost_score: options.false_positive_penalty,
+        });
+    }
+
+    details
+}
+
+pub fn detailed_report(
+    ground_truth_labels: &log_labels::Labels,
+    predicted_labels: &log_labels::Labels,
+    options: &ScoringOptions,
+) -> DetailedReport {
+    let mut dribbling_confusion = ConfusionMatrix::new(&["not dribbling", "dribbling"]);
+    let mut ball_possession_confusion = ConfusionMatrix::new(&["none", "yellow", "blue"]);
+    let mut team_accuracy = BTreeMap::<&'static str, TeamAccuracy>::new();
+    let mut robot_accuracy = BTreeMap::<(&'static str, u32), TeamAccuracy>::new();
+
+    for (ground_truth_label, predicted_label) in ground_truth_labels
+        .get_dribbling_labels()
+        .iter()
+        .zip(predicted_labels.get_dribbling_labels())
+    {
+        dribbling_confusion.add(
+            ground_truth_label.get_is_dribbling() as usize,
+            predicted_label.get_is_dribbling() as usize,
+        );
+
+        if ground_truth_label.get_is_dribbling() {
+            let correct = predicted_label.get_is_dribbling()
+                && ground_truth_label.get_team() == predicted_label.get_team()
+                && ground_truth_label.get_robot_id() == predicted_label.get_robot_id();
+            let team = team_name(ground_truth_label.get_team());
+            team_accuracy
+                .entry(team)
+                .or_default()
+                .dribbling
+                .add(correct);
+            robot_accuracy
+                .entry((team, ground_truth_label.get_robot_id()))
+                .or_default()
+                .dribbling
+                .add(correct);
+        }
+    }
+
+    for (ground_truth_label, predicted_label) in ground_truth_labels
+        .get_ball_possession_labels()
+        .iter()
+        .zip(predicted_labels.get_ball_possession_labels())
+    {
+        ball_possession_confusion.add(
+            possession_class(ground_truth_label.get_state()),
+            possession_class(predicted_label.get_state()),
+        );
+
+        let team = match ground_truth_label.get_state() {
+            log_labels::BallPossessionLabel_State::NONE => continue,
+            log_labels::BallPossessionLabel_State::YELLOW_POSSES => "yellow",
+            log_labels::BallPossessionLabel_State::BLUE_POSSES => "blue",
+        };
+        let correct = ground_truth_label.get_state() == predicted_label.get_state()
+            && ground_truth_label.get_robot_id() == predicted_label.get_robot_id();
+        team_accuracy
+            .entry(team)
+            .or_default()
+            .ball_possession
+            .add(correct);
+        robot_accuracy
+            .entry((team, ground_truth_label.get_robot_id()))
+            .or_default()
+            .ball_possession
+            .add(correct);
+    }
+
+    let mut events = event_details(
+        EventKind::Passing,
+        ground_truth_labels.get_passing_labels(),
+        predicted_labels.get_passing_labels(),
+        passing_span,
+        passing_pair_score,
+        MAX_PASSING_PAIR_SCORE,
+        options,
+    );
+    events.extend(event_details(
+        EventKind::GoalShot,
+        ground_truth_labels.get_goal_shot_labels(),
+        predicted_labels.get_goal_shot_labels(),
+        goal_shot_span,
+        goal_shot_pair_score,
+        MAX_GOAL_SHOT_PAIR_SCORE,
+        options,
+    ));
+    events.sort_by(|a, b| b.lost_score.partial_cmp(&a.lost_score).unwrap());
+
+    DetailedReport {
+        scores: score_labels_with_options(ground_truth_labels, predicted_labels, options),
+        dribbling_confusion,
+        ball_possession_confusion,
+        team_accuracy,
+        robot_accuracy,
+        events,
+    }
+}
+
+fn span_to_json(span: Option<(u64, u64)>) -> serde_json::Value {
+    match span {
+        Some((start, end)) => json!([start, end]),
+        None => serde_json::Value::Null,
+    }
+}
+
+fn span_to_markdown(span: Option<(u64, u64)>) -> String {
+    match span {
+        Some((start, end)) => format!("{}-{}", start, end),
+        None => "-".to_owned(),
+    }
+}
+
+impl DetailedReport {
+    // Only the `max_events` worst events are included.
+    pub fn to_json(&self, max_events: usize) -> serde_json::Value {
+        let category_json = |score: &CategoryScore| {
+            json!({
+                "score": score.score,
+                "max_score": score.max_score,
+            })
+        };
+        let matches_json = |counts: &MatchCounts| {
+            json!({
+                "true_positives": counts.true_positives,
+                "false_positives": counts.false_positives,
+                "false_negatives": counts.false_negatives,
+                "precision": counts.precision(),
+                "recall": counts.recall(),
+                "f1": counts.f1(),
+            })
+        };
+
+        json!({
+            "scores": {
+                "dribbling": category_json(&self.scores.dribbling),
+                "ball_possession": category_json(&self.scores.ball_possession),
+                "passing": category_json(&self.scores.passing),
+                "goal_shot": category_json(&self.scores.goal_shot),
+                "total": category_json(&self.scores.total()),
+            },
+            "passing_matches": matches_json(&self.scores.passing_matches),
+            "goal_shot_matches": matches_json(&self.scores.goal_shot_matches),
+            "dribbling_confusion": self.dribbling_confusion.to_json(),
+            "ball_possession_confusion": self.ball_possession_confusion.to_json(),
+            "team_accuracy": self
+                .team_accuracy
+                .iter()
+                .map(|(team, accuracy)| json!({
+                    "team": team,
+                    "dribbling": accuracy_to_json(&accuracy.dribbling),
+                    "ball_possession": accuracy_to_json(&accuracy.ball_possession),
+                }))
+                .collect::<Vec<_>>(),
+            "robot_accuracy": self
+                .robot_accuracy
+                .iter()
+                .map(|((team, robot_id), accuracy)| json!({
+                    "team": team,
+                    "robot_id": robot_id,
+                    "dribbling": accuracy_to_json(&accuracy.dribbling),
+                    "ball_possession": accuracy_to_json(&accuracy.ball_possession),
+                }))
+                .collect::<Vec<_>>(),
+            "worst_events": self
+                .events
+                .iter()
+                .take(max_events)
+                .map(|event| json!({
+                    "kind": format!("{:?}", event.kind),
+                    "outcome": format!("{:?}", event.outcome),
+                    "ground_truth_frames": span_to_json(event.ground_truth_span),
+                    "predicted_frames": span_to_json(event.predicted_span),
+                    "iou": event.iou,
+                    "lost_score": event.lost_score,
+                }))
+                .collect::<Vec<_>>(),
+        })
+    }
+
+    // Only the `max_events` worst events are included.
+    pub fn to_markdown(&self, max_events: usize) -> String {
+        let mut output = String::new();
+
+        writeln!(output, "# Score Report\n").unwrap();
+        writeln!(output, "| category | score | max score |").unwrap();
+        writeln!(output, "|---|---|---|").unwrap();
+        for (name, score) in &[
+            ("dribbling", self.scores.dribbling),
+            ("ball possession", self.scores.ball_possession),
+            ("passing", self.scores.passing),
+            ("goal shot", self.scores.goal_shot),
+            ("total", self.scores.total()),
+        ] {
+            writeln!(
+                output,
+                "| {} | {} | {} |",
+                name, score.score, score.max_score
+            )
+            .unwrap();
+        }
+
+        writeln!(output, "\n## Event Detection\n").unwrap();
+        writeln!(
+            output,
+            "| event | true positives | false positives | misses | precision | recall | F1 |"
+        )
+        .unwrap();
+        writeln!(output, "|---|---|---|---|---|---|---|").unwrap();
+        for (name, counts) in &[
+            ("passing", self.scores.passing_matches),
+            ("goal shot", self.scores.goal_shot_matches),
+        ] {
+            writeln!(
+                output,
+                "| {} | {} | {} | {} | {:.3} | {:.3} | {:.3} |",
+                name,
+                counts.true_positives,
+                counts.false_positives,
+                counts.false_negatives,
+                counts.precision(),
+                counts.recall(),
+                counts.f1()
+            )
+            .unwrap();
+        }
+
+        writeln!(output, "\n## Dribbling Confusion Matrix\n").unwrap();
+        self.dribbling_confusion.to_markdown(&mut output);
+        writeln!(output, "\n## Ball Possession Confusion Matrix\n").unwrap();
+        self.ball_possession_confusion.to_markdown(&mut output);
+
+        writeln!(output, "\n## Accuracy per Team\n").unwrap();
+        writeln!(output, "| team | dribbling | ball possession |").unwrap();
+        writeln!(output, "|---|---|---|").unwrap();
+        for (team, accuracy) in &self.team_accuracy {
+            writeln!(
+                output,
+                "| {} | {}/{} | {}/{} |",
+                team,
+                accuracy.dribbling.correct,
+                accuracy.dribbling.total,
+                accuracy.ball_possession.correct,
+                accuracy.ball_possession.total
+            )
+            .unwrap();
+        }
+
+        writeln!(output, "\n## Accuracy per Robot\n").unwrap();
+        writeln!(output, "| team | robot | dribbling | ball possession |").unwrap();
+        writeln!(output, "|---|---|---|---|").unwrap();
+        for ((team, robot_id), accuracy) in &self.robot_accuracy {
+            writeln!(
+                output,
+                "| {} | {} | {}/{} | {}/{} |",
+                team,
+                robot_id,
+                accuracy.dribbling.correct,
+                accuracy.dribbling.total,
+                accuracy.ball_possession.correct,
+                accuracy.ball_possession.total
+            )
+            .unwrap();
+        }
+
+        writeln!(output, "\n## Worst Events\n").unwrap();
+        writeln!(
+            output,
+            "| event | outcome | ground truth frames | predicted frames | IoU | lost score |"
+        )
+        .unwrap();
+        writeln!(output, "|---|---|---|---|---|---|").unwrap();
+        for event in self.events.iter().take(max_events) {
+            writeln!(
+                output,
+                "| {:?} | {:?} | {} | {} | {:.3} | {} |",
+                event.kind,
+                event.outcome,
+                span_to_markdown(event.ground_truth_span),
+                span_to_markdown(event.predicted_span),
+                event.iou,
+                event.lost_score
+            )
+            .unwrap();
+        }
+
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::labels as test_utils_labels;
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn self_report_is_perfect(labels in test_utils_labels::random_labels_strategy(20, 10)) {
+            let report = detailed_report(&labels, &labels, &ScoringOptions::default());
+
+            let dribbling_accuracy = report.dribbling_confusion.accuracy();
+            prop_assert_eq!(dribbling_accuracy.correct, dribbling_accuracy.total);
+            prop_assert_eq!(dribbling_accuracy.total, labels.get_dribbling_labels().len());
+            let ball_possession_accuracy = report.ball_possession_confusion.accuracy();
+            prop_assert_eq!(ball_possession_accuracy.correct, ball_possession_accuracy.total);
+
+            for accuracy in report.team_accuracy.values().chain(report.robot_accuracy.values()) {
+                prop_assert_eq!(accuracy.dribbling.ratio(), 1.0);
+                prop_assert_eq!(accuracy.ball_possession.ratio(), 1.0);
+            }
+
+            prop_assert_eq!(
+                report.events.len(),
+                labels.get_passing_labels().len() + labels.get_goal_shot_labels().len()
+            );
+            for event in &report.events {
+                prop_assert_eq!(event.outcome, EventOutcome::Matched);
+                prop_assert_eq!(event.lost_score, 0.0);
+            }
+        }
+
+        #[test]
+        fn events_sorted_worst_first(ground_truth_labels in test_utils_labels::random_labels_strategy(20, 10),
+                                     predicted_labels in test_utils_labels::random_labels_strategy(20, 10)) {
+            let report = detailed_report(&ground_truth_labels, &predicted_labels, &ScoringOptions::default());
+
+            for pair in report.events.windows(2) {
+                prop_assert!(pair[0].lost_score >= pair[1].lost_score);
+            }
+        }
+    }
+
+    #[test]
+    fn missed_pass_is_worst_event() {
+        let mut ground_truth_labels = log_labels::Labels::new();
+        let mut passing_label = log_labels::PassingLabel::new();
+        passing_label.set_start_frame(10);
+        passing_label.set_end_frame(20);
+        ground_truth_labels.mut_passing_labels().push(passing_label);
+        let mut possession_label = log_labels::BallPossessionLabel::new();
+        possession_label.set_state(log_labels::BallPossessionLabel_State::BLUE_POSSES);
+        possession_label.set_robot_id(3);
+        ground_truth_labels
+            .mut_ball_possession_labels()
+            .push(possession_label);
+
+        let mut predicted_labels = log_labels::Labels::new();
+        predicted_labels
+            .mut_ball_possession_labels()
+            .push(log_labels::BallPossessionLabel::new());
+
+        let report = detailed_report(
+            &ground_truth_labels,
+            &predicted_labels,
+            &ScoringOptions::default(),
+        );
+
+        assert_eq!(report.ball_possession_confusion.counts[2][0], 1);
+        assert_eq!(report.team_accuracy["blue"].ball_possession.total, 1);
+        assert_eq!(report.team_accuracy["blue"].ball_possession.correct, 0);
+        assert_eq!(report.robot_accuracy[&("blue", 3)].ball_possession.total, 1);
+        assert_eq!(report.events[0].outcome, EventOutcome::Missed);
+        assert_eq!(report.events[0].ground_truth_span, Some((10, 20)));
+
+        let json = report.to_json(10);
+        assert_eq!(json["worst_events"][0]["outcome"], "Missed");
+        assert_eq!(
+            json["worst_events"][0]["ground_truth_frames"],
+            json!([10, 20])
+        );
+        assert!(report
+            .to_markdown(10)
+            .contains("| Passing | Missed | 10-20 | - |"));
+    }
+}
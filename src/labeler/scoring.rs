@@ -0,0 +1,693 @@
+use super::matching::max_weight_matching;
+use crate::protos::log_labels;
+use std::cmp;
+use std::ops::Add;
+
+// Scoring rules of the SSL 2019 Technical Challenge. Per frame
+// categories are scored by comparing the ground truth and predicted
+// labels in order. Duration events are first matched up by overlap,
+// see match_events. The maximum score only depends on the ground
+// truth.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScoringOptions {
+    // smallest IoU for a predicted event to count as a detection of a
+    // ground truth event
+    pub min_iou: f64,
+    // subtracted from the category score for every predicted event
+    // that does not match a ground truth event
+    pub false_positive_penalty: f64,
+}
+
+impl Default for ScoringOptions {
+    fn default() -> Self {
+        ScoringOptions {
+            min_iou: 0.1,
+            false_positive_penalty: 1.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct CategoryScore {
+    pub score: f64,
+    pub max_score: f64,
+}
+
+impl CategoryScore {
+    // Score as a fraction of the maximum. A category without any
+    // ground truth labels cannot lose points, so it counts as 1.
+    pub fn ratio(&self) -> f64 {
+        if self.max_score == 0.0 {
+            1.0
+        } else {
+            self.score / self.max_score
+        }
+    }
+}
+
+impl Add for CategoryScore {
+    type Output = CategoryScore;
+
+    fn add(self, other: CategoryScore) -> CategoryScore {
+        CategoryScore {
+            score: self.score + other.score,
+            max_score: self.max_score + other.max_score,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ScoreReport {
+    pub dribbling: CategoryScore,
+    pub ball_possession: CategoryScore,
+    pub passing: CategoryScore,
+    pub goal_shot: CategoryScore,
+    pub passing_matches: MatchCounts,
+    pub goal_shot_matches: MatchCounts,
+}
+
+impl ScoreReport {
+    pub fn total(&self) -> CategoryScore {
+        self.dribbling + self.ball_possession + self.passing + self.goal_shot
+    }
+}
+
+pub fn score_labels(
+    ground_truth_labels: &log_labels::Labels,
+    predicted_labels: &log_labels::Labels,
+) -> ScoreReport {
+    score_labels_with_options(
+        ground_truth_labels,
+        predicted_labels,
+        &ScoringOptions::default(),
+    )
+}
+
+pub fn score_labels_with_options(
+    ground_truth_labels: &log_labels::Labels,
+    predicted_labels: &log_labels::Labels,
+    options: &ScoringOptions,
+) -> ScoreReport {
+    let (passing, passing_matches) = score_passing(
+        ground_truth_labels.get_passing_labels(),
+        predicted_labels.get_passing_labels(),
+        options,
+    );
+    let (goal_shot, goal_shot_matches) = score_goal_shot(
+        ground_truth_labels.get_goal_shot_labels(),
+        predicted_labels.get_goal_shot_labels(),
+        options,
+    );
+
+    ScoreReport {
+        dribbling: score_dribbling(
+            ground_truth_labels.get_dribbling_labels(),
+            predicted_labels.get_dribbling_labels(),
+        ),
+        ball_possession: score_ball_possession(
+            ground_truth_labels.get_ball_possession_labels(),
+            predicted_labels.get_ball_possession_labels(),
+        ),
+        passing,
+        goal_shot,
+        passing_matches,
+        goal_shot_matches,
+    }
+}
+
+pub fn score_dribbling(
+    ground_truth_labels: &[log_labels::DribblingLabel],
+    predicted_labels: &[log_labels::DribblingLabel],
+) -> CategoryScore {
+    let mut score: f64 = 0.0;
+    for (ground_truth_label, predicted_label) in
+        ground_truth_labels.iter().zip(predicted_labels.iter())
+    {
+        if ground_truth_label.get_is_dribbling() == predicted_label.get_is_dribbling() {
+            score += 1.0;
+
+            // only score sub-fields if labeled as dribbling in ground truth
+            if ground_truth_label.get_is_dribbling() {
+                if ground_truth_label.get_robot_id() == predicted_label.get_robot_id() {
+                    score += 0.5;
+                }
+
+                if ground_truth_label.get_team() == predicted_label.get_team() {
+                    score += 0.5;
+                }
+            }
+        }
+    }
+
+    let max_score = ground_truth_labels
+        .iter()
+        .map(|label| if label.get_is_dribbling() { 2.0 } else { 1.0 })
+        .sum();
+
+    CategoryScore { score, max_score }
+}
+
+pub fn score_ball_possession(
+    ground_truth_labels: &[log_labels::BallPossessionLabel],
+    predicted_labels: &[log_labels::BallPossessionLabel],
+) -> CategoryScore {
+    let mut score: f64 = 0.0;
+    for (ground_truth_label, predicted_label) in
+        ground_truth_labels.iter().zip(predicted_labels.iter())
+    {
+        if ground_truth_label.get_state() == predicted_label.get_state() {
+            score += 1.0;
+
+            // only score if yellow/blue in possession
+            if ground_truth_label.get_state() != log_labels::BallPossessionLabel_State::NONE
+                && ground_truth_label.get_robot_id() == predicted_label.get_robot_id()
+            {
+                score += 0.5;
+            }
+        }
+    }
+
+    let max_score = ground_truth_labels
+        .iter()
+        .map(|label| {
+            if label.get_state() == log_labels::BallPossessionLabel_State::NONE {
+                1.0
+            } else {
+                1.5
+            }
+        })
+        .sum();
+
+    CategoryScore { score, max_score }
+}
+
+// Intersection over union of two frame spans. Spans with the start
+// after the end are flipped.
+pub fn calc_iou(span_a: (u64, u64), span_b: (u64, u64)) -> f64 {
+    let span_a = (cmp::min(span_a.0, span_a.1), cmp::max(span_a.0, span_a.1));
+    let span_b = (cmp::min(span_b.0, span_b.1), cmp::max(span_b.0, span_b.1));
+
+    // get the union
+    let union_start = cmp::min(span_a.0, span_b.0);
+    let union_end = cmp::max(span_a.1, span_b.1);
+
+    let union = union_end - union_start;
+    // this shouldn't really happen, but if it does happen in both the
+    // ground truth and predicted, count it as a perfect match
+    if union == 0 {
+        return 1.0;
+    }
+    let union = union as f64;
+
+    // get the overlap
+    let intersect: f64 = if span_a.0 > span_b.1 || span_b.0 > span_a.1 {
+        0.0
+    } else {
+        let intersect_start = cmp::max(span_a.0, span_b.0);
+        let intersect_end = cmp::min(span_a.1, span_b.1);
+
+        (intersect_end - intersect_start) as f64
+    };
+
+    intersect / union
+}
+
+// Counts of how ground truth and predicted events were matched.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct MatchCounts {
+    pub true_positives: usize,
+    pub false_positives: usize,
+    pub false_negatives: usize,
+}
+
+impl MatchCounts {
+    // Precision and recall are 1 when there is nothing to get wrong.
+    pub fn precision(&self) -> f64 {
+        let num_predicted = self.true_positives + self.false_positives;
+        if num_predicted == 0 {
+            1.0
+        } else {
+            self.true_positives as f64 / num_predicted as f64
+        }
+    }
+
+    pub fn recall(&self) -> f64 {
+        let num_ground_truth = self.true_positives + self.false_negatives;
+        if num_ground_truth == 0 {
+            1.0
+        } else {
+            self.true_positives as f64 / num_ground_truth as f64
+        }
+    }
+
+    pub fn f1(&self) -> f64 {
+        let precision = self.precision();
+        let recall = self.recall();
+        if precision + recall == 0.0 {
+            0.0
+        } else {
+            2.0 * precision * recall / (precision + recall)
+        }
+    }
+}
+
+impl Add for MatchCounts {
+    type Output = MatchCounts;
+
+    fn add(self, other: MatchCounts) -> MatchCounts {
+        MatchCounts {
+            true_positives: self.true_positives + other.true_positives,
+            false_positives: self.false_positives + other.false_positives,
+            false_negatives: self.false_negatives + other.false_negatives,
+        }
+    }
+}
+
+// Result of matching duration events. `pairs` holds the matched
+// (ground truth, predicted) indices.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct EventMatching {
+    pub pairs: Vec<(usize, usize)>,
+    pub unmatched_ground_truth: Vec<usize>,
+    pub unmatched_predicted: Vec<usize>,
+}
+
+impl EventMatching {
+    pub fn counts(&self) -> MatchCounts {
+        MatchCounts {
+            true_positives: self.pairs.len(),
+            false_positives: self.unmatched_predicted.len(),
+            false_negatives: self.unmatched_ground_truth.len(),
+        }
+    }
+}
+
+// Weight of the pair score in the matching. It is small enough that it
+// only decides between pairings with the same total IoU.
+const PAIR_SCORE_TIE_BREAK: f64 = 1e-6;
+
+// Pairs up ground truth and predicted events so that the total IoU of
+// the pairs is as large as possible. `pair_score` only breaks ties,
+// e.g. between events with the same span, so labels scored against
+// themselves are paired with themselves. Only events whose spans
+// overlap with at least `min_iou` can be paired, so a missed or extra
+// prediction does not shift the pairing of the other events.
+pub fn match_events<L, S, P>(
+    ground_truth_labels: &[L],
+    predicted_labels: &[L],
+    min_iou: f64,
+    span: S,
+    pair_score: P,
+) -> EventMatching
+where
+    S: Fn(&L) -> (u64, u64),
+    P: Fn(&L, &L) -> f64,
+{
+    let can_match = |ground_truth_label: &L, predicted_label: &L| {
+        let iou = calc_iou(span(ground_truth_label), span(predicted_label));
+        iou > 0.0 && iou >= min_iou
+    };
+
+    let weights: Vec<Vec<f64>> = ground_truth_labels
+        .iter()
+        .map(|ground_truth_label| {
+            predicted_labels
+                .iter()
+                .map(|predicted_label| {
+                    if can_match(ground_truth_label, predicted_label) {
+                        calc_iou(span(ground_truth_label), span(predicted_label))
+                            + PAIR_SCORE_TIE_BREAK * pair_score(ground_truth_label, predicted_label)
+                    } else {
+                        0.0
+                    }
+                })
+                .collect()
+        })
+        .collect();
+
+    let pairs: Vec<(usize, usize)> = max_weight_matching(&weights)
+        .into_iter()
+        .filter(|(ground_truth_index, predicted_index)| {
+            can_match(
+                &ground_truth_labels[*ground_truth_index],
+                &predicted_labels[*predicted_index],
+            )
+        })
+        .collect();
+
+    let unmatched_ground_truth = (0..ground_truth_labels.len())
+        .filter(|index| {
+            !pairs
+                .iter()
+                .any(|(ground_truth_index, _)| ground_truth_index == index)
+        })
+        .collect();
+    let unmatched_predicted = (0..predicted_labels.len())
+        .filter(|index| {
+            !pairs
+                .iter()
+                .any(|(_, predicted_index)| predicted_index == index)
+        })
+        .collect();
+
+    EventMatching {
+        pairs,
+        unmatched_ground_truth,
+        unmatched_predicted,
+    }
+}
+
+// Score of a matching. Every unmatched prediction costs the false
+// positive penalty, but the score never drops below 0.
+fn score_matching<L, P>(
+    ground_truth_labels: &[L],
+    predicted_labels: &[L],
+    matching: &EventMatching,
+    pair_score: P,
+    max_pair_score: f64,
+    options: &ScoringOptions,
+) -> CategoryScore
+where
+    P: Fn(&L, &L) -> f64,
+{
+    let matched_score: f64 = matching
+        .pairs
+        .iter()
+        .map(|(ground_truth_index, predicted_index)| {
+            pair_score(
+                &ground_truth_labels[*ground_truth_index],
+                &predicted_labels[*predicted_index],
+            )
+        })
+        .sum();
+    let penalty = options.false_positive_penalty * matching.unmatched_predicted.len() as f64;
+
+    CategoryScore {
+        score: (matched_score - penalty).max(0.0),
+        max_score: max_pair_score * ground_truth_labels.len() as f64,
+    }
+}
+
+// Pair scores of a perfectly predicted event
+pub const MAX_PASSING_PAIR_SCORE: f64 = 3.0;
+pub const MAX_GOAL_SHOT_PAIR_SCORE: f64 = 2.5;
+
+pub(crate) fn passing_span(label: &log_labels::PassingLabel) -> (u64, u64) {
+    (label.get_start_frame(), label.get_end_frame())
+}
+
+pub(crate) fn passing_pair_score(
+    ground_truth_label: &log_labels::PassingLabel,
+    predicted_label: &log_labels::PassingLabel,
+) -> f64 {
+    let mut score = calc_iou(
+        passing_span(ground_truth_label),
+        passing_span(predicted_label),
+    );
+
+    if ground_truth_label.get_successful() == predicted_label.get_successful() {
+        score += 0.5;
+    }
+
+    if ground_truth_label.get_passer_id() == predicted_label.get_passer_id() {
+        score += 0.5;
+    }
+
+    if ground_truth_label.get_passer_team() == predicted_label.get_passer_team() {
+        score += 0.5;
+    }
+
+    if ground_truth_label.get_receiver_id() == predicted_label.get_receiver_id() {
+        score += 0.5;
+    }
+
+    score
+}
+
+pub fn match_passing(
+    ground_truth_labels: &[log_labels::PassingLabel],
+    predicted_labels: &[log_labels::PassingLabel],
+    options: &ScoringOptions,
+) -> EventMatching {
+    match_events(
+        ground_truth_labels,
+        predicted_labels,
+        options.min_iou,
+        passing_span,
+        passing_pair_score,
+    )
+}
+
+pub fn score_passing(
+    ground_truth_labels: &[log_labels::PassingLabel],
+    predicted_labels: &[log_labels::PassingLabel],
+    options: &ScoringOptions,
+) -> (CategoryScore, MatchCounts) {
+    let matching = match_passing(ground_truth_labels, predicted_labels, options);
+    let score = score_matching(
+        ground_truth_labels,
+        predicted_labels,
+        &matching,
+        passing_pair_score,
+        MAX_PASSING_PAIR_SCORE,
+        options,
+    );
+
+    (score, matching.counts())
+}
+
+pub(crate) fn goal_shot_span(label: &log_labels::GoalShotLabel) -> (u64, u64) {
+    (label.get_start_frame(), label.get_end_frame())
+}
+
+pub(crate) fn goal_shot_pair_score(
+    ground_truth_label: &log_labels::GoalShotLabel,
+    predicted_label: &log_labels::GoalShotLabel,
+) -> f64 {
+    let mut score = calc_iou(
+        goal_shot_span(ground_truth_label),
+        goal_shot_span(predicted_label),
+    );
+
+    if ground_truth_label.get_successful() == predicted_label.get_successful() {
+        score += 0.5;
+    }
+
+    if ground_truth_label.get_shooter_id() == predicted_label.get_shooter_id() {
+        score += 0.5;
+    }
+
+    if ground_truth_label.get_shooter_team() == predicted_label.get_shooter_team() {
+        score += 0.5;
+    }
+
+    score
+}
+
+pub fn match_goal_shot(
+    ground_truth_labels: &[log_labels::GoalShotLabel],
+    predicted_labels: &[log_labels::GoalShotLabel],
+    options: &ScoringOptions,
+) -> EventMatching {
+    match_events(
+        ground_truth_labels,
+        predicted_labels,
+        options.min_iou,
+        goal_shot_span,
+        goal_shot_pair_score,
+    )
+}
+
+pub fn score_goal_shot(
+    ground_truth_labels: &[log_labels::GoalShotLabel],
+    predicted_labels: &[log_labels::GoalShotLabel],
+    options: &ScoringOptions,
+) -> (CategoryScore, MatchCounts) {
+    let matching = match_goal_shot(ground_truth_labels, predicted_labels, options);
+    let score = score_matching(
+        ground_truth_labels,
+        predicted_labels,
+        &matching,
+        goal_shot_pair_score,
+        MAX_GOAL_SHOT_PAIR_SCORE,
+        options,
+    );
+
+    (score, matching.counts())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::labels as test_utils_labels;
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn self_score_is_max(labels in test_utils_labels::random_labels_strategy(20, 10)) {
+            let report = score_labels(&labels, &labels);
+
+            prop_assert_eq!(report.dribbling.score, report.dribbling.max_score);
+            prop_assert_eq!(report.ball_possession.score, report.ball_possession.max_score);
+            prop_assert_eq!(report.passing.score, report.passing.max_score);
+            prop_assert_eq!(report.goal_shot.score, report.goal_shot.max_score);
+            prop_assert_eq!(report.total().ratio(), 1.0);
+        }
+
+        #[test]
+        fn score_within_max(ground_truth_labels in test_utils_labels::random_labels_strategy(20, 10),
+                            predicted_labels in test_utils_labels::random_labels_strategy(20, 10)) {
+            let report = score_labels(&ground_truth_labels, &predicted_labels);
+
+            for category in &[report.dribbling, report.ball_possession, report.passing, report.goal_shot] {
+                prop_assert!(category.score >= 0.0);
+                prop_assert!(category.score <= category.max_score);
+            }
+        }
+
+        #[test]
+        fn missing_prediction_only_costs_one_event(labels in test_utils_labels::random_labels_strategy(20, 10),
+                                                   removed_index in proptest::num::usize::ANY) {
+            prop_assume!(!labels.get_passing_labels().is_empty());
+            let ground_truth_labels = labels.get_passing_labels();
+            let mut predicted_labels = ground_truth_labels.to_vec();
+            predicted_labels.remove(removed_index % ground_truth_labels.len());
+
+            let (score, counts) = score_passing(ground_truth_labels, &predicted_labels, &ScoringOptions::default());
+
+            prop_assert_eq!(score.score, score.max_score - 3.0);
+            prop_assert_eq!(counts.true_positives, ground_truth_labels.len() - 1);
+            prop_assert_eq!(counts.false_negatives, 1);
+            prop_assert_eq!(counts.precision(), 1.0);
+        }
+
+        #[test]
+        fn iou_symmetric_and_bounded(span_a in test_utils_labels::random_span_strategy(100),
+                                     span_b in test_utils_labels::random_span_strategy(100)) {
+            let iou = calc_iou(span_a, span_b);
+
+            prop_assert_eq!(iou, calc_iou(span_b, span_a));
+            prop_assert!((0.0..=1.0).contains(&iou));
+        }
+
+        #[test]
+        fn iou_with_self_is_one(span in test_utils_labels::random_span_strategy(100)) {
+            prop_assert_eq!(calc_iou(span, span), 1.0);
+        }
+    }
+
+    #[test]
+    fn iou_disjoint_spans() {
+        assert_eq!(calc_iou((0, 10), (20, 30)), 0.0);
+        assert_eq!(calc_iou((0, 10), (5, 15)), 5.0 / 15.0);
+        assert_eq!(calc_iou((10, 0), (0, 10)), 1.0);
+    }
+
+    fn passing_label(span: (u64, u64)) -> log_labels::PassingLabel {
+        let mut label = log_labels::PassingLabel::new();
+        label.set_start_frame(span.0);
+        label.set_end_frame(span.1);
+        label
+    }
+
+    #[test]
+    fn missed_prediction_does_not_shift_matches() {
+        let ground_truth_labels: Vec<_> = [(0, 10), (20, 30), (40, 50)]
+            .iter()
+            .map(|span| passing_label(*span))
+            .collect();
+        let predicted_labels = vec![passing_label((0, 10)), passing_label((40, 50))];
+
+        let (score, counts) = score_passing(
+            &ground_truth_labels,
+            &predicted_labels,
+            &ScoringOptions::default(),
+        );
+
+        assert_eq!(score.score, 6.0);
+        assert_eq!(score.max_score, 9.0);
+        assert_eq!(
+            counts,
+            MatchCounts {
+                true_positives: 2,
+                false_positives: 0,
+                false_negatives: 1,
+            }
+        );
+        assert_eq!(counts.precision(), 1.0);
+        assert_eq!(counts.recall(), 2.0 / 3.0);
+    }
+
+    #[test]
+    fn extra_prediction_is_penalized() {
+        let ground_truth_labels = vec![passing_label((0, 10))];
+        let predicted_labels = vec![passing_label((50, 60)), passing_label((0, 10))];
+
+        let (score, counts) = score_passing(
+            &ground_truth_labels,
+            &predicted_labels,
+            &ScoringOptions::default(),
+        );
+
+        assert_eq!(score.score, 2.0);
+        assert_eq!(counts.false_positives, 1);
+        assert_eq!(counts.f1(), 2.0 / 3.0);
+    }
+
+    #[test]
+    fn low_overlap_is_not_a_match() {
+        let options = ScoringOptions {
+            min_iou: 0.5,
+            false_positive_penalty: 0.0,
+        };
+        let matching = match_passing(
+            &[passing_label((0, 10))],
+            &[passing_label((8, 20))],
+            &options,
+        );
+
+        assert!(matching.pairs.is_empty());
+        assert_eq!(matching.unmatched_ground_truth, vec![0]);
+        assert_eq!(matching.unmatched_predicted, vec![0]);
+    }
+
+    #[test]
+    fn overlap_decides_matching() {
+        let mut ground_truth_label = passing_label((0, 10));
+        ground_truth_label.set_passer_id(1);
+        let mut same_span = passing_label((0, 10));
+        same_span.set_passer_id(2);
+        let mut same_passer = passing_label((0, 6));
+        same_passer.set_passer_id(1);
+
+        // the second prediction has the higher pair score
+        let matching = match_passing(
+            &[ground_truth_label],
+            &[same_span, same_passer],
+            &ScoringOptions::default(),
+        );
+
+        assert_eq!(matching.pairs, vec![(0, 0)]);
+        assert_eq!(matching.unmatched_predicted, vec![1]);
+    }
+
+    #[test]
+    fn empty_predictions_score_zero() {
+        let mut ground_truth_labels = log_labels::Labels::new();
+        let mut dribbling_label = log_labels::DribblingLabel::new();
+        dribbling_label.set_is_dribbling(true);
+        ground_truth_labels
+            .mut_dribbling_labels()
+            .push(dribbling_label);
+        ground_truth_labels
+            .mut_passing_labels()
+            .push(log_labels::PassingLabel::new());
+
+        let report = score_labels(&ground_truth_labels, &log_labels::Labels::new());
+
+        assert_eq!(report.dribbling.max_score, 2.0);
+        assert_eq!(report.passing.max_score, 3.0);
+        assert_eq!(report.total().score, 0.0);
+    }
+}
@@ -0,0 +1,382 @@
+use super::reader::{LabelerDataReader, LabelerDataReaderError};
+use crate::protos::log_labels;
+use std::io::{Read, Seek};
+
+#[derive(Debug, Fail)]
+pub enum LabelTimestampError {
+    #[fail(display = "{}", _0)]
+    Reader(#[fail(cause)] LabelerDataReaderError),
+    #[fail(display = "label refers to frame {}, file has {}", frame, len)]
+    FrameOutOfRange { frame: u64, len: usize },
+    #[fail(
+        display = "labels were made from log {}, data file is from log {}",
+        labels_sha256, data_sha256
+    )]
+    SourceLogMismatch {
+        labels_sha256: String,
+        data_sha256: String,
+    },
+}
+
+impl From<LabelerDataReaderError> for LabelTimestampError {
+    fn from(error: LabelerDataReaderError) -> Self {
+        LabelTimestampError::Reader(error)
+    }
+}
+
+type LabelTimestampResult<T> = Result<T, LabelTimestampError>;
+
+impl<T: Read + Seek> LabelerDataReader<T> {
+    fn frame_range(&self, frame: u64) -> LabelTimestampResult<log_labels::FrameTimestampRange> {
+        if frame >= self.len() as u64 {
+            return Err(LabelTimestampError::FrameOutOfRange {
+                frame,
+                len: self.len(),
+            });
+        }
+        let (first_timestamp, last_timestamp) = self.timestamp_range(frame as usize)?;
+
+        let mut range = log_labels::FrameTimestampRange::new();
+        range.set_first_timestamp(first_timestamp);
+        range.set_last_timestamp(last_timestamp);
+        Ok(range)
+    }
+
+    // Frame group shown at `timestamp`. Events that start before the
+    // first frame group are moved to the first frame group.
+    fn frame_at(&self, timestamp: u64) -> LabelTimestampResult<u64> {
+        Ok(self.frame_group_at_timestamp(timestamp)?.unwrap_or(0) as u64)
+    }
+
+    // Per frame labels keep the time range of their frame group, the
+    // event labels keep the start of their first frame group and the
+    // end of their last frame group.
+    pub fn labels_to_timestamped(
+        &self,
+        labels: &log_labels::Labels,
+    ) -> LabelTimestampResult<log_labels::TimestampedLabels> {
+        let mut timestamped_labels = log_labels::TimestampedLabels::new();
+        timestamped_labels.set_source_log_sha256(self.source_log_sha256().unwrap_or("").to_owned());
+
+        for (frame, label) in labels.get_dribbling_labels().iter().enumerate() {
+            let mut timestamped_label = log_labels::TimestampedDribblingLabel::new();
+            timestamped_label.set_frame(self.frame_range(frame as u64)?);
+            timestamped_label.set_label(label.clone());
+            timestamped_labels
+                .mut_dribbling_labels()
+                .push(timestamped_label);
+        }
+
+        for (frame, label) in labels.get_ball_possession_labels().iter().enumerate() {
+            let mut timestamped_label = log_labels::TimestampedBallPossessionLabel::new();
+            timestamped_label.set_frame(self.frame_range(frame as u64)?);
+            timestamped_label.set_label(label.clone());
+            timestamped_labels
+                .mut_ball_possession_labels()
+                .push(timestamped_label);
+        }
+
+        for label in labels.get_passing_labels() {
+            let mut timestamped_label = log_labels::TimestampedPassingLabel::new();
+            timestamped_label.set_start_timestamp(
+                self.frame_range(label.get_start_frame())?
+                    .get_first_timestamp(),
+            );
+            timestamped_label.set_end_timestamp(
+                self.frame_range(label.get_end_frame())?
+                    .get_last_timestamp(),
+            );
+            let mut label = label.clone();
+            label.clear_start_frame();
+            label.clear_end_frame();
+            timestamped_label.set_label(label);
+            timestamped_labels
+                .mut_passing_labels()
+                .push(timestamped_label);
+        }
+
+        for label in labels.get_goal_shot_labels() {
+            let mut timestamped_label = log_labels::TimestampedGoalShotLabel::new();
+            timestamped_label.set_start_timestamp(
+                self.frame_range(label.get_start_frame())?
+                    .get_first_timestamp(),
+            );
+            timestamped_label.set_end_timestamp(
+                self.frame_range(label.get_end_frame())?
+                    .get_last_timestamp(),
+            );
+            let mut label = label.clone();
+            label.clear_start_frame();
+            label.clear_end_frame();
+            timestamped_label.set_label(label);
+            timestamped_labels
+                .mut_goal_shot_labels()
+                .push(timestamped_label);
+        }
+
+        Ok(timestamped_labels)
+    }
+
+    // Maps timestamped labels onto the frame groups of this file. Each
+    // frame group gets the per frame label that was current at its
+    // first timestamp, or the default label if there is none. Fails if
+    // both the labels and the file know their source log and they
+    // differ.
+    pub fn labels_from_timestamped(
+        &self,
+        timestamped_labels: &log_labels::TimestampedLabels,
+    ) -> LabelTimestampResult<log_labels::Labels> {
+        let labels_sha256 = timestamped_labels.get_source_log_sha256();
+        if let Some(data_sha256) = self.source_log_sha256() {
+            if !labels_sha256.is_empty() && labels_sha256 != data_sha256 {
+                return Err(LabelTimestampError::SourceLogMismatch {
+                    labels_sha256: labels_sha256.to_owned(),
+                    data_sha256: data_sha256.to_owned(),
+                });
+            }
+        }
+
+        let mut labels = log_labels::Labels::new();
+
+        let dribbling_labels = timestamped_labels.get_dribbling_labels();
+        if !dribbling_labels.is_empty() {
+            for index in 0..self.len() {
+                let (first_timestamp, _) = self.timestamp_range(index)?;
+                let label = current_label(dribbling_labels, first_timestamp, |l| l.get_frame())
+                    .map(|l| l.get_label().clone())
+                    .unwrap_or_default();
+                labels.mut_dribbling_labels().push(label);
+            }
+        }
+
+        let ball_possession_labels = timestamped_labels.get_ball_possession_labels();
+        if !ball_possession_labels.is_empty() {
+            for index in 0..self.len() {
+                let (first_timestamp, _) = self.timestamp_range(index)?;
+                let label =
+                    current_label(ball_possession_labels, first_timestamp, |l| l.get_frame())
+                        .map(|l| l.get_label().clone())
+                        .unwrap_or_default();
+                labels.mut_ball_possession_labels().push(label);
+            }
+        }
+
+        for timestamped_label in timestamped_labels.get_passing_labels() {
+            let mut label = timestamped_label.get_label().clone();
+            label.set_start_frame(self.frame_at(timestamped_label.get_start_timestamp())?);
+            label.set_end_frame(self.frame_at(timestamped_label.get_end_timestamp())?);
+            labels.mut_passing_labels().push(label);
+        }
+
+        for timestamped_label in timestamped_labels.get_goal_shot_labels() {
+            let mut label = timestamped_label.get_label().clone();
+            label.set_start_frame(self.frame_at(timestamped_label.get_start_timestamp())?);
+            label.set_end_frame(self.frame_at(timestamped_label.get_end_timestamp())?);
+            labels.mut_goal_shot_labels().push(label);
+        }
+
+        Ok(labels)
+    }
+}
+
+// Last label whose frame starts at or before `timestamp`. The labels
+// are in frame order.
+fn current_label<L, F>(labels: &[L], timestamp: u64, frame: F) -> Option<&L>
+where
+    F: Fn(&L) -> &log_labels::FrameTimestampRange,
+{
+    // binary search for the number of labels starting at or before the
+    // timestamp, so mapping every frame group stays fast on long logs
+    let mut low = 0;
+    let mut high = labels.len();
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if frame(&labels[mid]).get_first_timestamp() <= timestamp {
+            low = mid + 1;
+        } else {
+            high = mid;
+        }
+    }
+
+    low.checked_sub(1).map(|index| &labels[index])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::labeler::writer::write_metadata;
+    use crate::labeler::{LABELER_DATA_HEADER, LABELER_DATA_VERSION};
+    use crate::protos::log_labeler_data;
+    use crate::test_utils::labels as test_utils_labels;
+    use byteorder::{BigEndian, WriteBytesExt};
+    use proptest::prelude::*;
+    use std::io::{Cursor, Write};
+
+    impl From<LabelTimestampError> for TestCaseError {
+        fn from(error: LabelTimestampError) -> Self {
+            TestCaseError::fail(format!("{}", error))
+        }
+    }
+
+    // data file with no frame data, only the metadata describing the
+    // frame groups. Each frame group is (first timestamp, duration).
+    fn make_data_file(frame_groups: &[(u64, u64)], sha256: &str) -> Vec<u8> {
+        let mut metadata = log_labeler_data::LabelerMetadata::new();
+        for (index, (first_timestamp, duration)) in frame_groups.iter().enumerate() {
+            metadata.mut_message_offsets().push(20 + index as u64);
+            let mut timestamps = log_labeler_data::FrameGroupTimestamps::new();
+            timestamps.set_first_timestamp(*first_timestamp);
+            timestamps.set_last_timestamp(first_timestamp + duration);
+            metadata.mut_frame_group_timestamps().push(timestamps);
+        }
+        metadata.set_source_log_sha256(sha256.to_owned());
+
+        let mut buffer = Vec::<u8>::new();
+        buffer.write_all(&LABELER_DATA_HEADER).unwrap();
+        buffer.write_u32::<BigEndian>(LABELER_DATA_VERSION).unwrap();
+        write_metadata(&mut buffer, &metadata).unwrap();
+        buffer
+    }
+
+    // strictly increasing, non overlapping frame groups that last at
+    // least 1ns
+    fn frame_groups_strategy(
+        min_frames: usize,
+        max_frames: usize,
+    ) -> impl Strategy<Value = Vec<(u64, u64)>> {
+        proptest::collection::vec((1..1000u64, 1..1000u64), min_frames..max_frames).prop_map(
+            |gaps| {
+                let mut timestamp = 0;
+                gaps.into_iter()
+                    .map(|(gap, duration)| {
+                        let first_timestamp = timestamp + gap;
+                        timestamp = first_timestamp + duration;
+                        (first_timestamp, duration)
+                    })
+                    .collect()
+            },
+        )
+    }
+
+    fn frame_groups_and_labels_strategy(
+    ) -> impl Strategy<Value = (Vec<(u64, u64)>, log_labels::Labels)> {
+        frame_groups_strategy(1, 20).prop_flat_map(|frame_groups| {
+            let num_frames = frame_groups.len();
+            (
+                Just(frame_groups),
+                test_utils_labels::random_labels_strategy(num_frames, 5),
+            )
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn frame_group_at_own_timestamps(frame_groups in frame_groups_strategy(1, 20)) {
+            let buffer = make_data_file(&frame_groups, "");
+            let reader = LabelerDataReader::new(Cursor::new(&buffer))?;
+
+            for (index, (first_timestamp, duration)) in frame_groups.iter().enumerate() {
+                prop_assert_eq!(reader.timestamp_range(index)?, (*first_timestamp, first_timestamp + duration));
+                prop_assert_eq!(reader.frame_group_at_timestamp(*first_timestamp)?, Some(index));
+                prop_assert_eq!(reader.frame_group_at_timestamp(first_timestamp + duration)?, Some(index));
+            }
+            prop_assert_eq!(reader.frame_group_at_timestamp(frame_groups[0].0 - 1)?, None);
+        }
+
+        #[test]
+        fn timestamped_labels_round_trip(frame_groups_and_labels in frame_groups_and_labels_strategy()) {
+            let (frame_groups, labels) = frame_groups_and_labels;
+            let buffer = make_data_file(&frame_groups, "abc");
+            let reader = LabelerDataReader::new(Cursor::new(&buffer))?;
+
+            let timestamped_labels = reader.labels_to_timestamped(&labels)?;
+            prop_assert_eq!(timestamped_labels.get_source_log_sha256(), "abc");
+            prop_assert_eq!(reader.labels_from_timestamped(&timestamped_labels)?, labels);
+        }
+
+        // every frame group is split in two, as if the data file was
+        // regenerated with a finer grouping
+        #[test]
+        fn timestamped_labels_regrouped(frame_groups_and_labels in frame_groups_and_labels_strategy()) {
+            let (frame_groups, labels) = frame_groups_and_labels;
+            let buffer = make_data_file(&frame_groups, "");
+            let reader = LabelerDataReader::new(Cursor::new(&buffer))?;
+
+            let split_frame_groups: Vec<_> = frame_groups
+                .iter()
+                .flat_map(|(first_timestamp, duration)| {
+                    vec![
+                        (*first_timestamp, duration / 2),
+                        (first_timestamp + duration / 2 + 1, duration - duration / 2 - 1),
+                    ]
+                })
+                .collect();
+            let split_buffer = make_data_file(&split_frame_groups, "");
+            let split_reader = LabelerDataReader::new(Cursor::new(&split_buffer))?;
+
+            let timestamped_labels = reader.labels_to_timestamped(&labels)?;
+            let split_labels = split_reader.labels_from_timestamped(&timestamped_labels)?;
+
+            prop_assert_eq!(split_labels.get_dribbling_labels().len(), 2 * frame_groups.len());
+            for (index, label) in split_labels.get_dribbling_labels().iter().enumerate() {
+                prop_assert_eq!(label, &labels.get_dribbling_labels()[index / 2]);
+            }
+            for (split_label, label) in split_labels.get_passing_labels().iter().zip(labels.get_passing_labels()) {
+                prop_assert_eq!(split_label.get_start_frame(), 2 * label.get_start_frame());
+                prop_assert_eq!(split_label.get_end_frame(), 2 * label.get_end_frame() + 1);
+            }
+        }
+    }
+
+    #[test]
+    fn current_label_between_starts() {
+        // the last timestamp numbers the labels
+        let labels: Vec<log_labels::FrameTimestampRange> = [10, 20, 20, 35]
+            .iter()
+            .enumerate()
+            .map(|(index, start)| {
+                let mut frame = log_labels::FrameTimestampRange::new();
+                frame.set_first_timestamp(*start);
+                frame.set_last_timestamp(index as u64);
+                frame
+            })
+            .collect();
+        let label_at = |timestamp| {
+            current_label(&labels, timestamp, |frame| frame).map(|frame| frame.get_last_timestamp())
+        };
+
+        assert_eq!(label_at(5), None);
+        assert_eq!(label_at(10), Some(0));
+        assert_eq!(label_at(25), Some(2));
+        assert_eq!(label_at(100), Some(3));
+    }
+
+    #[test]
+    fn v1_file_has_no_timestamps() {
+        let mut metadata = log_labeler_data::LabelerMetadata::new();
+        metadata.set_message_offsets(vec![20]);
+        let mut buffer = Vec::<u8>::new();
+        buffer.write_all(&LABELER_DATA_HEADER).unwrap();
+        buffer.write_u32::<BigEndian>(1).unwrap();
+        write_metadata(&mut buffer, &metadata).unwrap();
+
+        let reader = LabelerDataReader::new(Cursor::new(&buffer)).unwrap();
+        match reader.frame_group_at_timestamp(0) {
+            Err(LabelerDataReaderError::MissingTimestamps) => {}
+            result => panic!("Unexpected result {:?}", result),
+        };
+    }
+
+    #[test]
+    fn mismatched_source_log() {
+        let buffer = make_data_file(&[(10, 5)], "abc");
+        let reader = LabelerDataReader::new(Cursor::new(&buffer)).unwrap();
+
+        let mut timestamped_labels = log_labels::TimestampedLabels::new();
+        timestamped_labels.set_source_log_sha256("def".to_owned());
+        match reader.labels_from_timestamped(&timestamped_labels) {
+            Err(LabelTimestampError::SourceLogMismatch { .. }) => {}
+            result => panic!("Unexpected result {:?}", result),
+        };
+    }
+}
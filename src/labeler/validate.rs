@@ -0,0 +1,578 @@
+use super::reader::{LabelerDataReader, LabelerDataReaderError};
+use crate::protos::log_labels;
+use protobuf::Message;
+use std::collections::BTreeSet;
+use std::fmt;
+use std::io::{Read, Seek};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LabelCategory {
+    Dribbling,
+    BallPossession,
+    Passing,
+    GoalShot,
+}
+
+impl fmt::Display for LabelCategory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            LabelCategory::Dribbling => "dribbling",
+            LabelCategory::BallPossession => "ball possession",
+            LabelCategory::Passing => "passing",
+            LabelCategory::GoalShot => "goal shot",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// A problem found in a label file. `index` is the position of the
+// label in its list.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LabelIssue {
+    WrongLabelCount {
+        category: LabelCategory,
+        num_labels: usize,
+        num_frames: usize,
+    },
+    FrameOutOfRange {
+        category: LabelCategory,
+        index: usize,
+        frame: u64,
+        num_frames: usize,
+    },
+    StartAfterEnd {
+        category: LabelCategory,
+        index: usize,
+        start_frame: u64,
+        end_frame: u64,
+    },
+    UnknownRobot {
+        category: LabelCategory,
+        index: usize,
+        team: log_labels::Team,
+        robot_id: u32,
+    },
+    // the enum value is not defined in log_labels.proto
+    InvalidEnum {
+        category: LabelCategory,
+        index: usize,
+        field: &'static str,
+    },
+    OverlappingPasses {
+        first_index: usize,
+        second_index: usize,
+        passer_team: log_labels::Team,
+        passer_id: u32,
+    },
+    WrongEventCount {
+        category: LabelCategory,
+        num_labels: usize,
+        expected: u32,
+    },
+}
+
+impl fmt::Display for LabelIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LabelIssue::WrongLabelCount {
+                category,
+                num_labels,
+                num_frames,
+            } => write!(
+                f,
+                "{} labels: {} labels for {} frames",
+                category, num_labels, num_frames
+            ),
+            LabelIssue::FrameOutOfRange {
+                category,
+                index,
+                frame,
+                num_frames,
+            } => write!(
+                f,
+                "{} label {}: frame {} is out of range, the file has {} frames",
+                category, index, frame, num_frames
+            ),
+            LabelIssue::StartAfterEnd {
+                category,
+                index,
+                start_frame,
+                end_frame,
+            } => write!(
+                f,
+                "{} label {}: start frame {} is after end frame {}",
+                category, index, start_frame, end_frame
+            ),
+            LabelIssue::UnknownRobot {
+                category,
+                index,
+                team,
+                robot_id,
+            } => write!(
+                f,
+                "{} label {}: {:?} robot {} is never seen by vision",
+                category, index, team, robot_id
+            ),
+            LabelIssue::InvalidEnum {
+                category,
+                index,
+                field,
+            } => write!(f, "{} label {}: invalid {} value", category, index, field),
+            LabelIssue::OverlappingPasses {
+                first_index,
+                second_index,
+                passer_team,
+                passer_id,
+            } => write!(
+                f,
+                "passing labels {} and {}: overlapping passes by {:?} robot {}",
+                first_index, second_index, passer_team, passer_id
+            ),
+            LabelIssue::WrongEventCount {
+                category,
+                num_labels,
+                expected,
+            } => write!(
+                f,
+                "{} labels: {} labels but the data file expects {}",
+                category, num_labels, expected
+            ),
+        }
+    }
+}
+
+// What the labels are checked against. Usually built from the
+// labeler data file with from_reader.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ValidationContext {
+    pub num_frames: usize,
+    pub yellow_robot_ids: BTreeSet<u32>,
+    pub blue_robot_ids: BTreeSet<u32>,
+    // 0 if unknown
+    pub num_passing_events: u32,
+    // 0 if unknown
+    pub num_goal_shot_events: u32,
+}
+
+impl ValidationContext {
+    // Reads every frame group to collect the robot ids seen by vision.
+    pub fn from_reader<T: Read + Seek>(
+        reader: &LabelerDataReader<T>,
+    ) -> Result<ValidationContext, LabelerDataReaderError> {
+        let mut context = ValidationContext {
+            num_frames: reader.len(),
+            num_passing_events: reader.num_passing_events(),
+            num_goal_shot_events: reader.num_goal_shot_events(),
+            ..Default::default()
+        };
+
+        for frame_group in reader {
+            for frame in frame_group?.get_frames() {
+                if !frame.has_vision_frame() || !frame.get_vision_frame().has_detection() {
+                    continue;
+                }
+                let detection = frame.get_vision_frame().get_detection();
+                for robot in detection.get_robots_yellow() {
+                    context.yellow_robot_ids.insert(robot.get_robot_id());
+                }
+                for robot in detection.get_robots_blue() {
+                    context.blue_robot_ids.insert(robot.get_robot_id());
+                }
+            }
+        }
+
+        Ok(context)
+    }
+
+    fn robot_ids(&self, team: log_labels::Team) -> &BTreeSet<u32> {
+        match team {
+            log_labels::Team::YELLOW => &self.yellow_robot_ids,
+            log_labels::Team::BLUE => &self.blue_robot_ids,
+        }
+    }
+}
+
+// proto3 enum values that are not in the .proto file end up in the
+// unknown fields when parsing
+fn has_invalid_enum<M: Message>(label: &M, field_number: u32) -> bool {
+    label.get_unknown_fields().get(field_number).is_some()
+}
+
+struct IssueCollector<'a> {
+    context: &'a ValidationContext,
+    issues: Vec<LabelIssue>,
+}
+
+impl<'a> IssueCollector<'a> {
+    fn check_robot(
+        &mut self,
+        category: LabelCategory,
+        index: usize,
+        team: log_labels::Team,
+        robot_id: u32,
+    ) {
+        if !self.context.robot_ids(team).contains(&robot_id) {
+            self.issues.push(LabelIssue::UnknownRobot {
+                category,
+                index,
+                team,
+                robot_id,
+            });
+        }
+    }
+
+    fn check_span(
+        &mut self,
+        category: LabelCategory,
+        index: usize,
+        start_frame: u64,
+        end_frame: u64,
+    ) {
+        for frame in &[start_frame, end_frame] {
+            if *frame >= self.context.num_frames as u64 {
+                self.issues.push(LabelIssue::FrameOutOfRange {
+                    category,
+                    index,
+                    frame: *frame,
+                    num_frames: self.context.num_frames,
+                });
+            }
+        }
+        if start_frame > end_frame {
+            self.issues.push(LabelIssue::StartAfterEnd {
+                category,
+                index,
+                start_frame,
+                end_frame,
+            });
+        }
+    }
+
+    fn check_event_count(&mut self, category: LabelCategory, num_labels: usize, expected: u32) {
+        // old data files do not know the number of events
+        if expected != 0 && num_labels != expected as usize {
+            self.issues.push(LabelIssue::WrongEventCount {
+                category,
+                num_labels,
+                expected,
+            });
+        }
+    }
+}
+
+pub fn validate_labels_with_context(
+    labels: &log_labels::Labels,
+    context: &ValidationContext,
+) -> Vec<LabelIssue> {
+    let mut collector = IssueCollector {
+        context,
+        issues: vec![],
+    };
+
+    let dribbling_labels = labels.get_dribbling_labels();
+    if dribbling_labels.len() != context.num_frames {
+        collector.issues.push(LabelIssue::WrongLabelCount {
+            category: LabelCategory::Dribbling,
+            num_labels: dribbling_labels.len(),
+            num_frames: context.num_frames,
+        });
+    }
+    for (index, label) in dribbling_labels.iter().enumerate() {
+        if has_invalid_enum(label, 3) {
+            collector.issues.push(LabelIssue::InvalidEnum {
+                category: LabelCategory::Dribbling,
+                index,
+                field: "team",
+            });
+        } else if label.get_is_dribbling() {
+            collector.check_robot(
+                LabelCategory::Dribbling,
+                index,
+                label.get_team(),
+                label.get_robot_id(),
+            );
+        }
+    }
+
+    let ball_possession_labels = labels.get_ball_possession_labels();
+    if ball_possession_labels.len() != context.num_frames {
+        collector.issues.push(LabelIssue::WrongLabelCount {
+            category: LabelCategory::BallPossession,
+            num_labels: ball_possession_labels.len(),
+            num_frames: context.num_frames,
+        });
+    }
+    for (index, label) in ball_possession_labels.iter().enumerate() {
+        if has_invalid_enum(label, 1) {
+            collector.issues.push(LabelIssue::InvalidEnum {
+                category: LabelCategory::BallPossession,
+                index,
+                field: "state",
+            });
+            continue;
+        }
+        let team = match label.get_state() {
+            log_labels::BallPossessionLabel_State::NONE => continue,
+            log_labels::BallPossessionLabel_State::YELLOW_POSSES => log_labels::Team::YELLOW,
+            log_labels::BallPossessionLabel_State::BLUE_POSSES => log_labels::Team::BLUE,
+        };
+        collector.check_robot(
+            LabelCategory::BallPossession,
+            index,
+            team,
+            label.get_robot_id(),
+        );
+    }
+
+    let passing_labels = labels.get_passing_labels();
+    for (index, label) in passing_labels.iter().enumerate() {
+        collector.check_span(
+            LabelCategory::Passing,
+            index,
+            label.get_start_frame(),
+            label.get_end_frame(),
+        );
+        if has_invalid_enum(label, 5) {
+            collector.issues.push(LabelIssue::InvalidEnum {
+                category: LabelCategory::Passing,
+                index,
+                field: "passer_team",
+            });
+            continue;
+        }
+        collector.check_robot(
+            LabelCategory::Passing,
+            index,
+            label.get_passer_team(),
+            label.get_passer_id(),
+        );
+        // the receiver of a failed pass can be anybody
+        if label.get_successful() {
+            collector.check_robot(
+                LabelCategory::Passing,
+                index,
+                label.get_passer_team(),
+                label.get_receiver_id(),
+            );
+        }
+    }
+    for (first_index, first) in passing_labels.iter().enumerate() {
+        for (second_index, second) in passing_labels.iter().enumerate().skip(first_index + 1) {
+            if first.get_passer_team() == second.get_passer_team()
+                && first.get_passer_id() == second.get_passer_id()
+                && first.get_start_frame() <= second.get_end_frame()
+                && second.get_start_frame() <= first.get_end_frame()
+            {
+                collector.issues.push(LabelIssue::OverlappingPasses {
+                    first_index,
+                    second_index,
+                    passer_team: first.get_passer_team(),
+                    passer_id: first.get_passer_id(),
+                });
+            }
+        }
+    }
+    collector.check_event_count(
+        LabelCategory::Passing,
+        passing_labels.len(),
+        context.num_passing_events,
+    );
+
+    let goal_shot_labels = labels.get_goal_shot_labels();
+    for (index, label) in goal_shot_labels.iter().enumerate() {
+        collector.check_span(
+            LabelCategory::GoalShot,
+            index,
+            label.get_start_frame(),
+            label.get_end_frame(),
+        );
+        if has_invalid_enum(label, 5) {
+            collector.issues.push(LabelIssue::InvalidEnum {
+                category: LabelCategory::GoalShot,
+                index,
+                field: "shooter_team",
+            });
+            continue;
+        }
+        collector.check_robot(
+            LabelCategory::GoalShot,
+            index,
+            label.get_shooter_team(),
+            label.get_shooter_id(),
+        );
+    }
+    collector.check_event_count(
+        LabelCategory::GoalShot,
+        goal_shot_labels.len(),
+        context.num_goal_shot_events,
+    );
+
+    collector.issues
+}
+
+// Checks the labels against the labeler data file they were made
+// with. An empty result means the labels are valid.
+pub fn validate_labels<T: Read + Seek>(
+    labels: &log_labels::Labels,
+    reader: &LabelerDataReader<T>,
+) -> Result<Vec<LabelIssue>, LabelerDataReaderError> {
+    let context = ValidationContext::from_reader(reader)?;
+    Ok(validate_labels_with_context(labels, &context))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::labels as test_utils_labels;
+    use proptest::prelude::*;
+
+    fn all_robots_context(num_frames: usize) -> ValidationContext {
+        ValidationContext {
+            num_frames,
+            yellow_robot_ids: (0..12).collect(),
+            blue_robot_ids: (0..12).collect(),
+            ..Default::default()
+        }
+    }
+
+    fn passing_label(start_frame: u64, end_frame: u64, passer_id: u32) -> log_labels::PassingLabel {
+        let mut label = log_labels::PassingLabel::new();
+        label.set_start_frame(start_frame);
+        label.set_end_frame(end_frame);
+        label.set_passer_id(passer_id);
+        label
+    }
+
+    // A valid dribbling and ball possession label for every frame
+    fn frame_labels(num_frames: usize) -> log_labels::Labels {
+        let mut labels = log_labels::Labels::new();
+        for _ in 0..num_frames {
+            labels
+                .mut_dribbling_labels()
+                .push(log_labels::DribblingLabel::new());
+            labels
+                .mut_ball_possession_labels()
+                .push(log_labels::BallPossessionLabel::new());
+        }
+        labels
+    }
+
+    proptest! {
+        #[test]
+        fn random_labels_only_overlap_issues(labels in test_utils_labels::random_labels_strategy(20, 10)) {
+            let issues = validate_labels_with_context(&labels, &all_robots_context(20));
+
+            // the random passes can overlap, everything else is valid
+            for issue in issues {
+                match issue {
+                    LabelIssue::OverlappingPasses { .. } => {}
+                    issue => return Err(TestCaseError::fail(format!("Unexpected issue: {}", issue))),
+                }
+            }
+        }
+
+        #[test]
+        fn wrong_frame_count(labels in test_utils_labels::random_labels_strategy(20, 10),
+                             num_frames in 21..40usize) {
+            let issues = validate_labels_with_context(&labels, &all_robots_context(num_frames));
+
+            let wrong_label_count = |category| LabelIssue::WrongLabelCount {
+                category,
+                num_labels: 20,
+                num_frames,
+            };
+            prop_assert!(issues.contains(&wrong_label_count(LabelCategory::Dribbling)));
+            prop_assert!(issues.contains(&wrong_label_count(LabelCategory::BallPossession)));
+        }
+    }
+
+    #[test]
+    fn invalid_spans() {
+        let mut labels = log_labels::Labels::new();
+        labels.mut_passing_labels().push(passing_label(5, 2, 0));
+        labels.mut_passing_labels().push(passing_label(8, 10, 1));
+
+        let issues = validate_labels_with_context(&labels, &all_robots_context(10));
+
+        assert!(issues.contains(&LabelIssue::StartAfterEnd {
+            category: LabelCategory::Passing,
+            index: 0,
+            start_frame: 5,
+            end_frame: 2,
+        }));
+        assert!(issues.contains(&LabelIssue::FrameOutOfRange {
+            category: LabelCategory::Passing,
+            index: 1,
+            frame: 10,
+            num_frames: 10,
+        }));
+    }
+
+    #[test]
+    fn overlapping_passes() {
+        let mut labels = frame_labels(10);
+        labels.mut_passing_labels().push(passing_label(0, 5, 1));
+        labels.mut_passing_labels().push(passing_label(5, 8, 1));
+        labels.mut_passing_labels().push(passing_label(2, 4, 2));
+
+        let issues = validate_labels_with_context(&labels, &all_robots_context(10));
+
+        assert_eq!(
+            issues,
+            vec![LabelIssue::OverlappingPasses {
+                first_index: 0,
+                second_index: 1,
+                passer_team: log_labels::Team::YELLOW,
+                passer_id: 1,
+            }]
+        );
+    }
+
+    #[test]
+    fn unknown_robot_and_event_count() {
+        let mut context = all_robots_context(10);
+        context.blue_robot_ids.remove(&3);
+        context.num_passing_events = 2;
+
+        let mut labels = log_labels::Labels::new();
+        let mut label = passing_label(0, 1, 3);
+        label.set_passer_team(log_labels::Team::BLUE);
+        labels.mut_passing_labels().push(label);
+
+        let issues = validate_labels_with_context(&labels, &context);
+
+        assert!(issues.contains(&LabelIssue::UnknownRobot {
+            category: LabelCategory::Passing,
+            index: 0,
+            team: log_labels::Team::BLUE,
+            robot_id: 3,
+        }));
+        assert!(issues.contains(&LabelIssue::WrongEventCount {
+            category: LabelCategory::Passing,
+            num_labels: 1,
+            expected: 2,
+        }));
+    }
+
+    #[test]
+    fn invalid_team_enum() {
+        // field 3 (team) set to 7 with varint encoding
+        let label: log_labels::DribblingLabel = protobuf::parse_from_bytes(&[0x18, 0x07]).unwrap();
+        let mut labels = log_labels::Labels::new();
+        labels.mut_dribbling_labels().push(label);
+        labels
+            .mut_ball_possession_labels()
+            .push(log_labels::BallPossessionLabel::new());
+
+        let issues = validate_labels_with_context(&labels, &all_robots_context(1));
+
+        assert_eq!(
+            issues,
+            vec![LabelIssue::InvalidEnum {
+                category: LabelCategory::Dribbling,
+                index: 0,
+                field: "team",
+            }]
+        );
+    }
+}
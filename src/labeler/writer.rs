@@ -1,14 +1,17 @@
+use super::reader::{LabelerDataReader, LabelerDataReaderError};
 use super::*;
 use crate::persistence::message::{Message, MessageType};
+use crate::persistence::reader::{LogReader, LogReaderError};
 use crate::protos::log_labeler_data;
-use crate::protos::messages_robocup_ssl_referee::{SSL_Referee_Command, SSL_Referee_Stage};
+use crate::protos::messages_robocup_ssl_geometry::SSL_GeometryData;
+use crate::referee::RefereeState;
 use byteorder::{BigEndian, WriteBytesExt};
 use protobuf;
-use protobuf::Message as ProtobufMessage;
-use std::collections::HashSet;
+use protobuf::{Message as ProtobufMessage, RepeatedField};
+use std::collections::{BTreeSet, HashSet};
 use std::fs::{File, OpenOptions};
 use std::io;
-use std::io::{BufWriter, Seek, SeekFrom, Write};
+use std::io::{BufWriter, Cursor, Seek, SeekFrom, Write};
 use std::path::Path;
 
 #[derive(Debug, Fail)]
@@ -35,13 +38,21 @@ type LabelerDataWriterResult<T> = Result<T, LabelerDataWriterError>;
 
 pub struct LabelerDataWriter<T: Write + Seek> {
     writer: BufWriter<T>,
+    config: FilterConfig,
     num_cameras: u32,
     // option allows moving out of self during drop, which prevents a
     // full copy of the vector being made
     message_offsets: Option<Vec<u64>>,
+    frame_group_timestamps: Option<Vec<log_labeler_data::FrameGroupTimestamps>>,
+    // extra metadata added in version 2 of the format
+    camera_ids: BTreeSet<u32>,
+    geometry: Option<SSL_GeometryData>,
+    yellow_team_name: String,
+    blue_team_name: String,
+    source_log_file_name: String,
+    source_log_sha256: String,
     // book-keeping for building up current LabelerData message
-    curr_stage: Option<SSL_Referee_Stage>,
-    last_command: Option<SSL_Referee_Command>,
+    curr_state: Option<RefereeState>,
     // option allows taking the internal vector without copying
     curr_frames: Option<Vec<log_labeler_data::LabelerFrame>>,
     curr_cam_set: HashSet<u32>,
@@ -49,6 +60,13 @@ pub struct LabelerDataWriter<T: Write + Seek> {
 
 impl<T: Write + Seek> LabelerDataWriter<T> {
     pub fn new(writer: T) -> LabelerDataWriterResult<LabelerDataWriter<T>> {
+        LabelerDataWriter::new_with_config(writer, FilterConfig::default())
+    }
+
+    pub fn new_with_config(
+        writer: T,
+        config: FilterConfig,
+    ) -> LabelerDataWriterResult<LabelerDataWriter<T>> {
         let mut writer = BufWriter::new(writer);
 
         // write the file header
@@ -59,10 +77,17 @@ impl<T: Write + Seek> LabelerDataWriter<T> {
 
         Ok(LabelerDataWriter {
             writer,
+            config,
             num_cameras: 0,
             message_offsets: Some(Vec::<u64>::new()),
-            curr_stage: None,
-            last_command: None,
+            frame_group_timestamps: Some(Vec::new()),
+            camera_ids: BTreeSet::new(),
+            geometry: None,
+            yellow_team_name: String::new(),
+            blue_team_name: String::new(),
+            source_log_file_name: String::new(),
+            source_log_sha256: String::new(),
+            curr_state: None,
             curr_frames: Some(Vec::new()),
             curr_cam_set: HashSet::new(),
         })
@@ -76,20 +101,50 @@ impl<T: Write + Seek> LabelerDataWriter<T> {
         &mut self.writer
     }
 
+    pub fn config(&self) -> &FilterConfig {
+        &self.config
+    }
+
+    // Record the log file this data file was created from. The hash
+    // is usually only known once the whole log has been read, so
+    // this can be called any time before the writer is dropped.
+    pub fn set_source_log<S1: Into<String>, S2: Into<String>>(
+        &mut self,
+        file_name: S1,
+        sha256: S2,
+    ) {
+        self.source_log_file_name = file_name.into();
+        self.source_log_sha256 = sha256.into();
+    }
+
     pub fn add_msg(&mut self, message: Message) -> LabelerDataWriterResult<()> {
         match message.msg_type {
             MessageType::Refbox2013(ref_msg) => {
-                let new_stage = Some(ref_msg.get_stage());
-                let new_command = Some(ref_msg.get_command());
-                if new_stage != self.curr_stage || new_command != self.last_command {
+                // team names are recorded even outside of running
+                // stages, as they are usually typed in before kickoff
+                if !ref_msg.get_yellow().get_name().is_empty() {
+                    self.yellow_team_name = ref_msg.get_yellow().get_name().to_owned();
+                }
+                if !ref_msg.get_blue().get_name().is_empty() {
+                    self.blue_team_name = ref_msg.get_blue().get_name().to_owned();
+                }
+
+                let new_state = RefereeState::from_referee(&ref_msg);
+                let state_changed = match self.curr_state {
+                    Some(ref curr_state) => {
+                        new_state.stage != curr_state.stage
+                            || new_state.command != curr_state.command
+                    }
+                    None => true,
+                };
+                if state_changed {
                     self.write_frame()?;
                 }
 
-                // update the current stage
-                self.curr_stage = new_stage;
-                self.last_command = new_command;
+                // update the current referee state
+                self.curr_state = Some(new_state);
 
-                if is_running_stage(self.curr_stage) && is_running_command(self.last_command) {
+                if self.is_running() {
                     let mut frame = log_labeler_data::LabelerFrame::new();
                     frame.set_timestamp(message.timestamp as u64);
                     frame.set_referee_frame(ref_msg);
@@ -97,9 +152,16 @@ impl<T: Write + Seek> LabelerDataWriter<T> {
                 }
             }
             MessageType::Vision2014(vision_msg) => {
-                if is_running_stage(self.curr_stage) && is_running_command(self.last_command) {
+                // geometry is only sent every few seconds, so keep
+                // the latest one regardless of the game state
+                if vision_msg.has_geometry() {
+                    self.geometry = Some(vision_msg.get_geometry().clone());
+                }
+
+                if self.is_running() {
                     let cam_id = vision_msg.get_detection().get_camera_id();
                     self.num_cameras = std::cmp::max(self.num_cameras, cam_id);
+                    self.camera_ids.insert(cam_id);
                     if self.curr_cam_set.contains(&cam_id) {
                         self.write_frame()?;
                     }
@@ -120,12 +182,25 @@ impl<T: Write + Seek> LabelerDataWriter<T> {
         Ok(())
     }
 
+    fn is_running(&self) -> bool {
+        self.config.is_running(self.curr_state.as_ref())
+    }
+
     fn write_frame(&mut self) -> LabelerDataWriterResult<()> {
         // only write if there is same data in the current frame
         if !self.curr_frames.as_ref().unwrap_or(&vec![]).is_empty() {
             let curr_offset = self.writer.seek(SeekFrom::Current(0))?;
             self.message_offsets.as_mut().unwrap().push(curr_offset);
 
+            let curr_frames = self.curr_frames.as_ref().unwrap();
+            let mut timestamps = log_labeler_data::FrameGroupTimestamps::new();
+            timestamps.set_first_timestamp(curr_frames.first().unwrap().get_timestamp());
+            timestamps.set_last_timestamp(curr_frames.last().unwrap().get_timestamp());
+            self.frame_group_timestamps
+                .as_mut()
+                .unwrap()
+                .push(timestamps);
+
             let mut frame_group = log_labeler_data::LabelerFrameGroup::new();
             frame_group.set_frames(self.curr_frames.take().unwrap().into());
 
@@ -163,49 +238,101 @@ impl<T: Write + Seek> Drop for LabelerDataWriter<T> {
         metadata.set_num_cameras(self.num_cameras);
         let message_offsets = self.message_offsets.take().unwrap_or_else(Vec::new);
         metadata.set_message_offsets(message_offsets);
+        let frame_group_timestamps = self.frame_group_timestamps.take().unwrap_or_else(Vec::new);
+        metadata.set_frame_group_timestamps(RepeatedField::from_vec(frame_group_timestamps));
+        metadata.set_camera_ids(self.camera_ids.iter().cloned().collect());
+        if let Some(geometry) = self.geometry.take() {
+            metadata.set_geometry(geometry);
+        }
+        metadata.set_yellow_team_name(std::mem::take(&mut self.yellow_team_name));
+        metadata.set_blue_team_name(std::mem::take(&mut self.blue_team_name));
+        metadata.set_source_log_file_name(std::mem::take(&mut self.source_log_file_name));
+        metadata.set_source_log_sha256(std::mem::take(&mut self.source_log_sha256));
+        metadata.set_filter_config(self.config.to_proto());
 
-        let metadata_bytes = metadata.write_to_bytes().unwrap();
-        self.writer.write_all(&metadata_bytes).unwrap();
-        self.writer
-            .write_u32::<BigEndian>(metadata_bytes.len() as u32)
-            .unwrap();
+        write_metadata(&mut self.writer, &metadata).unwrap();
     }
 }
 
-fn is_running_stage(stage: Option<SSL_Referee_Stage>) -> bool {
-    match stage {
-        Some(stage) => match stage {
-            SSL_Referee_Stage::NORMAL_FIRST_HALF
-            | SSL_Referee_Stage::NORMAL_SECOND_HALF
-            | SSL_Referee_Stage::EXTRA_FIRST_HALF
-            | SSL_Referee_Stage::EXTRA_SECOND_HALF => true,
-            _ => false,
-        },
-        None => false,
+#[derive(Debug, Fail)]
+pub enum LabelerDataFromLogError {
+    #[fail(display = "{}", _0)]
+    Io(#[fail(cause)] io::Error),
+    #[fail(display = "{}", _0)]
+    Log(#[fail(cause)] LogReaderError),
+    #[fail(display = "{}", _0)]
+    Writer(#[fail(cause)] LabelerDataWriterError),
+    #[fail(display = "{}", _0)]
+    Reader(#[fail(cause)] LabelerDataReaderError),
+}
+
+impl From<LogReaderError> for LabelerDataFromLogError {
+    fn from(error: LogReaderError) -> Self {
+        match error {
+            LogReaderError::Io(e) => LabelerDataFromLogError::Io(e),
+            e => LabelerDataFromLogError::Log(e),
+        }
     }
 }
 
-fn is_running_command(command: Option<SSL_Referee_Command>) -> bool {
-    match command {
-        Some(command) => match command {
-            SSL_Referee_Command::NORMAL_START
-            | SSL_Referee_Command::FORCE_START
-            | SSL_Referee_Command::DIRECT_FREE_YELLOW
-            | SSL_Referee_Command::DIRECT_FREE_BLUE
-            | SSL_Referee_Command::INDIRECT_FREE_YELLOW
-            | SSL_Referee_Command::INDIRECT_FREE_BLUE => true,
-            _ => false,
-        },
-        None => false,
+impl From<LabelerDataWriterError> for LabelerDataFromLogError {
+    fn from(error: LabelerDataWriterError) -> Self {
+        match error {
+            LabelerDataWriterError::Io(e) => LabelerDataFromLogError::Io(e),
+            e => LabelerDataFromLogError::Writer(e),
+        }
     }
 }
 
+impl From<LabelerDataReaderError> for LabelerDataFromLogError {
+    fn from(error: LabelerDataReaderError) -> Self {
+        match error {
+            LabelerDataReaderError::Io(e) => LabelerDataFromLogError::Io(e),
+            e => LabelerDataFromLogError::Reader(e),
+        }
+    }
+}
+
+// Runs a log through the filter in memory, so it can be read like a
+// labeler data file made from it. With the default config the frames
+// match those of make_labeler_data_file. Like the writer, unreadable
+// messages are skipped.
+pub fn labeler_data_from_log(
+    log_path: &Path,
+    config: FilterConfig,
+) -> Result<LabelerDataReader<Cursor<Vec<u8>>>, LabelerDataFromLogError> {
+    let mut buffer = vec![];
+    {
+        let log_reader = LogReader::new_from_path(log_path)?;
+        let mut writer = LabelerDataWriter::new_with_config(Cursor::new(&mut buffer), config)?;
+        for message in log_reader.filter_map(Result::ok) {
+            writer.add_msg(message)?;
+        }
+    }
+
+    Ok(LabelerDataReader::new(Cursor::new(buffer))?)
+}
+
+// Writes the metadata trailer. Must be positioned right after the
+// last frame group.
+pub(crate) fn write_metadata<T: Write>(
+    writer: &mut T,
+    metadata: &log_labeler_data::LabelerMetadata,
+) -> LabelerDataWriterResult<()> {
+    let metadata_bytes = metadata.write_to_bytes()?;
+    writer.write_all(&metadata_bytes)?;
+    writer.write_u32::<BigEndian>(metadata_bytes.len() as u32)?;
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     extern crate proptest;
     extern crate tempfile;
 
     use super::*;
+    use crate::persistence::writer::LogWriter;
     use crate::protos::log_labeler_data;
     use crate::test_utils::labeler as test_utils_labeler;
     use crate::test_utils::message as test_utils_message;
@@ -219,6 +346,12 @@ mod tests {
         }
     }
 
+    impl From<LabelerDataFromLogError> for TestCaseError {
+        fn from(error: LabelerDataFromLogError) -> Self {
+            TestCaseError::fail(format!("{}", error))
+        }
+    }
+
     fn check_header<T: Read>(mut reader: T) -> LabelerDataWriterResult<()> {
         // check header
         let mut header = [0u8; 16];
@@ -586,6 +719,87 @@ mod tests {
 
         }
 
+        #[test]
+        fn records_v2_metadata(running_ref_msg in test_utils_labeler::running_ref_msg(),
+                               camera_msgs in test_utils_labeler::no_camera_repeats_strategy(1, 10)) {
+            let mut buffer = Vec::<u8>::new();
+
+            let mut writer = LabelerDataWriter::new(Cursor::new(&mut buffer))?;
+            writer.add_msg(running_ref_msg.clone())?;
+            for camera_msg in camera_msgs.iter().cloned() {
+                writer.add_msg(camera_msg)?;
+            }
+            writer.set_source_log("test.log", "abcdef");
+            drop(writer);
+
+            let metadata = get_metadata(Cursor::new(buffer.as_mut_slice()))?;
+
+            let expected_camera_ids: Vec<u32> = (0..camera_msgs.len() as u32).collect();
+            prop_assert_eq!(metadata.get_camera_ids(), expected_camera_ids.as_slice());
+
+            prop_assert_eq!(metadata.get_frame_group_timestamps().len(), 1);
+            let timestamps = &metadata.get_frame_group_timestamps()[0];
+            prop_assert_eq!(timestamps.get_first_timestamp(), running_ref_msg.timestamp as u64);
+            prop_assert_eq!(timestamps.get_last_timestamp(), camera_msgs.last().unwrap().timestamp as u64);
+
+            if let MessageType::Refbox2013(ref ref_msg) = running_ref_msg.msg_type {
+                if !ref_msg.get_yellow().get_name().is_empty() {
+                    prop_assert_eq!(metadata.get_yellow_team_name(), ref_msg.get_yellow().get_name());
+                }
+                if !ref_msg.get_blue().get_name().is_empty() {
+                    prop_assert_eq!(metadata.get_blue_team_name(), ref_msg.get_blue().get_name());
+                }
+            }
+
+            if let MessageType::Vision2014(ref vision_msg) = camera_msgs.last().unwrap().msg_type {
+                prop_assert_eq!(metadata.get_geometry(), vision_msg.get_geometry());
+            }
+
+            prop_assert_eq!(metadata.get_source_log_file_name(), "test.log");
+            prop_assert_eq!(metadata.get_source_log_sha256(), "abcdef");
+            prop_assert_eq!(FilterConfig::from_proto(metadata.get_filter_config()), FilterConfig::default());
+        }
+
+        #[test]
+        fn geometry_recorded_while_not_running(camera_msgs in test_utils_labeler::no_camera_repeats_strategy(1, 8)) {
+            let mut buffer = Vec::<u8>::new();
+
+            let mut writer = LabelerDataWriter::new(Cursor::new(&mut buffer))?;
+            for camera_msg in camera_msgs.iter().cloned() {
+                writer.add_msg(camera_msg)?;
+            }
+            drop(writer);
+
+            let metadata = get_metadata(Cursor::new(buffer.as_mut_slice()))?;
+
+            prop_assert_eq!(metadata.get_message_offsets().len(), 0);
+            prop_assert!(metadata.get_camera_ids().is_empty());
+            if let MessageType::Vision2014(ref vision_msg) = camera_msgs.last().unwrap().msg_type {
+                prop_assert_eq!(metadata.get_geometry(), vision_msg.get_geometry());
+            }
+        }
+
+        #[test]
+        fn log_filtered_with_config(not_running_ref_msg in test_utils_labeler::not_running_ref_msg(),
+                                    camera_msgs in test_utils_labeler::no_camera_repeats_strategy(1, 8)) {
+            let tmpfile = tempfile::NamedTempFile::new()?;
+            {
+                let mut log_writer = LogWriter::new_from_path(tmpfile.path())?;
+                log_writer.write_message(&not_running_ref_msg)?;
+                for camera_msg in &camera_msgs {
+                    log_writer.write_message(camera_msg)?;
+                }
+                log_writer.flush()?;
+            }
+
+            let running_only = labeler_data_from_log(tmpfile.path(), FilterConfig::default())?;
+            prop_assert!(running_only.is_empty());
+
+            let everything = labeler_data_from_log(tmpfile.path(), FilterConfig::keep_all())?;
+            prop_assert_eq!(everything.len(), 1);
+            prop_assert_eq!(everything.get(0)?.get_frames().len(), camera_msgs.len() + 1);
+        }
+
     }
 
 }
@@ -3,12 +3,18 @@ extern crate failure;
 #[macro_use]
 extern crate lazy_static;
 
+pub mod geometry;
 #[cfg(feature = "gui")]
 pub mod gui;
 pub mod labeler;
 pub mod persistence;
 pub mod player;
 pub mod protos;
+pub mod referee;
+pub mod render;
+#[cfg(feature = "tui")]
+pub mod tui;
+pub mod vision;
 
 #[cfg(test)]
 pub mod test_utils;
@@ -1,5 +1,6 @@
 use crate::protos::messages_robocup_ssl_referee;
 use crate::protos::messages_robocup_ssl_wrapper;
+use crate::protos::ssl_vision_wrapper_tracked;
 use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 use protobuf;
 use protobuf::Message as ProtobufMessage;
@@ -36,6 +37,7 @@ const UNKNOWN_TYPE: i32 = 1;
 const VISION2010_TYPE: i32 = 2;
 const REFBOX2013_TYPE: i32 = 3;
 const VISION2014_TYPE: i32 = 4;
+const VISION_TRACKER2020_TYPE: i32 = 5;
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum MessageType {
@@ -49,6 +51,8 @@ pub enum MessageType {
     Refbox2013(messages_robocup_ssl_referee::SSL_Referee),
     // Main message type. Parse for the user and return the type
     Vision2014(messages_robocup_ssl_wrapper::SSL_WrapperPacket),
+    // Filtered vision data from a tracker
+    VisionTracker2020(ssl_vision_wrapper_tracked::TrackerWrapperPacket),
     // Could be a message from the future, that is unsupported by this
     // version of the tool. Just return the raw bytes and let the user
     // deal with it
@@ -90,6 +94,9 @@ impl Message {
             VISION2014_TYPE => Ok(Message::parse_vision_2014_msg_from_reader(
                 reader, timestamp, msg_size,
             )?),
+            VISION_TRACKER2020_TYPE => Ok(Message::parse_vision_tracker_2020_msg_from_reader(
+                reader, timestamp, msg_size,
+            )?),
             // Makes this future proof by just returning the specified
             // bytes unparsed, even if msg_type number is outside of
             // expected range.
@@ -176,6 +183,24 @@ impl Message {
         })
     }
 
+    fn parse_vision_tracker_2020_msg_from_reader<T: Read>(
+        reader: &mut T,
+        timestamp: i64,
+        msg_size: i32,
+    ) -> Result<Message, MessageError> {
+        let mut msg_bytes = vec![0u8; msg_size as usize];
+        reader.read_exact(&mut msg_bytes)?;
+
+        let tracker_msg = protobuf::parse_from_bytes::<
+            ssl_vision_wrapper_tracked::TrackerWrapperPacket,
+        >(&msg_bytes)?;
+
+        Ok(Message {
+            timestamp,
+            msg_type: MessageType::VisionTracker2020(tracker_msg),
+        })
+    }
+
     pub fn write_to_vec(&self, v: &mut Vec<u8>) -> Result<(), MessageError> {
         let (msg_type, msg_bytes) = match self.msg_type {
             MessageType::Blank => (BLANK_TYPE, vec![]),
@@ -183,6 +208,9 @@ impl Message {
             MessageType::Vision2010(ref msg_bytes) => (VISION2010_TYPE, msg_bytes.clone()),
             MessageType::Refbox2013(ref msg) => (REFBOX2013_TYPE, msg.write_to_bytes()?),
             MessageType::Vision2014(ref msg) => (VISION2014_TYPE, msg.write_to_bytes()?),
+            MessageType::VisionTracker2020(ref msg) => {
+                (VISION_TRACKER2020_TYPE, msg.write_to_bytes()?)
+            }
         };
 
         v.write_i64::<BigEndian>(self.timestamp)?;
@@ -202,6 +230,7 @@ impl Message {
                 }
                 MessageType::Refbox2013(ref msg) => msg.compute_size() as usize,
                 MessageType::Vision2014(ref msg) => msg.compute_size() as usize,
+                MessageType::VisionTracker2020(ref msg) => msg.compute_size() as usize,
             };
         let mut bytes = Vec::<u8>::with_capacity(vec_size);
         self.write_to_vec(&mut bytes)?;
@@ -669,5 +698,32 @@ mod tests {
             let parsed_vision2014_msg = Message::parse_from_reader(&mut vision2014_msg_bytes.as_slice())?;
             prop_assert_eq!(vision2014_msg, parsed_vision2014_msg);
         }
+
+        #[test]
+        fn parse_vision_tracker2020(timestamp in 0..std::i64::MAX, tracker_msg in vision_tracker2020_strategy()) {
+            let tracker_msg_bytes = tracker_msg.write_to_bytes()?;
+
+            let mut tmpfile = tempfile::tempfile()?;
+            write_msg(&mut tmpfile, timestamp, VISION_TRACKER2020_TYPE, tracker_msg_bytes.len() as i32, &tracker_msg_bytes)?;
+            tmpfile.seek(io::SeekFrom::Start(0))?;
+
+            let message = Message::parse_from_reader(&mut tmpfile)?;
+            prop_assert_eq!(message.timestamp, timestamp);
+            match message.msg_type {
+                MessageType::VisionTracker2020(parsed_tracker_msg) => prop_assert_eq!(parsed_tracker_msg, tracker_msg),
+                _ => {
+                    let message = format!("Mismatched message type. Got {:?}", message.msg_type);
+                    return Err(TestCaseError::fail(message));
+                }
+            }
+        }
+
+        #[test]
+        fn write_vision_tracker2020_to_bytes(tracker_msg in random_vision_tracker2020_msg_strategy()) {
+            let tracker_msg_bytes = tracker_msg.write_to_bytes()?;
+
+            let parsed_tracker_msg = Message::parse_from_reader(&mut tracker_msg_bytes.as_slice())?;
+            prop_assert_eq!(tracker_msg, parsed_tracker_msg);
+        }
     }
 }
@@ -1,2 +1,4 @@
 pub mod reader;
 pub mod message;
+pub mod writer;
+pub mod normalize;
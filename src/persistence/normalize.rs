@@ -0,0 +1,462 @@
+use super::message::{Message, MessageType};
+use crate::protos::messages_robocup_ssl_detection::SSL_DetectionRobot;
+use crate::protos::messages_robocup_ssl_game_event::SSL_Referee_Game_Event_Team;
+use crate::protos::messages_robocup_ssl_referee::{SSL_Referee, SSL_Referee_Command};
+use crate::protos::messages_robocup_ssl_wrapper::SSL_WrapperPacket;
+use crate::protos::ssl_vision_detection_tracked as tracked;
+use crate::protos::ssl_vision_wrapper_tracked::TrackerWrapperPacket;
+use crate::vision::TeamColor;
+use std::f32::consts::PI;
+use std::mem;
+
+// Rewrites messages so that the same team always attacks towards +x
+// and, optionally, always plays with the same color. Mirroring rotates
+// the field by 180 degrees around its center, so the left and right of
+// the robots stay the same. The field geometry is symmetric and left
+// as it is; camera calibrations are not mirrored.
+//
+// Which side a team plays on is taken from the last referee message.
+// Messages before the first referee message that tells it are passed
+// on unchanged.
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TeamSelector {
+    Color(TeamColor),
+    // compared ignoring case and surrounding whitespace
+    Name(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NormalizeConfig {
+    // team that attacks towards +x
+    pub team: TeamSelector,
+    // if set, colors are swapped so that the selected team has this
+    // color
+    pub team_color: Option<TeamColor>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Normalizer {
+    config: NormalizeConfig,
+    mirror: bool,
+    swap_colors: bool,
+}
+
+fn mirror_angle(angle: f32) -> f32 {
+    if angle > 0.0 {
+        angle - PI
+    } else {
+        angle + PI
+    }
+}
+
+fn mirror_robot(robot: &mut SSL_DetectionRobot) {
+    robot.set_x(-robot.get_x());
+    robot.set_y(-robot.get_y());
+    if robot.has_orientation() {
+        robot.set_orientation(mirror_angle(robot.get_orientation()));
+    }
+}
+
+fn swap_command(command: SSL_Referee_Command) -> SSL_Referee_Command {
+    use SSL_Referee_Command::*;
+    match command {
+        PREPARE_KICKOFF_YELLOW => PREPARE_KICKOFF_BLUE,
+        PREPARE_KICKOFF_BLUE => PREPARE_KICKOFF_YELLOW,
+        PREPARE_PENALTY_YELLOW => PREPARE_PENALTY_BLUE,
+        PREPARE_PENALTY_BLUE => PREPARE_PENALTY_YELLOW,
+        DIRECT_FREE_YELLOW => DIRECT_FREE_BLUE,
+        DIRECT_FREE_BLUE => DIRECT_FREE_YELLOW,
+        INDIRECT_FREE_YELLOW => INDIRECT_FREE_BLUE,
+        INDIRECT_FREE_BLUE => INDIRECT_FREE_YELLOW,
+        TIMEOUT_YELLOW => TIMEOUT_BLUE,
+        TIMEOUT_BLUE => TIMEOUT_YELLOW,
+        GOAL_YELLOW => GOAL_BLUE,
+        GOAL_BLUE => GOAL_YELLOW,
+        BALL_PLACEMENT_YELLOW => BALL_PLACEMENT_BLUE,
+        BALL_PLACEMENT_BLUE => BALL_PLACEMENT_YELLOW,
+        command => command,
+    }
+}
+
+impl Normalizer {
+    pub fn new(config: NormalizeConfig) -> Normalizer {
+        Normalizer {
+            config,
+            mirror: false,
+            swap_colors: false,
+        }
+    }
+
+    pub fn is_mirroring(&self) -> bool {
+        self.mirror
+    }
+
+    pub fn is_swapping_colors(&self) -> bool {
+        self.swap_colors
+    }
+
+    fn team_color(&self, referee: &SSL_Referee) -> Option<TeamColor> {
+        match self.config.team {
+            TeamSelector::Color(color) => Some(color),
+            TeamSelector::Name(ref name) => {
+                let name = name.trim().to_lowercase();
+                let matches = |team_name: &str| team_name.trim().to_lowercase() == name;
+                if matches(referee.get_yellow().get_name()) {
+                    Some(TeamColor::Yellow)
+                } else if matches(referee.get_blue().get_name()) {
+                    Some(TeamColor::Blue)
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    // Updates the decisions from an unmodified referee message
+    pub fn update(&mut self, referee: &SSL_Referee) {
+        let color = match self.team_color(referee) {
+            Some(color) => color,
+            None => return,
+        };
+
+        if let Some(team_color) = self.config.team_color {
+            self.swap_colors = color != team_color;
+        }
+        if referee.has_blueTeamOnPositiveHalf() {
+            // a team on the positive half defends the goal at +x
+            let on_positive_half = match color {
+                TeamColor::Blue => referee.get_blueTeamOnPositiveHalf(),
+                TeamColor::Yellow => !referee.get_blueTeamOnPositiveHalf(),
+            };
+            self.mirror = on_positive_half;
+        }
+    }
+
+    pub fn normalize_packet(&self, packet: &mut SSL_WrapperPacket) {
+        if !packet.has_detection() {
+            return;
+        }
+        let detection = packet.mut_detection();
+
+        if self.mirror {
+            for ball in detection.mut_balls().iter_mut() {
+                ball.set_x(-ball.get_x());
+                ball.set_y(-ball.get_y());
+            }
+            detection
+                .mut_robots_yellow()
+                .iter_mut()
+                .for_each(mirror_robot);
+            detection
+                .mut_robots_blue()
+                .iter_mut()
+                .for_each(mirror_robot);
+        }
+
+        if self.swap_colors {
+            let yellow = detection.take_robots_yellow();
+            let blue = mem::replace(detection.mut_robots_blue(), yellow);
+            detection.set_robots_yellow(blue);
+        }
+    }
+
+    pub fn normalize_referee(&self, referee: &mut SSL_Referee) {
+        if self.mirror {
+            if referee.has_designated_position() {
+                let position = referee.mut_designated_position();
+                position.set_x(-position.get_x());
+                position.set_y(-position.get_y());
+            }
+            if referee.has_blueTeamOnPositiveHalf() {
+                let on_positive_half = referee.get_blueTeamOnPositiveHalf();
+                referee.set_blueTeamOnPositiveHalf(!on_positive_half);
+            }
+        }
+
+        if self.swap_colors {
+            let yellow = referee.take_yellow();
+            let blue = mem::replace(referee.mut_blue(), yellow);
+            referee.set_yellow(blue);
+            referee.set_command(swap_command(referee.get_command()));
+            if referee.has_blueTeamOnPositiveHalf() {
+                let on_positive_half = referee.get_blueTeamOnPositiveHalf();
+                referee.set_blueTeamOnPositiveHalf(!on_positive_half);
+            }
+            if referee.has_gameEvent() && referee.get_gameEvent().has_originator() {
+                let originator = referee.mut_gameEvent().mut_originator();
+                originator.set_team(match originator.get_team() {
+                    SSL_Referee_Game_Event_Team::TEAM_YELLOW => {
+                        SSL_Referee_Game_Event_Team::TEAM_BLUE
+                    }
+                    SSL_Referee_Game_Event_Team::TEAM_BLUE => {
+                        SSL_Referee_Game_Event_Team::TEAM_YELLOW
+                    }
+                    team => team,
+                });
+            }
+        }
+    }
+
+    pub fn normalize_tracker_packet(&self, packet: &mut TrackerWrapperPacket) {
+        if !packet.has_tracked_frame() {
+            return;
+        }
+        let frame = packet.mut_tracked_frame();
+
+        let swap_team = |robot_id: &mut tracked::RobotId| {
+            robot_id.set_team(match robot_id.get_team() {
+                tracked::Team::YELLOW => tracked::Team::BLUE,
+                tracked::Team::BLUE => tracked::Team::YELLOW,
+                team => team,
+            });
+        };
+        let mirror2 = |vector: &mut tracked::Vector2| {
+            vector.set_x(-vector.get_x());
+            vector.set_y(-vector.get_y());
+        };
+        let mirror3 = |vector: &mut tracked::Vector3| {
+            vector.set_x(-vector.get_x());
+            vector.set_y(-vector.get_y());
+        };
+
+        for ball in frame.mut_balls().iter_mut() {
+            if self.mirror {
+                mirror3(ball.mut_pos());
+                if ball.has_vel() {
+                    mirror3(ball.mut_vel());
+                }
+            }
+        }
+        for robot in frame.mut_robots().iter_mut() {
+            if self.mirror {
+                mirror2(robot.mut_pos());
+                if robot.has_vel() {
+                    mirror2(robot.mut_vel());
+                }
+                robot.set_orientation(mirror_angle(robot.get_orientation()));
+            }
+            if self.swap_colors {
+                swap_team(robot.mut_robot_id());
+            }
+        }
+        if frame.has_kicked_ball() {
+            let kicked_ball = frame.mut_kicked_ball();
+            if self.mirror {
+                mirror2(kicked_ball.mut_pos());
+                mirror3(kicked_ball.mut_vel());
+                if kicked_ball.has_stop_pos() {
+                    mirror2(kicked_ball.mut_stop_pos());
+                }
+            }
+            if self.swap_colors && kicked_ball.has_robot_id() {
+                swap_team(kicked_ball.mut_robot_id());
+            }
+        }
+    }
+
+    // Referee messages update the decisions before they are rewritten
+    pub fn normalize_message(&mut self, message: &mut Message) {
+        match message.msg_type {
+            MessageType::Refbox2013(ref mut referee) => {
+                self.update(referee);
+                self.normalize_referee(referee);
+            }
+            MessageType::Vision2014(ref mut packet) => self.normalize_packet(packet),
+            MessageType::VisionTracker2020(ref mut packet) => self.normalize_tracker_packet(packet),
+            MessageType::Blank | MessageType::Vision2010(_) | MessageType::Unknown(_) => {}
+        }
+    }
+}
+
+// Iterator adapter normalizing the messages of a log reader or any
+// other iterator of message results
+pub struct Normalize<I> {
+    inner: I,
+    normalizer: Normalizer,
+}
+
+impl<I> Normalize<I> {
+    pub fn new(inner: I, config: NormalizeConfig) -> Normalize<I> {
+        Normalize {
+            inner,
+            normalizer: Normalizer::new(config),
+        }
+    }
+}
+
+impl<I, E> Iterator for Normalize<I>
+where
+    I: Iterator<Item = Result<Message, E>>,
+{
+    type Item = Result<Message, E>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut message = self.inner.next()?;
+        if let Ok(ref mut message) = message {
+            self.normalizer.normalize_message(message);
+        }
+        Some(message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protos::messages_robocup_ssl_detection::{SSL_DetectionBall, SSL_DetectionFrame};
+    use crate::test_utils::protos::refbox2013_strategy;
+    use proptest::prelude::*;
+
+    fn referee(yellow: &str, blue: &str, blue_on_positive_half: bool) -> Message {
+        let mut referee = SSL_Referee::new();
+        referee.mut_yellow().set_name(yellow.to_owned());
+        referee.mut_blue().set_name(blue.to_owned());
+        referee.set_blueTeamOnPositiveHalf(blue_on_positive_half);
+        referee.set_command(SSL_Referee_Command::DIRECT_FREE_BLUE);
+        Message {
+            timestamp: 0,
+            msg_type: MessageType::Refbox2013(referee),
+        }
+    }
+
+    fn vision(x: f32, y: f32, orientation: f32) -> Message {
+        let mut robot = SSL_DetectionRobot::new();
+        robot.set_robot_id(3);
+        robot.set_x(x);
+        robot.set_y(y);
+        robot.set_orientation(orientation);
+        let mut ball = SSL_DetectionBall::new();
+        ball.set_x(x);
+        ball.set_y(y);
+        let mut detection = SSL_DetectionFrame::new();
+        detection.mut_robots_blue().push(robot);
+        detection.mut_balls().push(ball);
+        let mut packet = SSL_WrapperPacket::new();
+        packet.set_detection(detection);
+        Message {
+            timestamp: 1,
+            msg_type: MessageType::Vision2014(packet),
+        }
+    }
+
+    fn detection(message: &Message) -> &SSL_DetectionFrame {
+        match message.msg_type {
+            MessageType::Vision2014(ref packet) => packet.get_detection(),
+            _ => panic!("not a vision message"),
+        }
+    }
+
+    fn normalize(messages: Vec<Message>, config: NormalizeConfig) -> Vec<Message> {
+        Normalize::new(messages.into_iter().map(Ok::<_, ()>), config)
+            .map(Result::unwrap)
+            .collect()
+    }
+
+    proptest! {
+        #[test]
+        fn normalizing_twice_changes_nothing(referee in refbox2013_strategy(),
+                                             blue_on_positive_half in proptest::bool::ANY,
+                                             x in -6000.0f32..6000.0,
+                                             y in -4500.0f32..4500.0,
+                                             orientation in -PI..PI) {
+            let mut referee = referee;
+            referee.set_blueTeamOnPositiveHalf(blue_on_positive_half);
+            let messages = vec![
+                Message { timestamp: 0, msg_type: MessageType::Refbox2013(referee) },
+                vision(x, y, orientation),
+            ];
+            let config = NormalizeConfig {
+                team: TeamSelector::Color(TeamColor::Yellow),
+                team_color: Some(TeamColor::Blue),
+            };
+
+            let once = normalize(messages, config.clone());
+            let twice = normalize(once.clone(), NormalizeConfig {
+                team: TeamSelector::Color(TeamColor::Blue),
+                team_color: Some(TeamColor::Blue),
+            });
+            prop_assert_eq!(once, twice);
+        }
+    }
+
+    #[test]
+    fn team_on_positive_half_is_mirrored() {
+        let messages = normalize(
+            vec![
+                vision(1000.0, 500.0, 0.5),
+                referee("ER-Force", "TIGERs", false),
+                vision(1000.0, 500.0, 0.5),
+            ],
+            NormalizeConfig {
+                team: TeamSelector::Name(" er-force ".to_owned()),
+                team_color: None,
+            },
+        );
+
+        // unchanged before the first referee message
+        assert_eq!(detection(&messages[0]).get_robots_blue()[0].get_x(), 1000.0);
+
+        let robot = &detection(&messages[2]).get_robots_blue()[0];
+        assert_eq!((robot.get_x(), robot.get_y()), (-1000.0, -500.0));
+        assert!((robot.get_orientation() - (0.5 - PI)).abs() < 1e-6);
+        let ball = &detection(&messages[2]).get_balls()[0];
+        assert_eq!((ball.get_x(), ball.get_y()), (-1000.0, -500.0));
+
+        match messages[1].msg_type {
+            MessageType::Refbox2013(ref referee) => {
+                assert!(referee.get_blueTeamOnPositiveHalf());
+                assert_eq!(referee.get_yellow().get_name(), "ER-Force");
+            }
+            _ => panic!("not a referee message"),
+        }
+    }
+
+    #[test]
+    fn team_color_is_swapped() {
+        let messages = normalize(
+            vec![
+                referee("ER-Force", "TIGERs", true),
+                vision(1000.0, 500.0, 0.5),
+            ],
+            NormalizeConfig {
+                team: TeamSelector::Name("TIGERs".to_owned()),
+                team_color: Some(TeamColor::Yellow),
+            },
+        );
+
+        match messages[0].msg_type {
+            MessageType::Refbox2013(ref referee) => {
+                assert_eq!(referee.get_yellow().get_name(), "TIGERs");
+                assert_eq!(referee.get_blue().get_name(), "ER-Force");
+                assert_eq!(
+                    referee.get_command(),
+                    SSL_Referee_Command::DIRECT_FREE_YELLOW
+                );
+                // TIGERs were blue on the positive half, now they are
+                // yellow on the negative half
+                assert!(referee.get_blueTeamOnPositiveHalf());
+            }
+            _ => panic!("not a referee message"),
+        }
+
+        let detection = detection(&messages[1]);
+        assert!(detection.get_robots_blue().is_empty());
+        assert_eq!(detection.get_robots_yellow()[0].get_x(), -1000.0);
+    }
+
+    #[test]
+    fn unknown_team_name_changes_nothing() {
+        let messages = vec![
+            referee("ER-Force", "TIGERs", true),
+            vision(1000.0, 500.0, 0.5),
+        ];
+        let normalized = normalize(
+            messages.clone(),
+            NormalizeConfig {
+                team: TeamSelector::Name("RoboTeam".to_owned()),
+                team_color: Some(TeamColor::Yellow),
+            },
+        );
+        assert_eq!(normalized, messages);
+    }
+}
@@ -39,11 +39,11 @@ pub struct LogReader<T: Read> {
     reader: BufReader<T>,
 }
 
-const EXPECTED_HEADER: [u8; 12] = [
+pub(crate) const EXPECTED_HEADER: [u8; 12] = [
     b'S', b'S', b'L', b'_', b'L', b'O', b'G', b'_', b'F', b'I', b'L', b'E',
 ];
 
-const SUPPORTED_VERSION: i32 = 1;
+pub(crate) const SUPPORTED_VERSION: i32 = 1;
 
 impl<T: Read> LogReader<T> {
     pub fn new(reader: T) -> Result<LogReader<T>, LogReaderError> {
@@ -81,6 +81,17 @@ impl<T: Read> LogReader<T> {
     }
 }
 
+// True if the file starts with the log file header. Files too short
+// for the header are not logs.
+pub fn is_log_file(path: &Path) -> io::Result<bool> {
+    let mut header = [0u8; 12];
+    match File::open(path)?.read_exact(&mut header) {
+        Ok(()) => Ok(header == EXPECTED_HEADER),
+        Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
 impl LogReader<File> {
     pub fn new_from_path(log_path: &Path) -> Result<LogReader<File>, LogReaderError> {
         let f = File::open(log_path)?;
@@ -268,4 +279,14 @@ mod tests {
         };
     }
 
+    #[test]
+    fn detects_log_files() {
+        let mut log_file = tempfile::NamedTempFile::new().unwrap();
+        log_file.write_all(&write_random_log(&[]).unwrap()).unwrap();
+        assert!(is_log_file(log_file.path()).unwrap());
+
+        let mut short_file = tempfile::NamedTempFile::new().unwrap();
+        short_file.write_all(b"SSL_LOG").unwrap();
+        assert!(!is_log_file(short_file.path()).unwrap());
+    }
 }
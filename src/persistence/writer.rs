@@ -0,0 +1,103 @@
+use super::message;
+use super::reader::{EXPECTED_HEADER, SUPPORTED_VERSION};
+use byteorder::{BigEndian, WriteBytesExt};
+use std::fs::File;
+use std::io;
+use std::io::prelude::*;
+use std::io::BufWriter;
+use std::path::Path;
+
+#[derive(Debug, Fail)]
+pub enum LogWriterError {
+    #[fail(display = "{}", _0)]
+    Io(#[fail(cause)] io::Error),
+    #[fail(display = "{}", _0)]
+    Message(#[fail(cause)] message::MessageError),
+}
+
+impl From<io::Error> for LogWriterError {
+    fn from(error: io::Error) -> Self {
+        LogWriterError::Io(error)
+    }
+}
+
+impl From<message::MessageError> for LogWriterError {
+    fn from(error: message::MessageError) -> Self {
+        match error {
+            message::MessageError::Io(e) => LogWriterError::Io(e),
+            e => LogWriterError::Message(e),
+        }
+    }
+}
+
+// Writes logs in the format read by LogReader
+#[derive(Debug)]
+pub struct LogWriter<T: Write> {
+    writer: BufWriter<T>,
+}
+
+impl<T: Write> LogWriter<T> {
+    pub fn new(writer: T) -> Result<LogWriter<T>, LogWriterError> {
+        let mut writer = BufWriter::new(writer);
+        writer.write_all(&EXPECTED_HEADER)?;
+        writer.write_i32::<BigEndian>(SUPPORTED_VERSION)?;
+
+        Ok(LogWriter { writer })
+    }
+
+    pub fn write_message(&mut self, message: &message::Message) -> Result<(), LogWriterError> {
+        self.writer.write_all(&message.write_to_bytes()?)?;
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> Result<(), LogWriterError> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+impl LogWriter<File> {
+    pub fn new_from_path(log_path: &Path) -> Result<LogWriter<File>, LogWriterError> {
+        let f = File::create(log_path)?;
+        LogWriter::new(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate proptest;
+
+    use super::*;
+    use crate::persistence::reader::LogReader;
+    use crate::test_utils::message::*;
+    use proptest::prelude::*;
+
+    impl From<LogWriterError> for TestCaseError {
+        fn from(error: LogWriterError) -> Self {
+            TestCaseError::fail(format!("{}", error))
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn write_and_read_back(messages in prop::collection::vec(prop_oneof![
+            random_blank_msg_strategy(),
+            random_refbox2013_msg_strategy(),
+            random_vision2014_msg_strategy(),
+            random_vision_tracker2020_msg_strategy(),
+        ], 0..10)) {
+            let mut bytes = Vec::<u8>::new();
+            {
+                let mut writer = LogWriter::new(&mut bytes)?;
+                for message in &messages {
+                    writer.write_message(message)?;
+                }
+                writer.flush()?;
+            }
+
+            let reader = LogReader::new(bytes.as_slice())?;
+            let parsed_messages = reader.collect::<Result<Vec<_>, _>>()?;
+            prop_assert_eq!(parsed_messages, messages);
+        }
+    }
+}
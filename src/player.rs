@@ -1,6 +1,7 @@
 use crate::persistence::message::MessageType;
 use crate::persistence::reader;
 use crate::protos::messages_robocup_ssl_referee::SSL_Referee_Stage;
+use crate::referee;
 use chrono::prelude::*;
 use protobuf::Message as ProtobufMessage;
 use socket2::{Domain, Protocol, SockAddr, Socket, Type};
@@ -11,17 +12,86 @@ use std::thread;
 
 pub const REFEREE_PORT: u16 = 10003;
 pub const VISION_PORT: u16 = 10006;
+pub const TRACKER_PORT: u16 = 10010;
 
 lazy_static! {
     pub static ref REFEREE_ADDR: IpAddr = Ipv4Addr::new(224, 5, 23, 1).into();
     pub static ref VISION_ADDR: IpAddr = Ipv4Addr::new(224, 5, 23, 2).into();
 }
 
+// Maps log timestamps (ns) to wall clock time at a playback speed. The
+// first timestamp after a reset is due immediately, so pausing or
+// seeking only needs a reset.
+#[derive(Debug, Clone)]
+pub struct PlaybackClock {
+    speed: f32,
+    start_time: DateTime<Utc>,
+    ref_timestamp: Option<i64>,
+}
+
+impl PlaybackClock {
+    pub fn new(speed: f32) -> PlaybackClock {
+        PlaybackClock {
+            speed,
+            start_time: Utc::now(),
+            ref_timestamp: None,
+        }
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed;
+        self.reset();
+    }
+
+    pub fn reset(&mut self) {
+        self.ref_timestamp = None;
+    }
+
+    // Wall clock nanoseconds until `timestamp` is due, negative if it
+    // is late
+    pub fn time_until(&mut self, timestamp: i64) -> i64 {
+        match self.ref_timestamp {
+            Some(ref_timestamp) => {
+                let real_elapsed = (Utc::now() - self.start_time)
+                    .num_nanoseconds()
+                    .expect("real_elapsed overflowed i64 nanoseconds");
+                let msg_elapsed = ((timestamp - ref_timestamp) as f32 / self.speed) as i64;
+                msg_elapsed - real_elapsed
+            }
+            None => {
+                self.start_time = Utc::now();
+                self.ref_timestamp = Some(timestamp);
+                0
+            }
+        }
+    }
+
+    // Sleeps until `timestamp` is due and returns how long that was
+    pub fn wait_until(&mut self, timestamp: i64) -> i64 {
+        let sleep_time = self.time_until(timestamp);
+        if sleep_time > 0 {
+            let sleep_time = chrono::Duration::nanoseconds(sleep_time);
+            thread::sleep(
+                sleep_time
+                    .to_std()
+                    .expect("Failed to convert to std::time::Duration"),
+            );
+        }
+        sleep_time
+    }
+}
+
 pub struct PlayerOptions {
     referee_addr: IpAddr,
     referee_port: u16,
     vision_addr: IpAddr,
     vision_port: u16,
+    tracker_addr: IpAddr,
+    tracker_port: u16,
 }
 
 impl Default for PlayerOptions {
@@ -31,6 +101,9 @@ impl Default for PlayerOptions {
             referee_port: REFEREE_PORT,
             vision_addr: *VISION_ADDR,
             vision_port: VISION_PORT,
+            // trackers send on the vision multicast address
+            tracker_addr: *VISION_ADDR,
+            tracker_port: TRACKER_PORT,
         }
     }
 }
@@ -55,6 +128,8 @@ pub struct Player<T: Read + Seek> {
     referee_socket: UdpSocket,
     vision_addr: SocketAddr,
     vision_socket: UdpSocket,
+    tracker_addr: SocketAddr,
+    tracker_socket: UdpSocket,
 }
 
 fn new_socket(addr: &SocketAddr) -> io::Result<Socket> {
@@ -91,6 +166,8 @@ impl<T: Read + Seek> Player<T> {
         let referee_socket = new_sender_socket(&referee_addr)?;
         let vision_addr = SocketAddr::new(options.vision_addr, options.vision_port);
         let vision_socket = new_sender_socket(&vision_addr)?;
+        let tracker_addr = SocketAddr::new(options.tracker_addr, options.tracker_port);
+        let tracker_socket = new_sender_socket(&tracker_addr)?;
 
         Ok(Player {
             reader,
@@ -98,6 +175,8 @@ impl<T: Read + Seek> Player<T> {
             referee_socket,
             vision_addr,
             vision_socket,
+            tracker_addr,
+            tracker_socket,
         })
     }
 
@@ -117,29 +196,11 @@ impl<T: Read + Seek> Player<T> {
         // iterators exist.
         let mut curr_stage: Option<SSL_Referee_Stage> = None;
 
-        let mut start_time = Utc::now();
-        let mut ref_timestamp = 0i64;
+        let mut clock = PlaybackClock::new(speed);
         for message in self.reader.filter_map(Result::ok) {
-            if is_running_stage(curr_stage) {
-                if ref_timestamp != 0 {
-                    let real_elapsed = (Utc::now() - start_time)
-                        .num_nanoseconds()
-                        .expect("real_elapsed overflowed i64 nanoseconds");
-                    let msg_elapsed = ((message.timestamp - ref_timestamp) as f32 / speed) as i64;                    
-                    let sleep_time = msg_elapsed - real_elapsed;
-                    println!("sleep_time: {}", sleep_time);
-                    if sleep_time > 0 {
-                        let sleep_time = chrono::Duration::nanoseconds(sleep_time);
-                        thread::sleep(
-                            sleep_time
-                                .to_std()
-                                .expect("Failed to convert to std::time::Duration"),
-                        );
-                    }
-                } else {
-                    start_time = Utc::now();
-                    ref_timestamp = message.timestamp;
-                }
+            // messages before the first referee message are played
+            if curr_stage.map_or(true, referee::is_running_stage) {
+                clock.wait_until(message.timestamp);
 
                 match message.msg_type {
                     MessageType::Blank | MessageType::Vision2010(_) | MessageType::Unknown(_) => {
@@ -162,9 +223,17 @@ impl<T: Read + Seek> Player<T> {
                             .send_to(&msg_bytes, self.vision_addr)
                             .expect("Could not send vision message");
                     }
+                    MessageType::VisionTracker2020(ref tracker_msg) => {
+                        let msg_bytes = tracker_msg
+                            .write_to_bytes()
+                            .expect("Failed to serialize tracker message");
+                        self.tracker_socket
+                            .send_to(&msg_bytes, self.tracker_addr)
+                            .expect("Could not send tracker message");
+                    }
                 };
             } else {
-                ref_timestamp = 0;
+                clock.reset();
             }
 
             if let MessageType::Refbox2013(ref ref_msg) = message.msg_type {
@@ -174,15 +243,20 @@ impl<T: Read + Seek> Player<T> {
     }
 }
 
-fn is_running_stage(stage: Option<SSL_Referee_Stage>) -> bool {
-    match stage {
-        Some(stage) => match stage {
-            SSL_Referee_Stage::NORMAL_FIRST_HALF
-            | SSL_Referee_Stage::NORMAL_SECOND_HALF
-            | SSL_Referee_Stage::EXTRA_FIRST_HALF
-            | SSL_Referee_Stage::EXTRA_SECOND_HALF => true,
-            _ => false,
-        },
-        None => true,
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clock_scales_log_time() {
+        let mut clock = PlaybackClock::new(2.0);
+        assert_eq!(clock.time_until(5_000_000_000), 0);
+        let time_until = clock.time_until(6_000_000_000);
+        assert!(time_until <= 500_000_000 && time_until > 400_000_000);
+
+        // after a reset the next timestamp is due at once
+        clock.reset();
+        assert_eq!(clock.time_until(60_000_000_000), 0);
+        assert!(clock.time_until(59_000_000_000) < -400_000_000);
     }
 }
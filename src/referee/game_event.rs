@@ -0,0 +1,193 @@
+use crate::protos::messages_robocup_ssl_game_event::{
+    SSL_Referee_Game_Event, SSL_Referee_Game_Event_GameEventType, SSL_Referee_Game_Event_Team,
+};
+use crate::protos::messages_robocup_ssl_referee::SSL_Referee;
+use crate::vision::TeamColor;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum GameEventKind {
+    Unknown,
+    Custom,
+    NumberOfPlayers,
+    BallLeftField,
+    Goal,
+    KickTimeout,
+    NoProgressInGame,
+    BotCollision,
+    MultipleDefender,
+    MultipleDefenderPartially,
+    AttackerInDefenseArea,
+    Icing,
+    BallSpeed,
+    RobotStopSpeed,
+    BallDribbling,
+    AttackerTouchKeeper,
+    DoubleTouch,
+    AttackerToDefenceArea,
+    DefenderToKickPointDistance,
+    BallHolding,
+    IndirectGoal,
+    BallPlacementFailed,
+    ChipOnGoal,
+}
+
+impl GameEventKind {
+    pub fn from_proto(event_type: SSL_Referee_Game_Event_GameEventType) -> GameEventKind {
+        use SSL_Referee_Game_Event_GameEventType::*;
+        match event_type {
+            UNKNOWN => GameEventKind::Unknown,
+            CUSTOM => GameEventKind::Custom,
+            NUMBER_OF_PLAYERS => GameEventKind::NumberOfPlayers,
+            BALL_LEFT_FIELD => GameEventKind::BallLeftField,
+            GOAL => GameEventKind::Goal,
+            KICK_TIMEOUT => GameEventKind::KickTimeout,
+            NO_PROGRESS_IN_GAME => GameEventKind::NoProgressInGame,
+            BOT_COLLISION => GameEventKind::BotCollision,
+            MULTIPLE_DEFENDER => GameEventKind::MultipleDefender,
+            MULTIPLE_DEFENDER_PARTIALLY => GameEventKind::MultipleDefenderPartially,
+            ATTACKER_IN_DEFENSE_AREA => GameEventKind::AttackerInDefenseArea,
+            ICING => GameEventKind::Icing,
+            BALL_SPEED => GameEventKind::BallSpeed,
+            ROBOT_STOP_SPEED => GameEventKind::RobotStopSpeed,
+            BALL_DRIBBLING => GameEventKind::BallDribbling,
+            ATTACKER_TOUCH_KEEPER => GameEventKind::AttackerTouchKeeper,
+            DOUBLE_TOUCH => GameEventKind::DoubleTouch,
+            ATTACKER_TO_DEFENCE_AREA => GameEventKind::AttackerToDefenceArea,
+            DEFENDER_TO_KICK_POINT_DISTANCE => GameEventKind::DefenderToKickPointDistance,
+            BALL_HOLDING => GameEventKind::BallHolding,
+            INDIRECT_GOAL => GameEventKind::IndirectGoal,
+            BALL_PLACEMENT_FAILED => GameEventKind::BallPlacementFailed,
+            CHIP_ON_GOAL => GameEventKind::ChipOnGoal,
+        }
+    }
+
+    // Name used in reports, matching the proto enum value
+    pub fn name(self) -> &'static str {
+        match self {
+            GameEventKind::Unknown => "UNKNOWN",
+            GameEventKind::Custom => "CUSTOM",
+            GameEventKind::NumberOfPlayers => "NUMBER_OF_PLAYERS",
+            GameEventKind::BallLeftField => "BALL_LEFT_FIELD",
+            GameEventKind::Goal => "GOAL",
+            GameEventKind::KickTimeout => "KICK_TIMEOUT",
+            GameEventKind::NoProgressInGame => "NO_PROGRESS_IN_GAME",
+            GameEventKind::BotCollision => "BOT_COLLISION",
+            GameEventKind::MultipleDefender => "MULTIPLE_DEFENDER",
+            GameEventKind::MultipleDefenderPartially => "MULTIPLE_DEFENDER_PARTIALLY",
+            GameEventKind::AttackerInDefenseArea => "ATTACKER_IN_DEFENSE_AREA",
+            GameEventKind::Icing => "ICING",
+            GameEventKind::BallSpeed => "BALL_SPEED",
+            GameEventKind::RobotStopSpeed => "ROBOT_STOP_SPEED",
+            GameEventKind::BallDribbling => "BALL_DRIBBLING",
+            GameEventKind::AttackerTouchKeeper => "ATTACKER_TOUCH_KEEPER",
+            GameEventKind::DoubleTouch => "DOUBLE_TOUCH",
+            GameEventKind::AttackerToDefenceArea => "ATTACKER_TO_DEFENCE_AREA",
+            GameEventKind::DefenderToKickPointDistance => "DEFENDER_TO_KICK_POINT_DISTANCE",
+            GameEventKind::BallHolding => "BALL_HOLDING",
+            GameEventKind::IndirectGoal => "INDIRECT_GOAL",
+            GameEventKind::BallPlacementFailed => "BALL_PLACEMENT_FAILED",
+            GameEventKind::ChipOnGoal => "CHIP_ON_GOAL",
+        }
+    }
+
+    // Rule violations by the originating team. Goals, the ball
+    // leaving the field and goals that do not count are part of the
+    // normal game flow.
+    pub fn is_foul(self) -> bool {
+        match self {
+            GameEventKind::Unknown
+            | GameEventKind::Custom
+            | GameEventKind::BallLeftField
+            | GameEventKind::Goal
+            | GameEventKind::NoProgressInGame
+            | GameEventKind::IndirectGoal
+            | GameEventKind::ChipOnGoal => false,
+            _ => true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameEvent {
+    pub kind: GameEventKind,
+    pub team: Option<TeamColor>,
+    pub robot_id: Option<u32>,
+    pub message: Option<String>,
+}
+
+impl GameEvent {
+    pub fn from_proto(game_event: &SSL_Referee_Game_Event) -> GameEvent {
+        let (team, robot_id) = if game_event.has_originator() {
+            let originator = game_event.get_originator();
+            let team = match originator.get_team() {
+                SSL_Referee_Game_Event_Team::TEAM_YELLOW => Some(TeamColor::Yellow),
+                SSL_Referee_Game_Event_Team::TEAM_BLUE => Some(TeamColor::Blue),
+                SSL_Referee_Game_Event_Team::TEAM_UNKNOWN => None,
+            };
+            let robot_id = if originator.has_botId() {
+                Some(originator.get_botId())
+            } else {
+                None
+            };
+            (team, robot_id)
+        } else {
+            (None, None)
+        };
+        let message = if game_event.has_message() {
+            Some(game_event.get_message().to_owned())
+        } else {
+            None
+        };
+
+        GameEvent {
+            kind: GameEventKind::from_proto(game_event.get_gameEventType()),
+            team,
+            robot_id,
+            message,
+        }
+    }
+
+    pub fn from_referee(referee: &SSL_Referee) -> Option<GameEvent> {
+        if referee.has_gameEvent() {
+            Some(GameEvent::from_proto(referee.get_gameEvent()))
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use protobuf::ProtobufEnum;
+
+    #[test]
+    fn kind_names_match_proto() {
+        for event_type in SSL_Referee_Game_Event_GameEventType::values() {
+            let kind = GameEventKind::from_proto(*event_type);
+            assert_eq!(kind.name(), event_type.descriptor().name());
+        }
+    }
+
+    #[test]
+    fn event_from_referee() {
+        let mut referee = SSL_Referee::new();
+        assert_eq!(GameEvent::from_referee(&referee), None);
+
+        let game_event = referee.mut_gameEvent();
+        game_event.set_gameEventType(SSL_Referee_Game_Event_GameEventType::BOT_COLLISION);
+        game_event
+            .mut_originator()
+            .set_team(SSL_Referee_Game_Event_Team::TEAM_BLUE);
+        game_event.mut_originator().set_botId(4);
+        assert_eq!(
+            GameEvent::from_referee(&referee),
+            Some(GameEvent {
+                kind: GameEventKind::BotCollision,
+                team: Some(TeamColor::Blue),
+                robot_id: Some(4),
+                message: None,
+            })
+        );
+    }
+}
@@ -0,0 +1,12 @@
+pub mod game_event;
+pub mod report;
+pub mod state;
+pub mod timeline;
+
+pub use game_event::{GameEvent, GameEventKind};
+pub use report::{game_event_report, FoulCounts, GameEventRecord, GameEventReport};
+pub use state::{
+    command_team, is_free_kick, is_running_command, is_running_stage, RefereeState, TeamState,
+    RUNNING_COMMANDS, RUNNING_STAGES,
+};
+pub use timeline::{Goal, Interval, Timeline};
@@ -0,0 +1,418 @@
+use super::game_event::{GameEvent, GameEventKind};
+use crate::geometry::Point;
+use crate::persistence::message::{Message, MessageType};
+use crate::protos::messages_robocup_ssl_referee::SSL_Referee_Stage;
+use crate::vision::{TeamColor, WorldFrameBuilder, WorldFrameConfig};
+use serde_json::{self, json};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+// Game events of a match with where they happened, for a post-match
+// breakdown of the fouls of each team.
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameEventRecord {
+    // log timestamp in ns
+    pub timestamp: i64,
+    pub stage: SSL_Referee_Stage,
+    // in us, if the referee sent it
+    pub stage_time_left: Option<i32>,
+    pub event: GameEvent,
+    // positions from the latest vision frames when the event was sent
+    pub ball: Option<Point>,
+    pub robot: Option<Point>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct FoulCounts {
+    pub yellow: usize,
+    pub blue: usize,
+    // events without an originator team
+    pub unknown: usize,
+}
+
+impl FoulCounts {
+    fn add(&mut self, team: Option<TeamColor>) {
+        match team {
+            Some(TeamColor::Yellow) => self.yellow += 1,
+            Some(TeamColor::Blue) => self.blue += 1,
+            None => self.unknown += 1,
+        }
+    }
+
+    pub fn total(&self) -> usize {
+        self.yellow + self.blue + self.unknown
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct GameEventReport {
+    pub yellow_team: String,
+    pub blue_team: String,
+    // log timestamp of the first message, event times are relative to
+    // it
+    pub start_timestamp: i64,
+    pub events: Vec<GameEventRecord>,
+}
+
+// The referee repeats the game event in every message until the next
+// command, so an event is only recorded once per command.
+pub fn game_event_report<I, E>(messages: I, config: &WorldFrameConfig) -> Result<GameEventReport, E>
+where
+    I: IntoIterator<Item = Result<Message, E>>,
+{
+    let mut report = GameEventReport::default();
+    let mut world_frame_builder = WorldFrameBuilder::new(config);
+    let mut last_event: Option<(u32, GameEvent)> = None;
+    let mut is_first_message = true;
+
+    for message in messages {
+        let message = message?;
+        if is_first_message {
+            report.start_timestamp = message.timestamp;
+            is_first_message = false;
+        }
+
+        match message.msg_type {
+            MessageType::Vision2014(ref packet) => world_frame_builder.add_packet(packet),
+            MessageType::Refbox2013(ref referee) => {
+                if !referee.get_yellow().get_name().is_empty() {
+                    report.yellow_team = referee.get_yellow().get_name().to_owned();
+                }
+                if !referee.get_blue().get_name().is_empty() {
+                    report.blue_team = referee.get_blue().get_name().to_owned();
+                }
+
+                let event = match GameEvent::from_referee(referee) {
+                    Some(event) => event,
+                    None => continue,
+                };
+                let key = (referee.get_command_counter(), event);
+                if last_event.as_ref() == Some(&key) {
+                    continue;
+                }
+
+                let event = key.1.clone();
+                last_event = Some(key);
+
+                let world_frame = world_frame_builder.build();
+                let ball = world_frame
+                    .as_ref()
+                    .and_then(|world_frame| world_frame.ball())
+                    .map(|ball| Point::new(ball.x, ball.y));
+                let robot = match (world_frame.as_ref(), event.team, event.robot_id) {
+                    (Some(world_frame), Some(team), Some(robot_id)) => world_frame
+                        .robot(team, robot_id)
+                        .map(|robot| Point::new(robot.x, robot.y)),
+                    _ => None,
+                };
+
+                report.events.push(GameEventRecord {
+                    timestamp: message.timestamp,
+                    stage: referee.get_stage(),
+                    stage_time_left: if referee.has_stage_time_left() {
+                        Some(referee.get_stage_time_left())
+                    } else {
+                        None
+                    },
+                    event,
+                    ball,
+                    robot,
+                });
+            }
+            _ => {}
+        }
+    }
+
+    Ok(report)
+}
+
+fn format_seconds(seconds: f64) -> String {
+    let sign = if seconds < 0.0 { "-" } else { "" };
+    let seconds = seconds.abs().round() as u64;
+    format!("{}{}:{:02}", sign, seconds / 60, seconds % 60)
+}
+
+fn point_to_json(point: Option<Point>) -> serde_json::Value {
+    match point {
+        Some(point) => json!([point.x, point.y]),
+        None => serde_json::Value::Null,
+    }
+}
+
+fn point_to_markdown(point: Option<Point>) -> String {
+    match point {
+        Some(point) => format!("({:.0}, {:.0})", point.x, point.y),
+        None => "-".to_owned(),
+    }
+}
+
+// A '|' would end the table cell early
+fn escape_markdown_cell(text: &str) -> String {
+    text.replace('|', "\\|")
+}
+
+fn team_to_str(team: Option<TeamColor>) -> &'static str {
+    match team {
+        Some(TeamColor::Yellow) => "yellow",
+        Some(TeamColor::Blue) => "blue",
+        None => "unknown",
+    }
+}
+
+impl GameEventReport {
+    // Time since the start of the log in s
+    pub fn time(&self, record: &GameEventRecord) -> f64 {
+        (record.timestamp - self.start_timestamp) as f64 / 1e9
+    }
+
+    pub fn fouls(&self) -> BTreeMap<GameEventKind, FoulCounts> {
+        let mut fouls = BTreeMap::<GameEventKind, FoulCounts>::new();
+        for record in &self.events {
+            if record.event.kind.is_foul() {
+                fouls
+                    .entry(record.event.kind)
+                    .or_default()
+                    .add(record.event.team);
+            }
+        }
+
+        fouls
+    }
+
+    pub fn foul_totals(&self) -> FoulCounts {
+        let mut totals = FoulCounts::default();
+        for counts in self.fouls().values() {
+            totals.yellow += counts.yellow;
+            totals.blue += counts.blue;
+            totals.unknown += counts.unknown;
+        }
+
+        totals
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        let counts_json = |counts: &FoulCounts| {
+            json!({
+                "yellow": counts.yellow,
+                "blue": counts.blue,
+                "unknown": counts.unknown,
+            })
+        };
+
+        json!({
+            "yellow_team": self.yellow_team,
+            "blue_team": self.blue_team,
+            "fouls": self
+                .fouls()
+                .iter()
+                .map(|(kind, counts)| json!({
+                    "type": kind.name(),
+                    "counts": counts_json(counts),
+                }))
+                .collect::<Vec<_>>(),
+            "foul_totals": counts_json(&self.foul_totals()),
+            "events": self
+                .events
+                .iter()
+                .map(|record| json!({
+                    "time": self.time(record),
+                    "stage": format!("{:?}", record.stage),
+                    "stage_time_left": record.stage_time_left.map(|t| f64::from(t) / 1e6),
+                    "type": record.event.kind.name(),
+                    "foul": record.event.kind.is_foul(),
+                    "team": team_to_str(record.event.team),
+                    "robot_id": record.event.robot_id,
+                    "message": record.event.message,
+                    "ball": point_to_json(record.ball),
+                    "robot": point_to_json(record.robot),
+                }))
+                .collect::<Vec<_>>(),
+        })
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut output = String::new();
+
+        writeln!(output, "# Game Events\n").unwrap();
+        writeln!(
+            output,
+            "Yellow: {}, Blue: {}\n",
+            self.yellow_team, self.blue_team
+        )
+        .unwrap();
+
+        writeln!(output, "## Fouls\n").unwrap();
+        writeln!(output, "| foul | yellow | blue | unknown team |").unwrap();
+        writeln!(output, "|---|---|---|---|").unwrap();
+        for (kind, counts) in &self.fouls() {
+            writeln!(
+                output,
+                "| {} | {} | {} | {} |",
+                kind.name(),
+                counts.yellow,
+                counts.blue,
+                counts.unknown
+            )
+            .unwrap();
+        }
+        let totals = self.foul_totals();
+        writeln!(
+            output,
+            "| total | {} | {} | {} |",
+            totals.yellow, totals.blue, totals.unknown
+        )
+        .unwrap();
+
+        writeln!(output, "\n## Events\n").unwrap();
+        writeln!(
+            output,
+            "| time | stage | stage time left | event | team | robot | ball | robot position | message |"
+        )
+        .unwrap();
+        writeln!(output, "|---|---|---|---|---|---|---|---|---|").unwrap();
+        for record in &self.events {
+            writeln!(
+                output,
+                "| {} | {:?} | {} | {} | {} | {} | {} | {} | {} |",
+                format_seconds(self.time(record)),
+                record.stage,
+                record
+                    .stage_time_left
+                    .map_or("-".to_owned(), |t| format_seconds(f64::from(t) / 1e6)),
+                record.event.kind.name(),
+                team_to_str(record.event.team),
+                record
+                    .event
+                    .robot_id
+                    .map_or("-".to_owned(), |robot_id| robot_id.to_string()),
+                point_to_markdown(record.ball),
+                point_to_markdown(record.robot),
+                record
+                    .event
+                    .message
+                    .as_ref()
+                    .map_or(String::new(), |message| escape_markdown_cell(message)),
+            )
+            .unwrap();
+        }
+
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protos::messages_robocup_ssl_game_event::{
+        SSL_Referee_Game_Event_GameEventType, SSL_Referee_Game_Event_Team,
+    };
+    use crate::protos::messages_robocup_ssl_referee::SSL_Referee;
+    use crate::protos::messages_robocup_ssl_wrapper::SSL_WrapperPacket;
+    use crate::test_utils::vision::{ball, detection, robot};
+
+    fn referee_msg(
+        timestamp: i64,
+        command_counter: u32,
+        event_type: SSL_Referee_Game_Event_GameEventType,
+        team: SSL_Referee_Game_Event_Team,
+    ) -> Message {
+        let mut referee = SSL_Referee::new();
+        referee.set_command_counter(command_counter);
+        referee.mut_yellow().set_name("ER-Force".to_owned());
+        referee.mut_blue().set_name("TIGERs".to_owned());
+        let game_event = referee.mut_gameEvent();
+        game_event.set_gameEventType(event_type);
+        game_event.mut_originator().set_team(team);
+        game_event.mut_originator().set_botId(2);
+        Message {
+            timestamp,
+            msg_type: MessageType::Refbox2013(referee),
+        }
+    }
+
+    fn vision_msg(timestamp: i64) -> Message {
+        let mut packet = SSL_WrapperPacket::new();
+        packet.set_detection(detection(
+            0,
+            0.0,
+            vec![ball(100.0, -200.0)],
+            vec![],
+            vec![robot(2, 300.0, 400.0, 0.0)],
+        ));
+        Message {
+            timestamp,
+            msg_type: MessageType::Vision2014(packet),
+        }
+    }
+
+    fn example_report() -> GameEventReport {
+        use SSL_Referee_Game_Event_GameEventType::*;
+        use SSL_Referee_Game_Event_Team::*;
+
+        let messages = vec![
+            vision_msg(1_000_000_000),
+            referee_msg(2_000_000_000, 1, BOT_COLLISION, TEAM_BLUE),
+            referee_msg(2_100_000_000, 1, BOT_COLLISION, TEAM_BLUE),
+            referee_msg(3_000_000_000, 2, BALL_LEFT_FIELD, TEAM_YELLOW),
+            referee_msg(4_000_000_000, 3, BOT_COLLISION, TEAM_YELLOW),
+            referee_msg(5_000_000_000, 4, DOUBLE_TOUCH, TEAM_UNKNOWN),
+        ];
+        game_event_report(
+            messages.into_iter().map(Ok::<_, ()>),
+            &WorldFrameConfig::default(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn repeated_events_are_recorded_once() {
+        let report = example_report();
+        assert_eq!(report.events.len(), 4);
+        assert_eq!(report.yellow_team, "ER-Force");
+        assert_eq!(report.time(&report.events[0]), 1.0);
+
+        let first = &report.events[0];
+        assert_eq!(first.ball, Some(Point::new(100.0, -200.0)));
+        assert_eq!(first.robot, Some(Point::new(300.0, 400.0)));
+        // there is no yellow robot 2
+        assert_eq!(report.events[2].robot, None);
+    }
+
+    #[test]
+    fn fouls_by_type_and_team() {
+        let report = example_report();
+        let fouls = report.fouls();
+        assert_eq!(fouls.len(), 2);
+        assert_eq!(
+            fouls[&GameEventKind::BotCollision],
+            FoulCounts {
+                yellow: 1,
+                blue: 1,
+                unknown: 0,
+            }
+        );
+        assert_eq!(fouls[&GameEventKind::DoubleTouch].unknown, 1);
+        assert_eq!(report.foul_totals().total(), 3);
+    }
+
+    #[test]
+    fn markdown_escapes_pipes_in_messages() {
+        let mut report = example_report();
+        report.events[0].event.message = Some("ball | robot".to_owned());
+        let markdown = report.to_markdown();
+        let row = markdown
+            .lines()
+            .find(|line| line.contains("| ball \\| robot |"))
+            .unwrap();
+        // the row still has all columns
+        assert_eq!(row.replace("\\|", "").matches('|').count(), 10);
+    }
+
+    #[test]
+    fn formatted_times() {
+        assert_eq!(format_seconds(0.0), "0:00");
+        assert_eq!(format_seconds(125.4), "2:05");
+        assert_eq!(format_seconds(-61.0), "-1:01");
+    }
+}
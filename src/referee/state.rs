@@ -0,0 +1,175 @@
+use crate::geometry::Point;
+use crate::protos::messages_robocup_ssl_referee::{
+    SSL_Referee, SSL_Referee_Command, SSL_Referee_Stage, SSL_Referee_TeamInfo,
+};
+use crate::vision::TeamColor;
+
+// Stages in which the game clock runs
+pub const RUNNING_STAGES: [SSL_Referee_Stage; 4] = [
+    SSL_Referee_Stage::NORMAL_FIRST_HALF,
+    SSL_Referee_Stage::NORMAL_SECOND_HALF,
+    SSL_Referee_Stage::EXTRA_FIRST_HALF,
+    SSL_Referee_Stage::EXTRA_SECOND_HALF,
+];
+
+// Commands after which the robots may touch the ball
+pub const RUNNING_COMMANDS: [SSL_Referee_Command; 6] = [
+    SSL_Referee_Command::NORMAL_START,
+    SSL_Referee_Command::FORCE_START,
+    SSL_Referee_Command::DIRECT_FREE_YELLOW,
+    SSL_Referee_Command::DIRECT_FREE_BLUE,
+    SSL_Referee_Command::INDIRECT_FREE_YELLOW,
+    SSL_Referee_Command::INDIRECT_FREE_BLUE,
+];
+
+pub fn is_running_stage(stage: SSL_Referee_Stage) -> bool {
+    RUNNING_STAGES.contains(&stage)
+}
+
+pub fn is_running_command(command: SSL_Referee_Command) -> bool {
+    RUNNING_COMMANDS.contains(&command)
+}
+
+// The team a command is for, None for commands for both teams
+pub fn command_team(command: SSL_Referee_Command) -> Option<TeamColor> {
+    use SSL_Referee_Command::*;
+    match command {
+        PREPARE_KICKOFF_YELLOW
+        | PREPARE_PENALTY_YELLOW
+        | DIRECT_FREE_YELLOW
+        | INDIRECT_FREE_YELLOW
+        | TIMEOUT_YELLOW
+        | GOAL_YELLOW
+        | BALL_PLACEMENT_YELLOW => Some(TeamColor::Yellow),
+        PREPARE_KICKOFF_BLUE | PREPARE_PENALTY_BLUE | DIRECT_FREE_BLUE | INDIRECT_FREE_BLUE
+        | TIMEOUT_BLUE | GOAL_BLUE | BALL_PLACEMENT_BLUE => Some(TeamColor::Blue),
+        HALT | STOP | NORMAL_START | FORCE_START => None,
+    }
+}
+
+pub fn is_free_kick(command: SSL_Referee_Command) -> bool {
+    use SSL_Referee_Command::*;
+    match command {
+        DIRECT_FREE_YELLOW | DIRECT_FREE_BLUE | INDIRECT_FREE_YELLOW | INDIRECT_FREE_BLUE => true,
+        _ => false,
+    }
+}
+
+// The parts of a team's referee info that only change at distinct
+// moments. The remaining timeout and yellow card times are left out
+// as they count down continuously.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TeamState {
+    pub name: String,
+    pub score: u32,
+    pub red_cards: u32,
+    pub yellow_cards: u32,
+    // number of yellow cards currently counting down
+    pub active_yellow_cards: u32,
+    pub timeouts: u32,
+    pub goalie: u32,
+}
+
+impl TeamState {
+    pub fn from_team_info(team_info: &SSL_Referee_TeamInfo) -> TeamState {
+        TeamState {
+            name: team_info.get_name().to_owned(),
+            score: team_info.get_score(),
+            red_cards: team_info.get_red_cards(),
+            yellow_cards: team_info.get_yellow_cards(),
+            active_yellow_cards: team_info.get_yellow_card_times().len() as u32,
+            timeouts: team_info.get_timeouts(),
+            goalie: team_info.get_goalie(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RefereeState {
+    pub stage: SSL_Referee_Stage,
+    pub command: SSL_Referee_Command,
+    pub command_counter: u32,
+    pub yellow: TeamState,
+    pub blue: TeamState,
+    // only sent for ball placement commands
+    pub designated_position: Option<Point>,
+    pub blue_team_on_positive_half: Option<bool>,
+}
+
+impl RefereeState {
+    pub fn from_referee(referee: &SSL_Referee) -> RefereeState {
+        let designated_position = if referee.has_designated_position() {
+            let position = referee.get_designated_position();
+            Some(Point::new(position.get_x(), position.get_y()))
+        } else {
+            None
+        };
+        let blue_team_on_positive_half = if referee.has_blueTeamOnPositiveHalf() {
+            Some(referee.get_blueTeamOnPositiveHalf())
+        } else {
+            None
+        };
+
+        RefereeState {
+            stage: referee.get_stage(),
+            command: referee.get_command(),
+            command_counter: referee.get_command_counter(),
+            yellow: TeamState::from_team_info(referee.get_yellow()),
+            blue: TeamState::from_team_info(referee.get_blue()),
+            designated_position,
+            blue_team_on_positive_half,
+        }
+    }
+
+    pub fn team(&self, color: TeamColor) -> &TeamState {
+        match color {
+            TeamColor::Yellow => &self.yellow,
+            TeamColor::Blue => &self.blue,
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        is_running_stage(self.stage) && is_running_command(self.command)
+    }
+
+    // True if both states belong to the same issued command
+    pub fn is_same_command(&self, other: &RefereeState) -> bool {
+        self.stage == other.stage
+            && self.command == other.command
+            && self.command_counter == other.command_counter
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::labeler as test_utils_labeler;
+    use crate::test_utils::protos::refbox2013_strategy;
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn running_stages(stage in test_utils_labeler::running_stage_strategy()) {
+            prop_assert!(is_running_stage(stage));
+        }
+
+        #[test]
+        fn not_running_stages(stage in test_utils_labeler::not_running_stage_strategy()) {
+            prop_assert!(!is_running_stage(stage));
+        }
+
+        #[test]
+        fn running_free_kicks_have_a_team(command in test_utils_labeler::running_command_strategy()) {
+            prop_assert_eq!(is_free_kick(command), command_team(command).is_some());
+        }
+
+        #[test]
+        fn state_from_referee(referee in refbox2013_strategy()) {
+            let state = RefereeState::from_referee(&referee);
+            prop_assert_eq!(state.stage, referee.get_stage());
+            prop_assert_eq!(&state.yellow.name, referee.get_yellow().get_name());
+            prop_assert_eq!(state.blue.score, referee.get_blue().get_score());
+            prop_assert_eq!(state.designated_position.is_some(), referee.has_designated_position());
+        }
+    }
+}
@@ -0,0 +1,285 @@
+use super::state::{command_team, is_free_kick, RefereeState};
+use crate::persistence::message::{Message, MessageType};
+use crate::persistence::reader::{LogReader, LogReaderError};
+use crate::protos::messages_robocup_ssl_referee::{SSL_Referee, SSL_Referee_Command};
+use crate::vision::TeamColor;
+use std::path::Path;
+
+// A stretch of the log in which the referee state did not change.
+// Timestamps are log timestamps in ns; the end is the start of the
+// next interval, or the last referee message for the last interval.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Interval {
+    pub start: i64,
+    pub end: i64,
+    pub state: RefereeState,
+}
+
+impl Interval {
+    pub fn duration(&self) -> i64 {
+        self.end - self.start
+    }
+
+    pub fn contains(&self, timestamp: i64) -> bool {
+        self.start <= timestamp && timestamp < self.end
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Goal {
+    pub timestamp: i64,
+    pub team: TeamColor,
+    // scores after the goal
+    pub yellow_score: u32,
+    pub blue_score: u32,
+}
+
+// The referee states of a whole log. Referee messages must be added
+// in log order.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Timeline {
+    intervals: Vec<Interval>,
+}
+
+impl Timeline {
+    pub fn new() -> Timeline {
+        Timeline::default()
+    }
+
+    // Messages other than referee messages are skipped, so a log can
+    // be passed in as is
+    pub fn from_messages<I, E>(messages: I) -> Result<Timeline, E>
+    where
+        I: IntoIterator<Item = Result<Message, E>>,
+    {
+        let mut timeline = Timeline::new();
+        for message in messages {
+            timeline.add_message(&message?);
+        }
+
+        Ok(timeline)
+    }
+
+    pub fn from_path(log_path: &Path) -> Result<Timeline, LogReaderError> {
+        Timeline::from_messages(LogReader::new_from_path(log_path)?)
+    }
+
+    pub fn add_message(&mut self, message: &Message) {
+        if let MessageType::Refbox2013(ref referee) = message.msg_type {
+            self.add_referee(message.timestamp, referee);
+        }
+    }
+
+    pub fn add_referee(&mut self, timestamp: i64, referee: &SSL_Referee) {
+        let state = RefereeState::from_referee(referee);
+        if let Some(last) = self.intervals.last_mut() {
+            if timestamp < last.start {
+                return;
+            }
+            last.end = timestamp;
+            if last.state == state {
+                return;
+            }
+            // a state replaced within the same timestamp never held
+            if last.start == timestamp {
+                last.state = state;
+                return;
+            }
+        }
+
+        self.intervals.push(Interval {
+            start: timestamp,
+            end: timestamp,
+            state,
+        });
+    }
+
+    pub fn intervals(&self) -> &[Interval] {
+        &self.intervals
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.intervals.is_empty()
+    }
+
+    // The last state stays in effect after the last referee message
+    pub fn interval_at(&self, timestamp: i64) -> Option<&Interval> {
+        match self
+            .intervals
+            .binary_search_by_key(&timestamp, |interval| interval.start)
+        {
+            Ok(index) => Some(&self.intervals[index]),
+            Err(0) => None,
+            Err(index) => Some(&self.intervals[index - 1]),
+        }
+    }
+
+    pub fn state_at(&self, timestamp: i64) -> Option<&RefereeState> {
+        self.interval_at(timestamp).map(|interval| &interval.state)
+    }
+
+    pub fn is_running_at(&self, timestamp: i64) -> bool {
+        self.state_at(timestamp)
+            .map_or(false, |state| state.is_running())
+    }
+
+    // One interval per issued command, with the state from when the
+    // command was issued
+    pub fn commands(&self) -> Vec<Interval> {
+        let mut commands: Vec<Interval> = vec![];
+        for interval in &self.intervals {
+            match commands.last_mut() {
+                Some(command) if command.state.is_same_command(&interval.state) => {
+                    command.end = interval.end;
+                }
+                _ => commands.push(interval.clone()),
+            }
+        }
+
+        commands
+    }
+
+    pub fn find_commands<F>(&self, predicate: F) -> Vec<Interval>
+    where
+        F: Fn(SSL_Referee_Command) -> bool,
+    {
+        self.commands()
+            .into_iter()
+            .filter(|command| predicate(command.state.command))
+            .collect()
+    }
+
+    pub fn free_kicks(&self, team: TeamColor) -> Vec<Interval> {
+        self.find_commands(|command| is_free_kick(command) && command_team(command) == Some(team))
+    }
+
+    // Goals are taken from the score, as the GOAL_ commands are not
+    // sent by newer referee software. Lowered scores, i.e. goals taken
+    // back, are ignored, and a score raised by more than one at once
+    // counts as one goal.
+    pub fn goals(&self) -> Vec<Goal> {
+        let mut goals = vec![];
+        for pair in self.intervals.windows(2) {
+            let (before, after) = (&pair[0].state, &pair[1].state);
+            for &team in &[TeamColor::Yellow, TeamColor::Blue] {
+                if after.team(team).score > before.team(team).score {
+                    goals.push(Goal {
+                        timestamp: pair[1].start,
+                        team,
+                        yellow_score: after.yellow.score,
+                        blue_score: after.blue.score,
+                    });
+                }
+            }
+        }
+
+        goals
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protos::messages_robocup_ssl_referee::SSL_Referee_Stage;
+    use crate::test_utils::protos::refbox2013_strategy;
+    use proptest::prelude::*;
+
+    fn referee(
+        command: SSL_Referee_Command,
+        command_counter: u32,
+        yellow_score: u32,
+        blue_score: u32,
+    ) -> SSL_Referee {
+        let mut referee = SSL_Referee::new();
+        referee.set_stage(SSL_Referee_Stage::NORMAL_FIRST_HALF);
+        referee.set_command(command);
+        referee.set_command_counter(command_counter);
+        referee.mut_yellow().set_score(yellow_score);
+        referee.mut_blue().set_score(blue_score);
+        referee
+    }
+
+    fn example_timeline() -> Timeline {
+        let mut timeline = Timeline::new();
+        timeline.add_referee(100, &referee(SSL_Referee_Command::NORMAL_START, 1, 0, 0));
+        timeline.add_referee(150, &referee(SSL_Referee_Command::NORMAL_START, 1, 0, 0));
+        timeline.add_referee(200, &referee(SSL_Referee_Command::STOP, 2, 0, 0));
+        timeline.add_referee(250, &referee(SSL_Referee_Command::STOP, 2, 0, 1));
+        timeline.add_referee(
+            300,
+            &referee(SSL_Referee_Command::DIRECT_FREE_BLUE, 3, 0, 1),
+        );
+        timeline.add_referee(
+            400,
+            &referee(SSL_Referee_Command::DIRECT_FREE_YELLOW, 4, 0, 1),
+        );
+        timeline.add_referee(
+            500,
+            &referee(SSL_Referee_Command::DIRECT_FREE_BLUE, 5, 0, 1),
+        );
+        timeline.add_referee(600, &referee(SSL_Referee_Command::FORCE_START, 6, 0, 1));
+        timeline
+    }
+
+    proptest! {
+        #[test]
+        fn state_at_interval_start(referees in prop::collection::vec(refbox2013_strategy(), 1..20)) {
+            let mut timeline = Timeline::new();
+            for (i, referee) in referees.iter().enumerate() {
+                timeline.add_referee(i as i64 * 10, referee);
+            }
+
+            prop_assert_eq!(timeline.state_at(-1), None);
+            prop_assert_eq!(
+                timeline.state_at(referees.len() as i64 * 10),
+                Some(&RefereeState::from_referee(referees.last().unwrap()))
+            );
+            for interval in timeline.intervals() {
+                prop_assert_eq!(timeline.state_at(interval.start), Some(&interval.state));
+                if interval.end > interval.start {
+                    prop_assert_eq!(timeline.state_at(interval.end - 1), Some(&interval.state));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn unchanged_state_extends_interval() {
+        let timeline = example_timeline();
+        let first = &timeline.intervals()[0];
+        assert_eq!((first.start, first.end), (100, 200));
+        assert_eq!(timeline.intervals().len(), 7);
+        assert!(timeline.is_running_at(120));
+        assert!(!timeline.is_running_at(220));
+    }
+
+    #[test]
+    fn goals_from_score() {
+        let goals = example_timeline().goals();
+        assert_eq!(
+            goals,
+            vec![Goal {
+                timestamp: 250,
+                team: TeamColor::Blue,
+                yellow_score: 0,
+                blue_score: 1,
+            }]
+        );
+    }
+
+    #[test]
+    fn free_kicks_for_team() {
+        let timeline = example_timeline();
+        let free_kicks: Vec<_> = timeline
+            .free_kicks(TeamColor::Blue)
+            .iter()
+            .map(|interval| (interval.start, interval.end))
+            .collect();
+        assert_eq!(free_kicks, vec![(300, 400), (500, 600)]);
+
+        // the goal changed the state but not the command
+        let stop = &timeline.commands()[1];
+        assert_eq!((stop.start, stop.end), (200, 300));
+        assert_eq!(stop.state.blue.score, 0);
+    }
+}
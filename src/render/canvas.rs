@@ -0,0 +1,43 @@
+use super::color::Color;
+use super::view::View;
+use crate::geometry::Point;
+use std::f32::consts::PI;
+
+// Something that can be drawn on in field coordinates. Positions and
+// sizes are in mm and mapped to the image by the canvas' view.
+pub trait Canvas {
+    fn view(&self) -> &View;
+
+    // Fills the whole image
+    fn clear(&mut self, color: Color);
+
+    // Axis-aligned rectangle between two corners
+    fn fill_rect(&mut self, corner1: Point, corner2: Point, color: Color);
+
+    fn fill_circle(&mut self, center: Point, radius: f32, color: Color);
+
+    fn line(&mut self, p1: Point, p2: Point, width: f32, color: Color);
+
+    // Connected line segments
+    fn polyline(&mut self, points: &[Point], width: f32, color: Color) {
+        for segment in points.windows(2) {
+            self.line(segment[0], segment[1], width, color);
+        }
+    }
+
+    // Counter-clockwise from angle `a1` to `a2` in radians
+    fn arc(&mut self, center: Point, radius: f32, a1: f32, a2: f32, width: f32, color: Color) {
+        let mut a2 = a2;
+        while a2 <= a1 {
+            a2 += 2.0 * PI;
+        }
+        let num_segments = ((a2 - a1) / (PI / 32.0)).ceil().max(1.0) as usize;
+        let points: Vec<Point> = (0..=num_segments)
+            .map(|i| {
+                let angle = a1 + (a2 - a1) * i as f32 / num_segments as f32;
+                center + Point::new(angle.cos(), angle.sin()) * radius
+            })
+            .collect();
+        self.polyline(&points, width, color);
+    }
+}
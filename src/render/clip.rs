@@ -0,0 +1,200 @@
+use super::color::Color;
+use super::image::RenderError;
+use super::raster::RasterCanvas;
+use super::scene::{draw_field, draw_path, draw_world_frame};
+use super::trajectory::ObjectPath;
+use super::view::View;
+use crate::geometry::{Field, Point};
+use crate::protos::log_labeler_data::LabelerFrameGroup;
+use crate::vision::{TeamColor, WorldFrame, WorldFrameConfig};
+use gif::SetParameter;
+use std::convert::TryFrom;
+use std::io::Write;
+
+// GIF delays are in hundredths of a second and most viewers show
+// shorter delays much slower, so frames last at least this long
+const MIN_GIF_DELAY: u16 = 2;
+// delay of the last frame, which has no next frame to measure against
+const LAST_FRAME_DELAY: u16 = 100;
+
+#[derive(Debug, Clone)]
+pub struct ClipConfig {
+    pub world_frame: WorldFrameConfig,
+    // draw the ball path since the start of the clip
+    pub ball_trail: bool,
+    // robot to draw with a white outline
+    pub highlight: Option<(TeamColor, u32)>,
+}
+
+impl Default for ClipConfig {
+    fn default() -> ClipConfig {
+        ClipConfig {
+            world_frame: WorldFrameConfig::default(),
+            ball_trail: true,
+            highlight: None,
+        }
+    }
+}
+
+// Newest receiver timestamp (ns) of the frames in the group
+pub fn frame_group_timestamp(frame_group: &LabelerFrameGroup) -> Option<u64> {
+    frame_group
+        .get_frames()
+        .iter()
+        .map(|frame| frame.get_timestamp())
+        .max()
+}
+
+// Draws consecutive frame groups of labeler data as images
+#[derive(Debug, Clone)]
+pub struct ClipRenderer {
+    field: Field,
+    view: View,
+    config: ClipConfig,
+    ball_trail: ObjectPath,
+}
+
+impl ClipRenderer {
+    pub fn new(field: Field, view: View, config: ClipConfig) -> ClipRenderer {
+        ClipRenderer {
+            field,
+            view,
+            config,
+            ball_trail: ObjectPath::default(),
+        }
+    }
+
+    // Frame groups without detections only show the field and the
+    // trail so far
+    pub fn render(&mut self, frame_group: &LabelerFrameGroup) -> RasterCanvas {
+        let world_frame = WorldFrame::from_frame_group(frame_group, &self.config.world_frame);
+        if let Some(ref world_frame) = world_frame {
+            if let Some(ball) = world_frame.ball() {
+                self.ball_trail
+                    .add(world_frame.t_capture, Point::new(ball.x, ball.y));
+            }
+        }
+
+        let mut canvas = RasterCanvas::new(self.view);
+        draw_field(&mut canvas, &self.field);
+        if self.config.ball_trail {
+            draw_path(
+                &mut canvas,
+                &self.ball_trail.segments,
+                Color::BALL.with_alpha(0.6),
+            );
+        }
+        if let Some(ref world_frame) = world_frame {
+            let highlight = self.config.highlight;
+            draw_world_frame(&mut canvas, world_frame, |robot| {
+                highlight == Some((robot.team, robot.robot_id))
+            });
+        }
+        canvas
+    }
+}
+
+// Writes rendered frames as a looping animated GIF. Each frame is shown
+// until the timestamp of the next one, so a frame is only written once
+// the next is added or the writer is finished.
+pub struct GifWriter<W: Write> {
+    encoder: gif::Encoder<W>,
+    width: u16,
+    height: u16,
+    // playback speed, 2.0 plays twice as fast as recorded
+    speed: f64,
+    pending: Option<(Vec<u8>, u64)>,
+}
+
+impl<W: Write> GifWriter<W> {
+    pub fn new(writer: W, view: &View, speed: f64) -> Result<GifWriter<W>, RenderError> {
+        let too_large = || RenderError::TooLargeForGif {
+            width: view.width,
+            height: view.height,
+        };
+        let width = u16::try_from(view.width).map_err(|_| too_large())?;
+        let height = u16::try_from(view.height).map_err(|_| too_large())?;
+        let mut encoder = gif::Encoder::new(writer, width, height, &[])?;
+        encoder.set(gif::Repeat::Infinite)?;
+
+        Ok(GifWriter {
+            encoder,
+            width,
+            height,
+            speed,
+            pending: None,
+        })
+    }
+
+    fn write_frame(&mut self, mut pixels: Vec<u8>, delay: u16) -> Result<(), RenderError> {
+        let mut frame = gif::Frame::from_rgba_speed(self.width, self.height, &mut pixels, 10);
+        frame.delay = delay.max(MIN_GIF_DELAY);
+        self.encoder.write_frame(&frame)?;
+
+        Ok(())
+    }
+
+    // `timestamp` is the receiver timestamp (ns) of the frame
+    pub fn add_frame(&mut self, canvas: &RasterCanvas, timestamp: u64) -> Result<(), RenderError> {
+        if let Some((pixels, last_timestamp)) = self.pending.take() {
+            let seconds = timestamp.saturating_sub(last_timestamp) as f64 / 1e9 / self.speed;
+            let delay = (seconds * 100.0).round().min(f64::from(u16::max_value())) as u16;
+            self.write_frame(pixels, delay)?;
+        }
+        self.pending = Some((canvas.pixels().to_vec(), timestamp));
+
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<(), RenderError> {
+        if let Some((pixels, _)) = self.pending.take() {
+            self.write_frame(pixels, LAST_FRAME_DELAY)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::Division;
+    use crate::protos::log_labeler_data::LabelerFrame;
+
+    #[test]
+    fn writes_animated_gif() {
+        let field = Field::new(Division::B);
+        let view = View::new(&field, 120);
+        let mut renderer = ClipRenderer::new(field, view, ClipConfig::default());
+
+        let mut buffer = vec![];
+        {
+            let mut writer = GifWriter::new(&mut buffer, &view, 1.0).unwrap();
+            for index in 0..3u64 {
+                let mut frame = LabelerFrame::new();
+                frame.set_timestamp(index * 16_000_000);
+                let mut frame_group = LabelerFrameGroup::new();
+                frame_group.mut_frames().push(frame);
+
+                let canvas = renderer.render(&frame_group);
+                assert_eq!(canvas.width(), 120);
+                writer
+                    .add_frame(&canvas, frame_group_timestamp(&frame_group).unwrap())
+                    .unwrap();
+            }
+            writer.finish().unwrap();
+        }
+        assert!(buffer.starts_with(b"GIF89a"));
+        // trailer
+        assert_eq!(buffer.last(), Some(&0x3b));
+    }
+
+    #[test]
+    fn too_wide_for_gif() {
+        let view = View::new(&Field::new(Division::B), 70000);
+        match GifWriter::new(vec![], &view, 1.0) {
+            Err(RenderError::TooLargeForGif { width: 70000, .. }) => {}
+            result => panic!("Unexpected result {:?}", result.map(|_| ())),
+        }
+    }
+}
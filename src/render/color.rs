@@ -0,0 +1,69 @@
+use crate::vision::TeamColor;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl Color {
+    pub const FIELD: Color = Color::rgb(0, 110, 40);
+    pub const LINES: Color = Color::rgb(255, 255, 255);
+    pub const GOALS: Color = Color::rgb(40, 40, 40);
+    pub const BALL: Color = Color::rgb(255, 128, 0);
+    pub const ROBOT_OUTLINE: Color = Color::rgb(20, 20, 20);
+    pub const YELLOW_TEAM: Color = Color::rgb(255, 220, 0);
+    pub const BLUE_TEAM: Color = Color::rgb(40, 100, 255);
+
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Color {
+        Color { r, g, b, a: 255 }
+    }
+
+    pub const fn rgba(r: u8, g: u8, b: u8, a: u8) -> Color {
+        Color { r, g, b, a }
+    }
+
+    pub fn team(team: TeamColor) -> Color {
+        match team {
+            TeamColor::Yellow => Color::YELLOW_TEAM,
+            TeamColor::Blue => Color::BLUE_TEAM,
+        }
+    }
+
+    // `alpha` in [0, 1] multiplies the current alpha
+    pub fn with_alpha(self, alpha: f32) -> Color {
+        Color {
+            a: (f32::from(self.a) * alpha.max(0.0).min(1.0)).round() as u8,
+            ..self
+        }
+    }
+
+    // Blue over yellow to red for `t` in [0, 1], used for heatmaps
+    pub fn heat(t: f32) -> Color {
+        let t = t.max(0.0).min(1.0);
+        let stops = [
+            (0.0, (0.0, 0.0, 255.0)),
+            (0.5, (255.0, 255.0, 0.0)),
+            (1.0, (255.0, 0.0, 0.0)),
+        ];
+        let (start, end) = if t <= 0.5 {
+            (stops[0], stops[1])
+        } else {
+            (stops[1], stops[2])
+        };
+        let s = (t - start.0) / (end.0 - start.0);
+        let mix = |a: f32, b: f32| (a + (b - a) * s).round() as u8;
+        Color::rgb(
+            mix((start.1).0, (end.1).0),
+            mix((start.1).1, (end.1).1),
+            mix((start.1).2, (end.1).2),
+        )
+    }
+
+    // #rrggbb, without the alpha
+    pub fn to_hex(self) -> String {
+        format!("#{:02x}{:02x}{:02x}", self.r, self.g, self.b)
+    }
+}
@@ -0,0 +1,118 @@
+use super::canvas::Canvas;
+use super::color::Color;
+use crate::geometry::{Field, Point};
+
+// Occupancy of the field, counted in square cells
+#[derive(Debug, Clone, PartialEq)]
+pub struct Heatmap {
+    // bottom left corner of the first cell
+    min: Point,
+    cell_size: f32,
+    columns: usize,
+    rows: usize,
+    // row by row from the bottom
+    values: Vec<f32>,
+}
+
+impl Heatmap {
+    // Covers the field and its boundary with cells of `cell_size` mm
+    pub fn new(field: &Field, cell_size: f32) -> Heatmap {
+        let half_length = field.half_length() + field.boundary_width;
+        let half_width = field.half_width() + field.boundary_width;
+        let columns = (2.0 * half_length / cell_size).ceil().max(1.0) as usize;
+        let rows = (2.0 * half_width / cell_size).ceil().max(1.0) as usize;
+
+        Heatmap {
+            min: Point::new(-half_length, -half_width),
+            cell_size,
+            columns,
+            rows,
+            values: vec![0.0; columns * rows],
+        }
+    }
+
+    fn cell_index(&self, point: Point) -> Option<usize> {
+        let column = ((point.x - self.min.x) / self.cell_size).floor();
+        let row = ((point.y - self.min.y) / self.cell_size).floor();
+        if column < 0.0 || row < 0.0 {
+            return None;
+        }
+        let (column, row) = (column as usize, row as usize);
+        if column >= self.columns || row >= self.rows {
+            return None;
+        }
+        Some(row * self.columns + column)
+    }
+
+    // Points outside of the cells are ignored
+    pub fn add(&mut self, point: Point, weight: f32) {
+        if let Some(index) = self.cell_index(point) {
+            self.values[index] += weight;
+        }
+    }
+
+    pub fn value_at(&self, point: Point) -> f32 {
+        self.cell_index(point)
+            .map_or(0.0, |index| self.values[index])
+    }
+
+    pub fn max(&self) -> f32 {
+        self.values.iter().cloned().fold(0.0, f32::max)
+    }
+
+    pub fn total(&self) -> f32 {
+        self.values.iter().sum()
+    }
+
+    // Cells are colored relative to the fullest cell, empty cells are
+    // left out
+    pub fn draw(&self, canvas: &mut dyn Canvas, opacity: f32) {
+        let max = self.max();
+        if max <= 0.0 {
+            return;
+        }
+        for (index, value) in self.values.iter().enumerate() {
+            if *value <= 0.0 {
+                continue;
+            }
+            let corner = self.min
+                + Point::new(
+                    (index % self.columns) as f32 * self.cell_size,
+                    (index / self.columns) as f32 * self.cell_size,
+                );
+            let t = value / max;
+            canvas.fill_rect(
+                corner,
+                corner + Point::new(self.cell_size, self.cell_size),
+                Color::heat(t).with_alpha(opacity * (0.3 + 0.7 * t)),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::Division;
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn points_on_field_are_counted(x in -6000.0f32..6000.0, y in -4500.0f32..4500.0) {
+            let mut heatmap = Heatmap::new(&Field::new(Division::A), 250.0);
+            heatmap.add(Point::new(x, y), 2.0);
+            prop_assert_eq!(heatmap.value_at(Point::new(x, y)), 2.0);
+            prop_assert_eq!(heatmap.total(), 2.0);
+        }
+    }
+
+    #[test]
+    fn points_outside_are_ignored() {
+        let mut heatmap = Heatmap::new(&Field::new(Division::B), 250.0);
+        heatmap.add(Point::new(10000.0, 0.0), 1.0);
+        heatmap.add(Point::new(0.0, 0.0), 1.0);
+        heatmap.add(Point::new(100.0, 100.0), 1.0);
+        assert_eq!(heatmap.total(), 2.0);
+        assert_eq!(heatmap.max(), 2.0);
+    }
+}
@@ -0,0 +1,95 @@
+use super::canvas::Canvas;
+use super::raster::RasterCanvas;
+use super::svg::SvgCanvas;
+use super::view::View;
+use std::io;
+use std::path::Path;
+
+#[derive(Debug, Fail)]
+pub enum RenderError {
+    #[fail(display = "{}", _0)]
+    Io(#[fail(cause)] io::Error),
+    #[fail(display = "{}", _0)]
+    Png(#[fail(cause)] png::EncodingError),
+    #[fail(display = "Unknown image format of {}, use .png or .svg", path)]
+    UnknownFormat { path: String },
+    #[fail(
+        display = "Image of {}x{} pixels is too large, GIFs are at most 65535 pixels wide and high",
+        width, height
+    )]
+    TooLargeForGif { width: u32, height: u32 },
+}
+
+impl From<io::Error> for RenderError {
+    fn from(error: io::Error) -> Self {
+        RenderError::Io(error)
+    }
+}
+
+impl From<png::EncodingError> for RenderError {
+    fn from(error: png::EncodingError) -> Self {
+        match error {
+            png::EncodingError::IoError(error) => RenderError::Io(error),
+            error => RenderError::Png(error),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    Png,
+    Svg,
+}
+
+impl ImageFormat {
+    // From the file extension, ignoring case
+    pub fn from_path(path: &Path) -> Option<ImageFormat> {
+        let extension = path.extension()?.to_str()?.to_lowercase();
+        match extension.as_str() {
+            "png" => Some(ImageFormat::Png),
+            "svg" => Some(ImageFormat::Svg),
+            _ => None,
+        }
+    }
+}
+
+// Draws an image with `draw` and saves it in the format given by the
+// extension of `path`
+pub fn save_image<F>(path: &Path, view: View, draw: F) -> Result<(), RenderError>
+where
+    F: FnOnce(&mut dyn Canvas),
+{
+    match ImageFormat::from_path(path) {
+        Some(ImageFormat::Png) => {
+            let mut canvas = RasterCanvas::new(view);
+            draw(&mut canvas);
+            canvas.save_png(path)
+        }
+        Some(ImageFormat::Svg) => {
+            let mut canvas = SvgCanvas::new(view);
+            draw(&mut canvas);
+            Ok(canvas.save_svg(path)?)
+        }
+        None => Err(RenderError::UnknownFormat {
+            path: path.display().to_string(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_from_extension() {
+        assert_eq!(
+            ImageFormat::from_path(Path::new("pass_1.PNG")),
+            Some(ImageFormat::Png)
+        );
+        assert_eq!(
+            ImageFormat::from_path(Path::new("out/heatmap.svg")),
+            Some(ImageFormat::Svg)
+        );
+        assert_eq!(ImageFormat::from_path(Path::new("heatmap")), None);
+    }
+}
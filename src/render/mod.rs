@@ -0,0 +1,24 @@
+pub mod canvas;
+pub mod clip;
+pub mod color;
+pub mod heatmap;
+pub mod image;
+pub mod raster;
+pub mod scene;
+pub mod svg;
+pub mod text;
+pub mod trajectory;
+pub mod view;
+
+pub use canvas::Canvas;
+pub use clip::{frame_group_timestamp, ClipConfig, ClipRenderer, GifWriter};
+pub use color::Color;
+pub use heatmap::Heatmap;
+pub use image::{save_image, ImageFormat, RenderError};
+pub use raster::RasterCanvas;
+pub use svg::SvgCanvas;
+pub use text::{Cell, TextCanvas};
+pub use trajectory::{
+    collect_trajectories, HeatmapSource, ObjectPath, Trajectories, TrajectoryFilter,
+};
+pub use view::View;
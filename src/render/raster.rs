@@ -0,0 +1,239 @@
+use super::canvas::Canvas;
+use super::color::Color;
+use super::image::RenderError;
+use super::view::View;
+use crate::geometry::{Pixel, Point};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+type RenderResult<T> = Result<T, RenderError>;
+
+// An RGBA image drawn in software. Edges are anti-aliased by how much
+// of a pixel a shape covers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RasterCanvas {
+    view: View,
+    // rows from top to bottom, 4 bytes per pixel
+    pixels: Vec<u8>,
+}
+
+impl RasterCanvas {
+    pub fn new(view: View) -> RasterCanvas {
+        RasterCanvas {
+            view,
+            pixels: vec![0; view.width as usize * view.height as usize * 4],
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.view.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.view.height
+    }
+
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> Color {
+        let index = (y as usize * self.view.width as usize + x as usize) * 4;
+        Color::rgba(
+            self.pixels[index],
+            self.pixels[index + 1],
+            self.pixels[index + 2],
+            self.pixels[index + 3],
+        )
+    }
+
+    // Draws `color` over the pixel with `coverage` in [0, 1]
+    fn blend(&mut self, x: i64, y: i64, color: Color, coverage: f32) {
+        if x < 0 || y < 0 || x >= i64::from(self.view.width) || y >= i64::from(self.view.height) {
+            return;
+        }
+        let alpha = f32::from(color.a) / 255.0 * coverage.max(0.0).min(1.0);
+        if alpha <= 0.0 {
+            return;
+        }
+
+        let index = (y as usize * self.view.width as usize + x as usize) * 4;
+        let pixel = &mut self.pixels[index..index + 4];
+        let dst_alpha = f32::from(pixel[3]) / 255.0;
+        let out_alpha = alpha + dst_alpha * (1.0 - alpha);
+        for (channel, src) in pixel.iter_mut().zip(&[color.r, color.g, color.b]) {
+            let dst = f32::from(*channel) * dst_alpha;
+            let out = (f32::from(*src) * alpha + dst * (1.0 - alpha)) / out_alpha;
+            *channel = out.round() as u8;
+        }
+        pixel[3] = (out_alpha * 255.0).round() as u8;
+    }
+
+    // Calls `coverage` for every pixel center in the bounding box of
+    // the pixel rectangle and blends in the result
+    fn fill_shape<F>(&mut self, min: Pixel, max: Pixel, color: Color, coverage: F)
+    where
+        F: Fn(Pixel) -> f32,
+    {
+        let x_range =
+            (min.x.floor().max(0.0) as i64)..=(max.x.ceil().min(self.view.width as f32) as i64);
+        let y_range =
+            (min.y.floor().max(0.0) as i64)..=(max.y.ceil().min(self.view.height as f32) as i64);
+        for y in y_range {
+            for x in x_range.clone() {
+                let center = Pixel::new(x as f32 + 0.5, y as f32 + 0.5);
+                self.blend(x, y, color, coverage(center));
+            }
+        }
+    }
+
+    pub fn write_png<W: Write>(&self, writer: W) -> RenderResult<()> {
+        let mut encoder = png::Encoder::new(writer, self.view.width, self.view.height);
+        encoder.set_color(png::ColorType::RGBA);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&self.pixels)?;
+
+        Ok(())
+    }
+
+    pub fn save_png(&self, path: &Path) -> RenderResult<()> {
+        self.write_png(BufWriter::new(File::create(path)?))
+    }
+}
+
+fn distance_to_segment(point: Pixel, p1: Pixel, p2: Pixel) -> f32 {
+    Point::new(point.x, point.y).distance_to_segment(Point::new(p1.x, p1.y), Point::new(p2.x, p2.y))
+}
+
+impl Canvas for RasterCanvas {
+    fn view(&self) -> &View {
+        &self.view
+    }
+
+    fn clear(&mut self, color: Color) {
+        for pixel in self.pixels.chunks_mut(4) {
+            pixel.copy_from_slice(&[color.r, color.g, color.b, color.a]);
+        }
+    }
+
+    fn fill_rect(&mut self, corner1: Point, corner2: Point, color: Color) {
+        let p1 = self.view.to_pixel(corner1);
+        let p2 = self.view.to_pixel(corner2);
+        let min = Pixel::new(p1.x.min(p2.x), p1.y.min(p2.y));
+        let max = Pixel::new(p1.x.max(p2.x), p1.y.max(p2.y));
+        // coverage of the pixel along each axis
+        let overlap = |center: f32, low: f32, high: f32| {
+            ((center + 0.5).min(high) - (center - 0.5).max(low)).max(0.0)
+        };
+        self.fill_shape(min, max, color, |center| {
+            overlap(center.x, min.x, max.x) * overlap(center.y, min.y, max.y)
+        });
+    }
+
+    fn fill_circle(&mut self, center: Point, radius: f32, color: Color) {
+        let center = self.view.to_pixel(center);
+        let radius = self.view.to_pixels(radius).max(0.5);
+        let min = Pixel::new(center.x - radius - 1.0, center.y - radius - 1.0);
+        let max = Pixel::new(center.x + radius + 1.0, center.y + radius + 1.0);
+        self.fill_shape(min, max, color, |pixel| {
+            let distance = (pixel.x - center.x).hypot(pixel.y - center.y);
+            radius - distance + 0.5
+        });
+    }
+
+    // Lines have round caps, so connected segments join without gaps.
+    // Lines are at least a pixel wide. Lines thinner than 2 pixels are
+    // moved to pixel centers, otherwise a line on a pixel border would
+    // only half cover the pixels on both sides.
+    fn line(&mut self, p1: Point, p2: Point, width: f32, color: Color) {
+        let mut p1 = self.view.to_pixel(p1);
+        let mut p2 = self.view.to_pixel(p2);
+        let half_width = (self.view.to_pixels(width) / 2.0).max(0.5);
+        if half_width < 1.0 {
+            let snap = |pixel: Pixel| Pixel::new(pixel.x.floor() + 0.5, pixel.y.floor() + 0.5);
+            p1 = snap(p1);
+            p2 = snap(p2);
+        }
+        let min = Pixel::new(
+            p1.x.min(p2.x) - half_width - 1.0,
+            p1.y.min(p2.y) - half_width - 1.0,
+        );
+        let max = Pixel::new(
+            p1.x.max(p2.x) + half_width + 1.0,
+            p1.y.max(p2.y) + half_width + 1.0,
+        );
+        self.fill_shape(min, max, color, |pixel| {
+            half_width - distance_to_segment(pixel, p1, p2) + 0.5
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::{Division, Field};
+
+    fn canvas() -> RasterCanvas {
+        // 1 pixel per 10 mm
+        RasterCanvas::new(View::of_area(
+            Point::new(0.0, 0.0),
+            Point::new(1000.0, 500.0),
+            100,
+        ))
+    }
+
+    #[test]
+    fn filled_shapes() {
+        let mut canvas = canvas();
+        canvas.clear(Color::FIELD);
+        canvas.fill_rect(Point::new(0.0, 0.0), Point::new(200.0, 200.0), Color::LINES);
+        canvas.fill_circle(Point::new(700.0, 250.0), 100.0, Color::BALL);
+
+        // y is flipped, so the rectangle is at the bottom left
+        assert_eq!(canvas.pixel(5, 45), Color::LINES);
+        assert_eq!(canvas.pixel(5, 5), Color::FIELD);
+        assert_eq!(canvas.pixel(70, 25), Color::BALL);
+        assert_eq!(canvas.pixel(70, 10), Color::FIELD);
+    }
+
+    #[test]
+    fn translucent_line() {
+        let mut canvas = canvas();
+        canvas.clear(Color::rgb(0, 0, 0));
+        canvas.line(
+            Point::new(100.0, 250.0),
+            Point::new(900.0, 250.0),
+            50.0,
+            Color::LINES.with_alpha(0.5),
+        );
+        let pixel = canvas.pixel(50, 24);
+        assert!(pixel.r >= 127 && pixel.r <= 128);
+        assert_eq!(pixel.a, 255);
+        assert_eq!(canvas.pixel(50, 10), Color::rgb(0, 0, 0));
+    }
+
+    #[test]
+    fn thin_line_covers_whole_pixels() {
+        let mut canvas = canvas();
+        canvas.clear(Color::rgb(0, 0, 0));
+        // 1 pixel wide on the border between two pixel columns
+        let (p1, p2) = (Point::new(500.0, 100.0), Point::new(500.0, 400.0));
+        canvas.line(p1, p2, 10.0, Color::LINES);
+
+        let x = canvas.view.to_pixel(p1).x.floor() as u32;
+        assert_eq!(canvas.pixel(x, 25), Color::LINES);
+        assert_eq!(canvas.pixel(x - 1, 25), Color::rgb(0, 0, 0));
+        assert_eq!(canvas.pixel(x + 1, 25), Color::rgb(0, 0, 0));
+    }
+
+    #[test]
+    fn writes_png() {
+        let mut canvas = RasterCanvas::new(View::new(&Field::new(Division::B), 200));
+        canvas.clear(Color::FIELD);
+        let mut buffer = vec![];
+        canvas.write_png(&mut buffer).unwrap();
+        assert_eq!(&buffer[1..4], b"PNG");
+    }
+}
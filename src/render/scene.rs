@@ -0,0 +1,118 @@
+use super::canvas::Canvas;
+use super::color::Color;
+use crate::geometry::{Field, FieldSide, Point};
+use crate::vision::{RobotState, WorldFrame};
+
+// Drawing of the field and the objects on it. Sizes are in mm.
+
+pub const ROBOT_RADIUS: f32 = 90.0;
+pub const BALL_RADIUS: f32 = 21.5;
+pub const PATH_WIDTH: f32 = 30.0;
+
+pub fn draw_field(canvas: &mut dyn Canvas, field: &Field) {
+    canvas.clear(Color::FIELD);
+
+    for line in &field.lines {
+        canvas.line(line.p1, line.p2, line.thickness, Color::LINES);
+    }
+    for arc in &field.arcs {
+        canvas.arc(
+            arc.center,
+            arc.radius,
+            arc.a1,
+            arc.a2,
+            arc.thickness,
+            Color::LINES,
+        );
+    }
+
+    for side in &[FieldSide::Left, FieldSide::Right] {
+        let (post1, post2) = field.goal_posts(*side);
+        let depth = Point::new(side.sign() * field.goal_depth, 0.0);
+        canvas.polyline(
+            &[post1, post1 + depth, post2 + depth, post2],
+            2.0 * field.line_thickness,
+            Color::GOALS,
+        );
+    }
+}
+
+pub fn draw_ball(canvas: &mut dyn Canvas, position: Point) {
+    canvas.fill_circle(position, BALL_RADIUS, Color::BALL);
+}
+
+// A circle in the team color with a line towards the front of the
+// robot, if its orientation is known
+pub fn draw_robot(canvas: &mut dyn Canvas, robot: &RobotState, highlight: bool) {
+    let center = Point::new(robot.x, robot.y);
+    let outline = if highlight {
+        Color::LINES
+    } else {
+        Color::ROBOT_OUTLINE
+    };
+    canvas.fill_circle(center, ROBOT_RADIUS, outline);
+    canvas.fill_circle(center, ROBOT_RADIUS - 15.0, Color::team(robot.team));
+    if let Some(orientation) = robot.orientation {
+        let front = center + Point::new(orientation.cos(), orientation.sin()) * ROBOT_RADIUS;
+        canvas.line(center, front, 20.0, Color::ROBOT_OUTLINE);
+    }
+}
+
+// Robots are drawn below the ball. `highlight` picks robots to draw
+// with a white outline.
+pub fn draw_world_frame<F>(canvas: &mut dyn Canvas, world_frame: &WorldFrame, highlight: F)
+where
+    F: Fn(&RobotState) -> bool,
+{
+    for robot in &world_frame.robots {
+        draw_robot(canvas, robot, highlight(robot));
+    }
+    if let Some(ball) = world_frame.ball() {
+        draw_ball(canvas, Point::new(ball.x, ball.y));
+    }
+}
+
+pub fn draw_path(canvas: &mut dyn Canvas, segments: &[Vec<Point>], color: Color) {
+    for segment in segments {
+        canvas.polyline(segment, PATH_WIDTH, color);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::Division;
+    use crate::render::raster::RasterCanvas;
+    use crate::render::view::View;
+    use crate::vision::TeamColor;
+
+    #[test]
+    fn field_and_robot() {
+        let field = Field::new(Division::B);
+        let view = View::new(&field, 960);
+        let mut canvas = RasterCanvas::new(view);
+        draw_field(&mut canvas, &field);
+        draw_robot(
+            &mut canvas,
+            &RobotState {
+                team: TeamColor::Blue,
+                robot_id: 0,
+                x: 1000.0,
+                y: 1000.0,
+                orientation: None,
+                confidence: 1.0,
+                cameras: vec![0],
+            },
+            false,
+        );
+
+        let pixel = |point: Point| {
+            let pixel = view.to_pixel(point);
+            canvas.pixel(pixel.x as u32, pixel.y as u32)
+        };
+        assert_eq!(pixel(Point::new(1000.0, 1000.0)), Color::BLUE_TEAM);
+        assert_eq!(pixel(Point::new(-1000.0, 1000.0)), Color::FIELD);
+        // the center line
+        assert_eq!(pixel(Point::new(0.0, 2000.0)), Color::LINES);
+    }
+}
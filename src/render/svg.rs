@@ -0,0 +1,162 @@
+use super::canvas::Canvas;
+use super::color::Color;
+use super::view::View;
+use crate::geometry::Point;
+use std::fmt::Write as FmtWrite;
+use std::fs::File;
+use std::io;
+use std::io::Write;
+use std::path::Path;
+
+// Collects the drawing as SVG elements in image pixel coordinates
+#[derive(Debug, Clone, PartialEq)]
+pub struct SvgCanvas {
+    view: View,
+    elements: String,
+}
+
+// fill or stroke attributes of a color, with the opacity only if it
+// is not opaque
+fn paint(attribute: &str, color: Color) -> String {
+    if color.a == 255 {
+        format!("{}=\"{}\"", attribute, color.to_hex())
+    } else {
+        format!(
+            "{}=\"{}\" {}-opacity=\"{:.3}\"",
+            attribute,
+            color.to_hex(),
+            attribute,
+            f32::from(color.a) / 255.0
+        )
+    }
+}
+
+impl SvgCanvas {
+    pub fn new(view: View) -> SvgCanvas {
+        SvgCanvas {
+            view,
+            elements: String::new(),
+        }
+    }
+
+    pub fn to_svg(&self) -> String {
+        format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" \
+             viewBox=\"0 0 {width} {height}\">\n{}</svg>\n",
+            self.elements,
+            width = self.view.width,
+            height = self.view.height
+        )
+    }
+
+    pub fn write_svg<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(self.to_svg().as_bytes())
+    }
+
+    pub fn save_svg(&self, path: &Path) -> io::Result<()> {
+        self.write_svg(File::create(path)?)
+    }
+}
+
+impl Canvas for SvgCanvas {
+    fn view(&self) -> &View {
+        &self.view
+    }
+
+    // Earlier elements are hidden anyway, so they are dropped
+    fn clear(&mut self, color: Color) {
+        self.elements.clear();
+        writeln!(
+            self.elements,
+            "<rect width=\"100%\" height=\"100%\" {}/>",
+            paint("fill", color)
+        )
+        .unwrap();
+    }
+
+    fn fill_rect(&mut self, corner1: Point, corner2: Point, color: Color) {
+        let p1 = self.view.to_pixel(corner1);
+        let p2 = self.view.to_pixel(corner2);
+        writeln!(
+            self.elements,
+            "<rect x=\"{:.2}\" y=\"{:.2}\" width=\"{:.2}\" height=\"{:.2}\" {}/>",
+            p1.x.min(p2.x),
+            p1.y.min(p2.y),
+            (p1.x - p2.x).abs(),
+            (p1.y - p2.y).abs(),
+            paint("fill", color)
+        )
+        .unwrap();
+    }
+
+    fn fill_circle(&mut self, center: Point, radius: f32, color: Color) {
+        let center = self.view.to_pixel(center);
+        writeln!(
+            self.elements,
+            "<circle cx=\"{:.2}\" cy=\"{:.2}\" r=\"{:.2}\" {}/>",
+            center.x,
+            center.y,
+            self.view.to_pixels(radius),
+            paint("fill", color)
+        )
+        .unwrap();
+    }
+
+    fn line(&mut self, p1: Point, p2: Point, width: f32, color: Color) {
+        self.polyline(&[p1, p2], width, color);
+    }
+
+    // A single element, so translucent lines do not darken where the
+    // segments meet
+    fn polyline(&mut self, points: &[Point], width: f32, color: Color) {
+        if points.len() < 2 {
+            return;
+        }
+        let points: Vec<String> = points
+            .iter()
+            .map(|point| {
+                let pixel = self.view.to_pixel(*point);
+                format!("{:.2},{:.2}", pixel.x, pixel.y)
+            })
+            .collect();
+        writeln!(
+            self.elements,
+            "<polyline points=\"{}\" fill=\"none\" stroke-width=\"{:.2}\" \
+             stroke-linecap=\"round\" stroke-linejoin=\"round\" {}/>",
+            points.join(" "),
+            self.view.to_pixels(width).max(1.0),
+            paint("stroke", color)
+        )
+        .unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn elements_in_pixels() {
+        let mut canvas = SvgCanvas::new(View::of_area(
+            Point::new(0.0, 0.0),
+            Point::new(1000.0, 500.0),
+            100,
+        ));
+        canvas.clear(Color::FIELD);
+        canvas.fill_circle(Point::new(700.0, 250.0), 100.0, Color::BALL.with_alpha(0.5));
+        canvas.line(
+            Point::new(0.0, 0.0),
+            Point::new(1000.0, 500.0),
+            10.0,
+            Color::LINES,
+        );
+
+        let svg = canvas.to_svg();
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.contains("viewBox=\"0 0 100 50\""));
+        assert!(svg.contains(
+            "<circle cx=\"70.00\" cy=\"25.00\" r=\"10.00\" fill=\"#ff8000\" fill-opacity=\"0.502\"/>"
+        ));
+        assert!(svg.contains("points=\"0.00,50.00 100.00,0.00\""));
+    }
+}
@@ -0,0 +1,226 @@
+use super::canvas::Canvas;
+use super::color::Color;
+use super::view::View;
+use crate::geometry::{Field, Pixel, Point};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cell {
+    pub ch: char,
+    pub fg: Color,
+    pub bg: Color,
+}
+
+// Draws on a grid of terminal cells. Cells are about twice as tall as
+// they are wide, so the view has two pixels per cell vertically. Lines
+// are drawn with ASCII characters following their direction, filled
+// shapes set the background of the cells.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextCanvas {
+    view: View,
+    columns: u32,
+    rows: u32,
+    // row by row from the top
+    cells: Vec<Cell>,
+}
+
+// Character of a line going `dx` cells right and `dy` cells down
+fn line_char(dx: f32, dy: f32) -> char {
+    if dy.abs() < 0.4 * dx.abs() {
+        '-'
+    } else if dx.abs() < 0.4 * dy.abs() {
+        '|'
+    } else if (dx < 0.0) == (dy < 0.0) {
+        '\\'
+    } else {
+        '/'
+    }
+}
+
+impl TextCanvas {
+    // The whole field including the boundary, as large as fits into
+    // `columns` x `rows` cells
+    pub fn new(field: &Field, columns: u32, rows: u32) -> TextCanvas {
+        let half_length = field.half_length() + field.boundary_width;
+        let half_width = field.half_width() + field.boundary_width;
+        let fitting_width = (2.0 * rows as f32 * half_length / half_width).floor() as u32;
+        let view = View::new(field, columns.min(fitting_width).max(1));
+        TextCanvas::with_view(view)
+    }
+
+    pub fn with_view(view: View) -> TextCanvas {
+        let columns = view.width;
+        let rows = (view.height + 1) / 2;
+        TextCanvas {
+            view,
+            columns,
+            rows,
+            cells: vec![
+                Cell {
+                    ch: ' ',
+                    fg: Color::LINES,
+                    bg: Color::FIELD,
+                };
+                columns as usize * rows as usize
+            ],
+        }
+    }
+
+    pub fn columns(&self) -> u32 {
+        self.columns
+    }
+
+    pub fn rows(&self) -> u32 {
+        self.rows
+    }
+
+    pub fn row(&self, row: u32) -> &[Cell] {
+        let start = row as usize * self.columns as usize;
+        &self.cells[start..start + self.columns as usize]
+    }
+
+    pub fn cell(&self, column: u32, row: u32) -> Cell {
+        self.cells[row as usize * self.columns as usize + column as usize]
+    }
+
+    fn cell_index(&self, column: i64, row: i64) -> Option<usize> {
+        if column < 0 || row < 0 || column >= i64::from(self.columns) || row >= i64::from(self.rows)
+        {
+            return None;
+        }
+        Some(row as usize * self.columns as usize + column as usize)
+    }
+
+    // Cell under a field point
+    fn cell_at(&self, point: Point) -> Option<usize> {
+        let pixel = self.view.to_pixel(point);
+        self.cell_index(pixel.x.floor() as i64, (pixel.y / 2.0).floor() as i64)
+    }
+
+    // Field point at the center of a cell
+    fn cell_center(&self, column: u32, row: u32) -> Point {
+        self.view
+            .to_field(Pixel::new(column as f32 + 0.5, 2.0 * row as f32 + 1.0))
+    }
+
+    // Writes `text` starting at the cell under `point`, keeping the
+    // background. Text running past the right edge is cut off.
+    pub fn text(&mut self, point: Point, text: &str, color: Color) {
+        let pixel = self.view.to_pixel(point);
+        let row = (pixel.y / 2.0).floor() as i64;
+        for (offset, ch) in text.chars().enumerate() {
+            let column = pixel.x.floor() as i64 + offset as i64;
+            if let Some(index) = self.cell_index(column, row) {
+                self.cells[index].ch = ch;
+                self.cells[index].fg = color;
+            }
+        }
+    }
+
+    // Sets the background of cells whose center passes `inside`. Shapes
+    // smaller than a cell still color the cell under `anchor`.
+    fn fill<F>(&mut self, anchor: Point, color: Color, inside: F)
+    where
+        F: Fn(Point) -> bool,
+    {
+        let mut is_filled = false;
+        for row in 0..self.rows {
+            for column in 0..self.columns {
+                if inside(self.cell_center(column, row)) {
+                    let index = (row * self.columns + column) as usize;
+                    self.cells[index].bg = color;
+                    is_filled = true;
+                }
+            }
+        }
+        if !is_filled {
+            if let Some(index) = self.cell_at(anchor) {
+                self.cells[index].bg = color;
+            }
+        }
+    }
+}
+
+impl Canvas for TextCanvas {
+    fn view(&self) -> &View {
+        &self.view
+    }
+
+    fn clear(&mut self, color: Color) {
+        for cell in &mut self.cells {
+            *cell = Cell {
+                ch: ' ',
+                fg: Color::LINES,
+                bg: color,
+            };
+        }
+    }
+
+    fn fill_rect(&mut self, corner1: Point, corner2: Point, color: Color) {
+        let min = Point::new(corner1.x.min(corner2.x), corner1.y.min(corner2.y));
+        let max = Point::new(corner1.x.max(corner2.x), corner1.y.max(corner2.y));
+        self.fill((min + max) * 0.5, color, |point| {
+            point.x >= min.x && point.x < max.x && point.y >= min.y && point.y < max.y
+        });
+    }
+
+    fn fill_circle(&mut self, center: Point, radius: f32, color: Color) {
+        self.fill(center, color, |point| point.distance(center) <= radius);
+    }
+
+    // Lines are one cell wide whatever their width
+    fn line(&mut self, p1: Point, p2: Point, _width: f32, color: Color) {
+        let pixel1 = self.view.to_pixel(p1);
+        let pixel2 = self.view.to_pixel(p2);
+        let dx = pixel2.x - pixel1.x;
+        let dy = (pixel2.y - pixel1.y) / 2.0;
+        let ch = line_char(dx, dy);
+
+        // two samples per cell, so no cell along the line is skipped
+        let num_steps = (2.0 * dx.abs().max(dy.abs())).ceil().max(1.0) as usize;
+        for step in 0..=num_steps {
+            let t = step as f32 / num_steps as f32;
+            if let Some(index) = self.cell_at(p1 + (p2 - p1) * t) {
+                self.cells[index].ch = ch;
+                self.cells[index].fg = color;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::Division;
+    use crate::render::scene::draw_field;
+
+    #[test]
+    fn fits_field_into_terminal() {
+        let field = Field::new(Division::A);
+        let canvas = TextCanvas::new(&field, 200, 40);
+        // the rows limit the size, cells are twice as tall as wide
+        assert_eq!(canvas.rows(), 40);
+        assert!(canvas.columns() <= 105);
+
+        let canvas = TextCanvas::new(&field, 80, 100);
+        assert_eq!(canvas.columns(), 80);
+        assert!(canvas.rows() <= 31);
+    }
+
+    #[test]
+    fn draws_field_lines() {
+        let field = Field::new(Division::B);
+        let mut canvas = TextCanvas::new(&field, 80, 40);
+        draw_field(&mut canvas, &field);
+        canvas.text(Point::new(1000.0, 1000.0), "3", Color::BLUE_TEAM);
+
+        let center_line = canvas.cell_at(Point::new(0.0, 2000.0)).unwrap();
+        assert_eq!(canvas.cells[center_line].ch, '|');
+        let touch_line = canvas
+            .cell_at(Point::new(2000.0, field.half_width()))
+            .unwrap();
+        assert_eq!(canvas.cells[touch_line].ch, '-');
+        let robot = canvas.cell_at(Point::new(1000.0, 1000.0)).unwrap();
+        assert_eq!(canvas.cells[robot].ch, '3');
+        assert_eq!(canvas.cells[robot].bg, Color::FIELD);
+    }
+}
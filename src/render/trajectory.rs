@@ -0,0 +1,248 @@
+use super::canvas::Canvas;
+use super::color::Color;
+use super::heatmap::Heatmap;
+use super::scene::{draw_path, draw_world_frame};
+use crate::geometry::{Division, Field, Point};
+use crate::persistence::message::{Message, MessageType};
+use crate::protos::log_labeler_data::LabelerFrameGroup;
+use crate::protos::messages_robocup_ssl_geometry::SSL_GeometryData;
+use crate::vision::{RobotState, TeamColor, WorldFrame, WorldFrameBuilder, WorldFrameConfig};
+use std::collections::BTreeMap;
+
+// Paths are split where an object was not seen for longer than this
+// many seconds, instead of drawing a straight jump
+pub const MAX_PATH_GAP: f64 = 0.5;
+
+// Selects the time window and robots to collect. Times are seconds
+// since the first message of the log.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TrajectoryFilter {
+    pub start: Option<f64>,
+    pub end: Option<f64>,
+    pub team: Option<TeamColor>,
+    pub robot_id: Option<u32>,
+}
+
+impl TrajectoryFilter {
+    pub fn includes_time(&self, time: f64) -> bool {
+        self.start.map_or(true, |start| time >= start) && self.end.map_or(true, |end| time <= end)
+    }
+
+    pub fn includes_robot(&self, team: TeamColor, robot_id: u32) -> bool {
+        self.team.map_or(true, |filter_team| filter_team == team)
+            && self
+                .robot_id
+                .map_or(true, |filter_id| filter_id == robot_id)
+    }
+
+    fn is_selecting_robots(&self) -> bool {
+        self.team.is_some() || self.robot_id.is_some()
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ObjectPath {
+    pub segments: Vec<Vec<Point>>,
+    last_time: Option<f64>,
+}
+
+impl ObjectPath {
+    pub fn add(&mut self, time: f64, point: Point) {
+        let is_gap = self
+            .last_time
+            .map_or(true, |last_time| (time - last_time).abs() > MAX_PATH_GAP);
+        if is_gap || self.segments.is_empty() {
+            self.segments.push(vec![]);
+        }
+        self.segments.last_mut().unwrap().push(point);
+        self.last_time = Some(time);
+    }
+
+    pub fn points(&self) -> impl Iterator<Item = &Point> {
+        self.segments.iter().flatten()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeatmapSource {
+    Ball,
+    Robots,
+}
+
+// Ball and robot paths of a stretch of a log
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Trajectories {
+    pub filter: TrajectoryFilter,
+    pub ball: ObjectPath,
+    pub robots: BTreeMap<(TeamColor, u32), ObjectPath>,
+    pub first_frame: Option<WorldFrame>,
+    pub last_frame: Option<WorldFrame>,
+    pub geometry: Option<SSL_GeometryData>,
+}
+
+impl Trajectories {
+    pub fn new(filter: TrajectoryFilter) -> Trajectories {
+        Trajectories {
+            filter,
+            ..Default::default()
+        }
+    }
+
+    pub fn add_world_frame(&mut self, world_frame: WorldFrame) {
+        let time = world_frame.t_capture;
+        if let Some(ball) = world_frame.ball() {
+            self.ball.add(time, Point::new(ball.x, ball.y));
+        }
+        for robot in &world_frame.robots {
+            if self.filter.includes_robot(robot.team, robot.robot_id) {
+                self.robots
+                    .entry((robot.team, robot.robot_id))
+                    .or_default()
+                    .add(time, Point::new(robot.x, robot.y));
+            }
+        }
+
+        if self.first_frame.is_none() {
+            self.first_frame = Some(world_frame.clone());
+        }
+        self.last_frame = Some(world_frame);
+    }
+
+    // Frame groups of labeler data are already cut to a time window, so
+    // only the robot part of the filter applies
+    pub fn add_frame_group(&mut self, frame_group: &LabelerFrameGroup, config: &WorldFrameConfig) {
+        for frame in frame_group.get_frames() {
+            if frame.has_vision_frame() && frame.get_vision_frame().has_geometry() {
+                self.geometry = Some(frame.get_vision_frame().get_geometry().clone());
+            }
+        }
+        if let Some(world_frame) = WorldFrame::from_frame_group(frame_group, config) {
+            self.add_world_frame(world_frame);
+        }
+    }
+
+    // The field from the last geometry packet, or division A if there
+    // was none
+    pub fn field(&self) -> Field {
+        Field::from_geometry(self.geometry.as_ref(), Division::A)
+    }
+
+    // Every sample counts once, so cells fill up with the time spent in
+    // them
+    pub fn heatmap(&self, field: &Field, cell_size: f32, source: HeatmapSource) -> Heatmap {
+        let mut heatmap = Heatmap::new(field, cell_size);
+        match source {
+            HeatmapSource::Ball => self
+                .ball
+                .points()
+                .for_each(|point| heatmap.add(*point, 1.0)),
+            HeatmapSource::Robots => self
+                .robots
+                .values()
+                .flat_map(ObjectPath::points)
+                .for_each(|point| heatmap.add(*point, 1.0)),
+        }
+        heatmap
+    }
+
+    // Draws the robot paths, the ball path on top and the robots of
+    // `snapshot`. Selected robots are highlighted.
+    pub fn draw(&self, canvas: &mut dyn Canvas, snapshot: Option<&WorldFrame>) {
+        for ((team, _), path) in &self.robots {
+            draw_path(canvas, &path.segments, Color::team(*team).with_alpha(0.6));
+        }
+        draw_path(canvas, &self.ball.segments, Color::BALL.with_alpha(0.8));
+
+        if let Some(world_frame) = snapshot {
+            let filter = &self.filter;
+            draw_world_frame(canvas, world_frame, |robot: &RobotState| {
+                filter.is_selecting_robots() && filter.includes_robot(robot.team, robot.robot_id)
+            });
+        }
+    }
+}
+
+// Collects the paths of the detections of a log. Messages after the end
+// of the filter's time window are not read.
+pub fn collect_trajectories<I, E>(
+    messages: I,
+    filter: TrajectoryFilter,
+    config: &WorldFrameConfig,
+) -> Result<Trajectories, E>
+where
+    I: IntoIterator<Item = Result<Message, E>>,
+{
+    let mut trajectories = Trajectories::new(filter);
+    let mut world_frame_builder = WorldFrameBuilder::new(config);
+    let mut first_timestamp = None;
+
+    for message in messages {
+        let message = message?;
+        let first_timestamp = *first_timestamp.get_or_insert(message.timestamp);
+        let time = (message.timestamp - first_timestamp) as f64 / 1e9;
+        if trajectories.filter.end.map_or(false, |end| time > end) {
+            break;
+        }
+
+        if let MessageType::Vision2014(ref packet) = message.msg_type {
+            if packet.has_geometry() {
+                trajectories.geometry = Some(packet.get_geometry().clone());
+            }
+            if !packet.has_detection() {
+                continue;
+            }
+            world_frame_builder.add_packet(packet);
+            if trajectories.filter.includes_time(time) {
+                if let Some(world_frame) = world_frame_builder.build() {
+                    trajectories.add_world_frame(world_frame);
+                }
+            }
+        }
+    }
+
+    Ok(trajectories)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn path_keeps_every_point(times in prop::collection::vec(0.0f64..2.0, 1..50)) {
+            let mut path = ObjectPath::default();
+            let mut time = 0.0;
+            for delta in &times {
+                time += delta;
+                path.add(time, Point::new(time as f32, 0.0));
+            }
+            let num_gaps = times.iter().skip(1).filter(|delta| **delta > MAX_PATH_GAP).count();
+            prop_assert_eq!(path.segments.len(), num_gaps + 1);
+            prop_assert_eq!(path.points().count(), times.len());
+        }
+    }
+
+    #[test]
+    fn filter_robots_and_time() {
+        let filter = TrajectoryFilter {
+            start: Some(10.0),
+            end: None,
+            team: Some(TeamColor::Blue),
+            robot_id: Some(3),
+        };
+        assert!(filter.includes_robot(TeamColor::Blue, 3));
+        assert!(!filter.includes_robot(TeamColor::Yellow, 3));
+        assert!(!filter.includes_robot(TeamColor::Blue, 4));
+        assert!(filter.includes_time(10.0));
+        assert!(!filter.includes_time(9.9));
+
+        let everything = TrajectoryFilter::default();
+        assert!(everything.includes_robot(TeamColor::Yellow, 7));
+        assert!(everything.includes_time(0.0));
+    }
+}
@@ -0,0 +1,92 @@
+use crate::geometry::{Field, Pixel, Point};
+
+// Maps field coordinates in mm to image pixels. +x points right and +y
+// up in the image, so the y axis is flipped.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct View {
+    // field coordinates of the top left corner of the image
+    pub min_x: f32,
+    pub max_y: f32,
+    // pixels per mm
+    pub scale: f32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl View {
+    // The whole field including the boundary, `width` pixels wide
+    pub fn new(field: &Field, width: u32) -> View {
+        let half_length = field.half_length() + field.boundary_width;
+        let half_width = field.half_width() + field.boundary_width;
+        View::of_area(
+            Point::new(-half_length, -half_width),
+            Point::new(half_length, half_width),
+            width,
+        )
+    }
+
+    // The rectangle spanned by the two corners, `width` pixels wide
+    pub fn of_area(corner1: Point, corner2: Point, width: u32) -> View {
+        let min_x = corner1.x.min(corner2.x);
+        let max_y = corner1.y.max(corner2.y);
+        let area_width = (corner1.x - corner2.x).abs().max(1.0);
+        let area_height = (corner1.y - corner2.y).abs().max(1.0);
+        let scale = width as f32 / area_width;
+
+        View {
+            min_x,
+            max_y,
+            scale,
+            width,
+            height: (area_height * scale).round().max(1.0) as u32,
+        }
+    }
+
+    pub fn to_pixel(&self, point: Point) -> Pixel {
+        Pixel::new(
+            (point.x - self.min_x) * self.scale,
+            (self.max_y - point.y) * self.scale,
+        )
+    }
+
+    pub fn to_field(&self, pixel: Pixel) -> Point {
+        Point::new(
+            self.min_x + pixel.x / self.scale,
+            self.max_y - pixel.y / self.scale,
+        )
+    }
+
+    // length in mm to pixels
+    pub fn to_pixels(&self, length: f32) -> f32 {
+        length * self.scale
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::Division;
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn pixel_round_trip(x in -6000.0f32..6000.0, y in -4500.0f32..4500.0) {
+            let view = View::new(&Field::new(Division::A), 1300);
+            let point = view.to_field(view.to_pixel(Point::new(x, y)));
+            prop_assert!((point.x - x).abs() < 0.1);
+            prop_assert!((point.y - y).abs() < 0.1);
+        }
+    }
+
+    #[test]
+    fn field_corners() {
+        let field = Field::new(Division::A);
+        let view = View::new(&field, 1000);
+        let top_left = Point::new(
+            -field.half_length() - field.boundary_width,
+            field.half_width() + field.boundary_width,
+        );
+        assert_eq!(view.to_pixel(top_left), Pixel::new(0.0, 0.0));
+        assert_eq!(view.height, 762);
+    }
+}
@@ -0,0 +1,99 @@
+use crate::protos::log_labels;
+use proptest::prelude::*;
+
+prop_compose! {
+    pub fn team_strategy()(team in prop_oneof![
+        Just(log_labels::Team::YELLOW),
+        Just(log_labels::Team::BLUE),
+    ]) -> log_labels::Team {
+        team
+    }
+}
+
+prop_compose! {
+    pub fn random_dribbling_label_strategy()
+        (is_dribbling in proptest::bool::ANY,
+         robot_id in 0..12u32,
+         team in team_strategy()) -> log_labels::DribblingLabel {
+            let mut label = log_labels::DribblingLabel::new();
+            label.set_is_dribbling(is_dribbling);
+            label.set_robot_id(robot_id);
+            label.set_team(team);
+            label
+        }
+}
+
+prop_compose! {
+    pub fn random_ball_possession_label_strategy()
+        (state in prop_oneof![
+            Just(log_labels::BallPossessionLabel_State::NONE),
+            Just(log_labels::BallPossessionLabel_State::YELLOW_POSSES),
+            Just(log_labels::BallPossessionLabel_State::BLUE_POSSES),
+        ],
+         robot_id in 0..12u32) -> log_labels::BallPossessionLabel {
+            let mut label = log_labels::BallPossessionLabel::new();
+            label.set_state(state);
+            label.set_robot_id(robot_id);
+            label
+        }
+}
+
+// span with start <= end < num_frames
+prop_compose! {
+    pub fn random_span_strategy(num_frames: u64)
+        (a in 0..num_frames, b in 0..num_frames) -> (u64, u64) {
+            (a.min(b), a.max(b))
+        }
+}
+
+prop_compose! {
+    pub fn random_passing_label_strategy(num_frames: u64)
+        (span in random_span_strategy(num_frames),
+         successful in proptest::bool::ANY,
+         passer_id in 0..12u32,
+         passer_team in team_strategy(),
+         receiver_id in 0..12u32) -> log_labels::PassingLabel {
+            let mut label = log_labels::PassingLabel::new();
+            label.set_start_frame(span.0);
+            label.set_end_frame(span.1);
+            label.set_successful(successful);
+            label.set_passer_id(passer_id);
+            label.set_passer_team(passer_team);
+            label.set_receiver_id(receiver_id);
+            label
+        }
+}
+
+prop_compose! {
+    pub fn random_goal_shot_label_strategy(num_frames: u64)
+        (span in random_span_strategy(num_frames),
+         successful in proptest::bool::ANY,
+         shooter_id in 0..12u32,
+         shooter_team in team_strategy()) -> log_labels::GoalShotLabel {
+            let mut label = log_labels::GoalShotLabel::new();
+            label.set_start_frame(span.0);
+            label.set_end_frame(span.1);
+            label.set_successful(successful);
+            label.set_shooter_id(shooter_id);
+            label.set_shooter_team(shooter_team);
+            label
+        }
+}
+
+// labels for a data file with num_frames frame groups. There is one
+// dribbling and ball possession label per frame.
+prop_compose! {
+    pub fn random_labels_strategy(num_frames: usize, max_num_events: usize)
+        (dribbling_labels in prop::collection::vec(random_dribbling_label_strategy(), num_frames),
+         ball_possession_labels in prop::collection::vec(random_ball_possession_label_strategy(), num_frames),
+         passing_labels in prop::collection::vec(random_passing_label_strategy(num_frames as u64), 0..max_num_events),
+         goal_shot_labels in prop::collection::vec(random_goal_shot_label_strategy(num_frames as u64), 0..max_num_events)
+        ) -> log_labels::Labels {
+            let mut labels = log_labels::Labels::new();
+            labels.set_dribbling_labels(dribbling_labels.into());
+            labels.set_ball_possession_labels(ball_possession_labels.into());
+            labels.set_passing_labels(passing_labels.into());
+            labels.set_goal_shot_labels(goal_shot_labels.into());
+            labels
+        }
+}
@@ -61,3 +61,12 @@ prop_compose! {
             message::Message { timestamp, msg_type: message::MessageType::Vision2014(vision_msg)}
         }
 }
+
+prop_compose! {
+    pub fn random_vision_tracker2020_msg_strategy()
+        (timestamp in 0..std::i64::MAX,
+         tracker_msg in test_utils_protos::vision_tracker2020_strategy()
+        ) -> message::Message {
+            message::Message { timestamp, msg_type: message::MessageType::VisionTracker2020(tracker_msg)}
+        }
+}
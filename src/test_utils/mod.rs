@@ -1,4 +1,6 @@
 pub mod labeler;
+pub mod labels;
 pub mod message;
 pub mod protos;
 pub mod reader;
+pub mod vision;
@@ -3,6 +3,8 @@ use crate::protos::messages_robocup_ssl_detection;
 use crate::protos::messages_robocup_ssl_geometry;
 use crate::protos::messages_robocup_ssl_referee;
 use crate::protos::messages_robocup_ssl_wrapper;
+use crate::protos::ssl_vision_detection_tracked;
+use crate::protos::ssl_vision_wrapper_tracked;
 use proptest::prelude::*;
 
 
@@ -112,3 +114,42 @@ prop_compose! {
         vision_msg
     }
 }
+
+prop_compose! {
+    pub fn tracked_frame_strategy()(frame_number in proptest::num::u32::ANY,
+                                timestamp in proptest::num::f64::NORMAL | proptest::num::f64::POSITIVE,
+                                robot_id in proptest::num::u32::ANY,
+                                team in one_of_protobuf_enum::<ssl_vision_detection_tracked::Team>(),
+                                x in proptest::num::f32::NORMAL,
+                                y in proptest::num::f32::NORMAL,
+                                orientation in proptest::num::f32::NORMAL
+    ) -> ssl_vision_detection_tracked::TrackedFrame {
+        let mut robot = ssl_vision_detection_tracked::TrackedRobot::new();
+        robot.mut_robot_id().set_id(robot_id);
+        robot.mut_robot_id().set_team(team);
+        robot.mut_pos().set_x(x);
+        robot.mut_pos().set_y(y);
+        robot.set_orientation(orientation);
+
+        let mut tracked_frame_msg = ssl_vision_detection_tracked::TrackedFrame::new();
+        tracked_frame_msg.set_frame_number(frame_number);
+        tracked_frame_msg.set_timestamp(timestamp);
+        tracked_frame_msg.mut_robots().push(robot);
+
+        tracked_frame_msg
+    }
+}
+
+prop_compose! {
+    pub fn vision_tracker2020_strategy()(uuid in "[0-9a-f]{32}",
+                                     source_name in "\\PC*",
+                                     tracked_frame in tracked_frame_strategy()
+    ) -> ssl_vision_wrapper_tracked::TrackerWrapperPacket {
+        let mut tracker_msg = ssl_vision_wrapper_tracked::TrackerWrapperPacket::new();
+        tracker_msg.set_uuid(uuid);
+        tracker_msg.set_source_name(source_name);
+        tracker_msg.set_tracked_frame(tracked_frame);
+
+        tracker_msg
+    }
+}
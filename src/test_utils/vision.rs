@@ -0,0 +1,91 @@
+use crate::protos::log_labeler_data::{LabelerFrame, LabelerFrameGroup};
+use crate::protos::messages_robocup_ssl_detection::{
+    SSL_DetectionBall, SSL_DetectionFrame, SSL_DetectionRobot,
+};
+use crate::protos::messages_robocup_ssl_geometry::{SSL_GeometryData, SSL_GeometryFieldSize};
+use crate::protos::messages_robocup_ssl_wrapper::SSL_WrapperPacket;
+
+// Detections for building vision frames by hand. The required fields
+// the tools do not use are filled in, so the messages can be
+// serialized.
+
+pub fn robot_with_confidence(
+    robot_id: u32,
+    confidence: f32,
+    x: f32,
+    y: f32,
+    orientation: f32,
+) -> SSL_DetectionRobot {
+    let mut robot = SSL_DetectionRobot::new();
+    robot.set_confidence(confidence);
+    robot.set_robot_id(robot_id);
+    robot.set_x(x);
+    robot.set_y(y);
+    robot.set_orientation(orientation);
+    robot.set_pixel_x(0.0);
+    robot.set_pixel_y(0.0);
+    robot
+}
+
+pub fn robot(robot_id: u32, x: f32, y: f32, orientation: f32) -> SSL_DetectionRobot {
+    robot_with_confidence(robot_id, 1.0, x, y, orientation)
+}
+
+pub fn ball_with_confidence(confidence: f32, x: f32, y: f32) -> SSL_DetectionBall {
+    let mut ball = SSL_DetectionBall::new();
+    ball.set_confidence(confidence);
+    ball.set_x(x);
+    ball.set_y(y);
+    ball.set_pixel_x(0.0);
+    ball.set_pixel_y(0.0);
+    ball
+}
+
+pub fn ball(x: f32, y: f32) -> SSL_DetectionBall {
+    ball_with_confidence(1.0, x, y)
+}
+
+pub fn detection(
+    camera_id: u32,
+    t_capture: f64,
+    balls: Vec<SSL_DetectionBall>,
+    robots_yellow: Vec<SSL_DetectionRobot>,
+    robots_blue: Vec<SSL_DetectionRobot>,
+) -> SSL_DetectionFrame {
+    let mut frame = SSL_DetectionFrame::new();
+    frame.set_camera_id(camera_id);
+    frame.set_frame_number(0);
+    frame.set_t_capture(t_capture);
+    frame.set_t_sent(t_capture);
+    frame.set_balls(balls.into());
+    frame.set_robots_yellow(robots_yellow.into());
+    frame.set_robots_blue(robots_blue.into());
+    frame
+}
+
+// One vision frame per detection
+pub fn frame_group(detections: Vec<SSL_DetectionFrame>) -> LabelerFrameGroup {
+    let mut frame_group = LabelerFrameGroup::new();
+    for detection in detections {
+        let mut packet = SSL_WrapperPacket::new();
+        packet.set_detection(detection);
+        let mut frame = LabelerFrame::new();
+        frame.set_vision_frame(packet);
+        frame_group.mut_frames().push(frame);
+    }
+    frame_group
+}
+
+// Geometry with only the required field size set
+pub fn geometry(field_length: i32, field_width: i32) -> SSL_GeometryData {
+    let mut field_size = SSL_GeometryFieldSize::new();
+    field_size.set_field_length(field_length);
+    field_size.set_field_width(field_width);
+    field_size.set_goal_width(1000);
+    field_size.set_goal_depth(180);
+    field_size.set_boundary_width(250);
+
+    let mut geometry = SSL_GeometryData::new();
+    geometry.set_field(field_size);
+    geometry
+}
@@ -0,0 +1,4 @@
+pub mod screen;
+pub mod viewer;
+
+pub use viewer::Viewer;
@@ -0,0 +1,38 @@
+use crate::render::{Color, TextCanvas};
+use std::io;
+use std::io::Write;
+use termion::{clear, color, cursor, style};
+
+fn rgb(color: Color) -> color::Rgb {
+    color::Rgb(color.r, color.g, color.b)
+}
+
+// Redraws the whole terminal: the text lines at the top and the canvas
+// below them. Colors are only sent when they change from the previous
+// cell, which keeps the output small enough for slow SSH connections.
+pub fn draw<W: Write>(out: &mut W, lines: &[String], canvas: &TextCanvas) -> io::Result<()> {
+    write!(out, "{}", cursor::Goto(1, 1))?;
+    for line in lines {
+        write!(out, "{}{}\r\n", line, clear::UntilNewline)?;
+    }
+
+    for row in 0..canvas.rows() {
+        let mut last_colors = None;
+        for cell in canvas.row(row) {
+            if last_colors != Some((cell.fg, cell.bg)) {
+                write!(
+                    out,
+                    "{}{}",
+                    color::Fg(rgb(cell.fg)),
+                    color::Bg(rgb(cell.bg))
+                )?;
+                last_colors = Some((cell.fg, cell.bg));
+            }
+            write!(out, "{}", cell.ch)?;
+        }
+        write!(out, "{}{}\r\n", style::Reset, clear::UntilNewline)?;
+    }
+    write!(out, "{}", clear::AfterCursor)?;
+
+    out.flush()
+}
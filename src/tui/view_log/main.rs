@@ -0,0 +1,129 @@
+use clap::{App, Arg};
+use ssl_rust_tools::labeler::reader::LabelerDataReader;
+use ssl_rust_tools::labeler::writer::labeler_data_from_log;
+use ssl_rust_tools::labeler::FilterConfig;
+use ssl_rust_tools::tui::{screen, Viewer};
+use std::io::{stdout, Read, Seek, Write};
+use std::path::Path;
+use std::{thread, time};
+use termion::event::Key;
+use termion::input::TermRead;
+use termion::raw::IntoRawMode;
+use termion::screen::AlternateScreen;
+use termion::{async_stdin, cursor, terminal_size};
+
+const HELP: &str = "space: play/pause  left/right: step  up/down: 5 s  \
+                    page up/down: 1 min  home/end  +/-: speed  q: quit";
+
+// Shows the cursor again however the viewer exits, also on errors and
+// panics
+struct ShowCursorOnDrop;
+
+impl Drop for ShowCursorOnDrop {
+    fn drop(&mut self) {
+        let mut out = stdout();
+        // there is nowhere left to report a failure to
+        let _ = write!(out, "{}", cursor::Show);
+        let _ = out.flush();
+    }
+}
+
+fn run<T: Read + Seek>(reader: LabelerDataReader<T>, speed: f32) -> Result<(), failure::Error> {
+    let mut viewer = Viewer::new(reader, speed)?;
+    if viewer.is_empty() {
+        println!("Nothing to show, the file has no frame groups");
+        return Ok(());
+    }
+
+    // dropped in reverse order, so the cursor is shown before the
+    // terminal leaves raw mode
+    let mut out = AlternateScreen::from(stdout().into_raw_mode()?);
+    let _show_cursor = ShowCursorOnDrop;
+    write!(out, "{}", cursor::Hide)?;
+    let mut keys = async_stdin().keys();
+    let mut last_size = None;
+
+    loop {
+        let mut needs_redraw = false;
+        for key in &mut keys {
+            match key? {
+                Key::Char('q') | Key::Esc | Key::Ctrl('c') => return Ok(()),
+                Key::Char(' ') => viewer.toggle_pause(),
+                Key::Right => viewer.step(1)?,
+                Key::Left => viewer.step(-1)?,
+                Key::Up => viewer.seek_seconds(5.0)?,
+                Key::Down => viewer.seek_seconds(-5.0)?,
+                Key::PageUp => viewer.seek_seconds(60.0)?,
+                Key::PageDown => viewer.seek_seconds(-60.0)?,
+                Key::Home => viewer.seek_to(0)?,
+                Key::End => viewer.seek_to(viewer.len() - 1)?,
+                Key::Char('+') => viewer.set_speed(viewer.speed() * 2.0),
+                Key::Char('-') => viewer.set_speed(viewer.speed() / 2.0),
+                _ => {}
+            }
+            needs_redraw = true;
+        }
+        if viewer.update()? {
+            needs_redraw = true;
+        }
+
+        let size = terminal_size()?;
+        if last_size != Some(size) {
+            last_size = Some(size);
+            needs_redraw = true;
+        }
+
+        if needs_redraw {
+            let (columns, rows) = size;
+            let mut lines = viewer.status_lines();
+            lines.push(HELP.to_owned());
+            let canvas = viewer.draw(
+                u32::from(columns),
+                u32::from(rows).saturating_sub(lines.len() as u32 + 1),
+            );
+            screen::draw(&mut out, &lines, &canvas)?;
+        }
+
+        thread::sleep(time::Duration::from_millis(5));
+    }
+}
+
+fn main() {
+    let matches = App::new("View a log.")
+        .version("1.0")
+        .author("Devin Schwab <dschwab@andrew.cmu.edu>")
+        .about("Play a log or labeler data file in the terminal")
+        .arg(
+            Arg::with_name("FILE")
+                .help("Path to a log file, or a labeler data file ending in .labeler")
+                .required(true)
+                .index(1),
+        )
+        .arg(
+            Arg::with_name("speed")
+                .short("s")
+                .long("speed")
+                .value_name("SPEED")
+                .help("Sets playback speed, defaults to 1.0 which is real-time.")
+                .takes_value(true),
+        )
+        .get_matches();
+
+    let speed = matches.value_of("speed").unwrap_or("1.0");
+    let speed = speed.parse::<f32>().expect("Failed to parse speed");
+
+    let path = Path::new(matches.value_of("FILE").unwrap());
+    let is_labeler_data = path
+        .extension()
+        .map_or(false, |extension| extension == "labeler");
+    if is_labeler_data {
+        let reader =
+            LabelerDataReader::new_from_path(path).expect("Failed to open labeler data file");
+        run(reader, speed).expect("Failed to view labeler data file");
+    } else {
+        // stoppages are kept, so the whole log can be watched
+        let reader =
+            labeler_data_from_log(path, FilterConfig::keep_all()).expect("Failed to filter log");
+        run(reader, speed).expect("Failed to view log");
+    }
+}
@@ -0,0 +1,266 @@
+use crate::geometry::{Division, Field, Point};
+use crate::labeler::reader::{LabelerDataReader, LabelerDataReaderError};
+use crate::player::PlaybackClock;
+use crate::protos::log_labeler_data::LabelerFrameGroup;
+use crate::protos::messages_robocup_ssl_referee::SSL_Referee;
+use crate::referee::RefereeState;
+use crate::render::scene::draw_field;
+use crate::render::{Color, TextCanvas};
+use crate::vision::{TeamColor, WorldFrame, WorldFrameConfig};
+use std::io::{Read, Seek};
+
+type ViewerResult<T> = Result<T, LabelerDataReaderError>;
+
+pub const MIN_SPEED: f32 = 1.0 / 16.0;
+pub const MAX_SPEED: f32 = 16.0;
+// Files written before frame group timestamps were stored are seeked
+// assuming this many frame groups per second
+const FALLBACK_FRAME_RATE: f64 = 60.0;
+// How far back a seek looks for the last referee message
+const REFEREE_SEARCH_LIMIT: usize = 600;
+
+// Plays labeler data in a terminal. Frame groups are read on demand, so
+// seeking anywhere is as cheap as stepping.
+pub struct Viewer<T: Read + Seek> {
+    reader: LabelerDataReader<T>,
+    field: Field,
+    world_frame_config: WorldFrameConfig,
+    clock: PlaybackClock,
+    is_playing: bool,
+    index: usize,
+    world_frame: Option<WorldFrame>,
+    referee: Option<SSL_Referee>,
+}
+
+fn last_referee(frame_group: &LabelerFrameGroup) -> Option<&SSL_Referee> {
+    frame_group
+        .get_frames()
+        .iter()
+        .rev()
+        .find(|frame| frame.has_referee_frame())
+        .map(|frame| frame.get_referee_frame())
+}
+
+// mm:ss of a duration in seconds
+fn format_time(seconds: f64) -> String {
+    let seconds = seconds.max(0.0) as u64;
+    format!("{:02}:{:02}", seconds / 60, seconds % 60)
+}
+
+impl<T: Read + Seek> Viewer<T> {
+    pub fn new(reader: LabelerDataReader<T>, speed: f32) -> ViewerResult<Viewer<T>> {
+        let field = Field::from_geometry(reader.geometry(), Division::A);
+        let mut viewer = Viewer {
+            reader,
+            field,
+            world_frame_config: WorldFrameConfig::default(),
+            clock: PlaybackClock::new(speed.max(MIN_SPEED).min(MAX_SPEED)),
+            is_playing: false,
+            index: 0,
+            world_frame: None,
+            referee: None,
+        };
+        if !viewer.reader.is_empty() {
+            viewer.seek_to(0)?;
+        }
+
+        Ok(viewer)
+    }
+
+    pub fn len(&self) -> usize {
+        self.reader.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.reader.is_empty()
+    }
+
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.is_playing
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.clock.speed()
+    }
+
+    // Receiver timestamp (ns) of the start of a frame group
+    fn timestamp(&self, index: usize) -> i64 {
+        match self.reader.frame_group_timestamps(index) {
+            Some((first_timestamp, _)) => first_timestamp as i64,
+            None => (index as f64 / FALLBACK_FRAME_RATE * 1e9) as i64,
+        }
+    }
+
+    // Starts the clock at the shown frame group, so the next one is due
+    // after the time between the two
+    fn restart_clock(&mut self) {
+        self.clock.reset();
+        let timestamp = self.timestamp(self.index);
+        self.clock.time_until(timestamp);
+    }
+
+    fn show(&mut self, frame_group: &LabelerFrameGroup) {
+        self.world_frame = WorldFrame::from_frame_group(frame_group, &self.world_frame_config);
+        if let Some(referee) = last_referee(frame_group) {
+            self.referee = Some(referee.clone());
+        }
+    }
+
+    pub fn seek_to(&mut self, index: usize) -> ViewerResult<()> {
+        if self.is_empty() {
+            return Ok(());
+        }
+        self.index = index.min(self.len() - 1);
+
+        // the shown frame group may not have a referee message, so the
+        // state comes from the last one before it
+        self.referee = None;
+        let search_start = self.index.saturating_sub(REFEREE_SEARCH_LIMIT);
+        for index in (search_start..self.index).rev() {
+            if let Some(referee) = last_referee(&self.reader.get(index)?) {
+                self.referee = Some(referee.clone());
+                break;
+            }
+        }
+        let frame_group = self.reader.get(self.index)?;
+        self.show(&frame_group);
+        self.restart_clock();
+
+        Ok(())
+    }
+
+    pub fn step(&mut self, frame_groups: i64) -> ViewerResult<()> {
+        let index = (self.index as i64 + frame_groups).max(0) as usize;
+        self.seek_to(index)
+    }
+
+    pub fn seek_seconds(&mut self, seconds: f64) -> ViewerResult<()> {
+        let timestamp = self.timestamp(self.index) + (seconds * 1e9) as i64;
+        let index = match self
+            .reader
+            .frame_group_at_timestamp(timestamp.max(0) as u64)
+        {
+            Ok(index) => index.unwrap_or(0),
+            Err(LabelerDataReaderError::MissingTimestamps) => {
+                (self.index as f64 + seconds * FALLBACK_FRAME_RATE).max(0.0) as usize
+            }
+            Err(error) => return Err(error),
+        };
+        self.seek_to(index)
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.is_playing = !self.is_playing;
+        self.restart_clock();
+    }
+
+    pub fn set_speed(&mut self, speed: f32) {
+        self.clock.set_speed(speed.max(MIN_SPEED).min(MAX_SPEED));
+        self.restart_clock();
+    }
+
+    // Advances to the frame group that is due when playing, skipping
+    // frame groups if drawing falls behind. True if the shown frame
+    // group changed.
+    pub fn update(&mut self) -> ViewerResult<bool> {
+        if !self.is_playing || self.is_empty() {
+            return Ok(false);
+        }
+
+        let mut index = self.index;
+        while index + 1 < self.len() && self.clock.time_until(self.timestamp(index + 1)) <= 0 {
+            index += 1;
+        }
+        if index + 1 >= self.len() {
+            self.is_playing = false;
+        }
+        if index == self.index {
+            return Ok(false);
+        }
+
+        // referee messages of skipped frame groups would be lost
+        for skipped in self.index + 1..index {
+            if let Some(referee) = last_referee(&self.reader.get(skipped)?) {
+                self.referee = Some(referee.clone());
+            }
+        }
+        self.index = index;
+        let frame_group = self.reader.get(index)?;
+        self.show(&frame_group);
+
+        Ok(true)
+    }
+
+    // The field scaled to `columns` x `rows` cells with robots as their
+    // ids in the team color and the ball as an 'o'
+    pub fn draw(&self, columns: u32, rows: u32) -> TextCanvas {
+        let mut canvas = TextCanvas::new(&self.field, columns, rows);
+        draw_field(&mut canvas, &self.field);
+
+        if let Some(ref world_frame) = self.world_frame {
+            for robot in &world_frame.robots {
+                canvas.text(
+                    Point::new(robot.x, robot.y),
+                    &format!("{:X}", robot.robot_id % 16),
+                    Color::team(robot.team),
+                );
+            }
+            if let Some(ball) = world_frame.ball() {
+                canvas.text(Point::new(ball.x, ball.y), "o", Color::BALL);
+            }
+        }
+        canvas
+    }
+
+    // Score and referee state, then the playback position
+    pub fn status_lines(&self) -> Vec<String> {
+        let referee_line = match self.referee {
+            Some(ref referee) => {
+                let state = RefereeState::from_referee(referee);
+                let team_name = |team: TeamColor| {
+                    let name = &state.team(team).name;
+                    if name.is_empty() {
+                        format!("{:?}", team)
+                    } else {
+                        name.clone()
+                    }
+                };
+                let mut line = format!(
+                    "{} {} : {} {}  |  {:?}  |  {:?}",
+                    team_name(TeamColor::Yellow),
+                    state.yellow.score,
+                    state.blue.score,
+                    team_name(TeamColor::Blue),
+                    state.stage,
+                    state.command
+                );
+                if referee.has_stage_time_left() {
+                    let time_left = referee.get_stage_time_left() as f64 / 1e6;
+                    line.push_str(&format!("  |  {} left", format_time(time_left)));
+                }
+                line
+            }
+            None => "No referee message".to_owned(),
+        };
+
+        let elapsed = if self.is_empty() {
+            0.0
+        } else {
+            (self.timestamp(self.index) - self.timestamp(0)) as f64 / 1e9
+        };
+        let playback_line = format!(
+            "frame {}/{}  |  {}  |  x{}  |  {}",
+            self.index,
+            self.len().saturating_sub(1),
+            format_time(elapsed),
+            self.speed(),
+            if self.is_playing { "playing" } else { "paused" }
+        );
+
+        vec![referee_line, playback_line]
+    }
+}
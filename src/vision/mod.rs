@@ -0,0 +1,7 @@
+pub mod tracker;
+pub mod world_frame;
+
+pub use tracker::{TrackedBall, TrackedFrame, TrackedRobot, Tracker, TrackerConfig};
+pub use world_frame::{
+    BallState, RobotState, TeamColor, WorldFrame, WorldFrameBuilder, WorldFrameConfig,
+};
@@ -0,0 +1,864 @@
+use super::world_frame::{is_finite_ball, is_finite_robot, TeamColor};
+use crate::protos::messages_robocup_ssl_detection::{
+    SSL_DetectionBall, SSL_DetectionFrame, SSL_DetectionRobot,
+};
+use crate::protos::messages_robocup_ssl_wrapper::SSL_WrapperPacket;
+use crate::protos::ssl_vision_detection_tracked as tracked;
+use crate::protos::ssl_vision_wrapper_tracked::TrackerWrapperPacket;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::f32::consts::PI;
+
+// Kalman filter tracker for robots and balls. Every object has
+// constant velocity filters for its x and y position and, for robots,
+// its orientation. Objects are tracked by team and id, not by camera,
+// so a robot moving from one camera to the next keeps its track. Tracks
+// that are not seen keep moving with their last velocity until they
+// are dropped.
+
+// Distances are in mm, angles in radians and times in seconds. The
+// noise values are standard deviations.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrackerConfig {
+    // tracked frames per second
+    pub output_rate: f64,
+    // detection noise
+    pub position_noise: f32,
+    pub orientation_noise: f32,
+    // how much the velocity can change between frames, in mm/s^2 and
+    // rad/s^2. Balls get kicked, so theirs is much higher.
+    pub robot_acceleration_noise: f32,
+    pub ball_acceleration_noise: f32,
+    pub angular_acceleration_noise: f32,
+    // detections farther than this from the predicted position are
+    // outliers
+    pub outlier_distance: f32,
+    // after this many outliers in a row the track restarts at the new
+    // position, for example when a robot was carried away
+    pub max_outliers: usize,
+    // tracks not seen for this long are dropped
+    pub max_missing_time: f64,
+    // tracks not seen for this long are not visible. A few vision
+    // periods, since each camera only sends about 60 frames per second.
+    pub visibility_timeout: f64,
+    // detections with a lower confidence are ignored
+    pub min_confidence: f32,
+}
+
+impl Default for TrackerConfig {
+    fn default() -> Self {
+        TrackerConfig {
+            output_rate: 100.0,
+            position_noise: 10.0,
+            orientation_noise: 0.05,
+            robot_acceleration_noise: 5000.0,
+            ball_acceleration_noise: 20000.0,
+            angular_acceleration_noise: 50.0,
+            outlier_distance: 500.0,
+            max_outliers: 5,
+            max_missing_time: 0.5,
+            visibility_timeout: 0.05,
+            min_confidence: 0.3,
+        }
+    }
+}
+
+// Uncertainty of the velocity of a new track
+const INITIAL_VELOCITY_NOISE: f32 = 5000.0;
+const INITIAL_ANGULAR_VELOCITY_NOISE: f32 = 10.0;
+
+fn wrap_angle(mut angle: f32) -> f32 {
+    while angle > PI {
+        angle -= 2.0 * PI;
+    }
+    while angle < -PI {
+        angle += 2.0 * PI;
+    }
+    angle
+}
+
+// Constant velocity Kalman filter of a single value. Angles wrap
+// around at PI.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Filter {
+    value: f32,
+    rate: f32,
+    covariance: [[f32; 2]; 2],
+    is_angle: bool,
+}
+
+impl Filter {
+    fn new(value: f32, value_noise: f32, rate_noise: f32, is_angle: bool) -> Filter {
+        Filter {
+            value,
+            rate: 0.0,
+            covariance: [[value_noise.powi(2), 0.0], [0.0, rate_noise.powi(2)]],
+            is_angle,
+        }
+    }
+
+    fn predict(&mut self, dt: f32, acceleration_noise: f32) {
+        if dt <= 0.0 {
+            return;
+        }
+
+        self.value += self.rate * dt;
+        if self.is_angle {
+            self.value = wrap_angle(self.value);
+        }
+
+        let [[p00, p01], [p10, p11]] = self.covariance;
+        let q = acceleration_noise.powi(2);
+        self.covariance = [
+            [
+                p00 + dt * (p01 + p10) + dt * dt * p11 + q * dt.powi(4) / 4.0,
+                p01 + dt * p11 + q * dt.powi(3) / 2.0,
+            ],
+            [p10 + dt * p11 + q * dt.powi(3) / 2.0, p11 + q * dt * dt],
+        ];
+    }
+
+    fn innovation(&self, measurement: f32) -> f32 {
+        let innovation = measurement - self.value;
+        if self.is_angle {
+            wrap_angle(innovation)
+        } else {
+            innovation
+        }
+    }
+
+    fn update(&mut self, measurement: f32, noise: f32) {
+        let innovation = self.innovation(measurement);
+        let [[p00, p01], [p10, p11]] = self.covariance;
+        let s = p00 + noise.powi(2);
+        let (k0, k1) = (p00 / s, p10 / s);
+
+        self.value += k0 * innovation;
+        if self.is_angle {
+            self.value = wrap_angle(self.value);
+        }
+        self.rate += k1 * innovation;
+        self.covariance = [
+            [(1.0 - k0) * p00, (1.0 - k0) * p01],
+            [p10 - k1 * p00, p11 - k1 * p01],
+        ];
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Track {
+    x: Filter,
+    y: Filter,
+    // None for balls and robots whose orientation is unknown
+    orientation: Option<Filter>,
+    // time the filters were last predicted to
+    time: f64,
+    last_seen: f64,
+    // outliers since the last accepted detection
+    num_outliers: usize,
+}
+
+impl Track {
+    fn new(time: f64, x: f32, y: f32, orientation: Option<f32>, config: &TrackerConfig) -> Track {
+        Track {
+            x: Filter::new(x, config.position_noise, INITIAL_VELOCITY_NOISE, false),
+            y: Filter::new(y, config.position_noise, INITIAL_VELOCITY_NOISE, false),
+            orientation: orientation.map(|orientation| {
+                Filter::new(
+                    orientation,
+                    config.orientation_noise,
+                    INITIAL_ANGULAR_VELOCITY_NOISE,
+                    true,
+                )
+            }),
+            time,
+            last_seen: time,
+            num_outliers: 0,
+        }
+    }
+
+    // Detections from cameras that are slightly behind the others can
+    // be older than the track. They are used as if they were current.
+    fn predict(&mut self, time: f64, acceleration_noise: f32, config: &TrackerConfig) {
+        let dt = (time - self.time) as f32;
+        if dt <= 0.0 {
+            return;
+        }
+        self.x.predict(dt, acceleration_noise);
+        self.y.predict(dt, acceleration_noise);
+        if let Some(ref mut orientation) = self.orientation {
+            orientation.predict(dt, config.angular_acceleration_noise);
+        }
+        self.time = time;
+    }
+
+    fn distance(&self, x: f32, y: f32) -> f32 {
+        self.x.innovation(x).hypot(self.y.innovation(y))
+    }
+
+    // Returns false if the detection is an outlier
+    fn update(
+        &mut self,
+        time: f64,
+        x: f32,
+        y: f32,
+        orientation: Option<f32>,
+        config: &TrackerConfig,
+    ) -> bool {
+        if self.distance(x, y) > config.outlier_distance {
+            self.num_outliers += 1;
+            return false;
+        }
+
+        self.x.update(x, config.position_noise);
+        self.y.update(y, config.position_noise);
+        if let Some(orientation) = orientation {
+            match self.orientation {
+                Some(ref mut filter) => filter.update(orientation, config.orientation_noise),
+                None => {
+                    self.orientation = Some(Filter::new(
+                        orientation,
+                        config.orientation_noise,
+                        INITIAL_ANGULAR_VELOCITY_NOISE,
+                        true,
+                    ))
+                }
+            }
+        }
+        self.last_seen = self.last_seen.max(time);
+        self.num_outliers = 0;
+        true
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrackedRobot {
+    pub team: TeamColor,
+    pub robot_id: u32,
+    pub x: f32,
+    pub y: f32,
+    pub vx: f32,
+    pub vy: f32,
+    // None if no detection of the robot had an orientation
+    pub orientation: Option<f32>,
+    pub angular_velocity: Option<f32>,
+    // seen within the visibility timeout
+    pub visible: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrackedBall {
+    pub x: f32,
+    pub y: f32,
+    pub vx: f32,
+    pub vy: f32,
+    // seen within the visibility timeout
+    pub visible: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TrackedFrame {
+    pub frame_number: u32,
+    // capture time the states are predicted to
+    pub timestamp: f64,
+    pub ball: Option<TrackedBall>,
+    // sorted by team and robot id
+    pub robots: Vec<TrackedRobot>,
+}
+
+fn visibility(visible: bool) -> f32 {
+    if visible {
+        1.0
+    } else {
+        0.0
+    }
+}
+
+// tracker packets use m instead of mm
+fn vector2(x: f32, y: f32) -> tracked::Vector2 {
+    let mut vector = tracked::Vector2::new();
+    vector.set_x(x / 1000.0);
+    vector.set_y(y / 1000.0);
+    vector
+}
+
+fn vector3(x: f32, y: f32) -> tracked::Vector3 {
+    let mut vector = tracked::Vector3::new();
+    vector.set_x(x / 1000.0);
+    vector.set_y(y / 1000.0);
+    vector.set_z(0.0);
+    vector
+}
+
+impl TrackedFrame {
+    pub fn robot(&self, team: TeamColor, robot_id: u32) -> Option<&TrackedRobot> {
+        self.robots
+            .iter()
+            .find(|robot| robot.team == team && robot.robot_id == robot_id)
+    }
+
+    // `uuid` identifies the tracker source and should stay the same
+    // for all frames of a run
+    pub fn to_tracker_packet(&self, uuid: &str, source_name: &str) -> TrackerWrapperPacket {
+        let mut tracked_frame = tracked::TrackedFrame::new();
+        tracked_frame.set_frame_number(self.frame_number);
+        tracked_frame.set_timestamp(self.timestamp);
+
+        if let Some(ref ball) = self.ball {
+            let mut tracked_ball = tracked::TrackedBall::new();
+            tracked_ball.set_pos(vector3(ball.x, ball.y));
+            tracked_ball.set_vel(vector3(ball.vx, ball.vy));
+            tracked_ball.set_visibility(visibility(ball.visible));
+            tracked_frame.mut_balls().push(tracked_ball);
+        }
+
+        for robot in &self.robots {
+            let mut tracked_robot = tracked::TrackedRobot::new();
+            tracked_robot.mut_robot_id().set_id(robot.robot_id);
+            tracked_robot.mut_robot_id().set_team(match robot.team {
+                TeamColor::Yellow => tracked::Team::YELLOW,
+                TeamColor::Blue => tracked::Team::BLUE,
+            });
+            tracked_robot.set_pos(vector2(robot.x, robot.y));
+            tracked_robot.set_orientation(robot.orientation.unwrap_or(0.0));
+            tracked_robot.set_vel(vector2(robot.vx, robot.vy));
+            if let Some(angular_velocity) = robot.angular_velocity {
+                tracked_robot.set_vel_angular(angular_velocity);
+            }
+            tracked_robot.set_visibility(visibility(robot.visible));
+            tracked_frame.mut_robots().push(tracked_robot);
+        }
+
+        let mut packet = TrackerWrapperPacket::new();
+        packet.set_uuid(uuid.to_owned());
+        packet.set_source_name(source_name.to_owned());
+        packet.set_tracked_frame(tracked_frame);
+        packet
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Tracker {
+    config: TrackerConfig,
+    robots: BTreeMap<(TeamColor, u32), Track>,
+    ball: Option<Track>,
+    // newest capture time of any detection
+    latest_time: Option<f64>,
+    // tracked frames are output at the capture time of the first
+    // detection plus multiples of the output period
+    first_output_time: f64,
+    num_outputs: u64,
+}
+
+impl Default for Tracker {
+    fn default() -> Self {
+        Tracker::new(&TrackerConfig::default())
+    }
+}
+
+impl Tracker {
+    pub fn new(config: &TrackerConfig) -> Tracker {
+        Tracker {
+            config: *config,
+            robots: BTreeMap::new(),
+            ball: None,
+            latest_time: None,
+            first_output_time: 0.0,
+            num_outputs: 0,
+        }
+    }
+
+    // Packets without a detection frame are ignored
+    pub fn add_packet(&mut self, packet: &SSL_WrapperPacket) -> Vec<TrackedFrame> {
+        if packet.has_detection() {
+            self.add_detection(packet.get_detection())
+        } else {
+            vec![]
+        }
+    }
+
+    // Adds a camera frame and returns the tracked frames that are due
+    // before its capture time. Tracked frames only use detections
+    // captured before them, so they can be computed while playing a
+    // log or receiving live data.
+    pub fn add_detection(&mut self, detection: &SSL_DetectionFrame) -> Vec<TrackedFrame> {
+        let time = detection.get_t_capture();
+        let mut tracked_frames = vec![];
+        match self.latest_time {
+            None => self.first_output_time = time,
+            Some(latest_time) if time > latest_time => {
+                // across a gap in the vision data frames are only output
+                // while the tracks are kept, the rest of the gap is
+                // skipped
+                let output_end = time.min(latest_time + self.config.max_missing_time);
+                while self.next_output_time() < output_end {
+                    let tracked_frame = self.state_at(self.next_output_time());
+                    tracked_frames.push(tracked_frame);
+                    self.num_outputs += 1;
+                }
+                if self.next_output_time() < time {
+                    self.num_outputs =
+                        ((time - self.first_output_time) * self.config.output_rate).ceil() as u64;
+                }
+            }
+            _ => {}
+        }
+        let latest_time = self.latest_time.map_or(time, |latest| latest.max(time));
+        self.latest_time = Some(latest_time);
+
+        self.update_robots(time, detection);
+        self.update_ball(time, detection.get_balls());
+
+        let max_missing_time = self.config.max_missing_time;
+        self.robots
+            .retain(|_, track| latest_time - track.last_seen <= max_missing_time);
+        if let Some(ref track) = self.ball {
+            if latest_time - track.last_seen > max_missing_time {
+                self.ball = None;
+            }
+        }
+
+        tracked_frames
+    }
+
+    fn update_robots(&mut self, time: f64, detection: &SSL_DetectionFrame) {
+        let config = self.config;
+        for (team, detections) in &[
+            (TeamColor::Yellow, detection.get_robots_yellow()),
+            (TeamColor::Blue, detection.get_robots_blue()),
+        ] {
+            let mut by_id: BTreeMap<u32, Vec<&SSL_DetectionRobot>> = BTreeMap::new();
+            for robot in detections
+                .iter()
+                .filter(|robot| is_finite_robot(robot))
+                .filter(|robot| robot.get_confidence() >= config.min_confidence)
+            {
+                by_id.entry(robot.get_robot_id()).or_default().push(robot);
+            }
+
+            for (robot_id, robots) in by_id {
+                let orientation = |robot: &SSL_DetectionRobot| {
+                    if robot.has_orientation() {
+                        Some(robot.get_orientation())
+                    } else {
+                        None
+                    }
+                };
+
+                let track = match self.robots.get_mut(&(*team, robot_id)) {
+                    Some(track) => track,
+                    None => {
+                        let robot = most_confident(&robots, SSL_DetectionRobot::get_confidence);
+                        let track = Track::new(
+                            time,
+                            robot.get_x(),
+                            robot.get_y(),
+                            orientation(robot),
+                            &config,
+                        );
+                        self.robots.insert((*team, robot_id), track);
+                        continue;
+                    }
+                };
+
+                track.predict(time, config.robot_acceleration_noise, &config);
+                let robot = closest(&robots, track, |robot| (robot.get_x(), robot.get_y()));
+                let (x, y) = (robot.get_x(), robot.get_y());
+                if !track.update(time, x, y, orientation(robot), &config)
+                    && track.num_outliers >= config.max_outliers
+                {
+                    *track = Track::new(time, x, y, orientation(robot), &config);
+                }
+            }
+        }
+    }
+
+    fn update_ball(&mut self, time: f64, balls: &[SSL_DetectionBall]) {
+        let config = self.config;
+        let balls: Vec<&SSL_DetectionBall> = balls
+            .iter()
+            .filter(|ball| is_finite_ball(ball))
+            .filter(|ball| ball.get_confidence() >= config.min_confidence)
+            .collect();
+        if balls.is_empty() {
+            return;
+        }
+
+        match self.ball {
+            Some(ref mut track) => {
+                track.predict(time, config.ball_acceleration_noise, &config);
+                let ball = closest(&balls, track, |ball| (ball.get_x(), ball.get_y()));
+                let (x, y) = (ball.get_x(), ball.get_y());
+                if !track.update(time, x, y, None, &config)
+                    && track.num_outliers >= config.max_outliers
+                {
+                    *track = Track::new(time, x, y, None, &config);
+                }
+            }
+            None => {
+                let ball = most_confident(&balls, SSL_DetectionBall::get_confidence);
+                self.ball = Some(Track::new(time, ball.get_x(), ball.get_y(), None, &config));
+            }
+        }
+    }
+
+    // The tracked state predicted to `time` without changing the
+    // tracks. Tracks not seen for longer than the max missing time
+    // before `time` are left out.
+    pub fn state_at(&self, time: f64) -> TrackedFrame {
+        let config = &self.config;
+        let visible_since = time - config.visibility_timeout;
+        let is_kept = |track: &Track| time - track.last_seen <= config.max_missing_time;
+
+        let robots = self
+            .robots
+            .iter()
+            .filter(|(_, track)| is_kept(*track))
+            .map(|(&(team, robot_id), track)| {
+                let mut track = *track;
+                track.predict(time, config.robot_acceleration_noise, config);
+                TrackedRobot {
+                    team,
+                    robot_id,
+                    x: track.x.value,
+                    y: track.y.value,
+                    vx: track.x.rate,
+                    vy: track.y.rate,
+                    orientation: track.orientation.map(|filter| filter.value),
+                    angular_velocity: track.orientation.map(|filter| filter.rate),
+                    visible: track.last_seen >= visible_since,
+                }
+            })
+            .collect();
+
+        let ball = self.ball.filter(is_kept).map(|mut track| {
+            track.predict(time, config.ball_acceleration_noise, config);
+            TrackedBall {
+                x: track.x.value,
+                y: track.y.value,
+                vx: track.x.rate,
+                vy: track.y.rate,
+                visible: track.last_seen >= visible_since,
+            }
+        });
+
+        TrackedFrame {
+            frame_number: self.num_outputs as u32,
+            timestamp: time,
+            ball,
+            robots,
+        }
+    }
+
+    fn next_output_time(&self) -> f64 {
+        self.first_output_time + self.num_outputs as f64 / self.config.output_rate
+    }
+}
+
+fn most_confident<'a, T, F: Fn(&T) -> f32>(detections: &[&'a T], confidence: F) -> &'a T {
+    detections
+        .iter()
+        .max_by(|a, b| {
+            confidence(**a)
+                .partial_cmp(&confidence(**b))
+                .unwrap_or(Ordering::Equal)
+        })
+        .unwrap()
+}
+
+fn closest<'a, T, F: Fn(&T) -> (f32, f32)>(
+    detections: &[&'a T],
+    track: &Track,
+    position: F,
+) -> &'a T {
+    detections
+        .iter()
+        .min_by(|a, b| {
+            let (ax, ay) = position(**a);
+            let (bx, by) = position(**b);
+            track
+                .distance(ax, ay)
+                .partial_cmp(&track.distance(bx, by))
+                .unwrap_or(Ordering::Equal)
+        })
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::vision::{ball, detection, robot};
+    use proptest::prelude::*;
+
+    // small deterministic noise in [-amplitude, amplitude]
+    fn noise(i: usize, amplitude: f32) -> f32 {
+        amplitude * ((i * 7919) % 200) as f32 / 100.0 - amplitude
+    }
+
+    proptest! {
+        #[test]
+        fn constant_velocity_is_tracked(vx in -3000.0f32..3000.0,
+                                        vy in -3000.0f32..3000.0,
+                                        angular_velocity in -5.0f32..5.0) {
+            let mut tracker = Tracker::default();
+            let mut tracked_frames = vec![];
+            for i in 0..120 {
+                let t = i as f64 / 60.0;
+                let robot = robot(
+                    3,
+                    vx * t as f32 + noise(i, 5.0),
+                    vy * t as f32 + noise(i + 1, 5.0),
+                    wrap_angle(angular_velocity * t as f32),
+                );
+                tracked_frames.extend(tracker.add_detection(&detection(i as u32 % 2, t, vec![], vec![], vec![robot])));
+            }
+
+            let last = tracked_frames.last().unwrap();
+            let tracked_robot = last.robot(TeamColor::Blue, 3).unwrap();
+            prop_assert!((tracked_robot.vx - vx).abs() < 100.0);
+            prop_assert!((tracked_robot.vy - vy).abs() < 100.0);
+            prop_assert!((tracked_robot.angular_velocity.unwrap() - angular_velocity).abs() < 0.2);
+            let expected_orientation = wrap_angle(angular_velocity * last.timestamp as f32);
+            prop_assert!(wrap_angle(tracked_robot.orientation.unwrap() - expected_orientation).abs() < 0.05);
+            prop_assert!(tracked_robot.visible);
+        }
+    }
+
+    #[test]
+    fn frames_are_output_at_fixed_rate() {
+        let mut tracker = Tracker::default();
+        let mut tracked_frames = vec![];
+        for i in 0..=60 {
+            let t = 10.0 + i as f64 / 60.0;
+            tracked_frames.extend(tracker.add_detection(&detection(
+                0,
+                t,
+                vec![ball(0.0, 0.0)],
+                vec![],
+                vec![],
+            )));
+        }
+
+        assert_eq!(tracked_frames.len(), 100);
+        for (i, tracked_frame) in tracked_frames.iter().enumerate() {
+            assert_eq!(tracked_frame.frame_number, i as u32);
+            assert!((tracked_frame.timestamp - (10.0 + i as f64 / 100.0)).abs() < 1e-6);
+            assert!(tracked_frame.ball.is_some());
+        }
+    }
+
+    #[test]
+    fn outliers_are_rejected() {
+        let mut tracker = Tracker::default();
+        for i in 0..30 {
+            tracker.add_detection(&detection(
+                0,
+                i as f64 / 60.0,
+                vec![ball(0.0, 0.0)],
+                vec![],
+                vec![],
+            ));
+        }
+        // a reflection far away from the ball
+        tracker.add_detection(&detection(0, 0.5, vec![ball(3000.0, 0.0)], vec![], vec![]));
+
+        let tracked_ball = tracker.state_at(0.5).ball.unwrap();
+        assert!(tracked_ball.x.abs() < 1.0);
+    }
+
+    #[test]
+    fn track_restarts_after_repeated_outliers() {
+        let config = TrackerConfig::default();
+        let mut tracker = Tracker::new(&config);
+        for i in 0..30 {
+            tracker.add_detection(&detection(
+                0,
+                i as f64 / 60.0,
+                vec![],
+                vec![],
+                vec![robot(1, 0.0, 0.0, 0.0)],
+            ));
+        }
+        // the robot was put down somewhere else
+        for i in 30..(30 + config.max_outliers) {
+            tracker.add_detection(&detection(
+                0,
+                i as f64 / 60.0,
+                vec![],
+                vec![],
+                vec![robot(1, 2000.0, 0.0, 0.0)],
+            ));
+        }
+
+        let tracked_robot = tracker.state_at(1.0).robots[0].clone();
+        assert_eq!(tracked_robot.x, 2000.0);
+    }
+
+    #[test]
+    fn gap_in_vision_is_skipped() {
+        let config = TrackerConfig::default();
+        let mut tracker = Tracker::new(&config);
+        for i in 0..=60 {
+            let t = i as f64 / 60.0;
+            tracker.add_detection(&detection(
+                0,
+                t,
+                vec![ball(0.0, 0.0)],
+                vec![],
+                vec![robot(1, 1000.0 * t as f32, 0.0, 0.0)],
+            ));
+        }
+
+        // vision comes back after more than a minute
+        let tracked_frames =
+            tracker.add_detection(&detection(0, 100.0, vec![ball(0.0, 0.0)], vec![], vec![]));
+        let max_frames = (config.max_missing_time * config.output_rate).ceil() as usize + 1;
+        assert!(!tracked_frames.is_empty() && tracked_frames.len() <= max_frames);
+        for tracked_frame in &tracked_frames {
+            assert!(tracked_frame.timestamp <= 1.0 + config.max_missing_time + 1e-6);
+            assert_eq!(tracked_frame.robots.len(), 1);
+        }
+        assert!(tracker.state_at(100.0).robots.is_empty());
+
+        let tracked_frames =
+            tracker.add_detection(&detection(0, 100.1, vec![ball(0.0, 0.0)], vec![], vec![]));
+        assert_eq!(tracked_frames.len(), 10);
+        assert!(tracked_frames[0].timestamp >= 100.0 - 1e-6);
+        assert!(tracked_frames.iter().all(|frame| frame.robots.is_empty()));
+    }
+
+    #[test]
+    fn non_finite_detections_are_ignored() {
+        let mut tracker = Tracker::new(&TrackerConfig::default());
+        for i in 0..10 {
+            let t = i as f64 / 60.0;
+            tracker.add_detection(&detection(
+                0,
+                t,
+                vec![ball(0.0, 0.0), ball(std::f32::NAN, 0.0)],
+                vec![],
+                vec![robot(1, 0.0, 0.0, 0.0), robot(1, std::f32::NAN, 0.0, 0.0)],
+            ));
+        }
+
+        let tracked_frame = tracker.state_at(9.0 / 60.0);
+        let tracked_ball = tracked_frame.ball.unwrap();
+        assert_eq!((tracked_ball.x, tracked_ball.y), (0.0, 0.0));
+        assert_eq!(tracked_frame.robots.len(), 1);
+        assert_eq!(tracked_frame.robots[0].x, 0.0);
+    }
+
+    #[test]
+    fn seen_robot_stays_visible() {
+        let mut tracker = Tracker::default();
+        let mut tracked_frames = vec![];
+        for i in 0..60 {
+            let t = i as f64 / 60.0;
+            tracked_frames.extend(tracker.add_detection(&detection(
+                0,
+                t,
+                vec![],
+                vec![],
+                vec![robot(1, 0.0, 0.0, 0.0)],
+            )));
+        }
+
+        // more tracked frames than vision frames
+        assert!(tracked_frames.len() > 60);
+        for tracked_frame in &tracked_frames {
+            assert!(tracked_frame.robot(TeamColor::Blue, 1).unwrap().visible);
+        }
+    }
+
+    #[test]
+    fn occluded_robot_keeps_moving_until_dropped() {
+        let mut tracker = Tracker::default();
+        for i in 0..60 {
+            let t = i as f64 / 60.0;
+            tracker.add_detection(&detection(
+                0,
+                t,
+                vec![],
+                vec![],
+                vec![robot(1, 1000.0 * t as f32, 0.0, 0.0)],
+            ));
+        }
+        // only the ball is seen from now on
+        tracker.add_detection(&detection(0, 1.2, vec![ball(0.0, 0.0)], vec![], vec![]));
+
+        let tracked_robot = tracker.state_at(1.2).robots[0].clone();
+        assert!((tracked_robot.x - 1200.0).abs() < 50.0);
+        assert!(!tracked_robot.visible);
+
+        tracker.add_detection(&detection(0, 2.0, vec![ball(0.0, 0.0)], vec![], vec![]));
+        assert!(tracker.state_at(2.0).robots.is_empty());
+    }
+
+    #[test]
+    fn camera_handover_keeps_track() {
+        let mut tracker = Tracker::default();
+        for i in 0..60 {
+            let t = i as f64 / 60.0;
+            // the robot crosses from camera 0 to camera 1 at x = 500,
+            // and camera 1 is calibrated 10 mm off
+            let x = 1000.0 * t as f32;
+            let (camera_id, y) = if x < 500.0 { (0, 0.0) } else { (1, 10.0) };
+            tracker.add_detection(&detection(
+                camera_id,
+                t,
+                vec![],
+                vec![],
+                vec![robot(1, x, y, 0.0)],
+            ));
+        }
+
+        let tracked_frame = tracker.state_at(59.0 / 60.0);
+        assert_eq!(tracked_frame.robots.len(), 1);
+        assert!((tracked_frame.robots[0].vx - 1000.0).abs() < 100.0);
+    }
+
+    #[test]
+    fn tracker_packet_is_in_m() {
+        let tracked_frame = TrackedFrame {
+            frame_number: 4,
+            timestamp: 12.5,
+            ball: Some(TrackedBall {
+                x: 1000.0,
+                y: -500.0,
+                vx: 2000.0,
+                vy: 0.0,
+                visible: true,
+            }),
+            robots: vec![TrackedRobot {
+                team: TeamColor::Yellow,
+                robot_id: 7,
+                x: -3000.0,
+                y: 250.0,
+                vx: 0.0,
+                vy: 100.0,
+                orientation: Some(1.5),
+                angular_velocity: Some(0.5),
+                visible: false,
+            }],
+        };
+
+        let packet = tracked_frame.to_tracker_packet("uuid", "test");
+        assert_eq!(packet.get_uuid(), "uuid");
+        assert_eq!(packet.get_source_name(), "test");
+        let frame = packet.get_tracked_frame();
+        assert_eq!(frame.get_frame_number(), 4);
+        assert_eq!(frame.get_timestamp(), 12.5);
+        let ball = &frame.get_balls()[0];
+        assert_eq!(ball.get_pos().get_x(), 1.0);
+        assert_eq!(ball.get_pos().get_y(), -0.5);
+        assert_eq!(ball.get_vel().get_x(), 2.0);
+        assert_eq!(ball.get_visibility(), 1.0);
+        let robot = &frame.get_robots()[0];
+        assert_eq!(robot.get_robot_id().get_id(), 7);
+        assert_eq!(robot.get_robot_id().get_team(), tracked::Team::YELLOW);
+        assert_eq!(robot.get_pos().get_x(), -3.0);
+        assert_eq!(robot.get_orientation(), 1.5);
+        assert_eq!(robot.get_vel_angular(), 0.5);
+        assert_eq!(robot.get_visibility(), 0.0);
+    }
+}
@@ -0,0 +1,618 @@
+use crate::protos::log_labeler_data::LabelerFrameGroup;
+use crate::protos::log_labels;
+use crate::protos::messages_robocup_ssl_detection::{
+    SSL_DetectionBall, SSL_DetectionFrame, SSL_DetectionRobot,
+};
+use crate::protos::messages_robocup_ssl_wrapper::SSL_WrapperPacket;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+// Each SSL-Vision camera sends its own detection frame. The builder
+// keeps the latest frame of every camera and merges them into a single
+// snapshot of the field, combining robots and balls seen by more than
+// one camera.
+
+// Distances are in mm and times in seconds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WorldFrameConfig {
+    // camera frames captured more than this long before the newest
+    // frame are left out of the snapshot
+    pub max_frame_age: f64,
+    // balls from different cameras closer than this are the same ball
+    pub ball_merge_distance: f32,
+}
+
+impl Default for WorldFrameConfig {
+    fn default() -> Self {
+        WorldFrameConfig {
+            max_frame_age: 0.05,
+            ball_merge_distance: 200.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum TeamColor {
+    Yellow,
+    Blue,
+}
+
+impl From<log_labels::Team> for TeamColor {
+    fn from(team: log_labels::Team) -> Self {
+        match team {
+            log_labels::Team::YELLOW => TeamColor::Yellow,
+            log_labels::Team::BLUE => TeamColor::Blue,
+        }
+    }
+}
+
+impl From<TeamColor> for log_labels::Team {
+    fn from(team: TeamColor) -> Self {
+        match team {
+            TeamColor::Yellow => log_labels::Team::YELLOW,
+            TeamColor::Blue => log_labels::Team::BLUE,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RobotState {
+    pub team: TeamColor,
+    pub robot_id: u32,
+    pub x: f32,
+    pub y: f32,
+    // None if no camera reported an orientation
+    pub orientation: Option<f32>,
+    // highest confidence of any camera
+    pub confidence: f32,
+    pub cameras: Vec<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BallState {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    // highest confidence of any camera
+    pub confidence: f32,
+    pub cameras: Vec<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct WorldFrame {
+    // capture time of the newest camera frame
+    pub t_capture: f64,
+    // cameras whose frames are part of the snapshot
+    pub cameras: Vec<u32>,
+    // sorted by confidence, most confident first
+    pub balls: Vec<BallState>,
+    // sorted by team and robot id
+    pub robots: Vec<RobotState>,
+}
+
+impl WorldFrame {
+    // Merges the vision frames of a labeler frame group. None if the
+    // group has no detections.
+    pub fn from_frame_group(
+        frame_group: &LabelerFrameGroup,
+        config: &WorldFrameConfig,
+    ) -> Option<WorldFrame> {
+        let mut builder = WorldFrameBuilder::new(config);
+        builder.add_frame_group(frame_group);
+        builder.build()
+    }
+
+    // the most confident ball
+    pub fn ball(&self) -> Option<&BallState> {
+        self.balls.first()
+    }
+
+    pub fn robot(&self, team: TeamColor, robot_id: u32) -> Option<&RobotState> {
+        self.robots
+            .iter()
+            .find(|robot| robot.team == team && robot.robot_id == robot_id)
+    }
+
+    pub fn team_robots(&self, team: TeamColor) -> impl Iterator<Item = &RobotState> {
+        self.robots.iter().filter(move |robot| robot.team == team)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct WorldFrameBuilder {
+    config: WorldFrameConfig,
+    frames: BTreeMap<u32, SSL_DetectionFrame>,
+}
+
+// Detections with NaN or infinite values come from broken vision
+// setups. They are dropped, as they would spoil every average and
+// comparison they take part in.
+pub(crate) fn is_finite_robot(robot: &SSL_DetectionRobot) -> bool {
+    robot.get_confidence().is_finite()
+        && robot.get_x().is_finite()
+        && robot.get_y().is_finite()
+        && (!robot.has_orientation() || robot.get_orientation().is_finite())
+}
+
+pub(crate) fn is_finite_ball(ball: &SSL_DetectionBall) -> bool {
+    ball.get_confidence().is_finite()
+        && ball.get_x().is_finite()
+        && ball.get_y().is_finite()
+        && ball.get_z().is_finite()
+}
+
+// Detections with zero confidence still count, just barely
+fn weight(confidence: f32) -> f32 {
+    confidence.max(1e-3)
+}
+
+// Running confidence weighted average of detections of one object
+#[derive(Debug, Clone, Default)]
+struct Accumulator {
+    weight: f32,
+    x: f32,
+    y: f32,
+    z: f32,
+    // weighted sum of the orientation as a unit vector, so that angles
+    // on both sides of PI average correctly
+    orientation_cos: f32,
+    orientation_sin: f32,
+    has_orientation: bool,
+    confidence: f32,
+    cameras: Vec<u32>,
+}
+
+impl Accumulator {
+    fn add(&mut self, camera_id: u32, confidence: f32, x: f32, y: f32) {
+        let w = weight(confidence);
+        self.weight += w;
+        self.x += w * x;
+        self.y += w * y;
+        self.confidence = self.confidence.max(confidence);
+        self.cameras.push(camera_id);
+    }
+
+    fn add_robot(&mut self, camera_id: u32, robot: &SSL_DetectionRobot) {
+        self.add(
+            camera_id,
+            robot.get_confidence(),
+            robot.get_x(),
+            robot.get_y(),
+        );
+        if robot.has_orientation() {
+            let w = weight(robot.get_confidence());
+            self.orientation_cos += w * robot.get_orientation().cos();
+            self.orientation_sin += w * robot.get_orientation().sin();
+            self.has_orientation = true;
+        }
+    }
+
+    fn add_ball(&mut self, camera_id: u32, ball: &SSL_DetectionBall) {
+        self.add(camera_id, ball.get_confidence(), ball.get_x(), ball.get_y());
+        self.z += weight(ball.get_confidence()) * ball.get_z();
+    }
+
+    fn position(&self) -> (f32, f32) {
+        (self.x / self.weight, self.y / self.weight)
+    }
+
+    fn into_robot(mut self, team: TeamColor, robot_id: u32) -> RobotState {
+        let (x, y) = self.position();
+        let orientation = if self.has_orientation {
+            Some(self.orientation_sin.atan2(self.orientation_cos))
+        } else {
+            None
+        };
+        self.cameras.sort();
+        RobotState {
+            team,
+            robot_id,
+            x,
+            y,
+            orientation,
+            confidence: self.confidence,
+            cameras: self.cameras,
+        }
+    }
+
+    fn into_ball(mut self) -> BallState {
+        let (x, y) = self.position();
+        self.cameras.sort();
+        BallState {
+            x,
+            y,
+            z: self.z / self.weight,
+            confidence: self.confidence,
+            cameras: self.cameras,
+        }
+    }
+}
+
+impl WorldFrameBuilder {
+    pub fn new(config: &WorldFrameConfig) -> WorldFrameBuilder {
+        WorldFrameBuilder {
+            config: *config,
+            frames: BTreeMap::new(),
+        }
+    }
+
+    // Replaces the previous frame of the same camera. Frames older
+    // than the one already stored are ignored.
+    pub fn add_detection(&mut self, detection: &SSL_DetectionFrame) {
+        let is_newer = self
+            .frames
+            .get(&detection.get_camera_id())
+            .map_or(true, |frame| {
+                detection.get_t_capture() >= frame.get_t_capture()
+            });
+        if is_newer {
+            self.frames
+                .insert(detection.get_camera_id(), detection.clone());
+        }
+    }
+
+    // Packets without a detection frame are ignored
+    pub fn add_packet(&mut self, packet: &SSL_WrapperPacket) {
+        if packet.has_detection() {
+            self.add_detection(packet.get_detection());
+        }
+    }
+
+    pub fn add_frame_group(&mut self, frame_group: &LabelerFrameGroup) {
+        for frame in frame_group.get_frames() {
+            if frame.has_vision_frame() {
+                self.add_packet(frame.get_vision_frame());
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.frames.clear();
+    }
+
+    // Snapshot of the current frames. None if no frame was added since
+    // the builder was created or cleared.
+    pub fn build(&self) -> Option<WorldFrame> {
+        let t_capture = self
+            .frames
+            .values()
+            .map(|frame| frame.get_t_capture())
+            .fold(None, |newest: Option<f64>, t| {
+                Some(newest.map_or(t, |newest| newest.max(t)))
+            })?;
+        let frames: Vec<&SSL_DetectionFrame> = self
+            .frames
+            .values()
+            .filter(|frame| t_capture - frame.get_t_capture() <= self.config.max_frame_age)
+            .collect();
+
+        Some(WorldFrame {
+            t_capture,
+            cameras: frames.iter().map(|frame| frame.get_camera_id()).collect(),
+            balls: self.merge_balls(&frames),
+            robots: merge_robots(&frames),
+        })
+    }
+
+    // Balls are matched greedily, most confident first, to the closest
+    // ball within the merge distance that no other detection of the
+    // same camera was matched to. A camera can see more than one ball,
+    // for example when a ball lies outside the field.
+    fn merge_balls(&self, frames: &[&SSL_DetectionFrame]) -> Vec<BallState> {
+        let mut detections: Vec<(u32, &SSL_DetectionBall)> = frames
+            .iter()
+            .flat_map(|frame| {
+                frame
+                    .get_balls()
+                    .iter()
+                    .filter(|ball| is_finite_ball(ball))
+                    .map(move |ball| (frame.get_camera_id(), ball))
+            })
+            .collect();
+        detections.sort_by(|a, b| {
+            b.1.get_confidence()
+                .partial_cmp(&a.1.get_confidence())
+                .unwrap_or(Ordering::Equal)
+        });
+
+        let mut balls: Vec<Accumulator> = vec![];
+        for (camera_id, ball) in detections {
+            let closest = balls
+                .iter_mut()
+                .filter(|merged| !merged.cameras.contains(&camera_id))
+                .map(|merged| {
+                    let (x, y) = merged.position();
+                    let distance = (ball.get_x() - x).hypot(ball.get_y() - y);
+                    (merged, distance)
+                })
+                .filter(|(_, distance)| *distance <= self.config.ball_merge_distance)
+                .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal));
+
+            match closest {
+                Some((merged, _)) => merged.add_ball(camera_id, ball),
+                None => {
+                    let mut merged = Accumulator::default();
+                    merged.add_ball(camera_id, ball);
+                    balls.push(merged);
+                }
+            }
+        }
+
+        let mut balls: Vec<BallState> = balls.into_iter().map(Accumulator::into_ball).collect();
+        balls.sort_by(|a, b| {
+            b.confidence
+                .partial_cmp(&a.confidence)
+                .unwrap_or(Ordering::Equal)
+        });
+        balls
+    }
+}
+
+// Robots are matched by team and id. If a camera sees the same robot
+// twice, only its most confident detection is used.
+fn merge_robots(frames: &[&SSL_DetectionFrame]) -> Vec<RobotState> {
+    let mut robots: BTreeMap<(TeamColor, u32), Accumulator> = BTreeMap::new();
+    for frame in frames {
+        for (team, detections) in &[
+            (TeamColor::Yellow, frame.get_robots_yellow()),
+            (TeamColor::Blue, frame.get_robots_blue()),
+        ] {
+            let mut best: BTreeMap<u32, &SSL_DetectionRobot> = BTreeMap::new();
+            for robot in detections.iter().filter(|robot| is_finite_robot(robot)) {
+                let is_better = best.get(&robot.get_robot_id()).map_or(true, |other| {
+                    robot.get_confidence() > other.get_confidence()
+                });
+                if is_better {
+                    best.insert(robot.get_robot_id(), robot);
+                }
+            }
+
+            for (robot_id, robot) in best {
+                robots
+                    .entry((*team, robot_id))
+                    .or_insert_with(Accumulator::default)
+                    .add_robot(frame.get_camera_id(), robot);
+            }
+        }
+    }
+
+    robots
+        .into_iter()
+        .map(|((team, robot_id), merged)| merged.into_robot(team, robot_id))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::vision::{
+        ball_with_confidence, detection, robot, robot_with_confidence,
+    };
+    use proptest::prelude::*;
+    use std::f32::consts::PI;
+
+    fn build(frames: &[SSL_DetectionFrame]) -> WorldFrame {
+        let mut builder = WorldFrameBuilder::default();
+        for frame in frames {
+            builder.add_detection(frame);
+        }
+        builder.build().unwrap()
+    }
+
+    proptest! {
+        #[test]
+        fn single_camera_is_unchanged(positions in prop::collection::btree_map(0u32..16, (-6000.0f32..6000.0, -4500.0f32..4500.0, -PI..PI), 0..8),
+                                      camera_id in 0u32..8) {
+            let robots: Vec<SSL_DetectionRobot> = positions
+                .iter()
+                .map(|(robot_id, (x, y, orientation))| {
+                    robot_with_confidence(*robot_id, 0.9, *x, *y, *orientation)
+                })
+                .collect();
+            let world_frame = build(&[detection(camera_id, 1.0, vec![], vec![], robots)]);
+
+            prop_assert_eq!(world_frame.cameras, vec![camera_id]);
+            prop_assert_eq!(world_frame.robots.len(), positions.len());
+            for (state, (robot_id, (x, y, orientation))) in world_frame.robots.iter().zip(&positions) {
+                prop_assert_eq!(state.team, TeamColor::Blue);
+                prop_assert_eq!(state.robot_id, *robot_id);
+                prop_assert!((state.x - x).abs() < 1e-2);
+                prop_assert!((state.y - y).abs() < 1e-2);
+                let difference = state.orientation.unwrap() - orientation;
+                prop_assert!(difference.sin().abs() < 1e-4 && difference.cos() > 0.0);
+                prop_assert_eq!(&state.cameras, &vec![camera_id]);
+            }
+        }
+
+        #[test]
+        fn overlapping_robot_is_averaged(x in -6000.0f32..6000.0,
+                                         y in -4500.0f32..4500.0,
+                                         offset in -50.0f32..50.0) {
+            let world_frame = build(&[
+                detection(0, 1.0, vec![], vec![robot_with_confidence(3, 0.8, x - offset, y, 0.0)],
+                          vec![]),
+                detection(1, 1.01, vec![], vec![robot_with_confidence(3, 0.8, x + offset, y, 0.0)],
+                          vec![]),
+            ]);
+
+            prop_assert_eq!(world_frame.robots.len(), 1);
+            let state = &world_frame.robots[0];
+            prop_assert!((state.x - x).abs() < 1e-2);
+            prop_assert!((state.y - y).abs() < 1e-2);
+            prop_assert_eq!(&state.cameras, &vec![0, 1]);
+        }
+    }
+
+    #[test]
+    fn orientation_is_averaged_across_pi() {
+        let world_frame = build(&[
+            detection(0, 1.0, vec![], vec![robot(1, 0.0, 0.0, PI - 0.1)], vec![]),
+            detection(1, 1.0, vec![], vec![robot(1, 0.0, 0.0, -PI + 0.1)], vec![]),
+        ]);
+
+        let orientation = world_frame.robots[0].orientation.unwrap();
+        assert!(orientation.abs() > PI - 1e-3);
+    }
+
+    #[test]
+    fn non_finite_detections_are_dropped() {
+        let world_frame = build(&[
+            detection(
+                0,
+                1.0,
+                vec![ball_with_confidence(0.9, 0.0, 0.0)],
+                vec![robot(1, std::f32::NAN, 0.0, 0.0)],
+                vec![robot(2, 100.0, 0.0, 0.0)],
+            ),
+            detection(
+                1,
+                1.0,
+                vec![
+                    ball_with_confidence(std::f32::NAN, 10.0, 0.0),
+                    ball_with_confidence(0.8, std::f32::INFINITY, 0.0),
+                ],
+                vec![],
+                vec![robot(2, 100.0, std::f32::NAN, 0.0)],
+            ),
+        ]);
+
+        assert_eq!(world_frame.balls.len(), 1);
+        assert_eq!(world_frame.ball().unwrap().x, 0.0);
+        assert_eq!(world_frame.robots.len(), 1);
+        let robot = world_frame.robot(TeamColor::Blue, 2).unwrap();
+        assert_eq!((robot.x, robot.y), (100.0, 0.0));
+        assert_eq!(robot.cameras, vec![0]);
+    }
+
+    #[test]
+    fn team_color_label_round_trip() {
+        for team in &[TeamColor::Yellow, TeamColor::Blue] {
+            let label_team: log_labels::Team = (*team).into();
+            assert_eq!(TeamColor::from(label_team), *team);
+        }
+        assert_eq!(
+            log_labels::Team::from(TeamColor::Blue),
+            log_labels::Team::BLUE
+        );
+    }
+
+    #[test]
+    fn same_id_in_both_teams_is_kept_apart() {
+        let world_frame = build(&[detection(
+            0,
+            1.0,
+            vec![],
+            vec![robot(2, 100.0, 0.0, 0.0)],
+            vec![robot(2, -100.0, 0.0, 0.0)],
+        )]);
+
+        assert_eq!(world_frame.robots.len(), 2);
+        assert_eq!(world_frame.robot(TeamColor::Yellow, 2).unwrap().x, 100.0);
+        assert_eq!(world_frame.robot(TeamColor::Blue, 2).unwrap().x, -100.0);
+    }
+
+    #[test]
+    fn close_balls_are_merged() {
+        let world_frame = build(&[
+            detection(
+                0,
+                1.0,
+                vec![ball_with_confidence(0.9, 1000.0, 0.0)],
+                vec![],
+                vec![],
+            ),
+            detection(
+                1,
+                1.0,
+                vec![ball_with_confidence(0.3, 1040.0, 30.0)],
+                vec![],
+                vec![],
+            ),
+            detection(
+                2,
+                1.0,
+                vec![ball_with_confidence(0.6, -2000.0, 0.0)],
+                vec![],
+                vec![],
+            ),
+        ]);
+
+        assert_eq!(world_frame.balls.len(), 2);
+        let merged = world_frame.ball().unwrap();
+        assert_eq!(merged.confidence, 0.9);
+        assert_eq!(merged.cameras, vec![0, 1]);
+        assert!((merged.x - 1010.0).abs() < 1e-2);
+        assert!((merged.y - 7.5).abs() < 1e-2);
+        assert_eq!(world_frame.balls[1].cameras, vec![2]);
+    }
+
+    #[test]
+    fn balls_of_one_camera_are_not_merged() {
+        let world_frame = build(&[detection(
+            0,
+            1.0,
+            vec![
+                ball_with_confidence(0.9, 0.0, 0.0),
+                ball_with_confidence(0.5, 50.0, 0.0),
+            ],
+            vec![],
+            vec![],
+        )]);
+
+        assert_eq!(world_frame.balls.len(), 2);
+    }
+
+    #[test]
+    fn stale_cameras_are_left_out() {
+        let world_frame = build(&[
+            detection(
+                0,
+                1.0,
+                vec![ball_with_confidence(0.9, 0.0, 0.0)],
+                vec![],
+                vec![],
+            ),
+            detection(1, 2.0, vec![], vec![robot(1, 0.0, 0.0, 0.0)], vec![]),
+        ]);
+
+        assert_eq!(world_frame.t_capture, 2.0);
+        assert_eq!(world_frame.cameras, vec![1]);
+        assert!(world_frame.ball().is_none());
+        assert_eq!(world_frame.robots.len(), 1);
+    }
+
+    #[test]
+    fn older_frames_are_ignored() {
+        let world_frame = build(&[
+            detection(
+                0,
+                2.0,
+                vec![ball_with_confidence(0.9, 0.0, 0.0)],
+                vec![],
+                vec![],
+            ),
+            detection(
+                0,
+                1.0,
+                vec![ball_with_confidence(0.9, 500.0, 0.0)],
+                vec![],
+                vec![],
+            ),
+        ]);
+
+        assert_eq!(world_frame.balls.len(), 1);
+        assert_eq!(world_frame.ball().unwrap().x, 0.0);
+    }
+
+    #[test]
+    fn empty_builder_builds_nothing() {
+        let mut builder = WorldFrameBuilder::default();
+        assert!(builder.build().is_none());
+
+        builder.add_detection(&detection(0, 1.0, vec![], vec![], vec![]));
+        assert!(builder.build().is_some());
+
+        builder.clear();
+        assert!(builder.build().is_none());
+    }
+}